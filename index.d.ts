@@ -147,8 +147,15 @@ export declare class TuiUtils {
   static button(): JsElement
   /** Create an input element */
   static input(): JsElement
-  /** Parse CSS and return validation errors */
+  /**
+   * Parse CSS and return validation errors and warnings as
+   * `line:column: severity: message` strings
+   */
   static validateCss(css: string): Array<string>
+  /** Lint CSS and return structured diagnostics */
+  static lintCss(css: string): Array<JsCssDiagnostic>
+  /** Lint a stylesheet file and the files it imports */
+  static lintCssFile(path: string): Array<JsCssDiagnostic>
   /** Get terminal size */
   static getTerminalSize(): [number, number]
 }
@@ -161,3 +168,18 @@ export declare function init(): void
 
 /** Initialize the TUI library (call this first from JavaScript) */
 export declare function initTui(): void
+
+/** A stylesheet error or warning returned by `TuiUtils.lintCss` */
+export interface JsCssDiagnostic {
+  /** `error` or `warning` */
+  severity: string
+  /** Kind of problem, such as `unknown-property` or `invalid-value` */
+  kind: string
+  message: string
+  /** File the diagnostic points into, when linting a file */
+  file?: string
+  line: number
+  column: number
+  /** Suggested fix, such as ``did you mean `padding`?`` */
+  suggestion?: string
+}
@@ -42,10 +42,11 @@
 //! - **Hot Reload**: Live stylesheet reloading during development
 //! - **Driver Abstraction**: Support for different terminal backends
 
+use crate::compat::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
 use crate::events::actions::common;
 use crate::{
   components::Component,
-  css::CssEngine,
+  css::{ComponentTree, CssEngine, MediaEnvironment, Stylesheet},
   driver::{DriverConfig, DriverEvent, DriverManager},
   error::{Result, TuiError},
   events::{
@@ -56,12 +57,29 @@ use crate::{
     ComponentId, ReactiveBinding, ReactiveChangeEvent, ReactiveIntegration, UpdateRequest,
   },
   layout::LayoutEngine,
-  rendering::Renderer,
+  performance::PerformanceMonitor,
+  rendering::{hyperlink::global_hyperlink_support, DecorationSupport, Renderer},
 };
 use serde_json::Value;
 use std::{path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::{broadcast, RwLock};
 
+#[cfg(feature = "hot-reload")]
+use crate::{
+  hot_reload::{HotReloader, ReloadErrors, ReloadRequest, WatchTarget},
+  plugin::PluginManager,
+  themes::ColorTheme,
+};
+
+/// Rows or columns one notch of the mouse wheel scrolls by
+const WHEEL_STEP: i32 = 3;
+
+// Without the `hot-reload` feature the watcher slot can never be filled
+#[cfg(not(feature = "hot-reload"))]
+type HotReloader = std::convert::Infallible;
+#[cfg(not(feature = "hot-reload"))]
+type ReloadRequest = std::convert::Infallible;
+
 /// # TUI Application
 ///
 /// The main application struct that orchestrates terminal UI components, styling, and event handling.
@@ -141,7 +159,16 @@ pub struct TuiApp {
   focus_manager: Arc<RwLock<FocusManager>>,
   key_binding_manager: Arc<RwLock<KeyBindingManager>>,
   root_component: Option<Box<dyn Component>>,
+  /// Styled tree from the previous frame, updated in place while its shape is unchanged
+  component_tree: Option<ComponentTree>,
+  /// Style and layout timings of rendered frames
+  performance_monitor: Arc<RwLock<PerformanceMonitor>>,
   stylesheets: Vec<PathBuf>,
+  /// Watches stylesheets, themes and plugin configs when `hot-reload` is enabled
+  hot_reloader: Option<HotReloader>,
+  /// Errors from the last reload of each watched file, drawn over the UI
+  #[cfg(feature = "hot-reload")]
+  reload_errors: ReloadErrors,
   is_running: Arc<RwLock<bool>>,
   driver_manager: DriverManager,
   frame_rate: Duration,
@@ -184,8 +211,11 @@ impl TuiApp {
   /// Loads all configured stylesheets into the CSS engine.
   ///
   /// This method reads CSS files from the filesystem and parses them into the internal
-  /// CSS engine. It's called automatically during app initialization, but can be used
-  /// to reload stylesheets manually.
+  /// CSS engine, resolving `@import` rules relative to the importing file. It's called
+  /// automatically during app initialization, but can be used to reload stylesheets manually.
+  ///
+  /// Every file is read before any is loaded, and each replaces the sheet
+  /// previously read from it; CSS added with [`TuiApp::load_css`] is kept.
   ///
   /// # Errors
   ///
@@ -209,42 +239,37 @@ impl TuiApp {
   /// # Ok::<(), reactive_tui::error::TuiError>(())
   /// ```
   pub fn load_stylesheets(&mut self) -> Result<()> {
+    let stylesheets = self
+      .stylesheets
+      .iter()
+      .map(Stylesheet::from_file)
+      .collect::<Result<Vec<_>>>()?;
+    #[cfg(feature = "hot-reload")]
+    let imported_files: Vec<PathBuf> = stylesheets
+      .iter()
+      .flat_map(|stylesheet| stylesheet.imported_files.iter().cloned())
+      .collect();
+
     let mut css_engine = self
       .css_engine
       .try_write()
       .map_err(|_| TuiError::component("Failed to acquire CSS engine lock".to_string()))?;
+    css_engine.replace_file_stylesheets(stylesheets);
+    drop(css_engine);
 
-    for stylesheet_path in &self.stylesheets {
-      let css_content = std::fs::read_to_string(stylesheet_path).map_err(|e| {
-        TuiError::component(format!(
-          "Failed to read stylesheet {}: {}",
-          stylesheet_path.display(),
-          e
-        ))
-      })?;
-
-      css_engine.load_stylesheet(&css_content).map_err(|e| {
-        TuiError::component(format!(
-          "Failed to parse stylesheet {}: {}",
-          stylesheet_path.display(),
-          e
-        ))
-      })?;
+    // Editing an imported file reloads the stylesheets like editing the importer
+    #[cfg(feature = "hot-reload")]
+    for path in imported_files {
+      self.watch_or_report(WatchTarget::Stylesheet(path));
     }
 
     Ok(())
   }
 
-  /// Reload all stylesheets (useful for hot reload)
+  /// Reload all stylesheets (useful for hot reload).
+  ///
+  /// The loaded stylesheets are kept unless every file reads again.
   pub fn reload_stylesheets(&mut self) -> Result<()> {
-    let mut css_engine = self
-      .css_engine
-      .try_write()
-      .map_err(|_| TuiError::component("Failed to acquire CSS engine lock".to_string()))?;
-
-    css_engine.clear_stylesheets();
-    drop(css_engine);
-
     self.load_stylesheets()
   }
 
@@ -252,31 +277,39 @@ impl TuiApp {
   pub fn add_stylesheet<P: Into<PathBuf>>(&mut self, path: P) -> Result<()> {
     let path = path.into();
     self.stylesheets.push(path.clone());
+    #[cfg(feature = "hot-reload")]
+    self.watch_or_report(WatchTarget::Stylesheet(path.clone()));
 
-    let css_content = std::fs::read_to_string(&path).map_err(|e| {
-      TuiError::component(format!(
-        "Failed to read stylesheet {}: {}",
-        path.display(),
-        e
-      ))
-    })?;
+    let stylesheet = Stylesheet::from_file(&path)?;
+    #[cfg(feature = "hot-reload")]
+    for imported in &stylesheet.imported_files {
+      self.watch_or_report(WatchTarget::Stylesheet(imported.clone()));
+    }
 
     let mut css_engine = self
       .css_engine
       .try_write()
       .map_err(|_| TuiError::component("Failed to acquire CSS engine lock".to_string()))?;
-
-    css_engine.load_stylesheet(&css_content).map_err(|e| {
-      TuiError::component(format!(
-        "Failed to parse stylesheet {}: {}",
-        path.display(),
-        e
-      ))
-    })?;
+    css_engine.add_stylesheet(stylesheet);
 
     Ok(())
   }
 
+  /// Register the [`Component::scoped_css`] of a component rendered with
+  /// [`Component::render_scoped`] inside the app's tree.
+  ///
+  /// The root component's styles are registered automatically.
+  pub fn register_component_styles(&mut self, component: &dyn Component) -> Result<()> {
+    let Some(css) = component.scoped_css() else {
+      return Ok(());
+    };
+    let mut css_engine = self
+      .css_engine
+      .try_write()
+      .map_err(|_| TuiError::component("Failed to acquire CSS engine lock".to_string()))?;
+    css_engine.load_scoped_stylesheet(component.style_scope(), css)
+  }
+
   /// Set the window title (delegates to driver)
   pub fn set_title(&mut self, title: &str) {
     self.driver_manager.set_title(title);
@@ -294,6 +327,7 @@ impl TuiApp {
 
   /// Set the root component
   pub fn set_component(&mut self, component: Box<dyn Component>) -> Result<()> {
+    self.register_component_styles(component.as_ref())?;
     self.root_component = Some(component);
     Ok(())
   }
@@ -326,12 +360,18 @@ impl TuiApp {
                           self.stop().await;
                           break;
                       }
-                      self.event_handler.handle_key_event(key).await;
+                      // A page key that scrolled a container is used up;
+                      // otherwise the focused widget may page itself
+                      if !self.scroll_focused(&key).await {
+                        self.event_handler.handle_key_event(key).await;
+                      }
                       // Mark for re-render after input events
                       self.mark_for_render().await;
                   }
                   Some(DriverEvent::Mouse(mouse)) => {
-                      self.event_handler.handle_mouse_event(mouse).await;
+                      if !self.scroll_under_pointer(&mouse).await {
+                        self.event_handler.handle_mouse_event(mouse).await;
+                      }
                       // Mark for re-render after mouse events
                       self.mark_for_render().await;
                   }
@@ -357,6 +397,11 @@ impl TuiApp {
               }
           }
 
+          // Reload stylesheets, themes and plugin configs changed on disk
+          Some(request) = next_hot_reload(&mut self.hot_reloader) => {
+              self.apply_hot_reload(request).await;
+          }
+
           // Handle reactive updates
           update_request = self.update_receiver.recv() => {
               match update_request {
@@ -573,7 +618,7 @@ impl TuiApp {
   async fn render_frame(&mut self) -> Result<()> {
     if let Some(component) = &self.root_component {
       // Build virtual DOM
-      let mut element = component.render();
+      let mut element = component.render_scoped();
 
       // Update focus manager and apply focus to element tree
       {
@@ -582,18 +627,40 @@ impl TuiApp {
         focus_manager.apply_focus_to_tree(&mut element);
       }
 
-      // Apply CSS styles to entire component tree
+      // Apply hover and pressed state from mouse tracking
+      self
+        .event_handler
+        .apply_pointer_state_to_tree(&mut element)
+        .await;
+
+      // Apply CSS styles to entire component tree. When the tree keeps its shape,
+      // only elements reached by changed classes, attributes or state are restyled
+      let css_started = std::time::Instant::now();
       let component_tree = {
         let css_engine = self.css_engine.read().await;
-        // Use per-build style cache to avoid redundant style recomputation within one frame
-        css_engine.create_component_tree_cached(&element)
+        let mut previous = self.component_tree.take();
+        let reused = previous
+          .as_mut()
+          .is_some_and(|tree| tree.update(&element, &css_engine).is_some());
+        match previous {
+          Some(tree) if reused => tree,
+          // Use per-build style cache to avoid redundant style recomputation within one frame
+          _ => css_engine.create_component_tree_cached(&element),
+        }
       };
+      let css_time = css_started.elapsed();
 
       // Compute layout using component tree styles
-      let layout = {
+      let (layout, layout_timings) = {
         let mut layout_engine = self.layout_engine.write().await;
-        layout_engine.compute_layout_with_component_tree(&element, &component_tree)?
+        let layout = layout_engine.compute_layout_with_component_tree(&element, &component_tree)?;
+        (layout, layout_engine.last_timings().clone())
       };
+      {
+        let mut monitor = self.performance_monitor.write().await;
+        monitor.record_css_processing(css_time);
+        monitor.record_layout_timings(&layout_timings);
+      }
 
       // Update component bounds for mouse targeting
       self
@@ -601,17 +668,29 @@ impl TuiApp {
         .update_component_bounds(&element, &layout)
         .await?;
 
-      // Render to terminal with component tree styles
+      // Render the changes since the last frame with component tree styles
       {
         let mut renderer = self.renderer.write().await;
-        let bytes = renderer
-          .render_with_component_tree(&layout, &component_tree)
+        #[cfg_attr(not(feature = "hot-reload"), allow(unused_mut))]
+        let mut bytes = renderer
+          .render_diff_with_component_tree(&layout, &component_tree)
           .await?;
-        // Route frame through driver for output
-        let driver = self.driver_manager.driver_mut();
-        driver.write_bytes(&bytes)?;
-        driver.flush()?;
+        #[cfg(feature = "hot-reload")]
+        if !self.reload_errors.is_empty() {
+          let (width, height) = renderer.get_dimensions();
+          bytes.extend(crate::hot_reload::error_overlay(
+            &self.reload_errors,
+            width,
+            height,
+          ));
+          // The overlay covers cells the renderer believes it drew
+          renderer.invalidate();
+        }
+        // Route frame through driver as one synchronized update
+        self.driver_manager.driver_mut().write_frame(&bytes)?;
       }
+
+      self.component_tree = Some(component_tree);
     }
 
     Ok(())
@@ -672,15 +751,56 @@ impl TuiApp {
   }
 
   async fn handle_navigation(&self, direction: NavigationDirection) {
-    let mut focus_manager = self.focus_manager.write().await;
+    let focused = {
+      let mut focus_manager = self.focus_manager.write().await;
+      let focused = match direction {
+        NavigationDirection::Next | NavigationDirection::Down | NavigationDirection::Right => {
+          focus_manager.focus_next()
+        }
+        NavigationDirection::Previous | NavigationDirection::Up | NavigationDirection::Left => {
+          focus_manager.focus_previous()
+        }
+      };
+      focused.map(|element| element.id.clone())
+    };
 
-    match direction {
-      NavigationDirection::Next | NavigationDirection::Down | NavigationDirection::Right => {
-        focus_manager.focus_next();
-      }
-      NavigationDirection::Previous | NavigationDirection::Up | NavigationDirection::Left => {
-        focus_manager.focus_previous();
-      }
+    // Bring the newly focused element into view in its scroll containers
+    if let Some(id) = focused {
+      self.layout_engine.write().await.scroll_into_view(&id);
+    }
+  }
+
+  /// Scroll the scroll container under the pointer with the mouse wheel,
+  /// returning whether it moved
+  async fn scroll_under_pointer(&self, mouse: &MouseEvent) -> bool {
+    let (dx, dy) = match mouse.kind {
+      MouseEventKind::ScrollUp => (0, -WHEEL_STEP),
+      MouseEventKind::ScrollDown => (0, WHEEL_STEP),
+      MouseEventKind::ScrollLeft => (-WHEEL_STEP, 0),
+      MouseEventKind::ScrollRight => (WHEEL_STEP, 0),
+      _ => return false,
+    };
+    let mut layout_engine = self.layout_engine.write().await;
+    layout_engine.scroll_at(mouse.column, mouse.row, dx, dy)
+  }
+
+  /// Page through the scroll container that is focused or holds the
+  /// focused element with PageUp and PageDown, returning whether it moved
+  async fn scroll_focused(&self, key: &KeyEvent) -> bool {
+    let pages = match key.code {
+      KeyCode::PageUp => -1,
+      KeyCode::PageDown => 1,
+      _ => return false,
+    };
+    let focused = {
+      let focus_manager = self.focus_manager.read().await;
+      focus_manager
+        .get_focused_element()
+        .map(|element| element.id.clone())
+    };
+    match focused {
+      Some(id) => self.layout_engine.write().await.scroll_page(&id, pages),
+      None => false,
     }
   }
 
@@ -712,7 +832,13 @@ impl TuiApp {
 
   async fn focus_element_by_id(&self, element_id: &str) {
     let mut focus_manager = self.focus_manager.write().await;
-    focus_manager.focus_by_id(element_id);
+    if focus_manager.focus_by_id(element_id).is_some() {
+      self
+        .layout_engine
+        .write()
+        .await
+        .scroll_into_view(element_id);
+    }
   }
 
   async fn handle_element_action(&self, element_id: &str, action: &crate::events::ElementAction) {
@@ -755,9 +881,114 @@ impl TuiApp {
     }
   }
 
+  /// Start watching the configured stylesheets; failures are shown in the
+  /// error overlay rather than aborting startup
+  #[cfg(feature = "hot-reload")]
+  fn start_hot_reload(&mut self) {
+    match HotReloader::new() {
+      Ok(reloader) => self.hot_reloader = Some(reloader),
+      Err(e) => {
+        self
+          .reload_errors
+          .set(std::path::Path::new(""), vec![e.to_string()]);
+        return;
+      }
+    }
+    for path in self.stylesheets.clone() {
+      self.watch_or_report(WatchTarget::Stylesheet(path));
+    }
+    crate::hot_reload::check_stylesheets(&self.stylesheets, &mut self.reload_errors);
+  }
+
+  #[cfg(feature = "hot-reload")]
+  fn watch_or_report(&mut self, target: WatchTarget) {
+    if let Err(e) = self.watch(target.clone()) {
+      let path = match &target {
+        WatchTarget::Stylesheet(path) | WatchTarget::PluginConfig { path, .. } => path,
+        WatchTarget::ThemeDirectory { dir, .. } => dir,
+      };
+      self.reload_errors.set(path, vec![e.to_string()]);
+    }
+  }
+
+  #[cfg(feature = "hot-reload")]
+  fn watch(&mut self, target: WatchTarget) -> Result<()> {
+    self
+      .hot_reloader
+      .as_mut()
+      .ok_or_else(|| TuiError::component("File watcher is not running".to_string()))?
+      .watch(target)
+  }
+
+  /// Reload themes from `dir` whenever one of its JSON files changes.
+  ///
+  /// Each theme that parses is passed to `on_reload`; invalid files are shown
+  /// in the error overlay until fixed. Themes loaded from a directory are
+  /// only watched once it is registered here. Requires the `hot-reload`
+  /// feature.
+  #[cfg(feature = "hot-reload")]
+  pub fn watch_theme_directory<P, F>(&mut self, dir: P, on_reload: F) -> Result<()>
+  where
+    P: Into<PathBuf>,
+    F: Fn(ColorTheme) + Send + Sync + 'static,
+  {
+    self.watch(WatchTarget::ThemeDirectory {
+      dir: dir.into(),
+      on_reload: Arc::new(on_reload),
+    })
+  }
+
+  /// Re-run [`PluginManager::load_config`] whenever the file at `path`
+  /// changes. Requires the `hot-reload` feature.
+  #[cfg(feature = "hot-reload")]
+  pub fn watch_plugin_config<P: Into<PathBuf>>(
+    &mut self,
+    path: P,
+    manager: Arc<PluginManager>,
+  ) -> Result<()> {
+    self.watch(WatchTarget::PluginConfig {
+      path: path.into(),
+      manager,
+    })
+  }
+
+  #[cfg(feature = "hot-reload")]
+  async fn apply_hot_reload(&mut self, request: ReloadRequest) {
+    self
+      .reload_errors
+      .set(std::path::Path::new(""), request.watcher_errors.clone());
+    if request.stylesheets {
+      crate::hot_reload::check_stylesheets(&self.stylesheets, &mut self.reload_errors);
+      // Unreadable files were just recorded for the overlay; anything else
+      // that kept the stylesheets from reloading is shown with them
+      if let Err(e) = self.reload_stylesheets() {
+        if self.reload_errors.is_empty() {
+          self
+            .reload_errors
+            .set(std::path::Path::new(""), vec![e.to_string()]);
+        }
+      }
+    }
+    request.reload_themes_and_plugins(&mut self.reload_errors);
+    self.mark_for_render().await;
+  }
+
+  #[cfg(not(feature = "hot-reload"))]
+  async fn apply_hot_reload(&mut self, request: ReloadRequest) {
+    match request {}
+  }
+
   async fn handle_resize(&self, width: u16, height: u16) -> Result<()> {
     let mut renderer = self.renderer.write().await;
     renderer.resize(width, height).await?;
+
+    // Re-evaluate @media rules; the cached component tree is discarded on the
+    // next frame if any of them changed outcome
+    self
+      .css_engine
+      .write()
+      .await
+      .set_viewport_size(width, height);
     Ok(())
   }
 
@@ -770,6 +1001,20 @@ impl TuiApp {
   pub fn driver_mut(&mut self) -> &mut dyn crate::driver::Driver {
     self.driver_manager.driver_mut()
   }
+
+  /// Monitor recording how long styling and layout take for each frame
+  pub fn performance_monitor(&self) -> Arc<RwLock<PerformanceMonitor>> {
+    self.performance_monitor.clone()
+  }
+}
+
+/// Wait for the next batch of watched file changes; never resolves without a watcher
+async fn next_hot_reload(reloader: &mut Option<HotReloader>) -> Option<ReloadRequest> {
+  match reloader {
+    #[cfg(feature = "hot-reload")]
+    Some(reloader) => reloader.next_request().await,
+    _ => std::future::pending().await,
+  }
 }
 
 /// # TUI Application Builder
@@ -993,12 +1238,31 @@ impl TuiAppBuilder {
   }
 
   pub fn build(self) -> Result<TuiApp> {
-    let css_engine = Arc::new(RwLock::new(CssEngine::new()));
     let layout_engine = Arc::new(RwLock::new(LayoutEngine::new()));
-    let renderer = Arc::new(RwLock::new(Renderer::new()?));
+    let mut renderer = Renderer::new()?;
     let mut event_handler = EventHandler::new();
     let driver_manager = DriverManager::with_config(self.driver_config)?;
 
+    // Evaluate @media rules against the real terminal from the first frame
+    let (width, height) = driver_manager
+      .driver()
+      .get_terminal_size()
+      .unwrap_or((80, 24));
+    let media_environment = MediaEnvironment::detect(width, height);
+    // Downsample colors and decorations the terminal cannot show; headless
+    // output keeps them
+    if !driver_manager.driver().capabilities().is_headless {
+      renderer.set_color_support(media_environment.color_support);
+      renderer.set_decoration_support(DecorationSupport::detect());
+    }
+    // Links are written as OSC 8 hyperlinks only where the driver supports
+    // them, with the URL printed after the text otherwise
+    global_hyperlink_support::set_override(driver_manager.driver().capabilities().hyperlinks);
+    let renderer = Arc::new(RwLock::new(renderer));
+    let mut css_engine = CssEngine::new();
+    css_engine.set_media_environment(media_environment);
+    let css_engine = Arc::new(RwLock::new(css_engine));
+
     // Set up default actions
     Self::setup_default_actions(&mut event_handler);
 
@@ -1031,7 +1295,12 @@ impl TuiAppBuilder {
       focus_manager,
       key_binding_manager: Arc::new(RwLock::new(KeyBindingManager::new())),
       root_component: self.component,
+      component_tree: None,
+      performance_monitor: Arc::new(RwLock::new(PerformanceMonitor::new())),
       stylesheets: self.stylesheets.clone(),
+      hot_reloader: None,
+      #[cfg(feature = "hot-reload")]
+      reload_errors: ReloadErrors::default(),
       is_running: Arc::new(RwLock::new(true)),
       driver_manager,
       frame_rate: self.frame_rate,
@@ -1047,6 +1316,14 @@ impl TuiAppBuilder {
       consecutive_skips: Arc::new(RwLock::new(0)),
     };
 
+    #[cfg(feature = "hot-reload")]
+    app.start_hot_reload();
+
+    // Register the root component's scoped stylesheet
+    if let Some(component) = app.root_component.take() {
+      app.set_component(component)?;
+    }
+
     // Load all stylesheets
     app.load_stylesheets()?;
 
@@ -1220,3 +1497,58 @@ impl Default for TuiAppBuilder {
     Self::new()
   }
 }
+
+#[cfg(all(test, feature = "hot-reload"))]
+mod tests {
+  use super::*;
+  use crate::components::Element;
+  use crate::hot_reload::ReloadRequest;
+
+  #[tokio::test]
+  async fn test_hot_reload_keeps_css_loaded_from_strings() {
+    let dir = std::env::temp_dir().join(format!("reactive-tui-app-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let stylesheet = dir.join("app.css");
+    std::fs::write(&stylesheet, ".file { padding: 1 }").unwrap();
+
+    let mut app = TuiApp::builder()
+      .headless()
+      .stylesheet(&stylesheet)
+      .build()
+      .unwrap();
+    app.load_css(".inline { padding: 3 }".to_string()).unwrap();
+    let padding = |app: &TuiApp, class: &str| {
+      let element = Element::with_tag("div").class(class).build();
+      app
+        .css_engine
+        .try_read()
+        .unwrap()
+        .apply_styles(&element)
+        .padding
+        .top
+    };
+    let reload = ReloadRequest {
+      stylesheets: true,
+      ..ReloadRequest::default()
+    };
+
+    std::fs::write(&stylesheet, ".file { padding: 2 }").unwrap();
+    app.apply_hot_reload(reload).await;
+    assert_eq!(padding(&app, "file"), 2);
+    assert_eq!(padding(&app, "inline"), 3);
+    assert!(app.reload_errors.is_empty());
+
+    // A file that is briefly missing keeps the stylesheets it last loaded
+    std::fs::remove_file(&stylesheet).unwrap();
+    let reload = ReloadRequest {
+      stylesheets: true,
+      ..ReloadRequest::default()
+    };
+    app.apply_hot_reload(reload).await;
+    assert_eq!(padding(&app, "file"), 2);
+    assert_eq!(padding(&app, "inline"), 3);
+    assert!(!app.reload_errors.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,74 @@
+//! Command line tools for reactive-tui projects
+//!
+//! ```text
+//! rtui lint-css [--deny-warnings] <file.css>...
+//! ```
+//!
+//! `lint-css` prints one diagnostic per line and exits with status 1 when any
+//! stylesheet has errors (or warnings, with `--deny-warnings`), so CI can
+//! reject broken theme CSS.
+
+use reactive_tui::css::{CssEngine, Severity};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: rtui lint-css [--deny-warnings] <file.css>...";
+
+fn main() -> ExitCode {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  match args.split_first() {
+    Some((command, rest)) if command == "lint-css" => lint_css(rest),
+    Some((flag, _)) if flag == "-h" || flag == "--help" => {
+      println!("{USAGE}");
+      ExitCode::SUCCESS
+    }
+    _ => {
+      eprintln!("{USAGE}");
+      ExitCode::from(2)
+    }
+  }
+}
+
+fn lint_css(args: &[String]) -> ExitCode {
+  let mut deny_warnings = false;
+  let mut files = Vec::new();
+  for arg in args {
+    match arg.as_str() {
+      "--deny-warnings" => deny_warnings = true,
+      flag if flag.starts_with('-') => {
+        eprintln!("rtui lint-css: unknown option `{flag}`\n{USAGE}");
+        return ExitCode::from(2);
+      }
+      file => files.push(file),
+    }
+  }
+  if files.is_empty() {
+    eprintln!("{USAGE}");
+    return ExitCode::from(2);
+  }
+
+  let (mut errors, mut warnings) = (0, 0);
+  for file in files {
+    match CssEngine::validate_css_file(file) {
+      Ok(diagnostics) => {
+        for diagnostic in diagnostics {
+          match diagnostic.severity {
+            Severity::Error => errors += 1,
+            Severity::Warning => warnings += 1,
+          }
+          println!("{diagnostic}");
+        }
+      }
+      Err(e) => {
+        errors += 1;
+        println!("{file}: error: {e}");
+      }
+    }
+  }
+
+  eprintln!("rtui lint-css: {errors} error(s), {warnings} warning(s)");
+  if errors > 0 || (deny_warnings && warnings > 0) {
+    ExitCode::FAILURE
+  } else {
+    ExitCode::SUCCESS
+  }
+}
@@ -23,6 +23,10 @@ pub struct Element {
   pub id: Option<String>,
   pub focusable: bool,
   pub focused: bool,
+  /// Under the mouse pointer (or an ancestor of the element that is)
+  pub hovered: bool,
+  /// A mouse button was pressed on the element and is still held
+  pub active: bool,
   pub disabled: bool,
   pub tab_index: Option<i32>,
   pub key_bindings: Vec<ElementKeyBinding>,
@@ -49,6 +53,8 @@ impl Element {
         id: None,
         focusable: false,
         focused: false,
+        hovered: false,
+        active: false,
         disabled: false,
         tab_index: None,
         key_bindings: Vec::new(),
@@ -77,6 +83,34 @@ impl Element {
     self.focused = focused;
   }
 
+  pub fn is_hovered(&self) -> bool {
+    self.hovered
+  }
+
+  pub fn set_hovered(&mut self, hovered: bool) {
+    self.hovered = hovered;
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.active
+  }
+
+  pub fn set_active(&mut self, active: bool) {
+    self.active = active;
+  }
+
+  /// Whether a checkbox-like element is checked, via `checked` or `aria-checked`
+  pub fn is_checked(&self) -> bool {
+    self
+      .attributes
+      .get("checked")
+      .is_some_and(|value| value != "false")
+      || self
+        .attributes
+        .get("aria-checked")
+        .is_some_and(|value| value == "true")
+  }
+
   pub fn is_disabled(&self) -> bool {
     self.disabled
   }
@@ -138,6 +172,17 @@ impl ElementBuilder {
     self
   }
 
+  /// Set inline declarations such as `"color: red; padding: 1"`.
+  ///
+  /// They override every stylesheet rule except `!important` ones.
+  pub fn style<S: Into<String>>(mut self, style: S) -> Self {
+    self
+      .element
+      .attributes
+      .insert("style".to_string(), style.into());
+    self
+  }
+
   pub fn content<S: Into<String>>(mut self, content: S) -> Self {
     self.element.content = Some(content.into());
     self
@@ -130,6 +130,33 @@ pub trait Component: Send + Sync {
   ) -> bool {
     true
   }
+
+  /// Stylesheet shipped with the component, applied only inside the subtree
+  /// it renders so its selectors cannot leak into the host app.
+  ///
+  /// Render the component with [`Component::render_scoped`]; the app root is
+  /// registered automatically, nested components with
+  /// [`TuiApp::register_component_styles`](crate::app::TuiApp::register_component_styles).
+  fn scoped_css(&self) -> Option<&str> {
+    None
+  }
+
+  /// Name of the component's style scope, unique per component type by default
+  fn style_scope(&self) -> &str {
+    std::any::type_name::<Self>()
+  }
+
+  /// Render the component, marking the root element as the root of its style scope
+  fn render_scoped(&self) -> Element {
+    let mut element = self.render();
+    if self.scoped_css().is_some() {
+      element.attributes.insert(
+        crate::css::SCOPE_ATTRIBUTE.to_string(),
+        self.style_scope().to_string(),
+      );
+    }
+    element
+  }
 }
 
 /// Props that can be passed to components
@@ -0,0 +1,670 @@
+//! Stylesheet diagnostics with source positions and suggested fixes
+//!
+//! Linting a stylesheet reports everything the engine would silently skip:
+//! syntax errors, unsupported selectors and at-rules, unknown properties,
+//! invalid values and properties that have no effect in a terminal.
+
+use super::{
+  parser, ComputedStyles, CssEngine, CssParseError, CssWideKeyword, Declaration, SourceLocation,
+  Stylesheet, SUPPORTED_PROPERTIES,
+};
+use crate::error::{Result, TuiError};
+use crate::layout::grid::GridAreas;
+use std::path::{Path, PathBuf};
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  /// The CSS is valid but probably not doing what was intended
+  Warning,
+  /// Part of the stylesheet is dropped
+  Error,
+}
+
+impl Severity {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Severity::Warning => "warning",
+      Severity::Error => "error",
+    }
+  }
+}
+
+impl std::fmt::Display for Severity {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// What a diagnostic is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+  /// Malformed CSS; the enclosing rule or declaration is skipped
+  Syntax,
+  /// A selector the engine cannot match; the whole rule is skipped
+  UnsupportedSelector,
+  /// An at-rule other than `@media`, `@import` and `@layer`
+  UnsupportedAtRule,
+  /// An `@import` that cannot be read or imports itself
+  Import,
+  /// A property that is not part of CSS
+  UnknownProperty,
+  /// A CSS property the engine does not implement
+  UnsupportedProperty,
+  /// A property that has no meaning in a terminal, such as `font-size`
+  IgnoredProperty,
+  /// A value the property does not accept; the declaration is skipped
+  InvalidValue,
+}
+
+impl DiagnosticKind {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DiagnosticKind::Syntax => "syntax",
+      DiagnosticKind::UnsupportedSelector => "unsupported-selector",
+      DiagnosticKind::UnsupportedAtRule => "unsupported-at-rule",
+      DiagnosticKind::Import => "import",
+      DiagnosticKind::UnknownProperty => "unknown-property",
+      DiagnosticKind::UnsupportedProperty => "unsupported-property",
+      DiagnosticKind::IgnoredProperty => "ignored-property",
+      DiagnosticKind::InvalidValue => "invalid-value",
+    }
+  }
+
+  pub fn severity(&self) -> Severity {
+    match self {
+      DiagnosticKind::UnknownProperty
+      | DiagnosticKind::UnsupportedProperty
+      | DiagnosticKind::IgnoredProperty => Severity::Warning,
+      _ => Severity::Error,
+    }
+  }
+}
+
+/// An error or warning about a stylesheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssDiagnostic {
+  pub severity: Severity,
+  pub kind: DiagnosticKind,
+  pub message: String,
+  /// File the diagnostic points into; `None` for CSS passed as a string
+  pub file: Option<PathBuf>,
+  pub location: SourceLocation,
+  /// How to fix the problem, such as ``did you mean `padding`?``
+  pub suggestion: Option<String>,
+}
+
+impl CssDiagnostic {
+  fn new(kind: DiagnosticKind, message: String, location: SourceLocation) -> Self {
+    Self {
+      severity: kind.severity(),
+      kind,
+      message,
+      file: None,
+      location,
+      suggestion: None,
+    }
+  }
+
+  fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+    self.suggestion = suggestion;
+    self
+  }
+
+  pub fn is_error(&self) -> bool {
+    self.severity == Severity::Error
+  }
+}
+
+impl From<&CssParseError> for CssDiagnostic {
+  fn from(error: &CssParseError) -> Self {
+    Self::new(error.kind, error.message.clone(), error.location)
+  }
+}
+
+impl std::fmt::Display for CssDiagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if let Some(file) = &self.file {
+      write!(f, "{}:", file.display())?;
+    }
+    write!(f, "{}: {}: {}", self.location, self.severity, self.message)?;
+    if let Some(suggestion) = &self.suggestion {
+      write!(f, " ({suggestion})")?;
+    }
+    Ok(())
+  }
+}
+
+/// Properties that exist in CSS but cannot be rendered with terminal cells
+const TERMINAL_IGNORED_PROPERTIES: &[&str] = &[
+  "animation",
+  "background-image",
+  "backdrop-filter",
+  "border-radius",
+  "box-shadow",
+  "cursor",
+  "filter",
+  "font-family",
+  "font-size",
+  "font-stretch",
+  "font-variant",
+  "letter-spacing",
+  "line-height",
+  "outline",
+  "pointer-events",
+  "text-shadow",
+  "transform",
+  "transition",
+  "user-select",
+  "word-spacing",
+];
+
+/// Standard CSS properties the engine does not apply from stylesheets
+const UNSUPPORTED_PROPERTIES: &[&str] = &[
+  "align-content",
+  "align-items",
+  "align-self",
+  "border",
+  "border-style",
+  "flex",
+  "flex-basis",
+  "flex-grow",
+  "flex-shrink",
+  "flex-wrap",
+  "gap",
+  "grid-column",
+  "grid-row",
+  "grid-template-columns",
+  "grid-template-rows",
+  "justify-content",
+  "margin-bottom",
+  "margin-left",
+  "margin-right",
+  "margin-top",
+  "order",
+  "padding-bottom",
+  "padding-left",
+  "padding-right",
+  "padding-top",
+  "text-transform",
+  "visibility",
+];
+
+/// Named colors accepted by color properties
+const COLOR_NAMES: &[&str] = &[
+  "black",
+  "red",
+  "green",
+  "yellow",
+  "blue",
+  "magenta",
+  "cyan",
+  "white",
+  "grey",
+  "gray",
+  "darkgrey",
+  "darkgray",
+  "darkred",
+  "darkgreen",
+  "darkyellow",
+  "darkblue",
+  "darkmagenta",
+  "darkcyan",
+  "transparent",
+];
+
+/// Values a property accepts, used to explain an invalid value
+enum Expected {
+  Keywords(&'static [&'static str]),
+  /// A width or height, which may also be sized by the content
+  Extent,
+  Size,
+  Cells,
+  Color,
+  Opacity,
+  ZIndex,
+  TextOverflow,
+  GridAreas,
+  AreaName,
+  ScrollbarColor,
+  AspectRatio,
+}
+
+impl Expected {
+  fn of(property: &str) -> Option<Self> {
+    Some(match property {
+      "display" => Expected::Keywords(&["block", "inline", "flex", "grid", "none"]),
+      "flex-direction" => Expected::Keywords(&["row", "column", "row-reverse", "column-reverse"]),
+      "width" | "height" | "min-width" | "min-height" | "max-width" | "max-height" => {
+        Expected::Extent
+      }
+      "top" | "right" | "bottom" | "left" => Expected::Size,
+      "aspect-ratio" => Expected::AspectRatio,
+      "position" => Expected::Keywords(&["static", "relative", "absolute", "fixed", "sticky"]),
+      "padding" | "margin" | "border-width" => Expected::Cells,
+      "color" | "background-color" | "background" | "border-color" | "text-decoration-color" => {
+        Expected::Color
+      }
+      "opacity" => Expected::Opacity,
+      "z-index" => Expected::ZIndex,
+      "font-weight" => Expected::Keywords(&["normal", "bold", "lighter"]),
+      "font-style" => Expected::Keywords(&["normal", "italic"]),
+      "text-decoration" | "text-decoration-line" => {
+        Expected::Keywords(&["none", "underline", "line-through", "overline", "blink"])
+      }
+      "text-decoration-style" => {
+        Expected::Keywords(&["solid", "double", "dotted", "dashed", "wavy"])
+      }
+      "text-align" => Expected::Keywords(&["left", "start", "center", "right", "end", "justify"]),
+      "white-space" => Expected::Keywords(&["normal", "nowrap", "pre", "pre-wrap", "pre-line"]),
+      "overflow-wrap" | "word-wrap" => Expected::Keywords(&["normal", "anywhere", "break-word"]),
+      "text-overflow" => Expected::TextOverflow,
+      "grid-template-areas" => Expected::GridAreas,
+      "grid-area" => Expected::AreaName,
+      "overflow" | "overflow-x" | "overflow-y" => {
+        Expected::Keywords(&["visible", "hidden", "clip", "scroll", "auto"])
+      }
+      "scrollbar-width" => Expected::Keywords(&["auto", "thin", "none"]),
+      "scrollbar-color" => Expected::ScrollbarColor,
+      _ => return None,
+    })
+  }
+
+  /// Suggest a fix for `value`
+  fn suggest(&self, value: &str) -> String {
+    match self {
+      Expected::Keywords(keywords) => match closest(value, keywords) {
+        Some(keyword) => format!("did you mean `{keyword}`?"),
+        None => format!("expected one of {}", quoted_list(keywords)),
+      },
+      Expected::Color => match closest(&value.to_ascii_lowercase(), COLOR_NAMES) {
+        Some(name) => format!("did you mean `{name}`?"),
+        None => {
+          "expected a hex color such as `#ff8800` or a named color such as `blue`".to_string()
+        }
+      },
+      Expected::Extent => match closest(value, CONTENT_SIZES) {
+        Some(keyword) => format!("did you mean `{keyword}`?"),
+        None if value.ends_with("px") => Expected::Size.suggest(value),
+        None => format!(
+          "expected a cell count, a percentage, `calc()` or one of {}",
+          quoted_list(CONTENT_SIZES)
+        ),
+      },
+      Expected::Size | Expected::Cells => match value.strip_suffix("px") {
+        Some(cells) if cells.parse::<u16>().is_ok() => {
+          format!("did you mean `{cells}`? sizes are measured in terminal cells")
+        }
+        _ if matches!(self, Expected::Size) => {
+          "expected a cell count, a percentage, `auto` or `calc()`".to_string()
+        }
+        _ => "expected a single cell count such as `1`".to_string(),
+      },
+      Expected::Opacity => "expected a number from 0 to 1 or a percentage".to_string(),
+      Expected::ZIndex => "expected an integer or `auto`".to_string(),
+      Expected::TextOverflow => "expected `clip`, `ellipsis` or a quoted string".to_string(),
+      Expected::GridAreas => match GridAreas::parse(value) {
+        Err(warning) => warning.suggestion.unwrap_or(warning.message),
+        Ok(_) => "expected quoted rows of area names or `none`".to_string(),
+      },
+      Expected::AreaName => {
+        "expected the name of an area in the container's `grid-template-areas`".to_string()
+      }
+      Expected::ScrollbarColor => "expected `auto` or a thumb color and a track color".to_string(),
+      Expected::AspectRatio => "expected `auto`, a number or a ratio such as `16 / 9`".to_string(),
+    }
+  }
+}
+
+/// Keywords a width or height takes besides lengths
+const CONTENT_SIZES: &[&str] = &["auto", "min-content", "max-content", "fit-content"];
+
+fn quoted_list(items: &[&str]) -> String {
+  items
+    .iter()
+    .map(|item| format!("`{item}`"))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// The candidate closest to `word` by edit distance, if it is close enough
+/// to be a likely typo
+fn closest<'a>(word: &str, candidates: &[&'a str]) -> Option<&'a str> {
+  let limit = (word.chars().count() / 3).clamp(1, 3);
+  candidates
+    .iter()
+    .map(|candidate| (edit_distance(word, candidate), *candidate))
+    .filter(|(distance, _)| *distance <= limit)
+    .min_by_key(|(distance, _)| *distance)
+    .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  let mut row: Vec<usize> = (0..=b.len()).collect();
+  for (i, a_char) in a.chars().enumerate() {
+    let mut diagonal = row[0];
+    row[0] = i + 1;
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = diagonal + usize::from(a_char != *b_char);
+      diagonal = row[j + 1];
+      row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+    }
+  }
+  row[b.len()]
+}
+
+/// Lints stylesheets against a default engine
+struct Linter {
+  engine: CssEngine,
+  diagnostics: Vec<CssDiagnostic>,
+  /// Files already linted, so shared imports are reported once
+  linted: Vec<PathBuf>,
+}
+
+impl Linter {
+  fn new() -> Self {
+    Self {
+      engine: CssEngine::new(),
+      diagnostics: Vec::new(),
+      linted: Vec::new(),
+    }
+  }
+
+  /// Lint parsed CSS whose `@import` rules are still unresolved, following
+  /// the imports relative to `file` when it is known
+  fn lint_sheet(&mut self, sheet: &Stylesheet, file: Option<&Path>, importing: &mut Vec<PathBuf>) {
+    let mut found: Vec<CssDiagnostic> = sheet.errors.iter().map(CssDiagnostic::from).collect();
+    for declaration in sheet.rules.iter().flat_map(|rule| &rule.declarations) {
+      found.extend(self.check_declaration(declaration));
+    }
+
+    if let Some(file) = file {
+      let base_dir = file.parent().unwrap_or(Path::new(""));
+      for import in &sheet.imports {
+        let path = base_dir.join(&import.url);
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        if importing.contains(&canonical) {
+          found.push(CssDiagnostic::from(
+            &import.error(format!("circular `@import` of `{}`", import.url)),
+          ));
+        } else if !self.linted.contains(&canonical) {
+          match std::fs::read_to_string(&path) {
+            Ok(css) => {
+              importing.push(canonical);
+              self.lint_file_source(&css, &path, importing);
+              importing.pop();
+            }
+            Err(e) => found.push(
+              CssDiagnostic::from(&import.error(format!("cannot import `{}`: {e}", import.url)))
+                .with_suggestion(Some(format!("no file at {}", path.display()))),
+            ),
+          }
+        }
+      }
+    }
+
+    found.sort_by_key(|diagnostic| (diagnostic.location.line, diagnostic.location.column));
+    for diagnostic in &mut found {
+      diagnostic.file = file.map(Path::to_path_buf);
+    }
+    self.diagnostics.extend(found);
+  }
+
+  fn lint_file_source(&mut self, css: &str, path: &Path, importing: &mut Vec<PathBuf>) {
+    self
+      .linted
+      .push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+    let sheet = parser::parse_stylesheet(css);
+    self.lint_sheet(&sheet, Some(path), importing);
+  }
+
+  fn check_declaration(&self, declaration: &Declaration) -> Option<CssDiagnostic> {
+    let property = declaration.property.as_str();
+    let value = declaration.value.as_str();
+    let location = declaration.location;
+
+    if property.starts_with("--") {
+      return None;
+    }
+    if TERMINAL_IGNORED_PROPERTIES.contains(&property) {
+      return Some(
+        CssDiagnostic::new(
+          DiagnosticKind::IgnoredProperty,
+          format!("`{property}` has no effect in a terminal"),
+          location,
+        )
+        .with_suggestion(Some("remove the declaration".to_string())),
+      );
+    }
+    if UNSUPPORTED_PROPERTIES.contains(&property) {
+      return Some(CssDiagnostic::new(
+        DiagnosticKind::UnsupportedProperty,
+        format!("`{property}` is not supported by the CSS engine and is ignored"),
+        location,
+      ));
+    }
+    if !SUPPORTED_PROPERTIES.contains(&property) {
+      let suggestion =
+        closest(property, SUPPORTED_PROPERTIES).map(|known| format!("did you mean `{known}`?"));
+      return Some(
+        CssDiagnostic::new(
+          DiagnosticKind::UnknownProperty,
+          format!("unknown property `{property}`"),
+          location,
+        )
+        .with_suggestion(suggestion),
+      );
+    }
+
+    // Values with `var()` are only known once the custom properties resolve
+    if value.contains("var(") || CssWideKeyword::parse(value).is_some() {
+      return None;
+    }
+    let mut scratch = ComputedStyles::default();
+    if self.engine.apply_declaration(&mut scratch, property, value) {
+      return None;
+    }
+    Some(
+      CssDiagnostic::new(
+        DiagnosticKind::InvalidValue,
+        format!("invalid value `{value}` for `{property}`"),
+        location,
+      )
+      .with_suggestion(Expected::of(property).map(|expected| expected.suggest(value))),
+    )
+  }
+}
+
+impl CssEngine {
+  /// Check CSS text and report syntax errors, unsupported selectors,
+  /// unknown properties and invalid values in source order.
+  ///
+  /// `@import` rules are not followed; use [`CssEngine::validate_css_file`]
+  /// to lint a stylesheet together with the files it imports.
+  pub fn validate_css(css: &str) -> Vec<CssDiagnostic> {
+    let mut linter = Linter::new();
+    let sheet = parser::parse_stylesheet(css);
+    linter.lint_sheet(&sheet, None, &mut Vec::new());
+    linter.diagnostics
+  }
+
+  /// Check a stylesheet file and every file it imports; each diagnostic
+  /// names the file it was found in
+  pub fn validate_css_file<P: AsRef<Path>>(path: P) -> Result<Vec<CssDiagnostic>> {
+    let path = path.as_ref();
+    let css = std::fs::read_to_string(path).map_err(|e| {
+      TuiError::component(format!("Failed to read stylesheet {}: {e}", path.display()))
+    })?;
+    let mut linter = Linter::new();
+    let mut importing = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+    linter.lint_file_source(&css, path, &mut importing);
+    Ok(linter.diagnostics)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lint(css: &str) -> Vec<CssDiagnostic> {
+    CssEngine::validate_css(css)
+  }
+
+  #[test]
+  fn test_valid_css_has_no_diagnostics() {
+    let css =
+      ":root { --gap: 2 }\n.a { padding: var(--gap); color: #ff0000; width: calc(100% - 2) }\n\
+               .b { display: inherit; text-overflow: \"…\" }";
+    assert_eq!(lint(css), Vec::new());
+  }
+
+  #[test]
+  fn test_unknown_property_suggests_closest() {
+    let diagnostics = lint(".a {\n  color: red;\n  paddng: 1;\n}");
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.kind, DiagnosticKind::UnknownProperty);
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.location, SourceLocation { line: 3, column: 3 });
+    assert_eq!(
+      diagnostic.suggestion.as_deref(),
+      Some("did you mean `padding`?")
+    );
+    assert_eq!(
+      diagnostic.to_string(),
+      "3:3: warning: unknown property `paddng` (did you mean `padding`?)"
+    );
+  }
+
+  #[test]
+  fn test_invalid_values() {
+    let diagnostics = lint(
+      ".a { display: blok; color: blu; width: 10px; font-weight: 900; max-width: fit-contnet; \
+       aspect-ratio: wide; color: var(--x) }",
+    );
+    let suggestions: Vec<_> = diagnostics
+      .iter()
+      .map(|d| (d.kind, d.suggestion.as_deref().unwrap_or("")))
+      .collect();
+    assert_eq!(
+      suggestions,
+      vec![
+        (DiagnosticKind::InvalidValue, "did you mean `block`?"),
+        (DiagnosticKind::InvalidValue, "did you mean `blue`?"),
+        (
+          DiagnosticKind::InvalidValue,
+          "did you mean `10`? sizes are measured in terminal cells"
+        ),
+        (
+          DiagnosticKind::InvalidValue,
+          "expected one of `normal`, `bold`, `lighter`"
+        ),
+        (DiagnosticKind::InvalidValue, "did you mean `fit-content`?"),
+        (
+          DiagnosticKind::InvalidValue,
+          "expected `auto`, a number or a ratio such as `16 / 9`"
+        ),
+      ]
+    );
+    assert!(diagnostics.iter().all(CssDiagnostic::is_error));
+  }
+
+  #[test]
+  fn test_grid_area_values() {
+    let diagnostics = lint(
+      ".shell { display: grid; grid-template-areas: \"head head\" \"side main\" }\n\
+       .main { grid-area: main }\n\
+       .bad { grid-template-areas: \"a a\" \"a b\"; grid-area: 1 / 2 }",
+    );
+    let suggestions: Vec<_> = diagnostics
+      .iter()
+      .map(|d| {
+        (
+          d.kind,
+          d.location.line,
+          d.suggestion.as_deref().unwrap_or(""),
+        )
+      })
+      .collect();
+    assert_eq!(
+      suggestions,
+      vec![
+        (
+          DiagnosticKind::InvalidValue,
+          3,
+          "Name the same cells in every row an area spans, or split it into two areas."
+        ),
+        (
+          DiagnosticKind::InvalidValue,
+          3,
+          "expected the name of an area in the container's `grid-template-areas`"
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_selector_and_terminal_diagnostics() {
+    let diagnostics = lint(
+      ".a > { color: red }\n.b { font-size: 12px; justify-content: center }\n@keyframes spin { }",
+    );
+    let kinds: Vec<_> = diagnostics
+      .iter()
+      .map(|d| (d.kind, d.location.line))
+      .collect();
+    assert_eq!(
+      kinds,
+      vec![
+        (DiagnosticKind::UnsupportedSelector, 1),
+        (DiagnosticKind::IgnoredProperty, 2),
+        (DiagnosticKind::UnsupportedProperty, 2),
+        (DiagnosticKind::UnsupportedAtRule, 3),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_file_diagnostics_follow_imports() {
+    let dir = std::env::temp_dir().join(format!("rtui-lint-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let main = dir.join("main.css");
+    let base = dir.join("base.css");
+    std::fs::write(
+      &main,
+      "@import \"base.css\";\n@import \"missing.css\";\n.a { colr: red }",
+    )
+    .unwrap();
+    std::fs::write(&base, "@import \"main.css\";\n.b { display: gird }").unwrap();
+
+    let diagnostics = CssEngine::validate_css_file(&main).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let found: Vec<_> = diagnostics
+      .iter()
+      .map(|d| {
+        (
+          d.file
+            .as_ref()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap(),
+          d.location.line,
+          d.kind,
+        )
+      })
+      .collect();
+    assert_eq!(
+      found,
+      vec![
+        ("base.css", 1, DiagnosticKind::Import),
+        ("base.css", 2, DiagnosticKind::InvalidValue),
+        ("main.css", 2, DiagnosticKind::Import),
+        ("main.css", 3, DiagnosticKind::UnknownProperty),
+      ]
+    );
+  }
+}
@@ -0,0 +1,510 @@
+//! Lookup structures that keep restyling proportional to what can match
+//!
+//! - [`RuleIndex`] buckets every selector by the id, class or tag its
+//!   rightmost compound requires, so an element is only tested against rules
+//!   that could match it.
+//! - [`AncestorFilter`] is a bloom filter of the ids, classes and tags of an
+//!   element's ancestors. Descendant and child selectors needing an ancestor
+//!   that is missing from it are rejected without walking up the tree.
+//! - [`InvalidationSets`] record where selectors mention each class, id,
+//!   attribute and state, so a change on one element restyles only the
+//!   elements the change can reach.
+
+use super::selector::{PseudoElement, Selector, StatePseudoClass};
+use super::{Stylesheet, SCOPE_ATTRIBUTE};
+use crate::components::Element;
+use std::collections::HashMap;
+
+/// Ancestor hashes kept per selector; any further ones are left to matching
+const MAX_ANCESTOR_HASHES: usize = 4;
+
+/// Size of [`AncestorFilter`] in 64-bit words
+const FILTER_WORDS: usize = 16;
+
+/// A selector of a loaded rule, with what is needed to look it up
+#[derive(Debug, Clone, Copy)]
+pub(super) struct IndexedSelector {
+  pub sheet: usize,
+  pub rule: usize,
+  pub selector: usize,
+  pseudo: Option<PseudoElement>,
+  /// Hashes of ids, classes and tags that some ancestor must have; unused
+  /// slots are 0
+  ancestor_hashes: [u32; MAX_ANCESTOR_HASHES],
+}
+
+/// Selectors of all loaded rules bucketed by what their subject requires
+#[derive(Debug, Default)]
+pub(super) struct RuleIndex {
+  by_id: HashMap<String, Vec<IndexedSelector>>,
+  by_class: HashMap<String, Vec<IndexedSelector>>,
+  /// Keyed by the lowercase tag, since tags match case-insensitively
+  by_tag: HashMap<String, Vec<IndexedSelector>>,
+  universal: Vec<IndexedSelector>,
+}
+
+impl RuleIndex {
+  pub fn new(stylesheets: &[Stylesheet]) -> Self {
+    let mut index = Self::default();
+    for (sheet_index, sheet) in stylesheets.iter().enumerate() {
+      for (rule_index, rule) in sheet.rules.iter().enumerate() {
+        for (selector_index, selector) in rule.selectors.iter().enumerate() {
+          let mut hashes = Vec::new();
+          collect_ancestor_hashes(selector, &mut hashes);
+          let mut ancestor_hashes = [0; MAX_ANCESTOR_HASHES];
+          for (slot, hash) in ancestor_hashes.iter_mut().zip(hashes) {
+            *slot = hash;
+          }
+
+          let entry = IndexedSelector {
+            sheet: sheet_index,
+            rule: rule_index,
+            selector: selector_index,
+            pseudo: pseudo_element(subject(selector)),
+            ancestor_hashes,
+          };
+          let parts = compound_parts(subject(selector));
+          let bucket = if let Some(id) = parts.iter().find_map(as_id) {
+            index.by_id.entry(id.to_string()).or_default()
+          } else if let Some(class) = parts.iter().find_map(as_class) {
+            index.by_class.entry(class.to_string()).or_default()
+          } else if let Some(tag) = parts.iter().find_map(as_tag) {
+            index.by_tag.entry(tag.to_ascii_lowercase()).or_default()
+          } else {
+            &mut index.universal
+          };
+          bucket.push(entry);
+        }
+      }
+    }
+    index
+  }
+
+  /// Selectors that may match `element`, or its `pseudo` box, in source
+  /// order. Those needing an ancestor missing from `ancestors` are left out.
+  pub fn candidates(
+    &self,
+    element: &Element,
+    pseudo: Option<PseudoElement>,
+    ancestors: Option<&AncestorFilter>,
+  ) -> Vec<IndexedSelector> {
+    let by_id = element.id.as_ref().and_then(|id| self.by_id.get(id));
+    let by_class = element
+      .classes
+      .iter()
+      .filter_map(|class| self.by_class.get(class));
+    let by_tag = self.by_tag.get(&element.tag.to_ascii_lowercase());
+
+    let mut candidates: Vec<IndexedSelector> = by_id
+      .into_iter()
+      .chain(by_class)
+      .chain(by_tag)
+      .chain(std::iter::once(&self.universal))
+      .flatten()
+      .filter(|entry| entry.pseudo == pseudo)
+      .filter(|entry| {
+        ancestors.is_none_or(|filter| filter.might_contain_all(&entry.ancestor_hashes))
+      })
+      .copied()
+      .collect();
+    candidates.sort_unstable_by_key(|entry| (entry.sheet, entry.rule, entry.selector));
+    // An element listing a class twice finds its bucket twice
+    candidates.dedup_by_key(|entry| (entry.sheet, entry.rule, entry.selector));
+    candidates
+  }
+}
+
+/// Bloom filter over the ids, classes and tags of an element's ancestors.
+///
+/// It may claim an ancestor exists when it does not, but never the reverse,
+/// so it can only reject selectors that would fail to match anyway.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct AncestorFilter {
+  bits: [u64; FILTER_WORDS],
+}
+
+impl AncestorFilter {
+  /// The filter for the children of `element`, given the filter for `element`
+  pub fn with_parent(&self, element: &Element) -> Self {
+    let mut filter = *self;
+    filter.insert(tag_hash(&element.tag));
+    if let Some(id) = &element.id {
+      filter.insert(id_hash(id));
+    }
+    for class in &element.classes {
+      filter.insert(class_hash(class));
+    }
+    filter
+  }
+
+  fn insert(&mut self, hash: u32) {
+    for bit in filter_bits(hash) {
+      self.bits[bit / 64] |= 1 << (bit % 64);
+    }
+  }
+
+  fn might_contain(&self, hash: u32) -> bool {
+    filter_bits(hash)
+      .iter()
+      .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+  }
+
+  fn might_contain_all(&self, hashes: &[u32]) -> bool {
+    hashes
+      .iter()
+      .take_while(|hash| **hash != 0)
+      .all(|hash| self.might_contain(*hash))
+  }
+}
+
+/// The two filter bits set for `hash`
+fn filter_bits(hash: u32) -> [usize; 2] {
+  let mask = (FILTER_WORDS * 64 - 1) as u32;
+  [(hash & mask) as usize, ((hash >> 16) & mask) as usize]
+}
+
+/// What a change to a class, id, attribute or state of an element can restyle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct Invalidation {
+  /// The element itself
+  pub element: bool,
+  /// Every descendant of the element
+  pub descendants: bool,
+  /// The later siblings of the element and their descendants
+  pub siblings: bool,
+}
+
+impl std::ops::BitOrAssign for Invalidation {
+  fn bitor_assign(&mut self, other: Self) {
+    self.element |= other.element;
+    self.descendants |= other.descendants;
+    self.siblings |= other.siblings;
+  }
+}
+
+/// Where a simple selector sits relative to the element a selector matches
+#[derive(Clone, Copy)]
+enum Position {
+  Subject,
+  Ancestor,
+  Sibling,
+}
+
+impl Position {
+  fn invalidation(self) -> Invalidation {
+    Invalidation {
+      element: matches!(self, Position::Subject),
+      descendants: matches!(self, Position::Ancestor),
+      siblings: matches!(self, Position::Sibling),
+    }
+  }
+}
+
+/// For every class, id, attribute and state mentioned by a loaded selector,
+/// which elements a change to it can restyle
+#[derive(Debug, Default)]
+pub(super) struct InvalidationSets {
+  classes: HashMap<String, Invalidation>,
+  ids: HashMap<String, Invalidation>,
+  attributes: HashMap<String, Invalidation>,
+  states: HashMap<StatePseudoClass, Invalidation>,
+}
+
+impl InvalidationSets {
+  pub fn new(stylesheets: &[Stylesheet]) -> Self {
+    let mut sets = Self::default();
+    let selectors = stylesheets
+      .iter()
+      .flat_map(|sheet| &sheet.rules)
+      .flat_map(|rule| &rule.selectors);
+    for selector in selectors {
+      sets.add(selector, Position::Subject);
+    }
+    sets
+  }
+
+  fn add(&mut self, selector: &Selector, position: Position) {
+    let entry = match selector {
+      Selector::Class(class) => self.classes.entry(class.clone()).or_default(),
+      Selector::Id(id) => self.ids.entry(id.clone()).or_default(),
+      Selector::Attribute { name, .. } => self.attributes.entry(name.clone()).or_default(),
+      Selector::State(state) => self.states.entry(*state).or_default(),
+      Selector::Compound(parts) | Selector::Not(parts) => {
+        for part in parts {
+          self.add(part, position);
+        }
+        return;
+      }
+      // Ancestors of the ancestors or siblings of an element are its
+      // ancestors too
+      Selector::Descendant(left, right) | Selector::Child(left, right) => {
+        self.add(right, position);
+        self.add(left, Position::Ancestor);
+        return;
+      }
+      Selector::AdjacentSibling(left, right) | Selector::GeneralSibling(left, right) => {
+        self.add(right, position);
+        self.add(left, Position::Sibling);
+        return;
+      }
+      _ => return,
+    };
+    *entry |= position.invalidation();
+  }
+
+  /// What needs restyling after an element changed from `old` to `new`.
+  ///
+  /// Class, id and attribute changes always restyle the element itself, as
+  /// inline styles, `attr()`, presentational attributes and utility classes
+  /// read them outside of selectors.
+  pub fn for_change(&self, old: &Element, new: &Element) -> Invalidation {
+    let mut invalidation = Invalidation::default();
+    let attribute_changed = |name: &str, invalidation: &mut Invalidation| {
+      invalidation.element = true;
+      if let Some(found) = self.attributes.get(name) {
+        *invalidation |= *found;
+      }
+      // Scoped stylesheets apply below the scope root
+      if name == SCOPE_ATTRIBUTE {
+        invalidation.descendants = true;
+      }
+    };
+
+    let added = new
+      .classes
+      .iter()
+      .filter(|class| !old.classes.contains(class));
+    let removed = old
+      .classes
+      .iter()
+      .filter(|class| !new.classes.contains(class));
+    let mut classes_changed = false;
+    for class in added.chain(removed) {
+      classes_changed = true;
+      if let Some(found) = self.classes.get(class) {
+        invalidation |= *found;
+      }
+    }
+    if classes_changed {
+      attribute_changed("class", &mut invalidation);
+    }
+
+    if old.id != new.id {
+      for id in [&old.id, &new.id].into_iter().flatten() {
+        if let Some(found) = self.ids.get(id) {
+          invalidation |= *found;
+        }
+      }
+      attribute_changed("id", &mut invalidation);
+    }
+
+    let changed = old
+      .attributes
+      .iter()
+      .filter(|(name, value)| new.attributes.get(*name) != Some(value))
+      .chain(
+        new
+          .attributes
+          .iter()
+          .filter(|(name, _)| !old.attributes.contains_key(*name)),
+      );
+    for (name, _) in changed {
+      attribute_changed(name, &mut invalidation);
+    }
+    if old.disabled != new.disabled {
+      if let Some(found) = self.attributes.get("disabled") {
+        invalidation |= *found;
+      }
+    }
+
+    for (state, found) in &self.states {
+      if state.matches(old) != state.matches(new) {
+        invalidation |= *found;
+      }
+    }
+    invalidation
+  }
+}
+
+/// The rightmost compound of a selector, which the element itself must match
+fn subject(selector: &Selector) -> &Selector {
+  match selector {
+    Selector::Descendant(_, right)
+    | Selector::Child(_, right)
+    | Selector::AdjacentSibling(_, right)
+    | Selector::GeneralSibling(_, right) => subject(right),
+    other => other,
+  }
+}
+
+fn compound_parts(selector: &Selector) -> &[Selector] {
+  match selector {
+    Selector::Compound(parts) => parts,
+    other => std::slice::from_ref(other),
+  }
+}
+
+fn pseudo_element(subject: &Selector) -> Option<PseudoElement> {
+  compound_parts(subject).iter().find_map(|part| match part {
+    Selector::PseudoElement(pseudo) => Some(*pseudo),
+    _ => None,
+  })
+}
+
+fn as_id(selector: &Selector) -> Option<&str> {
+  match selector {
+    Selector::Id(id) => Some(id),
+    _ => None,
+  }
+}
+
+fn as_class(selector: &Selector) -> Option<&str> {
+  match selector {
+    Selector::Class(class) => Some(class),
+    _ => None,
+  }
+}
+
+fn as_tag(selector: &Selector) -> Option<&str> {
+  match selector {
+    Selector::Tag(tag) => Some(tag),
+    _ => None,
+  }
+}
+
+/// Collect the hashes of the ids, classes and tags that ancestors of an
+/// element matching `selector` must have
+fn collect_ancestor_hashes(selector: &Selector, hashes: &mut Vec<u32>) {
+  match selector {
+    Selector::Descendant(left, right) | Selector::Child(left, right) => {
+      for part in compound_parts(subject(left)) {
+        match part {
+          Selector::Id(id) => hashes.push(id_hash(id)),
+          Selector::Class(class) => hashes.push(class_hash(class)),
+          Selector::Tag(tag) => hashes.push(tag_hash(tag)),
+          _ => {}
+        }
+      }
+      collect_ancestor_hashes(left, hashes);
+      collect_ancestor_hashes(right, hashes);
+    }
+    // Siblings share their ancestors with the subject
+    Selector::AdjacentSibling(left, right) | Selector::GeneralSibling(left, right) => {
+      collect_ancestor_hashes(left, hashes);
+      collect_ancestor_hashes(right, hashes);
+    }
+    _ => {}
+  }
+}
+
+fn tag_hash(tag: &str) -> u32 {
+  hash(b't', tag.bytes().map(|byte| byte.to_ascii_lowercase()))
+}
+
+fn class_hash(class: &str) -> u32 {
+  hash(b'.', class.bytes())
+}
+
+fn id_hash(id: &str) -> u32 {
+  hash(b'#', id.bytes())
+}
+
+/// FNV-1a over a kind marker and a name; never 0, which marks unused slots
+fn hash(kind: u8, name: impl Iterator<Item = u8>) -> u32 {
+  let mut hash: u32 = 0x811c_9dc5;
+  for byte in std::iter::once(kind).chain(name) {
+    hash ^= u32::from(byte);
+    hash = hash.wrapping_mul(0x0100_0193);
+  }
+  hash.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::css::parser::parse_stylesheet;
+
+  fn candidate_rules(
+    index: &RuleIndex,
+    element: &Element,
+    ancestors: Option<&AncestorFilter>,
+  ) -> Vec<usize> {
+    index
+      .candidates(element, None, ancestors)
+      .iter()
+      .map(|entry| entry.rule)
+      .collect()
+  }
+
+  #[test]
+  fn test_rules_are_bucketed_by_subject() {
+    let sheet = parse_stylesheet(
+      "#main { } .btn { } BUTTON { } [data-x] { } .btn.primary { } .panel .btn { } \
+       .btn::before { } button#main.btn { }",
+    );
+    let index = RuleIndex::new(std::slice::from_ref(&sheet));
+
+    let button = Element::with_tag("button").class("btn").build();
+    assert_eq!(candidate_rules(&index, &button, None), vec![1, 2, 3, 4, 5]);
+    let main = Element::with_tag("div").id("main").build();
+    assert_eq!(candidate_rules(&index, &main, None), vec![0, 3, 7]);
+
+    let before = index.candidates(&button, Some(PseudoElement::Before), None);
+    assert_eq!(
+      before.iter().map(|entry| entry.rule).collect::<Vec<_>>(),
+      vec![6]
+    );
+  }
+
+  #[test]
+  fn test_ancestor_filter_rejects_missing_ancestors() {
+    let sheet = parse_stylesheet(".panel .btn { } .panel > .btn { } .toolbar .btn { } .btn { }");
+    let index = RuleIndex::new(std::slice::from_ref(&sheet));
+    let panel = Element::with_tag("div").class("panel").build();
+    let button = Element::with_tag("button").class("btn").build();
+
+    let root = AncestorFilter::default();
+    assert_eq!(candidate_rules(&index, &button, Some(&root)), vec![3]);
+    let inside_panel = root.with_parent(&panel);
+    assert_eq!(
+      candidate_rules(&index, &button, Some(&inside_panel)),
+      vec![0, 1, 3]
+    );
+  }
+
+  #[test]
+  fn test_invalidation_sets_follow_selector_positions() {
+    let sheet = parse_stylesheet(
+      ".open .item { } .selected + .item { } .item:hover { } [data-mode=dense] .row { }",
+    );
+    let sets = InvalidationSets::new(std::slice::from_ref(&sheet));
+    let element = Element::with_tag("div").class("item").build();
+
+    let mut opened = element.clone();
+    opened.classes.push("open".to_string());
+    let change = sets.for_change(&element, &opened);
+    assert!(change.element && change.descendants && !change.siblings);
+
+    let mut selected = element.clone();
+    selected.classes.push("selected".to_string());
+    let change = sets.for_change(&element, &selected);
+    assert!(change.siblings && !change.descendants);
+
+    // Only states that selectors mention invalidate anything
+    let mut hovered = element.clone();
+    hovered.hovered = true;
+    assert_eq!(
+      sets.for_change(&element, &hovered),
+      Invalidation {
+        element: true,
+        ..Invalidation::default()
+      }
+    );
+    let mut focused = element.clone();
+    focused.focused = true;
+    assert_eq!(sets.for_change(&element, &focused), Invalidation::default());
+
+    let dense = Element::with_tag("div").attr("data-mode", "dense").build();
+    assert!(sets.for_change(&element, &dense).descendants);
+  }
+}
@@ -0,0 +1,470 @@
+//! Terminal-aware `@media` queries
+//!
+//! Queries are evaluated against a [`MediaEnvironment`] describing the
+//! terminal: its size in cells, its [`ColorSupport`] level and the user's
+//! color scheme and motion preferences. Size conditions compile to a layout
+//! [`Breakpoint`], so `@media (min-width: 120)` and the layout engine's
+//! breakpoints agree on what a width range means.
+
+use crate::layout::Breakpoint;
+use crate::themes::color_support::{global_color_support, ColorSupport};
+use cssparser::{match_ignore_ascii_case, ParseError, Parser, ParserInput, Token};
+
+/// A comma-separated list of media queries; it matches if any query matches
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQueryList {
+  pub queries: Vec<MediaQuery>,
+}
+
+/// A single media query such as `screen and (min-width: 80) and (color)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+  /// Set by a leading `not`, which negates the whole query
+  pub negated: bool,
+  /// Lowercased media type, `None` when the query only has conditions
+  pub media_type: Option<String>,
+  /// Width and height conditions, combined into one range
+  pub breakpoint: Breakpoint,
+  /// Remaining conditions; all of them must match
+  pub features: Vec<MediaFeature>,
+}
+
+/// A media feature condition other than width and height
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaFeature {
+  /// `(color)`: bits per color component
+  Color(ValueRange),
+  /// `(color-depth)`: total bits per color
+  ColorDepth(ValueRange),
+  /// `(colors)`: number of distinct colors the terminal can show
+  Colors(ValueRange),
+  /// `(monochrome)`: bits per pixel on a terminal without color, otherwise 0
+  Monochrome(ValueRange),
+  PrefersColorScheme(ColorScheme),
+  /// `true` for `reduce`, `false` for `no-preference`
+  PrefersReducedMotion(bool),
+}
+
+/// Inclusive range produced by a plain, `min-` or `max-` feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValueRange {
+  pub min: u32,
+  pub max: u32,
+}
+
+impl ValueRange {
+  pub fn contains(&self, value: u32) -> bool {
+    value >= self.min && value <= self.max
+  }
+}
+
+/// Preferred color scheme reported to `(prefers-color-scheme)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+  Light,
+  #[default]
+  Dark,
+}
+
+/// The terminal properties media queries are evaluated against
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaEnvironment {
+  /// Terminal width in cells
+  pub width: u16,
+  /// Terminal height in cells
+  pub height: u16,
+  pub color_support: ColorSupport,
+  pub color_scheme: ColorScheme,
+  pub reduced_motion: bool,
+}
+
+impl Default for MediaEnvironment {
+  fn default() -> Self {
+    Self {
+      width: 80,
+      height: 24,
+      color_support: ColorSupport::Truecolor,
+      color_scheme: ColorScheme::Dark,
+      reduced_motion: false,
+    }
+  }
+}
+
+impl MediaEnvironment {
+  /// Describe the current terminal at the given size.
+  ///
+  /// Color support comes from [`global_color_support::detect`]. The color
+  /// scheme is guessed from `COLORFGBG` (set by rxvt, Konsole and others) and
+  /// defaults to dark; `REACTIVE_TUI_REDUCED_MOTION=1` requests reduced motion.
+  pub fn detect(width: u16, height: u16) -> Self {
+    let color_scheme = std::env::var("COLORFGBG")
+      .ok()
+      .and_then(|value| color_scheme_from_colorfgbg(&value))
+      .unwrap_or_default();
+    let reduced_motion = std::env::var("REACTIVE_TUI_REDUCED_MOTION")
+      .is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"));
+
+    Self {
+      width,
+      height,
+      color_support: global_color_support::detect(),
+      color_scheme,
+      reduced_motion,
+    }
+  }
+
+  /// `(color)`, `(color-depth)`, `(colors)` and `(monochrome)` values for the
+  /// color support level
+  fn color_metrics(&self) -> (u32, u32, u32, u32) {
+    match self.color_support {
+      ColorSupport::Truecolor => (8, 24, 1 << 24, 0),
+      ColorSupport::Ansi256 => (2, 8, 256, 0),
+      ColorSupport::Ansi16 => (1, 4, 16, 0),
+      // The 256-color palette has a 24-step gray ramp
+      ColorSupport::Grayscale => (0, 0, 0, 5),
+      ColorSupport::NoColor => (0, 0, 0, 1),
+    }
+  }
+}
+
+/// `COLORFGBG` is `fg;bg` (sometimes `fg;default;bg`) with ANSI color indices
+fn color_scheme_from_colorfgbg(value: &str) -> Option<ColorScheme> {
+  let background: u8 = value.rsplit(';').next()?.parse().ok()?;
+  Some(match background {
+    7 | 9..=15 => ColorScheme::Light,
+    _ => ColorScheme::Dark,
+  })
+}
+
+impl MediaQueryList {
+  /// Parse the prelude of an `@media` rule
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    parser
+      .parse_entirely(parse_media_query_list)
+      .map_err(|error| match error.kind {
+        cssparser::ParseErrorKind::Custom(message) => message,
+        _ => format!("invalid media query `{}`", text.trim()),
+      })
+  }
+
+  pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+    self.queries.iter().any(|query| query.matches(environment))
+  }
+}
+
+impl MediaQuery {
+  pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+    let type_matches = self
+      .media_type
+      .as_deref()
+      .is_none_or(|media_type| matches!(media_type, "all" | "screen" | "tty"));
+    let matched = type_matches
+      && self
+        .breakpoint
+        .matches(environment.width, environment.height)
+      && self
+        .features
+        .iter()
+        .all(|feature| feature.matches(environment));
+    matched != self.negated
+  }
+}
+
+impl MediaFeature {
+  pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+    let (color, color_depth, colors, monochrome) = environment.color_metrics();
+    match *self {
+      MediaFeature::Color(range) => range.contains(color),
+      MediaFeature::ColorDepth(range) => range.contains(color_depth),
+      MediaFeature::Colors(range) => range.contains(colors),
+      MediaFeature::Monochrome(range) => range.contains(monochrome),
+      MediaFeature::PrefersColorScheme(scheme) => environment.color_scheme == scheme,
+      MediaFeature::PrefersReducedMotion(reduce) => environment.reduced_motion == reduce,
+    }
+  }
+}
+
+/// Parse a media query list from an `@media` prelude
+pub(crate) fn parse_media_query_list<'i>(
+  input: &mut Parser<'i, '_>,
+) -> Result<MediaQueryList, ParseError<'i, String>> {
+  let queries = input.parse_comma_separated(parse_media_query)?;
+  Ok(MediaQueryList { queries })
+}
+
+fn parse_media_query<'i>(input: &mut Parser<'i, '_>) -> Result<MediaQuery, ParseError<'i, String>> {
+  let mut query = MediaQuery {
+    negated: false,
+    media_type: None,
+    breakpoint: Breakpoint {
+      name: "media".to_string(),
+      min_width: None,
+      max_width: None,
+      min_height: None,
+      max_height: None,
+    },
+    features: Vec::new(),
+  };
+
+  let location = input.current_source_location();
+  let mut needs_condition = true;
+  if let Ok(word) = input.try_parse(|input| input.expect_ident_cloned()) {
+    let word = word.to_ascii_lowercase();
+    let media_type = if word == "not" || word == "only" {
+      query.negated = word == "not";
+      match input.try_parse(|input| input.expect_ident_cloned()) {
+        Ok(media_type) => Some(media_type.to_ascii_lowercase()),
+        Err(_) if word == "not" => None,
+        Err(_) => {
+          return Err(
+            location.new_custom_error("`only` must be followed by a media type".to_string()),
+          )
+        }
+      }
+    } else {
+      Some(word)
+    };
+    if let Some(media_type) = media_type {
+      if media_type == "and" {
+        return Err(location.new_custom_error("missing media type before `and`".to_string()));
+      }
+      query.media_type = Some(media_type);
+      needs_condition = false;
+    }
+  }
+
+  if needs_condition {
+    parse_condition(input, &mut query)?;
+  }
+  while input
+    .try_parse(|input| input.expect_ident_matching("and"))
+    .is_ok()
+  {
+    parse_condition(input, &mut query)?;
+  }
+  Ok(query)
+}
+
+/// Parse one parenthesized `(feature)` or `(feature: value)` into `query`
+fn parse_condition<'i>(
+  input: &mut Parser<'i, '_>,
+  query: &mut MediaQuery,
+) -> Result<(), ParseError<'i, String>> {
+  input.expect_parenthesis_block()?;
+  input.parse_nested_block(|input| {
+    let location = input.current_source_location();
+    let name = input.expect_ident_cloned()?.to_ascii_lowercase();
+    let has_value = input.try_parse(|input| input.expect_colon()).is_ok();
+    let (prefix, feature) = match name.split_once('-') {
+      Some((prefix @ ("min" | "max"), feature)) => (Some(prefix), feature),
+      _ => (None, name.as_str()),
+    };
+    if prefix.is_some() && !has_value {
+      return Err(location.new_custom_error(format!("media feature `{name}` requires a value")));
+    }
+
+    match feature {
+      "width" | "height" => {
+        let range = parse_range(input, prefix, has_value)?;
+        let min = u16::try_from(range.min).unwrap_or(u16::MAX);
+        let max = u16::try_from(range.max).unwrap_or(u16::MAX);
+        let breakpoint = &mut query.breakpoint;
+        let (min_slot, max_slot) = if feature == "width" {
+          (&mut breakpoint.min_width, &mut breakpoint.max_width)
+        } else {
+          (&mut breakpoint.min_height, &mut breakpoint.max_height)
+        };
+        if min > 0 {
+          *min_slot = Some(min_slot.map_or(min, |current| current.max(min)));
+        }
+        if max < u16::MAX {
+          *max_slot = Some(max_slot.map_or(max, |current| current.min(max)));
+        }
+      }
+      "color" => {
+        let range = parse_range(input, prefix, has_value)?;
+        query.features.push(MediaFeature::Color(range));
+      }
+      "color-depth" => {
+        let range = parse_range(input, prefix, has_value)?;
+        query.features.push(MediaFeature::ColorDepth(range));
+      }
+      "colors" => {
+        let range = parse_range(input, prefix, has_value)?;
+        query.features.push(MediaFeature::Colors(range));
+      }
+      "monochrome" => {
+        let range = parse_range(input, prefix, has_value)?;
+        query.features.push(MediaFeature::Monochrome(range));
+      }
+      "prefers-color-scheme" if prefix.is_none() && has_value => {
+        let location = input.current_source_location();
+        let scheme = input.expect_ident_cloned()?;
+        let scheme = match_ignore_ascii_case! { &scheme,
+          "light" => ColorScheme::Light,
+          "dark" => ColorScheme::Dark,
+          _ => return Err(location.new_custom_error(format!("unknown color scheme `{scheme}`"))),
+        };
+        query.features.push(MediaFeature::PrefersColorScheme(scheme));
+      }
+      "prefers-reduced-motion" if prefix.is_none() => {
+        let reduce = if has_value {
+          let location = input.current_source_location();
+          let value = input.expect_ident_cloned()?;
+          match_ignore_ascii_case! { &value,
+            "reduce" => true,
+            "no-preference" => false,
+            _ => return Err(location.new_custom_error(format!("unknown motion preference `{value}`"))),
+          }
+        } else {
+          true
+        };
+        query.features.push(MediaFeature::PrefersReducedMotion(reduce));
+      }
+      _ => {
+        return Err(location.new_custom_error(format!("unsupported media feature `{name}`")));
+      }
+    }
+    Ok(())
+  })
+}
+
+/// Parse the value of a range feature; the boolean form means "non-zero".
+///
+/// Widths and heights may be written in `ch`, which is one cell.
+fn parse_range<'i>(
+  input: &mut Parser<'i, '_>,
+  prefix: Option<&str>,
+  has_value: bool,
+) -> Result<ValueRange, ParseError<'i, String>> {
+  if !has_value {
+    return Ok(ValueRange {
+      min: 1,
+      max: u32::MAX,
+    });
+  }
+
+  let location = input.current_source_location();
+  let value = match input.next()? {
+    Token::Number {
+      int_value: Some(value),
+      ..
+    } if *value >= 0 => *value as u32,
+    Token::Dimension {
+      int_value: Some(value),
+      unit,
+      ..
+    } if *value >= 0 && unit.eq_ignore_ascii_case("ch") => *value as u32,
+    token => return Err(location.new_unexpected_token_error(token.clone())),
+  };
+  Ok(match prefix {
+    Some("min") => ValueRange {
+      min: value,
+      max: u32::MAX,
+    },
+    Some(_) => ValueRange { min: 0, max: value },
+    None => ValueRange {
+      min: value,
+      max: value,
+    },
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn environment(width: u16, height: u16, color_support: ColorSupport) -> MediaEnvironment {
+    MediaEnvironment {
+      width,
+      height,
+      color_support,
+      ..MediaEnvironment::default()
+    }
+  }
+
+  fn matches(query: &str, environment: &MediaEnvironment) -> bool {
+    MediaQueryList::parse(query).unwrap().matches(environment)
+  }
+
+  #[test]
+  fn test_size_queries_use_breakpoints() {
+    let list = MediaQueryList::parse("(min-width: 120) and (max-height: 40)").unwrap();
+    let breakpoint = &list.queries[0].breakpoint;
+    assert_eq!(breakpoint.min_width, Some(120));
+    assert_eq!(breakpoint.max_height, Some(40));
+
+    let wide = environment(150, 40, ColorSupport::Truecolor);
+    let narrow = environment(100, 40, ColorSupport::Truecolor);
+    let tall = environment(150, 50, ColorSupport::Truecolor);
+    assert!(list.matches(&wide));
+    assert!(!list.matches(&narrow));
+    assert!(!list.matches(&tall));
+    assert!(matches("(width: 100)", &narrow));
+    assert!(matches("(max-width: 100ch)", &narrow));
+  }
+
+  #[test]
+  fn test_color_queries() {
+    let truecolor = environment(80, 24, ColorSupport::Truecolor);
+    let ansi256 = environment(80, 24, ColorSupport::Ansi256);
+    let mono = environment(80, 24, ColorSupport::NoColor);
+
+    assert!(matches("(color)", &ansi256));
+    assert!(!matches("(color)", &mono));
+    assert!(matches("(colors: 256)", &ansi256));
+    assert!(!matches("(colors: 256)", &truecolor));
+    assert!(matches("(min-colors: 256)", &truecolor));
+    assert!(matches("(min-color-depth: 24)", &truecolor));
+    assert!(!matches("(min-color-depth: 24)", &ansi256));
+    assert!(matches("not (color)", &mono));
+    assert!(matches("(monochrome)", &mono));
+    assert!(!matches("(monochrome)", &ansi256));
+  }
+
+  #[test]
+  fn test_preference_queries() {
+    let mut env = MediaEnvironment::default();
+    assert!(matches("(prefers-color-scheme: dark)", &env));
+    assert!(!matches("(prefers-reduced-motion)", &env));
+    assert!(matches("(prefers-reduced-motion: no-preference)", &env));
+
+    env.color_scheme = ColorScheme::Light;
+    env.reduced_motion = true;
+    assert!(matches("(prefers-color-scheme: light)", &env));
+    assert!(matches("(prefers-reduced-motion)", &env));
+  }
+
+  #[test]
+  fn test_media_types_and_lists() {
+    let env = MediaEnvironment::default();
+    assert!(matches("screen and (min-width: 40)", &env));
+    assert!(!matches("print", &env));
+    assert!(matches("print, (max-width: 100)", &env));
+    assert!(matches("not print", &env));
+    assert!(matches("only screen", &env));
+  }
+
+  #[test]
+  fn test_invalid_queries() {
+    assert!(MediaQueryList::parse("(orientation: landscape)")
+      .unwrap_err()
+      .contains("unsupported media feature"));
+    assert!(MediaQueryList::parse("(min-width)").is_err());
+    assert!(MediaQueryList::parse("(min-width: 50%)").is_err());
+    assert!(MediaQueryList::parse("(prefers-color-scheme: blue)").is_err());
+    assert!(MediaQueryList::parse("only").is_err());
+  }
+
+  #[test]
+  fn test_colorfgbg_scheme() {
+    assert_eq!(color_scheme_from_colorfgbg("15;0"), Some(ColorScheme::Dark));
+    assert_eq!(
+      color_scheme_from_colorfgbg("0;default;15"),
+      Some(ColorScheme::Light)
+    );
+    assert_eq!(color_scheme_from_colorfgbg("garbage"), None);
+  }
+}
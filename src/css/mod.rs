@@ -1,11 +1,35 @@
 //! Advanced CSS parsing and styling engine with component tree support
 
+mod diagnostics;
+mod index;
+mod media;
+mod parser;
+mod selector;
+pub(crate) mod values;
+
+pub use crate::layout::text::{OverflowWrap, TextAlign, TextOverflow, TextStyle, WhiteSpace};
+pub use diagnostics::{CssDiagnostic, DiagnosticKind, Severity};
+pub use media::{
+  ColorScheme, MediaEnvironment, MediaFeature, MediaQuery, MediaQueryList, ValueRange,
+};
+pub use selector::{
+  AttributeOperator, ElementContext, PseudoElement, Selector, Specificity, StatePseudoClass,
+};
+
 use crate::components::Element;
-use crate::error::Result;
-use crate::layout::{AlignItems, DisplayType, FlexDirection, JustifyContent, SizeValue, Spacing};
+use crate::error::{Result, TuiError};
+use crate::layout::grid::{template, GridAreas};
+use crate::layout::{
+  AlignItems, DisplayType, FlexDirection, JustifyContent, OverflowBehavior, PositionType,
+  ScrollbarWidth, SizeValue, Spacing,
+};
 use crate::rendering::RenderStyle;
 use crate::themes::colors::hex;
+use index::{AncestorFilter, Invalidation, InvalidationSets, RuleIndex};
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[cfg(not(target_family = "wasm"))]
 use crossterm::style::Color as CrosstermColor;
@@ -13,8 +37,14 @@ use crossterm::style::Color as CrosstermColor;
 #[cfg(target_family = "wasm")]
 use crate::compat::Color as CrosstermColor;
 
+/// Attribute marking the root element of a component's style scope
+pub const SCOPE_ATTRIBUTE: &str = "data-scope";
+
+/// Cascade rank of rules outside any `@layer`, which beat every layer
+const UNLAYERED: &[u32] = &[u32::MAX];
+
 /// CSS computed styles with both layout and visual properties
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyles {
   // Layout properties
   pub display: DisplayType,
@@ -30,6 +60,21 @@ pub struct ComputedStyles {
   pub min_height: SizeValue,
   pub max_width: SizeValue,
   pub max_height: SizeValue,
+  /// Width over height, both in cells; `None` for `auto`
+  pub aspect_ratio: Option<f32>,
+  pub position: PositionType,
+  /// Offsets of a positioned element; `auto` leaves the side unconstrained
+  pub top: SizeValue,
+  pub right: SizeValue,
+  pub bottom: SizeValue,
+  pub left: SizeValue,
+  /// Area names of each row of a grid container
+  pub grid_template_areas: Vec<Vec<String>>,
+  /// Named area a grid item is placed in
+  pub grid_area: Option<String>,
+  /// `auto` and `scroll` make the element a scroll container
+  pub overflow: OverflowBehavior,
+  pub scrollbar_width: ScrollbarWidth,
 
   // Visual properties
   pub color: Option<CrosstermColor>,
@@ -37,14 +82,36 @@ pub struct ComputedStyles {
   pub font_weight: FontWeight,
   pub font_style: FontStyle,
   pub text_decoration: Vec<TextDecoration>,
+  pub text_decoration_style: TextDecorationStyle,
+  /// Color of underlines and other lines; `None` uses the text color
+  pub text_decoration_color: Option<CrosstermColor>,
+  pub text_align: TextAlign,
+  pub white_space: WhiteSpace,
+  pub overflow_wrap: OverflowWrap,
+  pub text_overflow: TextOverflow,
   pub border_color: Option<CrosstermColor>,
   pub border_width: u16,
+  /// Colors of a scroll container's scrollbars; `None` uses the text color
+  pub scrollbar_color: Option<ScrollbarColor>,
+  /// How much the element covers what is below it, from 0.0 to 1.0
+  pub opacity: f32,
+  /// Stacking order; elements with a non-zero `z-index` or an `opacity`
+  /// below 1.0 are composited as their own layer
+  pub z_index: i32,
+  /// Specified `content` value; only used by `::before` and `::after`
+  pub content: Option<String>,
+
+  /// Custom properties (`--name: value`) in effect, including inherited ones.
+  /// Shared with the parent until the element declares its own.
+  pub custom_properties: Arc<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FontWeight {
   Normal,
   Bold,
+  /// Rendered as dim text
+  Lighter,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -58,6 +125,134 @@ pub enum TextDecoration {
   None,
   Underline,
   Strikethrough,
+  Overline,
+  Blink,
+}
+
+/// Colors of the thumb and the track of a scrollbar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollbarColor {
+  pub thumb: CrosstermColor,
+  pub track: CrosstermColor,
+}
+
+/// Shape of the lines drawn by `text-decoration`; `wavy` becomes a curly
+/// underline in terminals that support it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextDecorationStyle {
+  Solid,
+  Double,
+  Dotted,
+  Dashed,
+  Wavy,
+}
+
+impl TextDecorationStyle {
+  fn parse(value: &str) -> Option<Self> {
+    Some(match value {
+      "solid" => Self::Solid,
+      "double" => Self::Double,
+      "dotted" => Self::Dotted,
+      "dashed" => Self::Dashed,
+      "wavy" => Self::Wavy,
+      _ => return None,
+    })
+  }
+}
+
+impl From<TextDecorationStyle> for crate::rendering::UnderlineStyle {
+  fn from(style: TextDecorationStyle) -> Self {
+    match style {
+      TextDecorationStyle::Solid => Self::Single,
+      TextDecorationStyle::Double => Self::Double,
+      TextDecorationStyle::Dotted => Self::Dotted,
+      TextDecorationStyle::Dashed => Self::Dashed,
+      TextDecorationStyle::Wavy => Self::Curly,
+    }
+  }
+}
+
+/// Properties whose computed value passes from parent to child unless the
+/// child declares its own
+pub const INHERITED_PROPERTIES: &[&str] = &[
+  "color",
+  "font-weight",
+  "font-style",
+  "text-decoration",
+  "text-decoration-style",
+  "text-decoration-color",
+  "text-align",
+  "white-space",
+  "overflow-wrap",
+  "scrollbar-color",
+];
+
+/// Properties stylesheets and inline `style` attributes can set
+pub const SUPPORTED_PROPERTIES: &[&str] = &[
+  "display",
+  "flex-direction",
+  "width",
+  "height",
+  "min-width",
+  "min-height",
+  "max-width",
+  "max-height",
+  "aspect-ratio",
+  "position",
+  "top",
+  "right",
+  "bottom",
+  "left",
+  "grid-template-areas",
+  "grid-area",
+  "overflow",
+  "overflow-x",
+  "overflow-y",
+  "scrollbar-width",
+  "scrollbar-color",
+  "padding",
+  "margin",
+  "color",
+  "background-color",
+  "background",
+  "border-color",
+  "border-width",
+  "opacity",
+  "z-index",
+  "font-weight",
+  "font-style",
+  "text-decoration",
+  "text-decoration-line",
+  "text-decoration-style",
+  "text-decoration-color",
+  "text-align",
+  "white-space",
+  "overflow-wrap",
+  "word-wrap",
+  "text-overflow",
+  "content",
+];
+
+/// The CSS-wide keywords accepted by every property
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CssWideKeyword {
+  Inherit,
+  Initial,
+  Unset,
+}
+
+impl CssWideKeyword {
+  fn parse(value: &str) -> Option<Self> {
+    if value.eq_ignore_ascii_case("inherit") {
+      Some(Self::Inherit)
+    } else if value.eq_ignore_ascii_case("initial") {
+      Some(Self::Initial)
+    } else if value.eq_ignore_ascii_case("unset") {
+      Some(Self::Unset)
+    } else {
+      None
+    }
+  }
 }
 
 impl Default for ComputedStyles {
@@ -77,6 +272,16 @@ impl Default for ComputedStyles {
       min_height: SizeValue::Auto,
       max_width: SizeValue::Auto,
       max_height: SizeValue::Auto,
+      aspect_ratio: None,
+      position: PositionType::Static,
+      top: SizeValue::Auto,
+      right: SizeValue::Auto,
+      bottom: SizeValue::Auto,
+      left: SizeValue::Auto,
+      grid_template_areas: Vec::new(),
+      grid_area: None,
+      overflow: OverflowBehavior::default(),
+      scrollbar_width: ScrollbarWidth::Auto,
 
       // Visual defaults
       color: None,
@@ -84,13 +289,92 @@ impl Default for ComputedStyles {
       font_weight: FontWeight::Normal,
       font_style: FontStyle::Normal,
       text_decoration: vec![TextDecoration::None],
+      text_decoration_style: TextDecorationStyle::Solid,
+      text_decoration_color: None,
+      text_align: TextAlign::Left,
+      white_space: WhiteSpace::Normal,
+      overflow_wrap: OverflowWrap::Normal,
+      text_overflow: TextOverflow::Clip,
       border_color: None,
       border_width: 0,
+      scrollbar_color: None,
+      opacity: 1.0,
+      z_index: 0,
+      content: None,
+
+      custom_properties: Arc::default(),
     }
   }
 }
 
 impl ComputedStyles {
+  /// Take the inherited properties from the parent's computed styles
+  pub fn inherit_from(&mut self, parent: &ComputedStyles) {
+    for property in INHERITED_PROPERTIES {
+      self.copy_property(parent, property);
+    }
+  }
+
+  /// Copy the computed value of `property` from `source`.
+  ///
+  /// Returns `false` for properties the engine does not know.
+  fn copy_property(&mut self, source: &ComputedStyles, property: &str) -> bool {
+    match property {
+      "display" => self.display = source.display,
+      "flex-direction" => self.flex_direction = source.flex_direction,
+      "justify-content" => self.justify_content = source.justify_content,
+      "align-items" => self.align_items = source.align_items,
+      "flex-grow" => self.flex_grow = source.flex_grow,
+      "padding" => self.padding = source.padding,
+      "margin" => self.margin = source.margin,
+      "width" => self.width = source.width,
+      "height" => self.height = source.height,
+      "min-width" => self.min_width = source.min_width,
+      "min-height" => self.min_height = source.min_height,
+      "max-width" => self.max_width = source.max_width,
+      "max-height" => self.max_height = source.max_height,
+      "aspect-ratio" => self.aspect_ratio = source.aspect_ratio,
+      "position" => self.position = source.position,
+      "top" => self.top = source.top,
+      "right" => self.right = source.right,
+      "bottom" => self.bottom = source.bottom,
+      "left" => self.left = source.left,
+      "grid-template-areas" => self.grid_template_areas = source.grid_template_areas.clone(),
+      "grid-area" => self.grid_area = source.grid_area.clone(),
+      "overflow" => self.overflow = source.overflow,
+      "overflow-x" => self.overflow.x = source.overflow.x,
+      "overflow-y" => self.overflow.y = source.overflow.y,
+      "scrollbar-width" => self.scrollbar_width = source.scrollbar_width,
+      "scrollbar-color" => self.scrollbar_color = source.scrollbar_color,
+      "color" => self.color = source.color,
+      "background-color" | "background" => self.background_color = source.background_color,
+      "font-weight" => self.font_weight = source.font_weight,
+      "font-style" => self.font_style = source.font_style,
+      "text-decoration" | "text-decoration-line" => {
+        self.text_decoration = source.text_decoration.clone()
+      }
+      "text-decoration-style" => self.text_decoration_style = source.text_decoration_style,
+      "text-decoration-color" => self.text_decoration_color = source.text_decoration_color,
+      "text-align" => self.text_align = source.text_align,
+      "white-space" => self.white_space = source.white_space,
+      "overflow-wrap" | "word-wrap" => self.overflow_wrap = source.overflow_wrap,
+      "text-overflow" => self.text_overflow = source.text_overflow.clone(),
+      "border-color" => self.border_color = source.border_color,
+      "border-width" => self.border_width = source.border_width,
+      "opacity" => self.opacity = source.opacity,
+      "z-index" => self.z_index = source.z_index,
+      "content" => self.content = source.content.clone(),
+      _ => return false,
+    }
+    true
+  }
+
+  /// Whether the element is composited as its own layer. Positioned
+  /// elements are, so they paint over the content laid out around them.
+  pub fn creates_layer(&self) -> bool {
+    self.z_index != 0 || self.opacity < 1.0 || self.position != PositionType::Static
+  }
+
   /// Convert CSS computed styles to terminal render style
   pub fn to_render_style(&self) -> RenderStyle {
     RenderStyle {
@@ -99,6 +383,15 @@ impl ComputedStyles {
       bold: self.font_weight == FontWeight::Bold,
       italic: self.font_style == FontStyle::Italic,
       underline: self.text_decoration.contains(&TextDecoration::Underline),
+      underline_style: self.text_decoration_style.into(),
+      underline_color: self.text_decoration_color.map(|c| c.into()),
+      strikethrough: self
+        .text_decoration
+        .contains(&TextDecoration::Strikethrough),
+      overline: self.text_decoration.contains(&TextDecoration::Overline),
+      blink: self.text_decoration.contains(&TextDecoration::Blink),
+      dim: self.font_weight == FontWeight::Lighter,
+      hyperlink: None,
     }
   }
 
@@ -106,7 +399,7 @@ impl ComputedStyles {
   pub fn to_layout_styles(&self) -> crate::layout::ComputedStyles {
     crate::layout::ComputedStyles {
       display: self.display,
-      position: crate::layout::PositionType::Static,
+      position: self.position,
       flex_direction: self.flex_direction,
       flex_wrap: crate::layout::FlexWrap::NoWrap,
       justify_content: self.justify_content,
@@ -117,11 +410,11 @@ impl ComputedStyles {
       flex_shrink: 1.0,
       flex_basis: crate::layout::SizeValue::Auto,
       order: 0,
-      z_index: 0,
-      top: crate::layout::SizeValue::Auto,
-      right: crate::layout::SizeValue::Auto,
-      bottom: crate::layout::SizeValue::Auto,
-      left: crate::layout::SizeValue::Auto,
+      z_index: self.z_index,
+      top: self.top,
+      right: self.right,
+      bottom: self.bottom,
+      left: self.left,
       padding: self.padding,
       margin: self.margin,
       width: self.width,
@@ -130,18 +423,31 @@ impl ComputedStyles {
       min_height: self.min_height,
       max_width: self.max_width,
       max_height: self.max_height,
-      overflow: crate::layout::OverflowBehavior::default(),
+      aspect_ratio: self.aspect_ratio,
+      overflow: self.overflow,
+      scrollbar_width: self.scrollbar_width,
       scroll_state: crate::layout::ScrollState::default(),
       grid_template_columns: Vec::new(),
       grid_template_rows: Vec::new(),
-      grid_template_areas: Vec::new(),
+      grid_template_areas: self.grid_template_areas.clone(),
       grid_column_gap: 0,
       grid_row_gap: 0,
       grid_column_start: crate::layout::GridPosition::default(),
       grid_column_end: crate::layout::GridPosition::default(),
       grid_row_start: crate::layout::GridPosition::default(),
       grid_row_end: crate::layout::GridPosition::default(),
-      grid_area: None,
+      grid_area: self.grid_area.clone(),
+      text: self.text_style(),
+    }
+  }
+
+  /// Properties that control how the element's text is broken into lines
+  pub fn text_style(&self) -> TextStyle {
+    TextStyle {
+      white_space: self.white_space,
+      overflow_wrap: self.overflow_wrap,
+      text_overflow: self.text_overflow.clone(),
+      text_align: self.text_align,
     }
   }
 }
@@ -150,29 +456,110 @@ impl ComputedStyles {
 #[derive(Debug, Clone)]
 pub struct Stylesheet {
   pub rules: Vec<CssRule>,
+  /// Errors recovered from while parsing; the offending rule or declaration is skipped
+  pub errors: Vec<CssParseError>,
+  /// `@import` rules not yet resolved; see [`Stylesheet::resolve_imports`]
+  pub imports: Vec<ImportRule>,
+  /// Full names of the cascade layers declared by the sheet, in declaration order
+  pub layers: Vec<String>,
+  /// Files pulled in by resolved `@import` rules
+  pub imported_files: Vec<PathBuf>,
+  /// Component scope the sheet is confined to; see [`CssEngine::load_scoped_stylesheet`]
+  pub scope: Option<String>,
+  /// File the sheet was read from with [`Stylesheet::from_file`]
+  pub source: Option<PathBuf>,
+}
+
+/// An `@import` rule waiting to be resolved against the importing file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRule {
+  pub url: String,
+  /// Layer the imported rules are placed in, from `layer` or `layer(name)`
+  pub layer: Option<String>,
+  /// Media conditions the imported rules only apply under
+  pub media: Option<MediaQueryList>,
+  pub location: SourceLocation,
+  /// Number of the importing sheet's layers declared before this import
+  pub(crate) layers_before: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct CssRule {
   pub selector: String,
-  pub declarations: HashMap<String, String>,
+  /// Parsed form of `selector`, one entry per comma-separated selector
+  pub selectors: Vec<Selector>,
+  /// Declarations in source order
+  pub declarations: Vec<Declaration>,
+  /// Enclosing `@media` conditions, outermost first; all of them must match
+  pub media: Vec<MediaQueryList>,
+  /// Full dotted name of the cascade layer the rule belongs to, if any
+  pub layer: Option<String>,
+  pub location: SourceLocation,
 }
 
-/// Selector types for CSS matching
-#[derive(Debug, Clone)]
-pub enum Selector {
-  Tag(String),
-  Class(String),
-  Id(String),
-  Universal,
-  Descendant(Box<Selector>, Box<Selector>),
-  Child(Box<Selector>, Box<Selector>),
+impl CssRule {
+  /// Whether the rule's `@media` conditions hold in `environment`
+  pub fn applies_in(&self, environment: &MediaEnvironment) -> bool {
+    self.media.iter().all(|media| media.matches(environment))
+  }
+}
+
+/// A single `property: value` declaration inside a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+  pub property: String,
+  pub value: String,
+  pub important: bool,
+  pub location: SourceLocation,
+}
+
+/// 1-based line and column of a rule or declaration in its source text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+  pub line: u32,
+  pub column: u32,
+}
+
+impl std::fmt::Display for SourceLocation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}", self.line, self.column)
+  }
+}
+
+/// A recoverable error encountered while parsing a stylesheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CssParseError {
+  pub kind: DiagnosticKind,
+  pub message: String,
+  pub location: SourceLocation,
+  /// The source text that was skipped because of the error
+  pub source: String,
+}
+
+impl std::fmt::Display for CssParseError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}: {}", self.location, self.message)
+  }
 }
 
 /// Advanced CSS engine with component tree support
 pub struct CssEngine {
   stylesheets: Vec<Stylesheet>,
   global_styles: HashMap<String, ComputedStyles>,
+  /// Bumped whenever the loaded stylesheets change, so component trees can
+  /// tell whether their resolved styles are stale
+  generation: u64,
+  /// Terminal properties that `@media` rules are evaluated against
+  media_environment: MediaEnvironment,
+  /// Cascade rank of every declared layer: its index among its siblings at
+  /// each nesting level, followed by [`UNLAYERED`] for the layer's own rules
+  layer_ranks: HashMap<String, Vec<u32>>,
+  /// Whether any rule targets `::before` or `::after`
+  has_pseudo_elements: bool,
+  /// Selectors of the loaded rules bucketed by id, class and tag
+  rule_index: RuleIndex,
+  /// What a change to a class, id, attribute or state can restyle
+  invalidation_sets: InvalidationSets,
 }
 
 impl CssEngine {
@@ -180,6 +567,12 @@ impl CssEngine {
     let mut engine = Self {
       stylesheets: Vec::new(),
       global_styles: HashMap::new(),
+      generation: 0,
+      media_environment: MediaEnvironment::default(),
+      layer_ranks: HashMap::new(),
+      has_pseudo_elements: false,
+      rule_index: RuleIndex::default(),
+      invalidation_sets: InvalidationSets::default(),
     };
 
     // Add default component styles
@@ -189,62 +582,168 @@ impl CssEngine {
 
   pub fn add_stylesheet(&mut self, stylesheet: Stylesheet) {
     self.stylesheets.push(stylesheet);
+    self.stylesheets_changed();
+  }
+
+  /// Refresh everything derived from the loaded stylesheets
+  fn stylesheets_changed(&mut self) {
+    self.rebuild_layer_ranks();
+    self.has_pseudo_elements = self.any_selector(Selector::has_pseudo_element);
+    self.rule_index = RuleIndex::new(&self.stylesheets);
+    self.invalidation_sets = InvalidationSets::new(&self.stylesheets);
+    self.generation += 1;
+  }
+
+  /// Rank layers by the order they were first declared across all loaded
+  /// stylesheets; later layers win over earlier ones
+  fn rebuild_layer_ranks(&mut self) {
+    let mut ranks: HashMap<String, Vec<u32>> = HashMap::new();
+    let mut child_counts: HashMap<String, u32> = HashMap::new();
+    let names = self.stylesheets.iter().flat_map(|sheet| {
+      sheet
+        .layers
+        .iter()
+        .chain(sheet.rules.iter().filter_map(|rule| rule.layer.as_ref()))
+    });
+    for name in names {
+      // Parents are ranked before their sublayers
+      let prefixes = name
+        .match_indices('.')
+        .map(|(end, _)| &name[..end])
+        .chain(std::iter::once(name.as_str()));
+      for prefix in prefixes {
+        if ranks.contains_key(prefix) {
+          continue;
+        }
+        let parent = prefix.rsplit_once('.').map_or("", |(parent, _)| parent);
+        let mut rank = ranks
+          .get(parent)
+          .map_or_else(Vec::new, |rank| rank[..rank.len() - 1].to_vec());
+        let index = child_counts.entry(parent.to_string()).or_insert(0);
+        rank.push(*index);
+        rank.push(u32::MAX);
+        *index += 1;
+        ranks.insert(prefix.to_string(), rank);
+      }
+    }
+    self.layer_ranks = ranks;
+  }
+
+  fn layer_rank(&self, rule: &CssRule) -> &[u32] {
+    rule
+      .layer
+      .as_ref()
+      .and_then(|layer| self.layer_ranks.get(layer))
+      .map_or(UNLAYERED, Vec::as_slice)
+  }
+
+  pub fn media_environment(&self) -> &MediaEnvironment {
+    &self.media_environment
+  }
+
+  /// Replace the environment `@media` rules are evaluated against.
+  ///
+  /// The generation only changes when some rule's media conditions flip, so
+  /// a resize that crosses no breakpoint keeps component trees valid.
+  pub fn set_media_environment(&mut self, environment: MediaEnvironment) {
+    if environment == self.media_environment {
+      return;
+    }
+    let changed = self
+      .stylesheets
+      .iter()
+      .flat_map(|sheet| &sheet.rules)
+      .any(|rule| rule.applies_in(&self.media_environment) != rule.applies_in(&environment));
+    self.media_environment = environment;
+    if changed {
+      self.generation += 1;
+    }
+  }
+
+  /// Update the terminal size used by `@media` width and height conditions
+  pub fn set_viewport_size(&mut self, width: u16, height: u16) {
+    let environment = MediaEnvironment {
+      width,
+      height,
+      ..self.media_environment.clone()
+    };
+    self.set_media_environment(environment);
   }
 
-  /// Load a stylesheet from CSS string content
+  /// Load a stylesheet from CSS string content; `@import` URLs are resolved
+  /// relative to the current directory
   pub fn load_stylesheet(&mut self, css_content: &str) -> Result<()> {
     let parsed_stylesheet = self.parse_css(css_content)?;
     self.add_stylesheet(parsed_stylesheet);
     Ok(())
   }
 
-  /// Clear all loaded stylesheets
+  /// Load a stylesheet file, resolving `@import` URLs relative to it
+  pub fn load_stylesheet_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+    let stylesheet = Stylesheet::from_file(path)?;
+    self.add_stylesheet(stylesheet);
+    Ok(())
+  }
+
+  /// Load CSS into the cascade layer `layer`.
+  ///
+  /// Layered rules lose to unlayered ones regardless of specificity, so e.g.
+  /// theme-generated CSS loaded with `@layer framework, theme;` ordering can
+  /// always be overridden by the app's own stylesheets.
+  pub fn load_stylesheet_in_layer(&mut self, css_content: &str, layer: &str) -> Result<()> {
+    let mut stylesheet = self.parse_css(css_content)?;
+    stylesheet.nest_in_layer(layer);
+    self.add_stylesheet(stylesheet);
+    Ok(())
+  }
+
+  /// Load a component's stylesheet whose rules only match inside elements
+  /// marked with a [`SCOPE_ATTRIBUTE`] of `scope` and their descendants.
+  ///
+  /// Loading the same scope again replaces its previous stylesheet. Scoped
+  /// rules lose ties against unscoped ones so app styles can override them.
+  pub fn load_scoped_stylesheet(&mut self, scope: &str, css_content: &str) -> Result<()> {
+    let mut stylesheet = self.parse_css(css_content)?;
+    stylesheet.scope = Some(scope.to_string());
+    self
+      .stylesheets
+      .retain(|sheet| sheet.scope.as_deref() != Some(scope));
+    self.add_stylesheet(stylesheet);
+    Ok(())
+  }
+
+  /// Clear all loaded stylesheets except component stylesheets added with
+  /// [`CssEngine::load_scoped_stylesheet`]
   pub fn clear_stylesheets(&mut self) {
-    self.stylesheets.clear();
+    self.stylesheets.retain(|sheet| sheet.scope.is_some());
     self.global_styles.clear();
+    self.stylesheets_changed();
     // Re-add default styles after clearing
     self.add_default_styles();
   }
 
-  /// Parse CSS string into a Stylesheet
-  fn parse_css(&self, css_content: &str) -> Result<Stylesheet> {
-    let mut rules = Vec::new();
-    let mut current_selector = String::new();
-    let mut current_declarations = HashMap::new();
-    let mut in_rule = false;
-
-    for line in css_content.lines() {
-      let line = line.trim();
-
-      if line.is_empty() || line.starts_with("/*") {
-        continue;
-      }
-
-      if line.contains('{') {
-        current_selector = line.replace('{', "").trim().to_string();
-        in_rule = true;
-        current_declarations.clear();
-      } else if line.contains('}') {
-        if in_rule && !current_selector.is_empty() {
-          rules.push(CssRule {
-            selector: current_selector.clone(),
-            declarations: current_declarations.clone(),
-          });
-        }
-        in_rule = false;
-        current_selector.clear();
-        current_declarations.clear();
-      } else if in_rule && line.contains(':') {
-        let parts: Vec<&str> = line.splitn(2, ':').collect();
-        if parts.len() == 2 {
-          let property = parts[0].trim().to_string();
-          let value = parts[1].trim().trim_end_matches(';').to_string();
-          current_declarations.insert(property, value);
-        }
+  /// Swap in stylesheets read from files, each taking the place of the
+  /// sheet previously read from the same file, in one step. Sheets loaded
+  /// from strings and component stylesheets are kept.
+  pub fn replace_file_stylesheets(&mut self, stylesheets: Vec<Stylesheet>) {
+    for stylesheet in stylesheets {
+      let previous = self
+        .stylesheets
+        .iter()
+        .position(|loaded| loaded.source.is_some() && loaded.source == stylesheet.source);
+      match previous {
+        Some(index) => self.stylesheets[index] = stylesheet,
+        None => self.stylesheets.push(stylesheet),
       }
     }
+    self.stylesheets_changed();
+  }
 
-    Ok(Stylesheet { rules })
+  /// Parse CSS string into a Stylesheet
+  fn parse_css(&self, css_content: &str) -> Result<Stylesheet> {
+    let mut stylesheet = parser::parse_stylesheet(css_content);
+    stylesheet.resolve_imports(Path::new(""));
+    Ok(stylesheet)
   }
 
   pub fn add_css(&mut self, css: &str) -> Result<()> {
@@ -253,12 +752,45 @@ impl CssEngine {
     Ok(())
   }
 
+  /// Resolve styles for an element on its own, without ancestors or siblings.
+  ///
+  /// Use [`CssEngine::apply_styles_in_context`] when the element's position in
+  /// the tree is known so combinators, structural pseudo-classes and inherited
+  /// custom properties apply.
   pub fn apply_styles(&self, element: &Element) -> ComputedStyles {
+    self.apply_styles_in_context(&ElementContext::root(element), None)
+  }
+
+  /// Resolve styles for an element at a position in the tree, inheriting from
+  /// the parent's resolved styles
+  pub fn apply_styles_in_context(
+    &self,
+    context: &ElementContext,
+    parent_styles: Option<&ComputedStyles>,
+  ) -> ComputedStyles {
+    self.resolve_styles(context, parent_styles, None)
+  }
+
+  /// Resolve styles for an element, skipping rules whose ancestors are
+  /// missing from `ancestors` when the caller tracks them
+  fn resolve_styles(
+    &self,
+    context: &ElementContext,
+    parent_styles: Option<&ComputedStyles>,
+    ancestors: Option<&AncestorFilter>,
+  ) -> ComputedStyles {
+    let element = context.element;
     let mut styles = ComputedStyles::default();
 
     // Apply default styles based on element tag
     self.apply_tag_styles(&mut styles, &element.tag);
 
+    // Inherit custom properties and inherited properties from the parent
+    if let Some(parent) = parent_styles {
+      styles.custom_properties = parent.custom_properties.clone();
+      styles.inherit_from(parent);
+    }
+
     // Apply class-based styles
     for class in &element.classes {
       self.apply_class_styles(&mut styles, class);
@@ -272,16 +804,219 @@ impl CssEngine {
     // Apply attribute-based styles
     self.apply_attribute_styles(&mut styles, element);
 
-    // Apply CSS rules from stylesheets
-    for stylesheet in &self.stylesheets {
-      for rule in &stylesheet.rules {
-        if self.selector_matches(&rule.selector, element) {
-          self.apply_declarations(&mut styles, &rule.declarations);
+    // Apply CSS rules from stylesheets and the inline `style` attribute.
+    // Custom properties are computed first so `var()` sees the element's own
+    // declarations regardless of their order.
+    let inline = element
+      .get_attribute("style")
+      .map(|style| parser::parse_declaration_list(style).0)
+      .unwrap_or_default();
+    let declarations = self.cascaded_declarations(context, &inline, None, ancestors);
+    self.apply_cascaded(&mut styles, declarations, parent_styles);
+    styles
+  }
+
+  /// Resolve the `::before` or `::after` box of the element in `context`,
+  /// whose own resolved styles are `element_styles`.
+  ///
+  /// Returns `None` unless some rule gives it a `content` other than `none`.
+  pub fn generated_content(
+    &self,
+    context: &ElementContext,
+    pseudo: PseudoElement,
+    element_styles: &ComputedStyles,
+  ) -> Option<GeneratedContent> {
+    self.resolve_generated_content(context, pseudo, element_styles, None)
+  }
+
+  fn resolve_generated_content(
+    &self,
+    context: &ElementContext,
+    pseudo: PseudoElement,
+    element_styles: &ComputedStyles,
+    ancestors: Option<&AncestorFilter>,
+  ) -> Option<GeneratedContent> {
+    if !self.has_pseudo_elements {
+      return None;
+    }
+    let declarations = self.cascaded_declarations(context, &[], Some(pseudo), ancestors);
+    if declarations.is_empty() {
+      return None;
+    }
+
+    // Generated boxes are inline children of their element
+    let mut styles = ComputedStyles {
+      display: DisplayType::Inline,
+      custom_properties: Arc::clone(&element_styles.custom_properties),
+      ..ComputedStyles::default()
+    };
+    styles.inherit_from(element_styles);
+    self.apply_cascaded(&mut styles, declarations, Some(element_styles));
+
+    let text = values::generated_text(styles.content.as_deref()?, context.element)?;
+    (!text.is_empty()).then_some(GeneratedContent { text, styles })
+  }
+
+  /// Apply declarations in cascade order on top of `styles`
+  fn apply_cascaded(
+    &self,
+    styles: &mut ComputedStyles,
+    declarations: Vec<&Declaration>,
+    parent_styles: Option<&ComputedStyles>,
+  ) {
+    let custom: Vec<(&str, &str)> = declarations
+      .iter()
+      .filter(|declaration| declaration.property.starts_with("--"))
+      .map(|declaration| (declaration.property.as_str(), declaration.value.as_str()))
+      .collect();
+    if !custom.is_empty() {
+      values::resolve_custom_properties(&custom, Arc::make_mut(&mut styles.custom_properties));
+    }
+
+    for declaration in declarations {
+      let property = declaration.property.as_str();
+      if property.starts_with("--") {
+        continue;
+      }
+      let value = if declaration.value.contains("var(") {
+        let properties = Arc::clone(&styles.custom_properties);
+        let substituted = values::substitute_vars(&declaration.value, &mut |name| {
+          properties.get(name).cloned()
+        });
+        // A reference to an undefined property without fallback invalidates the declaration
+        match substituted {
+          Some(value) => std::borrow::Cow::Owned(value),
+          None => continue,
+        }
+      } else {
+        std::borrow::Cow::Borrowed(declaration.value.as_str())
+      };
+
+      match CssWideKeyword::parse(&value) {
+        Some(keyword) => Self::apply_keyword(styles, property, keyword, parent_styles),
+        None => {
+          self.apply_declaration(styles, property, &value);
         }
       }
     }
+  }
 
-    styles
+  /// Apply `inherit`, `initial` or `unset` to `property`
+  fn apply_keyword(
+    styles: &mut ComputedStyles,
+    property: &str,
+    keyword: CssWideKeyword,
+    parent_styles: Option<&ComputedStyles>,
+  ) {
+    let inherits = match keyword {
+      CssWideKeyword::Inherit => true,
+      CssWideKeyword::Initial => false,
+      CssWideKeyword::Unset => INHERITED_PROPERTIES.contains(&property),
+    };
+    let initial = ComputedStyles::default();
+    // The root element inherits initial values
+    let source = match parent_styles {
+      Some(parent) if inherits => parent,
+      _ => &initial,
+    };
+    styles.copy_property(source, property);
+  }
+
+  /// Collect the declarations of every matching rule in cascade order: normal
+  /// declarations before `!important` ones, then inline `style` declarations
+  /// after rules, then by layer (reversed for `!important`), then by
+  /// specificity, then by source order with scoped stylesheets first.
+  ///
+  /// With `pseudo`, only rules for that pseudo-element of the element match.
+  /// Only rules from the element's buckets in the rule index are tested.
+  fn cascaded_declarations<'a>(
+    &'a self,
+    context: &ElementContext,
+    inline: &'a [Declaration],
+    pseudo: Option<PseudoElement>,
+    ancestors: Option<&AncestorFilter>,
+  ) -> Vec<&'a Declaration> {
+    // Matching rules with the specificity of their most specific matching
+    // selector; candidates come grouped by rule
+    let mut rules: Vec<(usize, &CssRule, Specificity)> = Vec::new();
+    let mut sheet_applies: Vec<Option<bool>> = vec![None; self.stylesheets.len()];
+    for candidate in self
+      .rule_index
+      .candidates(context.element, pseudo, ancestors)
+    {
+      let sheet = &self.stylesheets[candidate.sheet];
+      let applies = *sheet_applies[candidate.sheet].get_or_insert_with(|| {
+        sheet
+          .scope
+          .as_ref()
+          .is_none_or(|scope| in_scope(context, scope))
+      });
+      let rule = &sheet.rules[candidate.rule];
+      if !applies || !rule.applies_in(&self.media_environment) {
+        continue;
+      }
+      let selector = &rule.selectors[candidate.selector];
+      let matches = match pseudo {
+        Some(pseudo) => selector.matches_pseudo_element(context, pseudo),
+        None => selector.matches(context),
+      };
+      if !matches {
+        continue;
+      }
+      match rules.last_mut() {
+        Some((sheet, last, specificity))
+          if *sheet == candidate.sheet && std::ptr::eq(*last, rule) =>
+        {
+          *specificity = (*specificity).max(selector.specificity());
+        }
+        _ => rules.push((candidate.sheet, rule, selector.specificity())),
+      }
+    }
+    // Scoped stylesheets come first in source order
+    rules.sort_by_key(|(sheet, _, _)| (self.stylesheets[*sheet].scope.is_none(), *sheet));
+
+    let mut matched = Vec::new();
+    for (_, rule, specificity) in rules {
+      let layer = self.layer_rank(rule);
+      matched.extend(rule.declarations.iter().map(|declaration| {
+        (
+          declaration.important,
+          false,
+          layer,
+          specificity,
+          declaration,
+        )
+      }));
+    }
+    matched.extend(inline.iter().map(|declaration| {
+      (
+        declaration.important,
+        true,
+        UNLAYERED,
+        Specificity::default(),
+        declaration,
+      )
+    }));
+
+    // Stable sort keeps source order among equal importance, layer and specificity
+    matched.sort_by(|a, b| {
+      let layers = if a.0 { b.2.cmp(a.2) } else { a.2.cmp(b.2) };
+      (a.0, a.1).cmp(&(b.0, b.1)).then(layers).then(a.3.cmp(&b.3))
+    });
+    matched
+      .into_iter()
+      .map(|(_, _, _, _, declaration)| declaration)
+      .collect()
+  }
+
+  /// Whether any selector of any loaded rule satisfies `predicate`
+  fn any_selector(&self, predicate: fn(&Selector) -> bool) -> bool {
+    self
+      .stylesheets
+      .iter()
+      .flat_map(|sheet| &sheet.rules)
+      .flat_map(|rule| &rule.selectors)
+      .any(predicate)
   }
 
   pub fn create_component_tree(&self, root: &Element) -> ComponentTree {
@@ -289,27 +1024,13 @@ impl CssEngine {
   }
 
   /// Create a component tree using a per-build style cache to avoid recomputing
-  /// identical style resolutions for elements with the same tag/classes/id/attrs.
+  /// identical style resolutions for elements with the same tag/classes/id/attrs
+  /// and ancestors.
   pub fn create_component_tree_cached(&self, root: &Element) -> ComponentTree {
-    let mut cache: HashMap<String, ComputedStyles> = HashMap::new();
+    let mut cache = StyleCache::new(self.any_selector(Selector::depends_on_siblings));
     ComponentTree::new_cached(root.clone(), self, &mut cache)
   }
 
-  /// Internal: apply styles using a cache key derived from the element's identity
-  fn apply_styles_cached(
-    &self,
-    element: &Element,
-    cache: &mut HashMap<String, ComputedStyles>,
-  ) -> ComputedStyles {
-    let key = style_cache_key(element);
-    if let Some(cached) = cache.get(&key) {
-      return cached.clone();
-    }
-    let computed = self.apply_styles(element);
-    cache.insert(key, computed.clone());
-    computed
-  }
-
   fn add_default_styles(&mut self) {
     // HTML-like element defaults
     let div_styles = ComputedStyles {
@@ -441,118 +1162,253 @@ impl CssEngine {
     }
   }
 
-  fn selector_matches(&self, selector: &str, element: &Element) -> bool {
-    // Simple selector matching - can be expanded for complex selectors
-    if let Some(class) = selector.strip_prefix('.') {
-      // Class selector
-      element.classes.contains(&class.to_string())
-    } else if let Some(id) = selector.strip_prefix('#') {
-      // ID selector
-      element.id.as_ref().is_some_and(|el_id| el_id == id)
-    } else {
-      // Tag selector
-      element.tag == selector
-    }
-  }
-
-  fn apply_declarations(
-    &self,
-    styles: &mut ComputedStyles,
-    declarations: &HashMap<String, String>,
-  ) {
-    for (property, value) in declarations {
-      self.apply_declaration(styles, property, value);
-    }
-  }
-
-  fn apply_declaration(&self, styles: &mut ComputedStyles, property: &str, value: &str) {
+  /// Apply a single declaration to `styles`.
+  ///
+  /// Returns `false`, leaving `styles` untouched, when the property is not
+  /// supported or the value is invalid for it.
+  fn apply_declaration(&self, styles: &mut ComputedStyles, property: &str, value: &str) -> bool {
     match property {
       "display" => match value {
         "block" => styles.display = DisplayType::Block,
         "inline" => styles.display = DisplayType::Inline,
         "flex" => styles.display = DisplayType::Flex,
+        "grid" => styles.display = DisplayType::Grid,
         "none" => styles.display = DisplayType::None,
-        _ => {}
+        _ => return false,
       },
       "flex-direction" => match value {
         "row" => styles.flex_direction = FlexDirection::Row,
         "column" => styles.flex_direction = FlexDirection::Column,
         "row-reverse" => styles.flex_direction = FlexDirection::RowReverse,
         "column-reverse" => styles.flex_direction = FlexDirection::ColumnReverse,
-        _ => {}
+        _ => return false,
       },
-      "width" => {
-        if let Ok(px) = value.parse::<u16>() {
-          styles.width = SizeValue::Pixels(px);
-        } else if value.ends_with('%') {
-          if let Ok(pct) = value.trim_end_matches('%').parse::<f32>() {
-            styles.width = SizeValue::Percent(pct);
-          }
-        } else if value == "auto" {
-          styles.width = SizeValue::Auto;
+      "width" | "height" | "min-width" | "min-height" | "max-width" | "max-height" => {
+        let Some(size) = values::parse_size(value) else {
+          return false;
+        };
+        match property {
+          "width" => styles.width = size,
+          "height" => styles.height = size,
+          "min-width" => styles.min_width = size,
+          "min-height" => styles.min_height = size,
+          "max-width" => styles.max_width = size,
+          _ => styles.max_height = size,
         }
       }
-      "height" => {
-        if let Ok(px) = value.parse::<u16>() {
-          styles.height = SizeValue::Pixels(px);
-        } else if value.ends_with('%') {
-          if let Ok(pct) = value.trim_end_matches('%').parse::<f32>() {
-            styles.height = SizeValue::Percent(pct);
-          }
-        } else if value == "auto" {
-          styles.height = SizeValue::Auto;
+      "aspect-ratio" => match value {
+        "auto" => styles.aspect_ratio = None,
+        _ => match values::parse_aspect_ratio(value) {
+          Some(ratio) => styles.aspect_ratio = Some(ratio),
+          None => return false,
+        },
+      },
+      "position" => match value {
+        "static" => styles.position = PositionType::Static,
+        "relative" => styles.position = PositionType::Relative,
+        "absolute" => styles.position = PositionType::Absolute,
+        "fixed" => styles.position = PositionType::Fixed,
+        "sticky" => styles.position = PositionType::Sticky,
+        _ => return false,
+      },
+      "top" | "right" | "bottom" | "left" => {
+        let Some(offset) = values::parse_offset(value) else {
+          return false;
+        };
+        match property {
+          "top" => styles.top = offset,
+          "right" => styles.right = offset,
+          "bottom" => styles.bottom = offset,
+          _ => styles.left = offset,
         }
       }
-      "padding" => {
-        if let Ok(px) = value.parse::<u16>() {
-          styles.padding = Spacing::uniform(px);
+      "grid-template-areas" => {
+        if value == "none" {
+          styles.grid_template_areas = Vec::new();
+        } else {
+          // Maps with ragged rows or areas that are not rectangles are dropped
+          let Some(rows) = template::parse_areas(value).filter(|rows| GridAreas::new(rows).is_ok())
+          else {
+            return false;
+          };
+          styles.grid_template_areas = rows;
         }
       }
-      "margin" => {
-        if let Ok(px) = value.parse::<u16>() {
-          styles.margin = Spacing::uniform(px);
+      "grid-area" => match value {
+        "auto" => styles.grid_area = None,
+        _ if values::is_ident(value) => styles.grid_area = Some(value.to_string()),
+        _ => return false,
+      },
+      "overflow" => {
+        // One keyword for both axes, or one for `x` then one for `y`
+        let keywords: Vec<_> = value
+          .split_whitespace()
+          .map(values::parse_overflow)
+          .collect();
+        match keywords[..] {
+          [Some(both)] => styles.overflow = OverflowBehavior { x: both, y: both },
+          [Some(x), Some(y)] => styles.overflow = OverflowBehavior { x, y },
+          _ => return false,
         }
       }
-      "color" => {
-        if let Some(color) = self.parse_color(value) {
-          styles.color = Some(color);
+      "overflow-x" | "overflow-y" => {
+        let Some(overflow) = values::parse_overflow(value) else {
+          return false;
+        };
+        match property {
+          "overflow-x" => styles.overflow.x = overflow,
+          _ => styles.overflow.y = overflow,
         }
       }
-      "background-color" => {
-        if let Some(color) = self.parse_color(value) {
-          styles.background_color = Some(color);
+      "scrollbar-width" => match value {
+        "auto" => styles.scrollbar_width = ScrollbarWidth::Auto,
+        "thin" => styles.scrollbar_width = ScrollbarWidth::Thin,
+        "none" => styles.scrollbar_width = ScrollbarWidth::None,
+        _ => return false,
+      },
+      "scrollbar-color" => {
+        if value == "auto" {
+          styles.scrollbar_color = None;
+        } else {
+          let colors: Vec<_> = value
+            .split_whitespace()
+            .map(|color| self.parse_color(color))
+            .collect();
+          let [Some(thumb), Some(track)] = colors[..] else {
+            return false;
+          };
+          styles.scrollbar_color = Some(ScrollbarColor { thumb, track });
         }
       }
-      "border-color" => {
-        if let Some(color) = self.parse_color(value) {
-          styles.border_color = Some(color);
+      "padding" | "margin" | "border-width" => {
+        let Some(cells) = values::parse_cells(value) else {
+          return false;
+        };
+        match property {
+          "padding" => styles.padding = Spacing::uniform(cells),
+          "margin" => styles.margin = Spacing::uniform(cells),
+          _ => styles.border_width = cells,
         }
       }
-      "border-width" => {
-        if let Ok(width) = value.parse::<u16>() {
-          styles.border_width = width;
+      "background-color" | "background" if value.eq_ignore_ascii_case("transparent") => {
+        styles.background_color = None;
+      }
+      "color" | "background-color" | "background" | "border-color" => {
+        let Some(color) = self.parse_color(value) else {
+          return false;
+        };
+        match property {
+          "color" => styles.color = Some(color),
+          "background-color" | "background" => styles.background_color = Some(color),
+          _ => styles.border_color = Some(color),
         }
       }
       "font-weight" => match value {
         "normal" => styles.font_weight = FontWeight::Normal,
         "bold" => styles.font_weight = FontWeight::Bold,
-        _ => {}
+        "lighter" => styles.font_weight = FontWeight::Lighter,
+        _ => return false,
       },
       "font-style" => match value {
         "normal" => styles.font_style = FontStyle::Normal,
         "italic" => styles.font_style = FontStyle::Italic,
-        _ => {}
+        _ => return false,
       },
-      "text-decoration" => {
-        styles.text_decoration = match value {
-          "none" => vec![TextDecoration::None],
-          "underline" => vec![TextDecoration::Underline],
-          "strikethrough" => vec![TextDecoration::Strikethrough],
-          _ => vec![TextDecoration::None],
-        };
+      "text-decoration" | "text-decoration-line" => {
+        // The shorthand also takes a style and a color, in any order
+        let shorthand = property == "text-decoration";
+        let mut lines = Vec::new();
+        let mut style = None;
+        let mut color = None;
+        for token in value.split_whitespace() {
+          let line = match token {
+            "none" => Some(TextDecoration::None),
+            "underline" => Some(TextDecoration::Underline),
+            "line-through" | "strikethrough" => Some(TextDecoration::Strikethrough),
+            "overline" => Some(TextDecoration::Overline),
+            "blink" => Some(TextDecoration::Blink),
+            _ => None,
+          };
+          if let Some(line) = line {
+            if lines.contains(&line) {
+              return false;
+            }
+            lines.push(line);
+          } else if let Some(parsed) = TextDecorationStyle::parse(token).filter(|_| shorthand) {
+            if style.replace(parsed).is_some() {
+              return false;
+            }
+          } else if let Some(parsed) = self.parse_color(token).filter(|_| shorthand) {
+            if color.replace(parsed).is_some() {
+              return false;
+            }
+          } else {
+            return false;
+          }
+        }
+        if lines.len() > 1 && lines.contains(&TextDecoration::None) {
+          return false;
+        }
+        if lines.is_empty() {
+          lines.push(TextDecoration::None);
+        }
+        styles.text_decoration = lines;
+        if shorthand {
+          styles.text_decoration_style = style.unwrap_or(TextDecorationStyle::Solid);
+          styles.text_decoration_color = color;
+        }
       }
-      _ => {}
+      "text-decoration-style" => match TextDecorationStyle::parse(value) {
+        Some(style) => styles.text_decoration_style = style,
+        None => return false,
+      },
+      "text-decoration-color" if value.eq_ignore_ascii_case("currentcolor") => {
+        styles.text_decoration_color = None;
+      }
+      "text-decoration-color" => match self.parse_color(value) {
+        Some(color) => styles.text_decoration_color = Some(color),
+        None => return false,
+      },
+      "text-align" => match value {
+        "left" | "start" => styles.text_align = TextAlign::Left,
+        "center" => styles.text_align = TextAlign::Center,
+        "right" | "end" => styles.text_align = TextAlign::Right,
+        "justify" => styles.text_align = TextAlign::Justify,
+        _ => return false,
+      },
+      "white-space" => match value {
+        "normal" => styles.white_space = WhiteSpace::Normal,
+        "nowrap" => styles.white_space = WhiteSpace::NoWrap,
+        "pre" => styles.white_space = WhiteSpace::Pre,
+        "pre-wrap" => styles.white_space = WhiteSpace::PreWrap,
+        "pre-line" => styles.white_space = WhiteSpace::PreLine,
+        _ => return false,
+      },
+      "overflow-wrap" | "word-wrap" => match value {
+        "normal" => styles.overflow_wrap = OverflowWrap::Normal,
+        "anywhere" => styles.overflow_wrap = OverflowWrap::Anywhere,
+        "break-word" => styles.overflow_wrap = OverflowWrap::BreakWord,
+        _ => return false,
+      },
+      "text-overflow" => match values::parse_text_overflow(value) {
+        Some(overflow) => styles.text_overflow = overflow,
+        None => return false,
+      },
+      "opacity" => match values::parse_opacity(value) {
+        Some(opacity) => styles.opacity = opacity,
+        None => return false,
+      },
+      "z-index" => match value {
+        "auto" => styles.z_index = 0,
+        _ => match value.parse() {
+          Ok(z_index) => styles.z_index = z_index,
+          Err(_) => return false,
+        },
+      },
+      "content" => styles.content = Some(value.to_string()),
+      _ => return false,
     }
+    true
   }
 
   /// Parse CSS color value to CrosstermColor
@@ -597,6 +1453,8 @@ impl CssEngine {
 #[derive(Debug)]
 pub struct ComponentTree {
   root: ComponentNode,
+  /// [`CssEngine`] generation the styles were resolved against
+  generation: u64,
 }
 
 #[derive(Debug)]
@@ -604,55 +1462,167 @@ pub struct ComponentNode {
   pub element: Element,
   pub styles: ComputedStyles,
   pub children: Vec<ComponentNode>,
+  /// Text generated by `::before`, placed ahead of the element's content
+  pub before: Option<GeneratedContent>,
+  /// Text generated by `::after`, placed after the element's content
+  pub after: Option<GeneratedContent>,
+}
+
+/// Text generated by a `::before` or `::after` rule, laid out inline with
+/// its element's content
+#[derive(Debug, Clone)]
+pub struct GeneratedContent {
+  pub text: String,
+  pub styles: ComputedStyles,
+}
+
+impl ComponentNode {
+  /// The element's content with generated `::before` and `::after` text
+  /// around it, or `None` when there is neither
+  pub fn inline_content(&self) -> Option<String> {
+    if self.before.is_none() && self.after.is_none() {
+      return self.element.content.clone();
+    }
+    let mut content = String::new();
+    for part in [
+      self.before.as_ref().map(|before| before.text.as_str()),
+      self.element.content.as_deref(),
+      self.after.as_ref().map(|after| after.text.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    {
+      content.push_str(part);
+    }
+    Some(content)
+  }
+}
+
+/// An element's resolved styles together with its generated content
+#[derive(Clone)]
+struct ResolvedStyles {
+  styles: ComputedStyles,
+  before: Option<GeneratedContent>,
+  after: Option<GeneratedContent>,
+}
+
+impl ResolvedStyles {
+  fn resolve(
+    css_engine: &CssEngine,
+    context: &ElementContext,
+    parent_styles: Option<&ComputedStyles>,
+    ancestors: &AncestorFilter,
+  ) -> Self {
+    let ancestors = Some(ancestors);
+    let styles = css_engine.resolve_styles(context, parent_styles, ancestors);
+    let generated =
+      |pseudo| css_engine.resolve_generated_content(context, pseudo, &styles, ancestors);
+    let before = generated(PseudoElement::Before);
+    let after = generated(PseudoElement::After);
+    Self {
+      styles,
+      before,
+      after,
+    }
+  }
+
+  fn into_node(self, element: Element, children: Vec<ComponentNode>) -> ComponentNode {
+    ComponentNode {
+      element,
+      styles: self.styles,
+      children,
+      before: self.before,
+      after: self.after,
+    }
+  }
 }
 
 impl ComponentTree {
   pub fn new(root_element: Element, css_engine: &CssEngine) -> Self {
-    let root = Self::build_node(root_element, css_engine);
-    Self { root }
+    let root = Self::build_node(
+      &ElementContext::root(&root_element),
+      None,
+      &AncestorFilter::default(),
+      css_engine,
+    );
+    Self {
+      root,
+      generation: css_engine.generation,
+    }
   }
 
   pub(crate) fn new_cached(
     root_element: Element,
     css_engine: &CssEngine,
-    cache: &mut HashMap<String, ComputedStyles>,
+    cache: &mut StyleCache,
   ) -> Self {
-    let root = Self::build_node_cached(root_element, css_engine, cache);
-    Self { root }
+    let root = Self::build_node_cached(
+      &ElementContext::root(&root_element),
+      None,
+      &AncestorFilter::default(),
+      0,
+      css_engine,
+      cache,
+    );
+    Self {
+      root,
+      generation: css_engine.generation,
+    }
   }
 
-  fn build_node(element: Element, css_engine: &CssEngine) -> ComponentNode {
-    let styles = css_engine.apply_styles(&element);
-    let children = element
-      .children
-      .iter()
-      .map(|child| Self::build_node(child.clone(), css_engine))
+  fn build_node(
+    context: &ElementContext,
+    parent_styles: Option<&ComputedStyles>,
+    ancestors: &AncestorFilter,
+    css_engine: &CssEngine,
+  ) -> ComponentNode {
+    let resolved = ResolvedStyles::resolve(css_engine, context, parent_styles, ancestors);
+    let child_ancestors = ancestors.with_parent(context.element);
+    let children = (0..context.element.children.len())
+      .map(|index| {
+        Self::build_node(
+          &context.child(index),
+          Some(&resolved.styles),
+          &child_ancestors,
+          css_engine,
+        )
+      })
       .collect();
 
-    ComponentNode {
-      element,
-      styles,
-      children,
-    }
+    resolved.into_node(context.element.clone(), children)
   }
 
   fn build_node_cached(
-    element: Element,
+    context: &ElementContext,
+    parent_styles: Option<&ComputedStyles>,
+    ancestors: &AncestorFilter,
+    parent_key: u64,
     css_engine: &CssEngine,
-    cache: &mut HashMap<String, ComputedStyles>,
+    cache: &mut StyleCache,
   ) -> ComponentNode {
-    let styles = css_engine.apply_styles_cached(&element, cache);
-    let children = element
-      .children
-      .iter()
-      .map(|child| Self::build_node_cached(child.clone(), css_engine, cache))
+    // The parent key identifies the ancestor chain, which determines the
+    // inherited values as well
+    let key = cache.key(context, parent_key);
+    let resolved = cache
+      .entries
+      .entry(key)
+      .or_insert_with(|| ResolvedStyles::resolve(css_engine, context, parent_styles, ancestors))
+      .clone();
+    let child_ancestors = ancestors.with_parent(context.element);
+    let children = (0..context.element.children.len())
+      .map(|index| {
+        Self::build_node_cached(
+          &context.child(index),
+          Some(&resolved.styles),
+          &child_ancestors,
+          key,
+          css_engine,
+          cache,
+        )
+      })
       .collect();
 
-    ComponentNode {
-      element,
-      styles,
-      children,
-    }
+    resolved.into_node(context.element.clone(), children)
   }
 
   pub fn root(&self) -> &ComponentNode {
@@ -660,25 +1630,305 @@ impl ComponentTree {
   }
 
   pub fn update_styles(&mut self, css_engine: &CssEngine) {
-    Self::update_node_styles(&mut self.root, css_engine);
+    Self::update_node_styles(
+      &mut self.root,
+      None,
+      None,
+      &AncestorFilter::default(),
+      0,
+      css_engine,
+    );
+    self.generation = css_engine.generation;
   }
 
-  fn update_node_styles(node: &mut ComponentNode, css_engine: &CssEngine) {
-    node.styles = css_engine.apply_styles(&node.element);
-    for child in &mut node.children {
-      Self::update_node_styles(child, css_engine);
+  /// Bring the tree in line with a freshly rendered element tree of the same
+  /// shape, i.e. with the same tags and number of children everywhere.
+  ///
+  /// An element whose classes, id, attributes or interaction state (focus,
+  /// hover, active, disabled, checked) changed is restyled together with the
+  /// descendants and later siblings that selectors mentioning the change can
+  /// reach. Children are also restyled when the styles they inherit from
+  /// changed. Returns the number of restyled elements, or `None` when the
+  /// shape or the stylesheets changed, in which case the tree should be
+  /// rebuilt.
+  pub fn update(&mut self, root: &Element, css_engine: &CssEngine) -> Option<usize> {
+    if self.generation != css_engine.generation || !same_shape(&self.root.element, root) {
+      return None;
     }
+
+    let mut restyled = 0;
+    let invalidation = css_engine
+      .invalidation_sets
+      .for_change(&self.root.element, root);
+    Self::update_node(
+      &mut self.root,
+      &ElementContext::root(root),
+      None,
+      &AncestorFilter::default(),
+      Restyle::from(invalidation),
+      css_engine,
+      &mut restyled,
+    );
+    Some(restyled)
+  }
+
+  fn update_node(
+    node: &mut ComponentNode,
+    context: &ElementContext,
+    parent_styles: Option<&ComputedStyles>,
+    ancestors: &AncestorFilter,
+    restyle: Restyle,
+    css_engine: &CssEngine,
+    restyled: &mut usize,
+  ) {
+    let mut children_restyle = Restyle {
+      element: restyle.descendants,
+      descendants: restyle.descendants,
+    };
+    if restyle.element {
+      let resolved = ResolvedStyles::resolve(css_engine, context, parent_styles, ancestors);
+      // Children inherit from the new styles
+      children_restyle.element |= resolved.styles != node.styles;
+      node.styles = resolved.styles;
+      node.before = resolved.before;
+      node.after = resolved.after;
+      *restyled += 1;
+    }
+
+    let child_ancestors = ancestors.with_parent(context.element);
+    let mut siblings_dirty = false;
+    let ComponentNode {
+      element,
+      styles,
+      children,
+      ..
+    } = node;
+    for (index, child) in children.iter_mut().enumerate() {
+      let child_context = context.child(index);
+      let invalidation = css_engine
+        .invalidation_sets
+        .for_change(&child.element, child_context.element);
+      let mut child_restyle = children_restyle;
+      child_restyle |= Restyle::from(invalidation);
+      if siblings_dirty {
+        child_restyle |= Restyle::SUBTREE;
+      }
+      Self::update_node(
+        child,
+        &child_context,
+        Some(styles),
+        &child_ancestors,
+        child_restyle,
+        css_engine,
+        restyled,
+      );
+      siblings_dirty |= invalidation.siblings;
+    }
+    *element = context.element.clone();
+  }
+
+  fn update_node_styles(
+    node: &mut ComponentNode,
+    parent: Option<&ElementContext>,
+    parent_styles: Option<&ComputedStyles>,
+    ancestors: &AncestorFilter,
+    index: usize,
+    css_engine: &CssEngine,
+  ) {
+    let ComponentNode {
+      element,
+      styles,
+      children,
+      before,
+      after,
+    } = node;
+    let context = ElementContext {
+      element,
+      parent,
+      index,
+    };
+    let resolved = ResolvedStyles::resolve(css_engine, &context, parent_styles, ancestors);
+    *styles = resolved.styles;
+    *before = resolved.before;
+    *after = resolved.after;
+    let child_ancestors = ancestors.with_parent(element);
+    for (index, child) in children.iter_mut().enumerate() {
+      Self::update_node_styles(
+        child,
+        Some(&context),
+        Some(styles),
+        &child_ancestors,
+        index,
+        css_engine,
+      );
+    }
+  }
+}
+
+/// What [`ComponentTree::update`] restyles at an element
+#[derive(Debug, Clone, Copy, Default)]
+struct Restyle {
+  /// The element itself
+  element: bool,
+  /// All of its descendants
+  descendants: bool,
+}
+
+impl Restyle {
+  const SUBTREE: Self = Self {
+    element: true,
+    descendants: true,
+  };
+}
+
+impl From<Invalidation> for Restyle {
+  fn from(invalidation: Invalidation) -> Self {
+    Self {
+      element: invalidation.element,
+      descendants: invalidation.descendants,
+    }
+  }
+}
+
+impl std::ops::BitOrAssign for Restyle {
+  fn bitor_assign(&mut self, other: Self) {
+    self.element |= other.element;
+    self.descendants |= other.descendants;
   }
 }
 
 impl Stylesheet {
   pub fn empty() -> Self {
-    Self { rules: Vec::new() }
+    Self {
+      rules: Vec::new(),
+      errors: Vec::new(),
+      imports: Vec::new(),
+      layers: Vec::new(),
+      imported_files: Vec::new(),
+      source: None,
+      scope: None,
+    }
   }
 
+  /// Parse CSS text; `@import` rules are left unresolved in [`Stylesheet::imports`]
   pub fn from_string(css: &str) -> Result<Self> {
-    let engine = CssEngine::new();
-    engine.parse_css(css)
+    Ok(parser::parse_stylesheet(css))
+  }
+
+  /// Read and parse a stylesheet file, resolving its `@import` rules
+  /// relative to the file's directory
+  pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+    let path = path.as_ref();
+    let css = std::fs::read_to_string(path).map_err(|e| {
+      TuiError::component(format!("Failed to read stylesheet {}: {e}", path.display()))
+    })?;
+    let mut stylesheet = Self::from_string(&css)?;
+    let mut importing = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+    stylesheet.resolve_imports_from(path.parent().unwrap_or(Path::new("")), &mut importing);
+    stylesheet.source = Some(path.to_path_buf());
+    Ok(stylesheet)
+  }
+
+  /// Inline the sheet's `@import` rules, reading their URLs relative to
+  /// `base_dir`.
+  ///
+  /// Imported rules come before the sheet's own rules, inside the import's
+  /// layer and media conditions. Missing files and import cycles are
+  /// recorded in [`Stylesheet::errors`] and otherwise skipped.
+  pub fn resolve_imports(&mut self, base_dir: &Path) {
+    self.resolve_imports_from(base_dir, &mut Vec::new());
+  }
+
+  fn resolve_imports_from(&mut self, base_dir: &Path, importing: &mut Vec<PathBuf>) {
+    let mut rules = Vec::new();
+    let mut layers = Vec::new();
+    let mut declared = 0;
+
+    for import in std::mem::take(&mut self.imports) {
+      layers.extend_from_slice(&self.layers[declared..import.layers_before]);
+      declared = import.layers_before;
+
+      let path = base_dir.join(&import.url);
+      let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+      if importing.contains(&canonical) {
+        self
+          .errors
+          .push(import.error(format!("circular `@import` of `{}`", import.url)));
+        continue;
+      }
+      let css = match std::fs::read_to_string(&path) {
+        Ok(css) => css,
+        Err(e) => {
+          self
+            .errors
+            .push(import.error(format!("cannot import `{}`: {e}", import.url)));
+          continue;
+        }
+      };
+
+      let mut imported = parser::parse_stylesheet(&css);
+      importing.push(canonical);
+      imported.resolve_imports_from(path.parent().unwrap_or(Path::new("")), importing);
+      importing.pop();
+      if let Some(layer) = &import.layer {
+        imported.nest_in_layer(layer);
+      }
+      for rule in &mut imported.rules {
+        if let Some(media) = &import.media {
+          rule.media.insert(0, media.clone());
+        }
+      }
+
+      self
+        .errors
+        .extend(imported.errors.into_iter().map(|error| CssParseError {
+          kind: error.kind,
+          message: format!("{}:{}: {}", import.url, error.location, error.message),
+          ..import.error(String::new())
+        }));
+      self.imported_files.push(path);
+      self.imported_files.extend(imported.imported_files);
+      for layer in imported.layers {
+        if !layers.contains(&layer) {
+          layers.push(layer);
+        }
+      }
+      rules.extend(imported.rules);
+    }
+
+    for layer in self.layers.drain(declared..) {
+      if !layers.contains(&layer) {
+        layers.push(layer);
+      }
+    }
+    self.layers = layers;
+    rules.append(&mut self.rules);
+    self.rules = rules;
+  }
+
+  /// Move every rule and layer of the sheet into the layer `name`
+  fn nest_in_layer(&mut self, name: &str) {
+    for rule in &mut self.rules {
+      rule.layer = Some(match rule.layer.take() {
+        Some(layer) => format!("{name}.{layer}"),
+        None => name.to_string(),
+      });
+    }
+    for layer in &mut self.layers {
+      *layer = format!("{name}.{layer}");
+    }
+    self.layers.insert(0, name.to_string());
+  }
+}
+
+impl ImportRule {
+  fn error(&self, message: String) -> CssParseError {
+    CssParseError {
+      kind: DiagnosticKind::Import,
+      message,
+      location: self.location,
+      source: format!("@import \"{}\"", self.url),
+    }
   }
 }
 
@@ -688,29 +1938,755 @@ impl Default for CssEngine {
   }
 }
 
-/// Build a stable cache key from an element's styling-relevant identity
-fn style_cache_key(element: &Element) -> String {
-  // Sort classes and attributes for stable key
-  let mut classes = element.classes.clone();
+/// Per-build cache of resolved styles keyed by an element's styling context
+pub(crate) struct StyleCache {
+  entries: HashMap<u64, ResolvedStyles>,
+  /// When sibling selectors are in use, an element's position among its
+  /// siblings becomes part of its key
+  sibling_sensitive: bool,
+}
+
+impl StyleCache {
+  fn new(sibling_sensitive: bool) -> Self {
+    Self {
+      entries: HashMap::new(),
+      sibling_sensitive,
+    }
+  }
+
+  /// Key combining the element's identity with its parent's key, so elements
+  /// only share styles when their ancestors do too
+  fn key(&self, context: &ElementContext, parent_key: u64) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    parent_key.hash(&mut hasher);
+    hash_style_identity(context.element, &mut hasher);
+    if self.sibling_sensitive {
+      context.index.hash(&mut hasher);
+      context.siblings().len().hash(&mut hasher);
+      for sibling in context.previous_siblings() {
+        hash_style_identity(sibling.element, &mut hasher);
+      }
+    }
+    hasher.finish()
+  }
+}
+
+/// Whether the element or one of its ancestors is the root of `scope`
+fn in_scope(context: &ElementContext, scope: &str) -> bool {
+  let is_root = |element: &Element| {
+    element
+      .get_attribute(SCOPE_ATTRIBUTE)
+      .is_some_and(|value| value == scope)
+  };
+  is_root(context.element)
+    || context
+      .ancestors()
+      .any(|ancestor| is_root(ancestor.element))
+}
+
+/// Hash an element's styling-relevant identity
+fn hash_style_identity(element: &Element, hasher: &mut impl Hasher) {
+  // Sort classes and attributes for a stable hash
+  let mut classes: Vec<&String> = element.classes.iter().collect();
   classes.sort();
 
-  let mut attrs: Vec<(String, String)> = element
-    .attributes
-    .iter()
-    .map(|(k, v)| (k.clone(), v.clone()))
-    .collect();
-  attrs.sort_by(|a, b| a.0.cmp(&b.0));
+  let mut attrs: Vec<(&String, &String)> = element.attributes.iter().collect();
+  attrs.sort();
 
-  let id_part = element.id.clone().unwrap_or_default();
-  let classes_part = classes.join(".");
-  let attrs_part = attrs
-    .into_iter()
-    .map(|(k, v)| format!("{k}={v}"))
-    .collect::<Vec<_>>()
-    .join(";");
-
-  format!(
-    "tag={}#{}.[{}]{{{}}}",
-    element.tag, id_part, classes_part, attrs_part
-  )
+  element.tag.hash(hasher);
+  element.id.hash(hasher);
+  classes.hash(hasher);
+  attrs.hash(hasher);
+  ElementState::of(element).hash(hasher);
+}
+
+/// Interaction state of an element that state pseudo-classes match against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ElementState {
+  focused: bool,
+  hovered: bool,
+  active: bool,
+  disabled: bool,
+  checked: bool,
+}
+
+impl ElementState {
+  fn of(element: &Element) -> Self {
+    Self {
+      focused: element.focused,
+      hovered: element.hovered,
+      active: element.active,
+      disabled: element.disabled,
+      checked: element.is_checked(),
+    }
+  }
+}
+
+/// Whether two element trees have the same tags and numbers of children
+/// everywhere, so the styles of one can be updated in place to match the other
+fn same_shape(a: &Element, b: &Element) -> bool {
+  a.tag == b.tag
+    && a.children.len() == b.children.len()
+    && a
+      .children
+      .iter()
+      .zip(&b.children)
+      .all(|(x, y)| same_shape(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn engine(css: &str) -> CssEngine {
+    let mut engine = CssEngine::new();
+    engine.load_stylesheet(css).unwrap();
+    engine
+  }
+
+  #[test]
+  fn test_cascade_orders_by_specificity_then_source_order() {
+    let engine = engine(
+      "#ok { color: red } button.primary { color: blue } .primary { color: green }\n\
+       button { width: 10 } button { width: 20 }",
+    );
+    let button = Element::with_tag("button")
+      .class("primary")
+      .id("ok")
+      .build();
+    let styles = engine.apply_styles(&button);
+    assert_eq!(styles.color, Some(CrosstermColor::Red));
+    assert!(matches!(styles.width, SizeValue::Pixels(20)));
+  }
+
+  #[test]
+  fn test_important_overrides_specificity() {
+    let engine = engine(".a { color: red !important } #b.a { color: blue }");
+    let element = Element::with_tag("div").class("a").id("b").build();
+    assert_eq!(
+      engine.apply_styles(&element).color,
+      Some(CrosstermColor::Red)
+    );
+  }
+
+  #[test]
+  fn test_component_tree_matches_combinators() {
+    let engine =
+      engine(".list > .item + .item { color: red } .list .item:last-child { color: blue }");
+    let root = Element::with_tag("div")
+      .class("list")
+      .child(Element::with_tag("div").class("item").build())
+      .child(Element::with_tag("div").class("item").build())
+      .child(Element::with_tag("div").class("item").build())
+      .build();
+
+    for tree in [
+      engine.create_component_tree(&root),
+      engine.create_component_tree_cached(&root),
+    ] {
+      let colors: Vec<_> = tree
+        .root()
+        .children
+        .iter()
+        .map(|c| c.styles.color)
+        .collect();
+      assert_eq!(
+        colors,
+        vec![None, Some(CrosstermColor::Red), Some(CrosstermColor::Blue)]
+      );
+    }
+  }
+
+  fn buttons(focused: Option<usize>) -> Element {
+    let mut root = Element::with_tag("div")
+      .child(Element::with_tag("button").class("btn").build())
+      .child(Element::with_tag("button").class("btn").build())
+      .child(Element::with_tag("button").class("btn").build())
+      .build();
+    if let Some(index) = focused {
+      root.children[index].focused = true;
+    }
+    root
+  }
+
+  #[test]
+  fn test_state_pseudo_classes_apply() {
+    let engine = engine(".btn:focus { color: red } .btn:hover { background-color: blue }");
+    let mut button = Element::with_tag("button").class("btn").build();
+    assert_eq!(engine.apply_styles(&button).color, None);
+
+    button.focused = true;
+    button.hovered = true;
+    let styles = engine.apply_styles(&button);
+    assert_eq!(styles.color, Some(CrosstermColor::Red));
+    assert_eq!(styles.background_color, Some(CrosstermColor::Blue));
+  }
+
+  #[test]
+  fn test_update_restyles_only_changed_elements() {
+    let engine = engine(".btn:focus { color: red }");
+    let mut tree = engine.create_component_tree(&buttons(None));
+
+    assert_eq!(tree.update(&buttons(Some(1)), &engine), Some(1));
+    let colors: Vec<_> = tree
+      .root()
+      .children
+      .iter()
+      .map(|c| c.styles.color)
+      .collect();
+    assert_eq!(colors, vec![None, Some(CrosstermColor::Red), None]);
+
+    // Moving focus restyles the element losing it and the one gaining it
+    assert_eq!(tree.update(&buttons(Some(2)), &engine), Some(2));
+    let colors: Vec<_> = tree
+      .root()
+      .children
+      .iter()
+      .map(|c| c.styles.color)
+      .collect();
+    assert_eq!(colors, vec![None, None, Some(CrosstermColor::Red)]);
+
+    assert_eq!(tree.update(&buttons(Some(2)), &engine), Some(0));
+  }
+
+  #[test]
+  fn test_update_invalidates_siblings_and_rebuilds_on_change() {
+    let mut engine = engine(".btn:focus + .btn { color: red }");
+    let mut tree = engine.create_component_tree(&buttons(None));
+
+    // Focus only appears before a combinator, so just the siblings after the
+    // focused element are restyled
+    assert_eq!(tree.update(&buttons(Some(0)), &engine), Some(2));
+    let colors: Vec<_> = tree
+      .root()
+      .children
+      .iter()
+      .map(|c| c.styles.color)
+      .collect();
+    assert_eq!(colors, vec![None, Some(CrosstermColor::Red), None]);
+
+    let other = Element::with_tag("div").class("other").build();
+    assert_eq!(tree.update(&other, &engine), None);
+
+    engine.add_css(".btn { color: blue }").unwrap();
+    assert_eq!(tree.update(&buttons(Some(0)), &engine), None);
+  }
+
+  #[test]
+  fn test_update_restyles_only_subtrees_reached_by_class_changes() {
+    let engine = engine(
+      ".list.open .row { color: green }\n\
+       .row.selected { background-color: blue }\n\
+       .list { color: red }",
+    );
+    let list = |open: bool, selected: Option<usize>| {
+      let mut list = Element::with_tag("div").class("list");
+      if open {
+        list = list.class("open");
+      }
+      for index in 0..4 {
+        let mut row = Element::with_tag("div").class("row");
+        if selected == Some(index) {
+          row = row.class("selected");
+        }
+        list = list.child(row.child(Element::with_tag("text").build()).build());
+      }
+      Element::with_tag("div")
+        .child(list.build())
+        .child(Element::with_tag("div").class("status").build())
+        .build()
+    };
+    let mut tree = engine.create_component_tree(&list(false, None));
+
+    // Selecting a row restyles the row, and its child since the row's styles
+    // changed, but none of the other rows
+    assert_eq!(tree.update(&list(false, Some(2)), &engine), Some(2));
+    let rows = &tree.root().children[0].children;
+    assert_eq!(rows[2].styles.background_color, Some(CrosstermColor::Blue));
+
+    // A class that selectors use on ancestors restyles the whole list
+    assert_eq!(tree.update(&list(true, Some(2)), &engine), Some(9));
+    let rows = &tree.root().children[0].children;
+    assert_eq!(rows[0].styles.color, Some(CrosstermColor::Green));
+    assert_eq!(
+      rows[0].children[0].styles.color,
+      Some(CrosstermColor::Green)
+    );
+
+    // Classes no selector mentions only restyle their element
+    let mut tagged = list(true, Some(2));
+    tagged.children[1].classes.push("busy".to_string());
+    assert_eq!(tree.update(&tagged, &engine), Some(1));
+
+    // The result matches a full rebuild
+    let rebuilt = engine.create_component_tree(&tagged);
+    assert_eq!(
+      tree.root().children[0].children[3].children[0].styles,
+      rebuilt.root().children[0].children[3].children[0].styles
+    );
+  }
+
+  #[test]
+  fn test_custom_properties_inherit_and_feed_values() {
+    let engine = engine(
+      ":root { --accent: #007acc; --gap: 2 }\n\
+       .panel { --gap: 3 }\n\
+       .card { color: var(--accent); padding: var(--gap); width: calc(100% - var(--gap) * 2) }\n\
+       .broken { color: var(--missing); background-color: var(--missing, red) }",
+    );
+    let root = Element::with_tag("div")
+      .child(Element::with_tag("div").class("card").build())
+      .child(
+        Element::with_tag("div")
+          .class("panel")
+          .child(
+            Element::with_tag("div")
+              .class("card")
+              .class("broken")
+              .build(),
+          )
+          .build(),
+      )
+      .build();
+    let tree = engine.create_component_tree_cached(&root);
+
+    let card = &tree.root().children[0].styles;
+    assert_eq!(
+      card.color,
+      Some(CrosstermColor::Rgb {
+        r: 0x00,
+        g: 0x7a,
+        b: 0xcc
+      })
+    );
+    assert_eq!(card.padding.top, 2);
+    assert_eq!(
+      card.width,
+      SizeValue::Calc {
+        percent: 100.0,
+        cells: -4.0
+      }
+    );
+    assert_eq!(
+      card.to_layout_styles().width,
+      SizeValue::Calc {
+        percent: 100.0,
+        cells: -4.0
+      }
+    );
+
+    let nested = &tree.root().children[1].children[0].styles;
+    assert_eq!(nested.padding.left, 3);
+    assert_eq!(nested.background_color, Some(CrosstermColor::Red));
+    // `var(--missing)` without fallback invalidates the declaration, so the
+    // earlier `.card` color remains
+    assert!(matches!(nested.color, Some(CrosstermColor::Rgb { .. })));
+  }
+
+  #[test]
+  fn test_media_rules_follow_viewport_and_color_support() {
+    let mut engine = engine(
+      ".panel { width: 20 }\n\
+       @media (min-width: 120) { .panel { width: 40 } }\n\
+       @media (colors: 256) { .panel { color: red } }",
+    );
+    let root = Element::with_tag("div").class("panel").build();
+    engine.set_media_environment(MediaEnvironment {
+      width: 100,
+      height: 30,
+      color_support: crate::themes::ColorSupport::Ansi256,
+      ..MediaEnvironment::default()
+    });
+    let mut tree = engine.create_component_tree(&root);
+    assert_eq!(tree.root().styles.width, SizeValue::Pixels(20));
+    assert_eq!(tree.root().styles.color, Some(CrosstermColor::Red));
+
+    // Resizing within the same breakpoint keeps the tree valid
+    engine.set_viewport_size(110, 40);
+    assert_eq!(tree.update(&root, &engine), Some(0));
+
+    engine.set_viewport_size(120, 40);
+    assert_eq!(tree.update(&root, &engine), None);
+    tree.update_styles(&engine);
+    assert_eq!(tree.root().styles.width, SizeValue::Pixels(40));
+  }
+
+  #[test]
+  fn test_text_properties_inherit_and_css_wide_keywords() {
+    let mut engine = engine(
+      ".panel { color: red; font-weight: bold; text-align: center; white-space: nowrap; padding: 2 }\n\
+       .plain { color: initial; font-weight: unset; padding: inherit }\n\
+       .boxed { border-color: blue } .boxed .edge { border-color: inherit; text-align: unset }",
+    );
+    let root = Element::with_tag("div")
+      .class("panel")
+      .child(
+        Element::with_tag("div")
+          .child(Element::with_tag("text").build())
+          .build(),
+      )
+      .child(Element::with_tag("div").class("plain").build())
+      .child(
+        Element::with_tag("div")
+          .class("boxed")
+          .child(Element::with_tag("div").class("edge").build())
+          .build(),
+      )
+      .build();
+    let tree = engine.create_component_tree(&root);
+
+    // Inherited through an element without rules of its own
+    let leaf = &tree.root().children[0].children[0].styles;
+    assert_eq!(leaf.color, Some(CrosstermColor::Red));
+    assert_eq!(leaf.font_weight, FontWeight::Bold);
+    assert_eq!(leaf.text_align, TextAlign::Center);
+    assert_eq!(leaf.white_space, WhiteSpace::NoWrap);
+    // Padding is not inherited by default
+    assert_eq!(leaf.padding.top, 0);
+
+    let plain = &tree.root().children[1].styles;
+    assert_eq!(plain.color, None);
+    assert_eq!(plain.font_weight, FontWeight::Bold);
+    assert_eq!(plain.padding.top, 2);
+
+    let edge = &tree.root().children[2].children[0].styles;
+    assert_eq!(edge.border_color, Some(CrosstermColor::Blue));
+    assert_eq!(edge.text_align, TextAlign::Center);
+
+    // Restyling the tree keeps inheritance intact
+    engine.add_css(".panel { color: green }").unwrap();
+    let mut tree = tree;
+    tree.update_styles(&engine);
+    let leaf = &tree.root().children[0].children[0].styles;
+    assert_eq!(leaf.color, Some(CrosstermColor::Green));
+  }
+
+  #[test]
+  fn test_text_wrapping_properties() {
+    let engine = engine(
+      ".card { word-wrap: anywhere; text-overflow: \"..\" }\n\
+       .title { white-space: pre-wrap; text-overflow: ellipsis; text-align: justify }",
+    );
+    let root = Element::with_tag("div")
+      .class("card")
+      .child(Element::with_tag("text").class("title").build())
+      .build();
+    let tree = engine.create_component_tree(&root);
+
+    let card = &tree.root().styles;
+    assert_eq!(card.overflow_wrap, OverflowWrap::Anywhere);
+    assert_eq!(card.text_overflow, TextOverflow::String("..".to_string()));
+
+    // overflow-wrap inherits, text-overflow does not
+    let title = tree.root().children[0].styles.text_style();
+    assert_eq!(
+      title,
+      TextStyle {
+        white_space: WhiteSpace::PreWrap,
+        overflow_wrap: OverflowWrap::Anywhere,
+        text_overflow: TextOverflow::Ellipsis,
+        text_align: TextAlign::Justify,
+      }
+    );
+    assert_eq!(
+      tree.root().children[0].styles.to_layout_styles().text,
+      title
+    );
+  }
+
+  #[test]
+  fn test_opacity_z_index_and_transparent_background() {
+    let engine = engine(
+      ".veil { opacity: 40%; z-index: 2; background: blue }\n\
+       .veil.clear { background: transparent; opacity: 1.5; z-index: auto }",
+    );
+    let veil = engine.apply_styles(&Element::with_tag("div").class("veil").build());
+    assert_eq!(veil.opacity, 0.4);
+    assert_eq!(veil.z_index, 2);
+    assert_eq!(veil.background_color, Some(CrosstermColor::Blue));
+    assert!(veil.creates_layer());
+    assert_eq!(veil.to_layout_styles().z_index, 2);
+
+    let clear = engine.apply_styles(
+      &Element::with_tag("div")
+        .class("veil")
+        .class("clear")
+        .build(),
+    );
+    assert_eq!(clear.opacity, 1.0);
+    assert_eq!(clear.z_index, 0);
+    assert_eq!(clear.background_color, None);
+    assert!(!clear.creates_layer());
+  }
+
+  #[test]
+  fn test_position_and_offsets() {
+    let engine = engine(
+      ".badge { position: absolute; top: 0; right: -1; bottom: 50%; left: auto }\n\
+       .footer { position: sticky; bottom: 0 }",
+    );
+    let badge = engine.apply_styles(&Element::with_tag("div").class("badge").build());
+    assert_eq!(badge.position, PositionType::Absolute);
+    assert!(badge.creates_layer());
+    let layout = badge.to_layout_styles();
+    assert_eq!(layout.position, PositionType::Absolute);
+    assert_eq!(layout.top, SizeValue::Pixels(0));
+    assert_eq!(
+      layout.right,
+      SizeValue::Calc {
+        percent: 0.0,
+        cells: -1.0
+      }
+    );
+    assert_eq!(layout.bottom, SizeValue::Percent(50.0));
+    assert_eq!(layout.left, SizeValue::Auto);
+
+    let footer = engine.apply_styles(&Element::with_tag("div").class("footer").build());
+    assert_eq!(footer.position, PositionType::Sticky);
+    assert_eq!(footer.bottom, SizeValue::Pixels(0));
+    assert_eq!(footer.top, SizeValue::Auto);
+  }
+
+  #[test]
+  fn test_text_decoration_style_and_color() {
+    let engine = engine(
+      ".error { text-decoration: underline wavy red }\n\
+       .error.fixed { text-decoration-line: line-through overline; text-decoration-color: currentcolor }\n\
+       .hint { text-decoration: underline; text-decoration-style: dotted; font-weight: lighter }",
+    );
+    let error = engine.apply_styles(&Element::with_tag("span").class("error").build());
+    let style = error.to_render_style();
+    assert!(style.underline);
+    assert_eq!(
+      style.underline_style,
+      crate::rendering::UnderlineStyle::Curly
+    );
+    assert_eq!(
+      style.underline_color,
+      Some(crate::rendering::SerializableColor::Red)
+    );
+
+    let fixed = engine.apply_styles(
+      &Element::with_tag("span")
+        .class("error")
+        .class("fixed")
+        .build(),
+    );
+    let style = fixed.to_render_style();
+    assert!(!style.underline && style.strikethrough && style.overline);
+    // The longhand keeps the style set by the shorthand
+    assert_eq!(fixed.text_decoration_style, TextDecorationStyle::Wavy);
+    assert_eq!(style.underline_color, None);
+
+    let hint = engine.apply_styles(&Element::with_tag("span").class("hint").build());
+    let style = hint.to_render_style();
+    assert_eq!(
+      style.underline_style,
+      crate::rendering::UnderlineStyle::Dotted
+    );
+    assert!(style.dim);
+
+    let mut scratch = ComputedStyles::default();
+    assert!(!engine.apply_declaration(&mut scratch, "text-decoration", "underline none"));
+    assert!(!engine.apply_declaration(&mut scratch, "text-decoration-line", "wavy"));
+  }
+
+  #[test]
+  fn test_overflow_and_scrollbar_styles() {
+    use crate::layout::Overflow;
+
+    let engine = engine(
+      ".list { overflow: hidden auto; scrollbar-width: thin; scrollbar-color: red blue }\n\
+       .list.wide { overflow-x: scroll }\n\
+       .plain { scrollbar-color: auto }",
+    );
+    let list = engine.apply_styles(&Element::with_tag("div").class("list").build());
+    assert_eq!(list.overflow.x, Overflow::Hidden);
+    assert_eq!(list.overflow.y, Overflow::Auto);
+    assert_eq!(list.scrollbar_width, ScrollbarWidth::Thin);
+    assert_eq!(
+      list.scrollbar_color,
+      Some(ScrollbarColor {
+        thumb: CrosstermColor::Red,
+        track: CrosstermColor::Blue,
+      })
+    );
+    let layout = list.to_layout_styles();
+    assert!(layout.overflow.scrolls() && layout.overflow.clips());
+
+    let wide = engine.apply_styles(&Element::with_tag("div").class("list").class("wide").build());
+    assert_eq!(wide.overflow.x, Overflow::Scroll);
+    assert_eq!(wide.overflow.y, Overflow::Auto);
+
+    let plain = engine.apply_styles(&Element::with_tag("div").class("plain").build());
+    assert_eq!(plain.scrollbar_color, None);
+
+    let mut scratch = ComputedStyles::default();
+    assert!(!engine.apply_declaration(&mut scratch, "overflow", "auto scroll hidden"));
+    assert!(!engine.apply_declaration(&mut scratch, "scrollbar-width", "wide"));
+    assert!(!engine.apply_declaration(&mut scratch, "scrollbar-color", "red"));
+  }
+
+  #[test]
+  fn test_inline_style_attribute() {
+    let engine = engine(
+      "#gauge { color: blue; padding: 3 } .gauge { width: 10 !important } .gauge { background-color: white !important }",
+    );
+    let element = Element::with_tag("div")
+      .id("gauge")
+      .class("gauge")
+      .style("color: red; padding: 1; width: 40; background-color: black !important; bogus; ")
+      .build();
+    let styles = engine.apply_styles(&element);
+    // Inline declarations beat any selector...
+    assert_eq!(styles.color, Some(CrosstermColor::Red));
+    assert_eq!(styles.padding.top, 1);
+    // ...but not `!important` rules, unless they are `!important` themselves
+    assert_eq!(styles.width, SizeValue::Pixels(10));
+    assert_eq!(styles.background_color, Some(CrosstermColor::Black));
+  }
+
+  #[test]
+  fn test_layers_order_the_cascade() {
+    let engine = engine(
+      "@layer base, theme;\n\
+       .a { color: white }\n\
+       @layer theme { .a { color: blue } #x { padding: 3 !important } }\n\
+       @layer base { #x.a { color: red } #x { padding: 1 !important } }",
+    );
+    let element = Element::with_tag("div").id("x").class("a").build();
+    let styles = engine.apply_styles(&element);
+    // Unlayered rules beat any layer, later layers beat earlier ones
+    assert_eq!(styles.color, Some(CrosstermColor::White));
+    // `!important` reverses layer order
+    assert_eq!(styles.padding.top, 1);
+
+    let mut engine = engine;
+    engine
+      .load_stylesheet_in_layer(".b { color: red }", "theme")
+      .unwrap();
+    engine
+      .load_stylesheet("@layer base { .b { color: green } }")
+      .unwrap();
+    let styles = engine.apply_styles(&Element::with_tag("div").class("b").build());
+    assert_eq!(styles.color, Some(CrosstermColor::Red));
+  }
+
+  #[test]
+  fn test_imports_resolve_relative_to_importing_file() {
+    let dir = std::env::temp_dir().join(format!("reactive-tui-import-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("widgets")).unwrap();
+    std::fs::write(
+      dir.join("main.css"),
+      "@import \"widgets/all.css\" layer(widgets);\n@import \"missing.css\";\n.a { padding: 2 }",
+    )
+    .unwrap();
+    std::fs::write(
+      dir.join("widgets/all.css"),
+      "@import \"button.css\";\n.a { padding: 1; color: blue }",
+    )
+    .unwrap();
+    std::fs::write(
+      dir.join("widgets/button.css"),
+      "@import \"all.css\";\n.b { color: red }",
+    )
+    .unwrap();
+
+    let sheet = Stylesheet::from_file(dir.join("main.css")).unwrap();
+    let selectors: Vec<_> = sheet.rules.iter().map(|r| r.selector.as_str()).collect();
+    assert_eq!(selectors, vec![".b", ".a", ".a"]);
+    assert_eq!(sheet.rules[0].layer.as_deref(), Some("widgets"));
+    assert_eq!(sheet.rules[2].layer, None);
+    assert_eq!(sheet.imported_files.len(), 2);
+    let messages: Vec<_> = sheet.errors.iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(messages.len(), 2, "{messages:?}");
+    assert!(messages[0].contains("circular `@import` of `all.css`"));
+    assert!(messages[1].starts_with("cannot import `missing.css`"));
+
+    let mut engine = CssEngine::new();
+    engine.load_stylesheet_file(dir.join("main.css")).unwrap();
+    let styles = engine.apply_styles(&Element::with_tag("div").class("a").build());
+    assert_eq!(styles.padding.top, 2);
+    assert_eq!(styles.color, Some(CrosstermColor::Blue));
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_scoped_stylesheets_stay_inside_their_subtree() {
+    let mut engine = engine(".title { padding: 2 }");
+    engine
+      .load_scoped_stylesheet("widget", ".title { color: red; padding: 1 }")
+      .unwrap();
+    let root = Element::with_tag("div")
+      .child(Element::with_tag("text").class("title").build())
+      .child(
+        Element::with_tag("div")
+          .attr(SCOPE_ATTRIBUTE, "widget")
+          .child(Element::with_tag("text").class("title").build())
+          .build(),
+      )
+      .build();
+    let tree = engine.create_component_tree(&root);
+    let host = &tree.root().children[0].styles;
+    let widget = &tree.root().children[1].children[0].styles;
+    assert_eq!(host.color, None);
+    assert_eq!(widget.color, Some(CrosstermColor::Red));
+    // App styles win ties against component styles
+    assert_eq!(widget.padding.top, 2);
+
+    // Reloading app stylesheets keeps component styles
+    engine.clear_stylesheets();
+    let tree = engine.create_component_tree(&root);
+    assert_eq!(tree.root().children[1].children[0].styles.padding.top, 1);
+  }
+
+  #[test]
+  fn test_before_and_after_generate_inline_content() {
+    let engine = engine(
+      ".item { color: white }\n\
+       .item::before { content: \"▶ \"; color: green }\n\
+       .item:hover::before { content: \"▷ \" }\n\
+       .item::after { content: \" (\" attr(data-count) \")\" }\n\
+       .plain::before { content: none }",
+    );
+    let root = Element::with_tag("ul")
+      .child(
+        Element::with_tag("li")
+          .class("item")
+          .attr("data-count", "3")
+          .content("Inbox")
+          .build(),
+      )
+      .child(
+        Element::with_tag("li")
+          .class("plain")
+          .content("Sent")
+          .build(),
+      )
+      .build();
+    let tree = engine.create_component_tree_cached(&root);
+    let item = &tree.root().children[0];
+    let before = item.before.as_ref().unwrap();
+    assert_eq!(before.text, "▶ ");
+    assert_eq!(before.styles.color, Some(CrosstermColor::Green));
+    assert_eq!(before.styles.display, DisplayType::Inline);
+    // `::after` inherits from its element
+    assert_eq!(
+      item.after.as_ref().unwrap().styles.color,
+      Some(CrosstermColor::White)
+    );
+    assert_eq!(item.inline_content().as_deref(), Some("▶ Inbox (3)"));
+    // Rules for generated content never style the element itself
+    assert_eq!(item.styles.color, Some(CrosstermColor::White));
+
+    let plain = &tree.root().children[1];
+    assert!(plain.before.is_none() && plain.after.is_none());
+    assert_eq!(plain.inline_content().as_deref(), Some("Sent"));
+
+    let mut tree = tree;
+    let mut hovered = root.clone();
+    hovered.children[0].hovered = true;
+    assert!(tree.update(&hovered, &engine).is_some());
+    assert_eq!(tree.root().children[0].before.as_ref().unwrap().text, "▷ ");
+  }
 }
@@ -0,0 +1,697 @@
+//! Tokenizer-driven stylesheet parser built on the `cssparser` crate
+//!
+//! Produces [`Stylesheet`] values with source locations for every rule and
+//! declaration. Errors are recovered per rule (or per declaration inside a
+//! rule) and collected on the stylesheet instead of aborting the whole parse.
+
+use super::media::{parse_media_query_list, MediaQueryList};
+use super::{
+  CssParseError, CssRule, Declaration, DiagnosticKind, ImportRule, Selector, SourceLocation,
+  Stylesheet,
+};
+use cssparser::{
+  match_ignore_ascii_case, AtRuleParser, BasicParseErrorKind, CowRcStr, DeclarationParser,
+  ParseError, ParseErrorKind, Parser, ParserInput, ParserState, QualifiedRuleParser,
+  RuleBodyItemParser, RuleBodyParser, StyleSheetParser, ToCss, Token,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Start of the message for a rule dropped because of its selector
+const UNSUPPORTED_SELECTOR: &str = "unsupported selector";
+
+/// Parse CSS source text into a stylesheet, recovering from errors per rule
+pub(crate) fn parse_stylesheet(css: &str) -> Stylesheet {
+  let mut input = ParserInput::new(css);
+  let mut parser = Parser::new(&mut input);
+  let mut rule_parser = TopLevelRuleParser::default();
+  let mut rules = Vec::new();
+  let mut pending_errors = Vec::new();
+
+  for result in StyleSheetParser::new(&mut parser, &mut rule_parser) {
+    match result {
+      Ok(parsed) => rules.extend(parsed),
+      Err((error, slice)) => pending_errors.push(convert_error(&error, slice)),
+    }
+  }
+
+  let mut errors = rule_parser.errors;
+  errors.extend(pending_errors);
+  errors.sort_by_key(|e| (e.location.line, e.location.column));
+
+  Stylesheet {
+    rules,
+    errors,
+    imports: rule_parser.imports,
+    layers: rule_parser.layers,
+    ..Stylesheet::empty()
+  }
+}
+
+/// Parse the contents of an inline `style` attribute, such as
+/// `color: red; padding: 1`, recovering from errors per declaration
+pub(crate) fn parse_declaration_list(css: &str) -> (Vec<Declaration>, Vec<CssParseError>) {
+  let mut input = ParserInput::new(css);
+  let mut parser = Parser::new(&mut input);
+  let mut body_parser = DeclarationListParser;
+  let mut declarations = Vec::new();
+  let mut errors = Vec::new();
+
+  for result in RuleBodyParser::new(&mut parser, &mut body_parser) {
+    match result {
+      Ok(declaration) => declarations.push(declaration),
+      Err((error, slice)) => errors.push(convert_error(&error, slice)),
+    }
+  }
+
+  (declarations, errors)
+}
+
+fn to_location(location: cssparser::SourceLocation) -> SourceLocation {
+  // cssparser reports 0-based lines and 1-based columns
+  SourceLocation {
+    line: location.line + 1,
+    column: location.column,
+  }
+}
+
+fn convert_error(error: &ParseError<'_, String>, slice: &str) -> CssParseError {
+  let kind = match &error.kind {
+    ParseErrorKind::Basic(BasicParseErrorKind::AtRuleInvalid(_)) => {
+      DiagnosticKind::UnsupportedAtRule
+    }
+    ParseErrorKind::Custom(message) if message.starts_with(UNSUPPORTED_SELECTOR) => {
+      DiagnosticKind::UnsupportedSelector
+    }
+    _ => DiagnosticKind::Syntax,
+  };
+  let message = match &error.kind {
+    ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(token)) => {
+      format!("unexpected token `{}`", token.to_css_string())
+    }
+    ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput) => "unexpected end of input".to_string(),
+    ParseErrorKind::Basic(BasicParseErrorKind::AtRuleInvalid(name)) => {
+      format!("unsupported at-rule `@{name}`")
+    }
+    ParseErrorKind::Basic(BasicParseErrorKind::AtRuleBodyInvalid) => {
+      "invalid at-rule body".to_string()
+    }
+    ParseErrorKind::Basic(BasicParseErrorKind::QualifiedRuleInvalid) => "invalid rule".to_string(),
+    ParseErrorKind::Custom(message) => message.clone(),
+  };
+
+  CssParseError {
+    kind,
+    message,
+    location: to_location(error.location),
+    source: slice.trim().to_string(),
+  }
+}
+
+/// Serialize the remaining tokens of `input` into normalized CSS text.
+///
+/// Comments are dropped and runs of whitespace collapse to a single space.
+fn serialize_tokens<'i>(
+  input: &mut Parser<'i, '_>,
+  out: &mut String,
+) -> Result<(), ParseError<'i, String>> {
+  let mut pending_space = false;
+  loop {
+    let token = match input.next_including_whitespace() {
+      Ok(token) => token.clone(),
+      Err(_) => break,
+    };
+
+    if let Token::WhiteSpace(_) = token {
+      pending_space = true;
+      continue;
+    }
+
+    if pending_space && !out.is_empty() {
+      out.push(' ');
+    }
+    pending_space = false;
+
+    token
+      .to_css(out)
+      .map_err(|_| input.new_custom_error("failed to serialize token".to_string()))?;
+
+    let closing = match token {
+      Token::Function(_) | Token::ParenthesisBlock => Some(')'),
+      Token::SquareBracketBlock => Some(']'),
+      Token::CurlyBracketBlock => Some('}'),
+      _ => None,
+    };
+
+    if let Some(closing) = closing {
+      input.parse_nested_block(|nested| serialize_tokens(nested, out))?;
+      out.push(closing);
+    }
+  }
+  Ok(())
+}
+
+/// Serialize a declaration value, stripping a trailing `!important`.
+///
+/// Returns the normalized value text and whether it was marked important.
+fn parse_value_text<'i>(
+  input: &mut Parser<'i, '_>,
+) -> Result<(String, bool), ParseError<'i, String>> {
+  let start = input.position();
+  let mut important = false;
+  let mut end = None;
+
+  // Find a trailing `!important` so it can be excluded from the value text
+  loop {
+    let before = input.position();
+    let state = input.state();
+    if input.try_parse(cssparser::parse_important).is_ok() && input.is_exhausted() {
+      important = true;
+      end = Some(before);
+      break;
+    }
+    input.reset(&state);
+    match input.next() {
+      Err(_) => break,
+      // Step over whole blocks so positions always fall between complete tokens
+      Ok(Token::Function(_))
+      | Ok(Token::ParenthesisBlock)
+      | Ok(Token::SquareBracketBlock)
+      | Ok(Token::CurlyBracketBlock) => {
+        input.parse_nested_block(|nested| {
+          while nested.next().is_ok() {}
+          Ok::<_, ParseError<'i, String>>(())
+        })?;
+      }
+      Ok(_) => {}
+    }
+  }
+
+  let value_source = match end {
+    Some(end) => input.slice(start..end),
+    None => input.slice_from(start),
+  };
+
+  let mut value_input = ParserInput::new(value_source);
+  let mut value_parser = Parser::new(&mut value_input);
+  let mut value = String::new();
+  serialize_tokens(&mut value_parser, &mut value)?;
+
+  Ok((value, important))
+}
+
+/// Parse a dotted layer name such as `framework.base`
+fn parse_layer_name<'i>(input: &mut Parser<'i, '_>) -> Result<String, ParseError<'i, String>> {
+  let mut name = input.expect_ident()?.to_string();
+  loop {
+    let state = input.state();
+    match input.next_including_whitespace() {
+      Ok(Token::Delim('.')) => match input.next_including_whitespace() {
+        Ok(Token::Ident(part)) => {
+          name.push('.');
+          name.push_str(part);
+        }
+        _ => return Err(input.new_custom_error(format!("invalid layer name `{name}.`"))),
+      },
+      _ => {
+        input.reset(&state);
+        return Ok(name);
+      }
+    }
+  }
+}
+
+/// Generate a name for an anonymous `@layer { ... }` block; no other block
+/// can refer to it, so every one is a distinct layer
+pub(crate) fn anonymous_layer_name() -> String {
+  static NEXT: AtomicU64 = AtomicU64::new(0);
+  format!("<anonymous-{}>", NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+#[derive(Default)]
+struct TopLevelRuleParser {
+  errors: Vec<CssParseError>,
+  imports: Vec<ImportRule>,
+  /// Full names of every layer in the order they were first declared
+  layers: Vec<String>,
+  /// Full names of the enclosing `@layer` blocks, innermost last
+  layer_stack: Vec<String>,
+  /// Depth of enclosing at-rule blocks
+  nesting: usize,
+  /// Whether a rule other than `@layer` statements has been seen, after
+  /// which `@import` is no longer allowed
+  seen_rules: bool,
+}
+
+impl TopLevelRuleParser {
+  /// Qualify `name` with the enclosing layer block and register it, along
+  /// with its parent layers, in declaration order
+  fn declare_layer(&mut self, name: &str) -> String {
+    let full = match self.layer_stack.last() {
+      Some(parent) => format!("{parent}.{name}"),
+      None => name.to_string(),
+    };
+    let mut end = 0;
+    for part in full.split('.') {
+      end += part.len();
+      if !self.layers.iter().any(|layer| layer == &full[..end]) {
+        self.layers.push(full[..end].to_string());
+      }
+      end += 1;
+    }
+    full
+  }
+
+  /// Parse the rest of an `@import` prelude after the URL
+  fn parse_import<'i>(
+    &mut self,
+    input: &mut Parser<'i, '_>,
+  ) -> Result<AtRulePrelude, ParseError<'i, String>> {
+    let location = to_location(input.current_source_location());
+    if self.nesting > 0 || self.seen_rules {
+      return Err(input.new_custom_error(
+        "`@import` must come before all rules other than `@layer` statements".to_string(),
+      ));
+    }
+    let url = input.expect_url_or_string()?.to_string();
+
+    let layer = if input
+      .try_parse(|input| input.expect_ident_matching("layer"))
+      .is_ok()
+    {
+      Some(anonymous_layer_name())
+    } else if input
+      .try_parse(|input| input.expect_function_matching("layer"))
+      .is_ok()
+    {
+      Some(input.parse_nested_block(parse_layer_name)?)
+    } else {
+      None
+    };
+
+    let media = if input.is_exhausted() {
+      None
+    } else {
+      Some(parse_media_query_list(input)?)
+    };
+
+    Ok(AtRulePrelude::Import(ImportRule {
+      url,
+      layer,
+      media,
+      location,
+      layers_before: 0,
+    }))
+  }
+}
+
+impl<'i> QualifiedRuleParser<'i> for TopLevelRuleParser {
+  type Prelude = (String, Vec<Selector>);
+  type QualifiedRule = Vec<CssRule>;
+  type Error = String;
+
+  fn parse_prelude<'t>(
+    &mut self,
+    input: &mut Parser<'i, 't>,
+  ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
+    let start = input.current_source_location();
+    let mut selector = String::new();
+    serialize_tokens(input, &mut selector)?;
+    if selector.is_empty() {
+      return Err(input.new_custom_error("missing selector before `{`".to_string()));
+    }
+
+    // A selector list that fails to parse invalidates the whole rule
+    let selectors = Selector::parse_list(&selector).map_err(|reason| {
+      start.new_custom_error(format!("{UNSUPPORTED_SELECTOR} `{selector}`: {reason}"))
+    })?;
+    Ok((selector, selectors))
+  }
+
+  fn parse_block<'t>(
+    &mut self,
+    prelude: Self::Prelude,
+    start: &ParserState,
+    input: &mut Parser<'i, 't>,
+  ) -> Result<Self::QualifiedRule, ParseError<'i, Self::Error>> {
+    let mut body_parser = DeclarationListParser;
+    let mut declarations = Vec::new();
+
+    for result in RuleBodyParser::new(input, &mut body_parser) {
+      match result {
+        Ok(declaration) => declarations.push(declaration),
+        Err((error, slice)) => self.errors.push(convert_error(&error, slice)),
+      }
+    }
+
+    let (selector, selectors) = prelude;
+    self.seen_rules = true;
+    Ok(vec![CssRule {
+      selector,
+      selectors,
+      declarations,
+      media: Vec::new(),
+      layer: self.layer_stack.last().cloned(),
+      location: to_location(start.source_location()),
+    }])
+  }
+}
+
+/// Preludes of the at-rules the parser understands
+enum AtRulePrelude {
+  Media(MediaQueryList),
+  Import(ImportRule),
+  /// Layer names of an `@layer` statement or block; empty for an anonymous block
+  Layer(Vec<String>),
+}
+
+impl<'i> AtRuleParser<'i> for TopLevelRuleParser {
+  type Prelude = AtRulePrelude;
+  type AtRule = Vec<CssRule>;
+  type Error = String;
+
+  fn parse_prelude<'t>(
+    &mut self,
+    name: CowRcStr<'i>,
+    input: &mut Parser<'i, 't>,
+  ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
+    match_ignore_ascii_case! { &name,
+      "media" => Ok(AtRulePrelude::Media(parse_media_query_list(input)?)),
+      "import" => self.parse_import(input),
+      "layer" => {
+        let names = if input.is_exhausted() {
+          Vec::new()
+        } else {
+          input.parse_comma_separated(parse_layer_name)?
+        };
+        Ok(AtRulePrelude::Layer(names))
+      },
+      _ => Err(input.new_error(BasicParseErrorKind::AtRuleInvalid(name))),
+    }
+  }
+
+  fn rule_without_block(
+    &mut self,
+    prelude: Self::Prelude,
+    _start: &ParserState,
+  ) -> Result<Self::AtRule, ()> {
+    match prelude {
+      AtRulePrelude::Import(mut import) => {
+        // The import's layer is declared where the import appears
+        if let Some(layer) = &import.layer {
+          self.declare_layer(layer);
+        }
+        import.layers_before = self.layers.len();
+        self.imports.push(import);
+        Ok(Vec::new())
+      }
+      // `@layer a, b;` only fixes the order of the named layers
+      AtRulePrelude::Layer(names) if !names.is_empty() => {
+        for name in &names {
+          self.declare_layer(name);
+        }
+        Ok(Vec::new())
+      }
+      AtRulePrelude::Layer(_) | AtRulePrelude::Media(_) => Err(()),
+    }
+  }
+
+  fn parse_block<'t>(
+    &mut self,
+    prelude: Self::Prelude,
+    _start: &ParserState,
+    input: &mut Parser<'i, 't>,
+  ) -> Result<Self::AtRule, ParseError<'i, Self::Error>> {
+    let (media, layer) = match prelude {
+      AtRulePrelude::Media(media) => (Some(media), None),
+      AtRulePrelude::Layer(names) => match names.as_slice() {
+        [] => (None, Some(self.declare_layer(&anonymous_layer_name()))),
+        [name] => (None, Some(self.declare_layer(name))),
+        _ => {
+          return Err(
+            input.new_custom_error("an `@layer` block takes a single layer name".to_string()),
+          )
+        }
+      },
+      AtRulePrelude::Import(_) => {
+        return Err(input.new_error(BasicParseErrorKind::AtRuleBodyInvalid))
+      }
+    };
+    self.seen_rules = true;
+    let mut rules = Vec::new();
+    let mut pending_errors = Vec::new();
+
+    // Rules nested in `@media` (including nested `@media`) only apply while it
+    // matches; rules nested in `@layer` blocks belong to that layer
+    self.nesting += 1;
+    if let Some(layer) = &layer {
+      self.layer_stack.push(layer.clone());
+    }
+    for result in RuleBodyParser::new(input, self) {
+      match result {
+        Ok(parsed) => rules.extend(parsed),
+        Err((error, slice)) => pending_errors.push(convert_error(&error, slice)),
+      }
+    }
+    if layer.is_some() {
+      self.layer_stack.pop();
+    }
+    self.nesting -= 1;
+    self.errors.extend(pending_errors);
+
+    if let Some(media) = media {
+      for rule in &mut rules {
+        rule.media.insert(0, media.clone());
+      }
+    }
+    Ok(rules)
+  }
+}
+
+/// Only rules are allowed inside `@media` and `@layer` blocks
+impl<'i> DeclarationParser<'i> for TopLevelRuleParser {
+  type Declaration = Vec<CssRule>;
+  type Error = String;
+}
+
+impl<'i> RuleBodyItemParser<'i, Vec<CssRule>, String> for TopLevelRuleParser {
+  fn parse_declarations(&self) -> bool {
+    false
+  }
+
+  fn parse_qualified(&self) -> bool {
+    true
+  }
+}
+
+/// Parses the contents of a `{ ... }` block as a list of declarations
+struct DeclarationListParser;
+
+impl<'i> DeclarationParser<'i> for DeclarationListParser {
+  type Declaration = Declaration;
+  type Error = String;
+
+  fn parse_value<'t>(
+    &mut self,
+    name: CowRcStr<'i>,
+    input: &mut Parser<'i, 't>,
+    declaration_start: &ParserState,
+  ) -> Result<Self::Declaration, ParseError<'i, Self::Error>> {
+    let (value, important) = parse_value_text(input)?;
+    if value.is_empty() {
+      return Err(input.new_custom_error(format!("missing value for property `{name}`")));
+    }
+
+    // Custom property names are case-sensitive
+    let property = if name.starts_with("--") {
+      name.to_string()
+    } else {
+      name.to_ascii_lowercase()
+    };
+
+    Ok(Declaration {
+      property,
+      value,
+      important,
+      location: to_location(declaration_start.source_location()),
+    })
+  }
+}
+
+impl<'i> AtRuleParser<'i> for DeclarationListParser {
+  type Prelude = ();
+  type AtRule = Declaration;
+  type Error = String;
+}
+
+impl<'i> QualifiedRuleParser<'i> for DeclarationListParser {
+  type Prelude = ();
+  type QualifiedRule = Declaration;
+  type Error = String;
+}
+
+impl<'i> RuleBodyItemParser<'i, Declaration, String> for DeclarationListParser {
+  fn parse_declarations(&self) -> bool {
+    true
+  }
+
+  fn parse_qualified(&self) -> bool {
+    false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn decl<'a>(rule: &'a CssRule, property: &str) -> Option<&'a str> {
+    rule
+      .declarations
+      .iter()
+      .rev()
+      .find(|d| d.property == property)
+      .map(|d| d.value.as_str())
+  }
+
+  #[test]
+  fn test_single_line_rules() {
+    let sheet = parse_stylesheet(".a { color: red; padding: 1 } #b{display:flex}");
+    assert_eq!(sheet.rules.len(), 2);
+    assert_eq!(sheet.rules[0].selector, ".a");
+    assert_eq!(decl(&sheet.rules[0], "color"), Some("red"));
+    assert_eq!(decl(&sheet.rules[0], "padding"), Some("1"));
+    assert_eq!(sheet.rules[1].selector, "#b");
+    assert_eq!(decl(&sheet.rules[1], "display"), Some("flex"));
+    assert!(sheet.errors.is_empty());
+  }
+
+  #[test]
+  fn test_multiline_selectors_and_comments() {
+    let css =
+      "/* header */\n.card,\n  .panel > .title /* inline */ {\n  color: /* c */ #ff0000;\n}\n";
+    let sheet = parse_stylesheet(css);
+    assert_eq!(sheet.rules.len(), 1);
+    assert_eq!(sheet.rules[0].selector, ".card, .panel > .title");
+    assert_eq!(decl(&sheet.rules[0], "color"), Some("#ff0000"));
+    assert_eq!(sheet.rules[0].location.line, 2);
+  }
+
+  #[test]
+  fn test_values_containing_colons() {
+    let sheet = parse_stylesheet(".bg { background-image: url(http://example.com/a.png); }");
+    assert_eq!(
+      decl(&sheet.rules[0], "background-image"),
+      Some("url(http://example.com/a.png)")
+    );
+  }
+
+  #[test]
+  fn test_function_values() {
+    let sheet =
+      parse_stylesheet(".x { width: calc(100% - 4); color: var(--accent, #fff) !important }");
+    let declarations = &sheet.rules[0].declarations;
+    assert_eq!(declarations[0].value, "calc(100% - 4)");
+    assert_eq!(declarations[1].value, "var(--accent, #fff)");
+    assert!(declarations[1].important);
+  }
+
+  #[test]
+  fn test_important_flag() {
+    let sheet = parse_stylesheet(".x { color: red !important; width: 50% }");
+    let color = &sheet.rules[0].declarations[0];
+    assert_eq!(color.value, "red");
+    assert!(color.important);
+    assert!(!sheet.rules[0].declarations[1].important);
+  }
+
+  #[test]
+  fn test_recovers_from_invalid_declarations_and_rules() {
+    let css = ".a { color red; padding: 2 }\n{ color: blue }\n.b { margin: 1 }";
+    let sheet = parse_stylesheet(css);
+    let selectors: Vec<_> = sheet.rules.iter().map(|r| r.selector.as_str()).collect();
+    assert_eq!(selectors, vec![".a", ".b"]);
+    assert_eq!(decl(&sheet.rules[0], "padding"), Some("2"));
+    assert_eq!(sheet.errors.len(), 2);
+    assert_eq!(sheet.errors[0].location.line, 1);
+    assert_eq!(sheet.errors[1].location.line, 2);
+  }
+
+  #[test]
+  fn test_unsupported_selector_drops_rule() {
+    let sheet = parse_stylesheet(".a > { color: red }\n.b { color: blue }");
+    assert_eq!(sheet.rules.len(), 1);
+    assert_eq!(sheet.rules[0].selector, ".b");
+    assert_eq!(sheet.errors.len(), 1);
+    assert!(sheet.errors[0]
+      .message
+      .starts_with("unsupported selector `.a >`"));
+  }
+
+  #[test]
+  fn test_declaration_list() {
+    let (declarations, errors) =
+      parse_declaration_list("color: red; padding 1; width: calc(100% - 2) !important");
+    assert_eq!(declarations.len(), 2);
+    assert_eq!(declarations[0].property, "color");
+    assert_eq!(declarations[1].value, "calc(100% - 2)");
+    assert!(declarations[1].important);
+    assert_eq!(errors.len(), 1);
+  }
+
+  #[test]
+  fn test_media_rules() {
+    let css = ".a { color: red }\n@media (min-width: 120) {\n  .a { color: blue }\n  @media (color) { .b { color: green } }\n}\n.c { color: white }";
+    let sheet = parse_stylesheet(css);
+    assert!(sheet.errors.is_empty(), "{:?}", sheet.errors);
+    let selectors: Vec<_> = sheet.rules.iter().map(|r| r.selector.as_str()).collect();
+    assert_eq!(selectors, vec![".a", ".a", ".b", ".c"]);
+    assert!(sheet.rules[0].media.is_empty());
+    assert_eq!(sheet.rules[1].media.len(), 1);
+    assert_eq!(sheet.rules[2].media.len(), 2);
+    assert_eq!(
+      sheet.rules[2].media[0].queries[0].breakpoint.min_width,
+      Some(120)
+    );
+    assert!(sheet.rules[3].media.is_empty());
+    assert_eq!(sheet.rules[1].location.line, 3);
+  }
+
+  #[test]
+  fn test_invalid_media_query_drops_block() {
+    let sheet =
+      parse_stylesheet("@media (orientation: portrait) { .a { color: red } }\n.b { color: blue }");
+    assert_eq!(sheet.rules.len(), 1);
+    assert_eq!(sheet.rules[0].selector, ".b");
+    assert_eq!(sheet.errors.len(), 1);
+    assert!(sheet.errors[0].message.contains("orientation"));
+  }
+
+  #[test]
+  fn test_layer_statements_and_blocks() {
+    let css = "@layer base, theme.colors;\n@layer theme { .a { color: red } @layer colors { .b { color: blue } } }\n@layer { .c { color: green } }\n.d { color: white }";
+    let sheet = parse_stylesheet(css);
+    assert!(sheet.errors.is_empty(), "{:?}", sheet.errors);
+    assert_eq!(&sheet.layers[..3], &["base", "theme", "theme.colors"]);
+    assert_eq!(sheet.layers.len(), 4);
+    let layers: Vec<_> = sheet.rules.iter().map(|r| r.layer.as_deref()).collect();
+    assert_eq!(layers[0], Some("theme"));
+    assert_eq!(layers[1], Some("theme.colors"));
+    assert_eq!(layers[2], Some(sheet.layers[3].as_str()));
+    assert_eq!(layers[3], None);
+  }
+
+  #[test]
+  fn test_import_rules() {
+    let css = "@layer reset;\n@import \"base.css\" layer(framework);\n@import url(print.css) (min-width: 100);\n.a { color: red }\n@import \"late.css\";";
+    let sheet = parse_stylesheet(css);
+    assert_eq!(sheet.imports.len(), 2);
+    assert_eq!(sheet.imports[0].url, "base.css");
+    assert_eq!(sheet.imports[0].layer.as_deref(), Some("framework"));
+    assert_eq!(sheet.imports[0].location.line, 2);
+    assert_eq!(sheet.imports[1].url, "print.css");
+    assert!(sheet.imports[1].media.is_some());
+    assert_eq!(sheet.layers, vec!["reset", "framework"]);
+    // `@import` after a style rule is ignored
+    assert_eq!(sheet.errors.len(), 1);
+    assert_eq!(sheet.errors[0].location.line, 5);
+  }
+}
@@ -0,0 +1,776 @@
+//! Selector parsing, matching and specificity
+//!
+//! Selectors are parsed once when a stylesheet is loaded and matched against an
+//! [`ElementContext`], which gives access to the element's ancestors and
+//! siblings for combinators and structural pseudo-classes.
+
+use crate::components::Element;
+use cssparser::{
+  match_ignore_ascii_case, BasicParseErrorKind, ParseError, ParseErrorKind, Parser, ParserInput,
+  ToCss, Token,
+};
+use std::borrow::Cow;
+
+/// Selector types for CSS matching
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+  Tag(String),
+  Class(String),
+  Id(String),
+  Universal,
+  /// `[name]`, `[name=value]`, `[name~=value]`, ...
+  Attribute {
+    name: String,
+    operator: AttributeOperator,
+    value: String,
+    case_insensitive: bool,
+  },
+  /// Simple selectors that must all match the same element, e.g. `button.primary#ok`
+  Compound(Vec<Selector>),
+  /// `ancestor subject`
+  Descendant(Box<Selector>, Box<Selector>),
+  /// `parent > subject`
+  Child(Box<Selector>, Box<Selector>),
+  /// `previous + subject`
+  AdjacentSibling(Box<Selector>, Box<Selector>),
+  /// `earlier ~ subject`
+  GeneralSibling(Box<Selector>, Box<Selector>),
+  /// `:not(a, b)`
+  Not(Vec<Selector>),
+  /// `:root`: the element without a parent
+  Root,
+  FirstChild,
+  LastChild,
+  /// `:nth-child(an+b)`
+  NthChild {
+    a: i32,
+    b: i32,
+  },
+  /// `:focus`, `:hover`, ... matched against runtime element state
+  State(StatePseudoClass),
+  /// `::before` or `::after`; only valid at the end of a selector
+  PseudoElement(PseudoElement),
+}
+
+/// Boxes generated around an element's content by the `content` property
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+  Before,
+  After,
+}
+
+/// Pseudo-classes that reflect interaction state rather than document structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatePseudoClass {
+  Focus,
+  Hover,
+  Active,
+  Disabled,
+  Checked,
+}
+
+impl StatePseudoClass {
+  pub(super) fn matches(self, element: &Element) -> bool {
+    match self {
+      StatePseudoClass::Focus => element.focused,
+      StatePseudoClass::Hover => element.hovered,
+      StatePseudoClass::Active => element.active,
+      StatePseudoClass::Disabled => element.disabled,
+      StatePseudoClass::Checked => element.is_checked(),
+    }
+  }
+}
+
+/// Comparison performed by an attribute selector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+  /// `[name]`
+  Exists,
+  /// `[name=value]`
+  Equals,
+  /// `[name~=value]`: whitespace-separated list contains `value`
+  Includes,
+  /// `[name|=value]`: equals `value` or starts with `value-`
+  DashMatch,
+  /// `[name^=value]`
+  Prefix,
+  /// `[name$=value]`
+  Suffix,
+  /// `[name*=value]`
+  Substring,
+}
+
+/// Selector specificity as (ids, classes/attributes/pseudo-classes, types)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity(pub u32, pub u32, pub u32);
+
+impl std::ops::Add for Specificity {
+  type Output = Specificity;
+
+  fn add(self, other: Specificity) -> Specificity {
+    Specificity(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+  }
+}
+
+/// An element together with its position in the tree, used for selector matching
+#[derive(Debug, Clone, Copy)]
+pub struct ElementContext<'a> {
+  pub element: &'a Element,
+  pub parent: Option<&'a ElementContext<'a>>,
+  /// Index of the element among its parent's children
+  pub index: usize,
+}
+
+impl<'a> ElementContext<'a> {
+  /// Context for an element without a parent
+  pub fn root(element: &'a Element) -> Self {
+    Self {
+      element,
+      parent: None,
+      index: 0,
+    }
+  }
+
+  /// Context for the child at `index` of this element
+  pub fn child<'b>(&'b self, index: usize) -> ElementContext<'b> {
+    ElementContext {
+      element: &self.element.children[index],
+      parent: Some(self),
+      index,
+    }
+  }
+
+  /// The element and its siblings, in document order
+  pub fn siblings(&self) -> &'a [Element] {
+    match self.parent {
+      Some(parent) => &parent.element.children,
+      None => std::slice::from_ref(self.element),
+    }
+  }
+
+  /// Ancestors from the parent up to the root
+  pub fn ancestors(&self) -> impl Iterator<Item = &'a ElementContext<'a>> {
+    std::iter::successors(self.parent, |context| context.parent)
+  }
+
+  /// Preceding siblings, nearest first
+  pub fn previous_siblings(&self) -> impl Iterator<Item = ElementContext<'a>> + '_ {
+    let siblings = self.siblings();
+    (0..self.index).rev().map(move |index| ElementContext {
+      element: &siblings[index],
+      parent: self.parent,
+      index,
+    })
+  }
+}
+
+impl Selector {
+  /// Parse a comma-separated selector list such as `.a > b, #c`
+  pub fn parse_list(text: &str) -> std::result::Result<Vec<Selector>, String> {
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    parser
+      .parse_entirely(parse_selector_list)
+      .map_err(|error| describe_error(&error))
+  }
+
+  pub fn matches(&self, context: &ElementContext) -> bool {
+    let element = context.element;
+    match self {
+      Selector::Tag(tag) => element.tag.eq_ignore_ascii_case(tag),
+      Selector::Class(class) => element.classes.iter().any(|c| c == class),
+      Selector::Id(id) => element.id.as_deref() == Some(id.as_str()),
+      Selector::Universal => true,
+      Selector::Attribute {
+        name,
+        operator,
+        value,
+        case_insensitive,
+      } => attribute_value(element, name)
+        .is_some_and(|actual| operator.matches(&actual, value, *case_insensitive)),
+      Selector::Compound(parts) => parts.iter().all(|part| part.matches(context)),
+      Selector::Descendant(ancestor, subject) => {
+        subject.matches(context) && context.ancestors().any(|a| ancestor.matches(a))
+      }
+      Selector::Child(parent, subject) => {
+        subject.matches(context) && context.parent.is_some_and(|p| parent.matches(p))
+      }
+      Selector::AdjacentSibling(previous, subject) => {
+        subject.matches(context)
+          && context
+            .previous_siblings()
+            .next()
+            .is_some_and(|s| previous.matches(&s))
+      }
+      Selector::GeneralSibling(previous, subject) => {
+        subject.matches(context) && context.previous_siblings().any(|s| previous.matches(&s))
+      }
+      Selector::Not(selectors) => !selectors.iter().any(|s| s.matches(context)),
+      Selector::Root => context.parent.is_none(),
+      Selector::FirstChild => context.index == 0,
+      Selector::LastChild => context.index + 1 == context.siblings().len(),
+      Selector::NthChild { a, b } => nth_matches(*a, *b, context.index),
+      Selector::State(state) => state.matches(element),
+      // Rules for generated content never apply to the element itself
+      Selector::PseudoElement(_) => false,
+    }
+  }
+
+  /// Whether the selector targets the `pseudo` box generated for the element
+  /// in `context`, e.g. `.item:hover::before`
+  pub fn matches_pseudo_element(&self, context: &ElementContext, pseudo: PseudoElement) -> bool {
+    // The pseudo-element is always part of the rightmost compound
+    match self {
+      Selector::PseudoElement(own) => *own == pseudo,
+      Selector::Compound(parts) => parts.iter().all(|part| match part {
+        Selector::PseudoElement(own) => *own == pseudo,
+        _ => part.matches(context),
+      }),
+      Selector::Descendant(ancestor, subject) => {
+        subject.matches_pseudo_element(context, pseudo)
+          && context.ancestors().any(|a| ancestor.matches(a))
+      }
+      Selector::Child(parent, subject) => {
+        subject.matches_pseudo_element(context, pseudo)
+          && context.parent.is_some_and(|p| parent.matches(p))
+      }
+      Selector::AdjacentSibling(previous, subject) => {
+        subject.matches_pseudo_element(context, pseudo)
+          && context
+            .previous_siblings()
+            .next()
+            .is_some_and(|s| previous.matches(&s))
+      }
+      Selector::GeneralSibling(previous, subject) => {
+        subject.matches_pseudo_element(context, pseudo)
+          && context.previous_siblings().any(|s| previous.matches(&s))
+      }
+      _ => false,
+    }
+  }
+
+  pub fn specificity(&self) -> Specificity {
+    match self {
+      Selector::Universal => Specificity::default(),
+      Selector::Tag(_) | Selector::PseudoElement(_) => Specificity(0, 0, 1),
+      Selector::Id(_) => Specificity(1, 0, 0),
+      Selector::Class(_)
+      | Selector::Attribute { .. }
+      | Selector::Root
+      | Selector::FirstChild
+      | Selector::LastChild
+      | Selector::NthChild { .. }
+      | Selector::State(_) => Specificity(0, 1, 0),
+      Selector::Compound(parts) => parts
+        .iter()
+        .fold(Specificity::default(), |acc, part| acc + part.specificity()),
+      Selector::Descendant(left, right)
+      | Selector::Child(left, right)
+      | Selector::AdjacentSibling(left, right)
+      | Selector::GeneralSibling(left, right) => left.specificity() + right.specificity(),
+      // `:not()` takes the specificity of its most specific argument
+      Selector::Not(selectors) => selectors
+        .iter()
+        .map(Selector::specificity)
+        .max()
+        .unwrap_or_default(),
+    }
+  }
+
+  /// Whether matching depends on the element's siblings rather than only its
+  /// own state and ancestors
+  pub(crate) fn depends_on_siblings(&self) -> bool {
+    self.any_component(&|selector| {
+      matches!(
+        selector,
+        Selector::AdjacentSibling(..)
+          | Selector::GeneralSibling(..)
+          | Selector::FirstChild
+          | Selector::LastChild
+          | Selector::NthChild { .. }
+      )
+    })
+  }
+
+  /// Whether the selector targets a `::before` or `::after` box
+  pub(crate) fn has_pseudo_element(&self) -> bool {
+    self.any_component(&|selector| matches!(selector, Selector::PseudoElement(_)))
+  }
+
+  /// Whether `predicate` holds for this selector or any selector nested in it
+  fn any_component(&self, predicate: &dyn Fn(&Selector) -> bool) -> bool {
+    if predicate(self) {
+      return true;
+    }
+    match self {
+      Selector::Compound(parts) | Selector::Not(parts) => {
+        parts.iter().any(|part| part.any_component(predicate))
+      }
+      Selector::Descendant(left, right)
+      | Selector::Child(left, right)
+      | Selector::AdjacentSibling(left, right)
+      | Selector::GeneralSibling(left, right) => {
+        left.any_component(predicate) || right.any_component(predicate)
+      }
+      _ => false,
+    }
+  }
+}
+
+impl AttributeOperator {
+  fn matches(self, actual: &str, expected: &str, case_insensitive: bool) -> bool {
+    let (actual, expected) = if case_insensitive {
+      (
+        Cow::Owned(actual.to_lowercase()),
+        Cow::Owned(expected.to_lowercase()),
+      )
+    } else {
+      (Cow::Borrowed(actual), Cow::Borrowed(expected))
+    };
+
+    match self {
+      AttributeOperator::Exists => true,
+      AttributeOperator::Equals => actual == expected,
+      AttributeOperator::Includes => actual.split_whitespace().any(|word| word == expected),
+      AttributeOperator::DashMatch => {
+        actual == expected
+          || actual
+            .strip_prefix(expected.as_ref())
+            .is_some_and(|rest| rest.starts_with('-'))
+      }
+      AttributeOperator::Prefix => !expected.is_empty() && actual.starts_with(expected.as_ref()),
+      AttributeOperator::Suffix => !expected.is_empty() && actual.ends_with(expected.as_ref()),
+      AttributeOperator::Substring => !expected.is_empty() && actual.contains(expected.as_ref()),
+    }
+  }
+}
+
+/// Look up an attribute for selector matching, including the element fields
+/// that mirror HTML attributes (`id`, `class`, `disabled`)
+pub(super) fn attribute_value<'a>(element: &'a Element, name: &str) -> Option<Cow<'a, str>> {
+  match name {
+    "id" => element.id.as_deref().map(Cow::Borrowed),
+    "class" if !element.classes.is_empty() => Some(Cow::Owned(element.classes.join(" "))),
+    "disabled" if element.disabled => Some(Cow::Borrowed("")),
+    _ => element
+      .attributes
+      .get(name)
+      .map(|value| Cow::Borrowed(value.as_str())),
+  }
+}
+
+/// Whether the 0-based `index` is selected by `an+b` (which counts from 1)
+fn nth_matches(a: i32, b: i32, index: usize) -> bool {
+  let position = index as i64 + 1;
+  let (a, b) = (a as i64, b as i64);
+  if a == 0 {
+    return position == b;
+  }
+  let offset = position - b;
+  offset % a == 0 && offset / a >= 0
+}
+
+fn describe_error(error: &ParseError<'_, String>) -> String {
+  match &error.kind {
+    ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(token)) => {
+      format!("unexpected token `{}`", token.to_css_string())
+    }
+    ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput) => {
+      "unexpected end of selector".to_string()
+    }
+    ParseErrorKind::Basic(_) => "invalid selector".to_string(),
+    ParseErrorKind::Custom(message) => message.clone(),
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Combinator {
+  Descendant,
+  Child,
+  AdjacentSibling,
+  GeneralSibling,
+}
+
+impl Combinator {
+  fn join(self, left: Selector, right: Selector) -> Selector {
+    let (left, right) = (Box::new(left), Box::new(right));
+    match self {
+      Combinator::Descendant => Selector::Descendant(left, right),
+      Combinator::Child => Selector::Child(left, right),
+      Combinator::AdjacentSibling => Selector::AdjacentSibling(left, right),
+      Combinator::GeneralSibling => Selector::GeneralSibling(left, right),
+    }
+  }
+}
+
+/// Accumulates compound selectors and combinators into a left-nested selector
+#[derive(Default)]
+struct ComplexSelectorBuilder {
+  result: Option<Selector>,
+  combinator: Option<Combinator>,
+  compound: Vec<Selector>,
+}
+
+impl ComplexSelectorBuilder {
+  fn flush_compound(&mut self) {
+    let subject = match self.compound.len() {
+      0 => return,
+      1 => self.compound.pop().unwrap(),
+      _ => Selector::Compound(std::mem::take(&mut self.compound)),
+    };
+    let combinator = self.combinator.take().unwrap_or(Combinator::Descendant);
+    self.result = Some(match self.result.take() {
+      Some(left) => combinator.join(left, subject),
+      None => subject,
+    });
+  }
+}
+
+fn parse_selector_list<'i>(
+  input: &mut Parser<'i, '_>,
+) -> Result<Vec<Selector>, ParseError<'i, String>> {
+  input.parse_comma_separated(parse_complex_selector)
+}
+
+fn parse_complex_selector<'i>(
+  input: &mut Parser<'i, '_>,
+) -> Result<Selector, ParseError<'i, String>> {
+  let mut builder = ComplexSelectorBuilder::default();
+  let mut saw_whitespace = false;
+
+  loop {
+    let location = input.current_source_location();
+    let token = match input.next_including_whitespace() {
+      Ok(token) => token.clone(),
+      Err(_) => break,
+    };
+
+    let combinator = match token {
+      Token::WhiteSpace(_) => {
+        saw_whitespace = true;
+        continue;
+      }
+      Token::Delim('>') => Some(Combinator::Child),
+      Token::Delim('+') => Some(Combinator::AdjacentSibling),
+      Token::Delim('~') => Some(Combinator::GeneralSibling),
+      _ => None,
+    };
+
+    if let Some(combinator) = combinator {
+      if builder.compound.is_empty() && (builder.result.is_none() || builder.combinator.is_some()) {
+        return Err(location.new_unexpected_token_error(token));
+      }
+      if builder.compound.iter().any(Selector::has_pseudo_element) {
+        return Err(
+          location.new_custom_error("a pseudo-element must come last in a selector".to_string()),
+        );
+      }
+      builder.flush_compound();
+      builder.combinator = Some(combinator);
+      saw_whitespace = false;
+      continue;
+    }
+
+    // Nothing may follow a pseudo-element
+    if builder
+      .compound
+      .last()
+      .is_some_and(Selector::has_pseudo_element)
+    {
+      return Err(
+        location.new_custom_error("a pseudo-element must come last in a selector".to_string()),
+      );
+    }
+
+    // Whitespace between two compound selectors is the descendant combinator
+    if saw_whitespace && !builder.compound.is_empty() {
+      builder.flush_compound();
+      builder.combinator = Some(Combinator::Descendant);
+    }
+    saw_whitespace = false;
+
+    let first_in_compound = builder.compound.is_empty();
+    let simple = parse_simple_selector(input, token, location, first_in_compound)?;
+    builder.compound.push(simple);
+  }
+
+  if builder.compound.is_empty() {
+    let message = if builder.combinator.is_some() {
+      "selector ends with a combinator"
+    } else {
+      "expected a selector"
+    };
+    return Err(input.new_custom_error(message.to_string()));
+  }
+
+  builder.flush_compound();
+  Ok(builder.result.unwrap())
+}
+
+fn parse_simple_selector<'i>(
+  input: &mut Parser<'i, '_>,
+  token: Token<'i>,
+  location: cssparser::SourceLocation,
+  first_in_compound: bool,
+) -> Result<Selector, ParseError<'i, String>> {
+  match token {
+    Token::Ident(name) if first_in_compound => Ok(Selector::Tag(name.to_ascii_lowercase())),
+    Token::Delim('*') if first_in_compound => Ok(Selector::Universal),
+    Token::Delim('.') => {
+      let location = input.current_source_location();
+      match input.next_including_whitespace()? {
+        Token::Ident(class) => Ok(Selector::Class(class.to_string())),
+        other => Err(location.new_unexpected_token_error(other.clone())),
+      }
+    }
+    Token::IDHash(id) => Ok(Selector::Id(id.to_string())),
+    Token::SquareBracketBlock => input.parse_nested_block(parse_attribute_selector),
+    Token::Colon => parse_pseudo_class(input),
+    other => Err(location.new_unexpected_token_error(other)),
+  }
+}
+
+fn parse_attribute_selector<'i>(
+  input: &mut Parser<'i, '_>,
+) -> Result<Selector, ParseError<'i, String>> {
+  let name = input.expect_ident()?.to_ascii_lowercase();
+
+  let location = input.current_source_location();
+  let operator = match input.next() {
+    Err(_) => {
+      return Ok(Selector::Attribute {
+        name,
+        operator: AttributeOperator::Exists,
+        value: String::new(),
+        case_insensitive: false,
+      })
+    }
+    Ok(Token::Delim('=')) => AttributeOperator::Equals,
+    Ok(Token::IncludeMatch) => AttributeOperator::Includes,
+    Ok(Token::DashMatch) => AttributeOperator::DashMatch,
+    Ok(Token::PrefixMatch) => AttributeOperator::Prefix,
+    Ok(Token::SuffixMatch) => AttributeOperator::Suffix,
+    Ok(Token::SubstringMatch) => AttributeOperator::Substring,
+    Ok(other) => return Err(location.new_unexpected_token_error(other.clone())),
+  };
+
+  let value = input.expect_ident_or_string()?.to_string();
+  let case_insensitive = input
+    .try_parse(|input| input.expect_ident_matching("i"))
+    .is_ok();
+
+  Ok(Selector::Attribute {
+    name,
+    operator,
+    value,
+    case_insensitive,
+  })
+}
+
+fn parse_pseudo_class<'i>(input: &mut Parser<'i, '_>) -> Result<Selector, ParseError<'i, String>> {
+  let location = input.current_source_location();
+  match input.next_including_whitespace()?.clone() {
+    Token::Ident(name) => match_ignore_ascii_case! { &name,
+      "root" => Ok(Selector::Root),
+      "first-child" => Ok(Selector::FirstChild),
+      "last-child" => Ok(Selector::LastChild),
+      "focus" => Ok(Selector::State(StatePseudoClass::Focus)),
+      "hover" => Ok(Selector::State(StatePseudoClass::Hover)),
+      "active" => Ok(Selector::State(StatePseudoClass::Active)),
+      "disabled" => Ok(Selector::State(StatePseudoClass::Disabled)),
+      "checked" => Ok(Selector::State(StatePseudoClass::Checked)),
+      // Legacy single-colon syntax
+      "before" => Ok(Selector::PseudoElement(PseudoElement::Before)),
+      "after" => Ok(Selector::PseudoElement(PseudoElement::After)),
+      _ => Err(location.new_custom_error(format!("unsupported pseudo-class `:{name}`"))),
+    },
+    Token::Function(name) => match_ignore_ascii_case! { &name,
+      "not" => {
+        let selectors = input.parse_nested_block(parse_selector_list)?;
+        if selectors.iter().any(Selector::has_pseudo_element) {
+          return Err(location.new_custom_error(
+            "`:not()` cannot contain pseudo-elements".to_string(),
+          ));
+        }
+        Ok(Selector::Not(selectors))
+      },
+      "nth-child" => input.parse_nested_block(|input| {
+        let (a, b) = cssparser::parse_nth(input)?;
+        Ok(Selector::NthChild { a, b })
+      }),
+      _ => Err(location.new_custom_error(format!("unsupported pseudo-class `:{name}()`"))),
+    },
+    Token::Colon => parse_pseudo_element(input),
+    other => Err(location.new_unexpected_token_error(other)),
+  }
+}
+
+fn parse_pseudo_element<'i>(
+  input: &mut Parser<'i, '_>,
+) -> Result<Selector, ParseError<'i, String>> {
+  let location = input.current_source_location();
+  match input.next_including_whitespace()?.clone() {
+    Token::Ident(name) => match_ignore_ascii_case! { &name,
+      "before" => Ok(Selector::PseudoElement(PseudoElement::Before)),
+      "after" => Ok(Selector::PseudoElement(PseudoElement::After)),
+      _ => Err(location.new_custom_error(format!("unsupported pseudo-element `::{name}`"))),
+    },
+    other => Err(location.new_unexpected_token_error(other)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(text: &str) -> Selector {
+    let mut list = Selector::parse_list(text).unwrap();
+    assert_eq!(list.len(), 1);
+    list.remove(0)
+  }
+
+  fn list() -> Element {
+    Element::with_tag("ul")
+      .child(Element::with_tag("li").class("a").build())
+      .child(
+        Element::with_tag("li")
+          .class("b")
+          .attr("type", "password")
+          .build(),
+      )
+      .child(Element::with_tag("li").class("c").disabled(true).build())
+      .child(Element::with_tag("li").class("d").id("last").build())
+      .build()
+  }
+
+  /// Classes of the children of `root` matched by `selector`
+  fn matching_children(selector: &str, root: &Element) -> Vec<String> {
+    let selector = parse(selector);
+    let root_context = ElementContext::root(root);
+    (0..root.children.len())
+      .map(|index| root_context.child(index))
+      .filter(|context| selector.matches(context))
+      .map(|context| context.element.classes.join("."))
+      .collect()
+  }
+
+  #[test]
+  fn test_parse_compound_and_combinators() {
+    assert_eq!(
+      parse("button.primary#ok"),
+      Selector::Compound(vec![
+        Selector::Tag("button".to_string()),
+        Selector::Class("primary".to_string()),
+        Selector::Id("ok".to_string()),
+      ])
+    );
+    assert_eq!(
+      parse(".a > .b .c"),
+      Selector::Descendant(
+        Box::new(Selector::Child(
+          Box::new(Selector::Class("a".to_string())),
+          Box::new(Selector::Class("b".to_string())),
+        )),
+        Box::new(Selector::Class("c".to_string())),
+      )
+    );
+    assert_eq!(Selector::parse_list("a, .b ,#c").unwrap().len(), 3);
+  }
+
+  #[test]
+  fn test_parse_rejects_invalid_selectors() {
+    for invalid in [
+      "> a", "a >", "a > > b", ".", "a..b", "a,,b", "::marker", ":hovered",
+    ] {
+      assert!(Selector::parse_list(invalid).is_err(), "{invalid}");
+    }
+  }
+
+  #[test]
+  fn test_attribute_selectors() {
+    let root = list();
+    assert_eq!(matching_children("[disabled]", &root), vec!["c"]);
+    assert_eq!(matching_children("[type=\"password\"]", &root), vec!["b"]);
+    assert_eq!(matching_children("[type^=pass]", &root), vec!["b"]);
+    assert_eq!(matching_children("[type=PASSWORD i]", &root), vec!["b"]);
+    assert_eq!(matching_children("[class~=d]", &root), vec!["d"]);
+    assert_eq!(matching_children("[id$=st]", &root), vec!["d"]);
+  }
+
+  #[test]
+  fn test_structural_and_sibling_selectors() {
+    let root = list();
+    assert_eq!(matching_children("li:first-child", &root), vec!["a"]);
+    assert_eq!(matching_children(":last-child", &root), vec!["d"]);
+    assert_eq!(matching_children(":nth-child(2n+1)", &root), vec!["a", "c"]);
+    assert_eq!(matching_children(":nth-child(even)", &root), vec!["b", "d"]);
+    assert_eq!(matching_children(":nth-child(-n+2)", &root), vec!["a", "b"]);
+    assert_eq!(matching_children(".a + li", &root), vec!["b"]);
+    assert_eq!(matching_children(".b ~ li", &root), vec!["c", "d"]);
+    assert_eq!(
+      matching_children("ul > li:not(.a, [disabled])", &root),
+      vec!["b", "d"]
+    );
+    assert_eq!(matching_children("div li", &root), Vec::<String>::new());
+  }
+
+  #[test]
+  fn test_state_pseudo_classes() {
+    let mut root = list();
+    root.children[0].focused = true;
+    root.children[1].hovered = true;
+    root.children[2].active = true;
+    root.children[3]
+      .attributes
+      .insert("checked".to_string(), "true".to_string());
+
+    assert_eq!(matching_children("li:focus", &root), vec!["a"]);
+    assert_eq!(matching_children(":hover", &root), vec!["b"]);
+    assert_eq!(matching_children(".c:active", &root), vec!["c"]);
+    assert_eq!(matching_children(":disabled", &root), vec!["c"]);
+    assert_eq!(matching_children(":checked", &root), vec!["d"]);
+    assert_eq!(matching_children(":focus + li", &root), vec!["b"]);
+    assert_eq!(
+      matching_children("li:not(:disabled):not(:focus)", &root),
+      vec!["b", "d"]
+    );
+  }
+
+  #[test]
+  fn test_specificity() {
+    assert_eq!(parse("*").specificity(), Specificity(0, 0, 0));
+    assert_eq!(
+      parse("button.primary#ok").specificity(),
+      Specificity(1, 1, 1)
+    );
+    assert_eq!(
+      parse("ul li:nth-child(2) + [type]").specificity(),
+      Specificity(0, 2, 2)
+    );
+    assert_eq!(parse(":not(#a, .b)").specificity(), Specificity(1, 0, 0));
+    assert!(parse("#a").specificity() > parse(".a.b.c.d").specificity());
+  }
+
+  #[test]
+  fn test_pseudo_elements() {
+    let root = list();
+    let context = ElementContext::root(&root);
+    let first = context.child(0);
+    let selector = parse("ul > .a::before");
+    assert!(!selector.matches(&first));
+    assert!(selector.matches_pseudo_element(&first, PseudoElement::Before));
+    assert!(!selector.matches_pseudo_element(&first, PseudoElement::After));
+    assert!(!selector.matches_pseudo_element(&context.child(1), PseudoElement::Before));
+    assert!(parse("li:after").matches_pseudo_element(&first, PseudoElement::After));
+    assert_eq!(parse(".a::before").specificity(), Specificity(0, 1, 1));
+
+    for invalid in [
+      "::before .a",
+      "::after > li",
+      ":not(::before)",
+      "li::before::after",
+    ] {
+      assert!(Selector::parse_list(invalid).is_err(), "{invalid}");
+    }
+  }
+}
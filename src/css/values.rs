@@ -0,0 +1,561 @@
+//! Value evaluation: custom property substitution (`var()`), `calc()` and
+//! generated `content`
+//!
+//! Terminal layouts measure everything in cells, so plain numbers are cell
+//! counts and `calc()` results are a percentage of the available space plus
+//! a number of cells.
+
+use super::selector::attribute_value;
+use crate::components::Element;
+use crate::layout::text::TextOverflow;
+use crate::layout::{Overflow, SizeValue};
+use cssparser::{ParseError, Parser, ParserInput, ToCss, Token};
+use std::collections::{HashMap, HashSet};
+
+/// Replace every `var(--name, fallback)` in `value`.
+///
+/// Returns `None` when a referenced property is undefined and has no
+/// fallback, which makes the declaration invalid.
+pub(crate) fn substitute_vars(
+  value: &str,
+  lookup: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  let mut out = String::new();
+  substitute_in(&mut parser, lookup, &mut out).ok()?;
+  Some(out.trim().to_string())
+}
+
+fn substitute_in<'i>(
+  input: &mut Parser<'i, '_>,
+  lookup: &mut dyn FnMut(&str) -> Option<String>,
+  out: &mut String,
+) -> Result<(), ParseError<'i, ()>> {
+  loop {
+    let token = match input.next_including_whitespace() {
+      Ok(token) => token.clone(),
+      Err(_) => return Ok(()),
+    };
+
+    if let Token::Function(name) = &token {
+      if name.eq_ignore_ascii_case("var") {
+        let replacement = input.parse_nested_block(|nested| parse_var(nested, lookup))?;
+        out.push_str(&replacement);
+        continue;
+      }
+    }
+
+    if let Token::WhiteSpace(_) = token {
+      out.push(' ');
+      continue;
+    }
+
+    token.to_css(out).map_err(|_| input.new_custom_error(()))?;
+
+    let closing = match token {
+      Token::Function(_) | Token::ParenthesisBlock => Some(')'),
+      Token::SquareBracketBlock => Some(']'),
+      Token::CurlyBracketBlock => Some('}'),
+      _ => None,
+    };
+    if let Some(closing) = closing {
+      input.parse_nested_block(|nested| substitute_in(nested, lookup, out))?;
+      out.push(closing);
+    }
+  }
+}
+
+/// Parse the arguments of a `var()` and produce its substitution
+fn parse_var<'i>(
+  input: &mut Parser<'i, '_>,
+  lookup: &mut dyn FnMut(&str) -> Option<String>,
+) -> Result<String, ParseError<'i, ()>> {
+  let location = input.current_source_location();
+  let name = input.expect_ident()?.to_string();
+  if !name.starts_with("--") {
+    return Err(location.new_custom_error(()));
+  }
+
+  let value = lookup(&name);
+  let has_fallback = input.try_parse(|input| input.expect_comma()).is_ok();
+
+  match (value, has_fallback) {
+    (Some(value), true) => {
+      // The fallback is unused, skip it without validating its references
+      while input.next_including_whitespace().is_ok() {}
+      Ok(value)
+    }
+    (Some(value), false) => Ok(value),
+    (None, true) => {
+      let mut fallback = String::new();
+      substitute_in(input, lookup, &mut fallback)?;
+      Ok(fallback.trim().to_string())
+    }
+    (None, false) => Err(location.new_custom_error(())),
+  }
+}
+
+/// Compute custom properties declared on an element.
+///
+/// `declared` holds the winning `--name: value` declarations in cascade order;
+/// `computed` starts out as the inherited properties and receives the
+/// substituted values. Properties in a reference cycle become undefined.
+pub(crate) fn resolve_custom_properties(
+  declared: &[(&str, &str)],
+  computed: &mut HashMap<String, String>,
+) {
+  let raw: HashMap<&str, &str> = declared.iter().copied().collect();
+  let mut resolver = CustomPropertyResolver {
+    raw: &raw,
+    inherited: computed,
+    resolved: HashMap::new(),
+    in_progress: HashSet::new(),
+  };
+  for name in raw.keys() {
+    resolver.resolve(name);
+  }
+
+  let resolved = resolver.resolved;
+  for (name, value) in resolved {
+    match value {
+      Some(value) => computed.insert(name, value),
+      None => computed.remove(&name),
+    };
+  }
+}
+
+struct CustomPropertyResolver<'a> {
+  raw: &'a HashMap<&'a str, &'a str>,
+  inherited: &'a HashMap<String, String>,
+  resolved: HashMap<String, Option<String>>,
+  in_progress: HashSet<String>,
+}
+
+impl CustomPropertyResolver<'_> {
+  fn resolve(&mut self, name: &str) -> Option<String> {
+    if let Some(resolved) = self.resolved.get(name) {
+      return resolved.clone();
+    }
+    let Some(raw) = self.raw.get(name).copied() else {
+      return self.inherited.get(name).cloned();
+    };
+    if !self.in_progress.insert(name.to_string()) {
+      // Reference cycle
+      return None;
+    }
+
+    let value = if raw.eq_ignore_ascii_case("initial") {
+      // CSS-wide keywords: `initial` makes the property undefined
+      None
+    } else if raw.eq_ignore_ascii_case("inherit") || raw.eq_ignore_ascii_case("unset") {
+      self.inherited.get(name).cloned()
+    } else if raw.contains("var(") {
+      substitute_vars(raw, &mut |reference| self.resolve(reference))
+    } else {
+      Some(raw.to_string())
+    };
+
+    self.in_progress.remove(name);
+    self.resolved.insert(name.to_string(), value.clone());
+    value
+  }
+}
+
+/// A `calc()` result: a percentage of the available space plus a number of cells
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Linear {
+  percent: f32,
+  cells: f32,
+}
+
+impl Linear {
+  fn is_scalar(self) -> bool {
+    self.percent == 0.0
+  }
+
+  fn add(self, other: Linear) -> Linear {
+    Linear {
+      percent: self.percent + other.percent,
+      cells: self.cells + other.cells,
+    }
+  }
+
+  fn scale(self, factor: f32) -> Linear {
+    Linear {
+      percent: self.percent * factor,
+      cells: self.cells * factor,
+    }
+  }
+}
+
+/// Parse a size such as `10`, `50%`, `auto`, `fit-content` or
+/// `calc(100% - 4)`
+pub(crate) fn parse_size(value: &str) -> Option<SizeValue> {
+  match value {
+    "auto" => return Some(SizeValue::Auto),
+    "min-content" => return Some(SizeValue::MinContent),
+    "max-content" => return Some(SizeValue::MaxContent),
+    "fit-content" => return Some(SizeValue::FitContent),
+    _ => {}
+  }
+  if let Ok(cells) = value.parse::<u16>() {
+    return Some(SizeValue::Pixels(cells));
+  }
+
+  let linear = parse_linear(value)?;
+  Some(if linear.percent == 0.0 {
+    SizeValue::Pixels(to_cells(linear.cells))
+  } else if linear.cells == 0.0 {
+    SizeValue::Percent(linear.percent)
+  } else {
+    SizeValue::Calc {
+      percent: linear.percent,
+      cells: linear.cells,
+    }
+  })
+}
+
+/// Parse an inset such as `2`, `-1`, `50%`, `auto` or `calc(50% - 3)`.
+/// Unlike sizes, offsets may be negative.
+pub(crate) fn parse_offset(value: &str) -> Option<SizeValue> {
+  if value == "auto" {
+    return Some(SizeValue::Auto);
+  }
+  let linear = parse_linear(value)?;
+  Some(if linear.percent == 0.0 && linear.cells >= 0.0 {
+    SizeValue::Pixels(to_cells(linear.cells))
+  } else if linear.cells == 0.0 {
+    SizeValue::Percent(linear.percent)
+  } else {
+    SizeValue::Calc {
+      percent: linear.percent,
+      cells: linear.cells.round(),
+    }
+  })
+}
+
+/// Parse a cell count such as `2` or `calc(2 * 3)`; percentages are rejected
+pub(crate) fn parse_cells(value: &str) -> Option<u16> {
+  if let Ok(cells) = value.parse::<u16>() {
+    return Some(cells);
+  }
+  parse_linear(value)
+    .filter(|linear| linear.is_scalar())
+    .map(|linear| to_cells(linear.cells))
+}
+
+/// Evaluate the `content` of a `::before` or `::after`, such as
+/// `"▶ " attr(data-count)`, against its originating element.
+///
+/// Returns `None` for `none`, `normal` and values that fail to parse, which
+/// generate no box.
+pub(crate) fn generated_text(value: &str, element: &Element) -> Option<String> {
+  if value.eq_ignore_ascii_case("none") || value.eq_ignore_ascii_case("normal") {
+    return None;
+  }
+
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  parser
+    .parse_entirely(|input| -> Result<String, ParseError<'_, ()>> {
+      let mut text = String::new();
+      while !input.is_exhausted() {
+        match input.next()?.clone() {
+          Token::QuotedString(part) => text.push_str(&part),
+          Token::Function(name) if name.eq_ignore_ascii_case("attr") => {
+            input.parse_nested_block(|input| {
+              let name = input.expect_ident()?.to_ascii_lowercase();
+              let fallback = match input.try_parse(Parser::expect_comma) {
+                Ok(()) => Some(input.expect_string()?.to_string()),
+                Err(_) => None,
+              };
+              match attribute_value(element, &name) {
+                Some(value) => text.push_str(&value),
+                None => text.push_str(fallback.as_deref().unwrap_or("")),
+              }
+              Ok(())
+            })?;
+          }
+          _ => return Err(input.new_custom_error(())),
+        }
+      }
+      Ok(text)
+    })
+    .ok()
+}
+
+/// Parse `text-overflow`: `clip`, `ellipsis` or a quoted marker string
+pub(crate) fn parse_text_overflow(value: &str) -> Option<TextOverflow> {
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  parser
+    .parse_entirely(|input| -> Result<TextOverflow, ParseError<'_, ()>> {
+      match input.next()?.clone() {
+        Token::Ident(keyword) if keyword.eq_ignore_ascii_case("clip") => Ok(TextOverflow::Clip),
+        Token::Ident(keyword) if keyword.eq_ignore_ascii_case("ellipsis") => {
+          Ok(TextOverflow::Ellipsis)
+        }
+        Token::QuotedString(marker) => Ok(TextOverflow::String(marker.to_string())),
+        _ => Err(input.new_custom_error(())),
+      }
+    })
+    .ok()
+}
+
+/// Parse `opacity`: a number or percentage, clamped to `0..=1`
+pub(crate) fn parse_opacity(value: &str) -> Option<f32> {
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  let opacity = parser
+    .parse_entirely(|input| -> Result<f32, ParseError<'_, ()>> {
+      match input.next()?.clone() {
+        Token::Number { value, .. } => Ok(value),
+        Token::Percentage { unit_value, .. } => Ok(unit_value),
+        _ => Err(input.new_custom_error(())),
+      }
+    })
+    .ok()?;
+  Some(opacity.clamp(0.0, 1.0))
+}
+
+/// Parse an `aspect-ratio` such as `2` or `16 / 9`, width over height
+pub(crate) fn parse_aspect_ratio(value: &str) -> Option<f32> {
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  let ratio = parser
+    .parse_entirely(|input| -> Result<f32, ParseError<'_, ()>> {
+      let width = input.expect_number()?;
+      let height = match input.try_parse(|input| input.expect_delim('/')) {
+        Ok(()) => input.expect_number()?,
+        Err(_) => 1.0,
+      };
+      if width > 0.0 && height > 0.0 {
+        Ok(width / height)
+      } else {
+        Err(input.new_custom_error(()))
+      }
+    })
+    .ok()?;
+  ratio.is_finite().then_some(ratio)
+}
+
+/// Parse one `overflow` keyword
+pub(crate) fn parse_overflow(value: &str) -> Option<Overflow> {
+  Some(match value {
+    "visible" => Overflow::Visible,
+    "hidden" => Overflow::Hidden,
+    "clip" => Overflow::Clip,
+    "scroll" => Overflow::Scroll,
+    "auto" => Overflow::Auto,
+    _ => return None,
+  })
+}
+
+/// Whether `value` is a single identifier, such as a `grid-area` name
+pub(crate) fn is_ident(value: &str) -> bool {
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  parser
+    .parse_entirely(|input| -> Result<(), ParseError<'_, ()>> {
+      input.expect_ident()?;
+      Ok(())
+    })
+    .is_ok()
+}
+
+fn to_cells(value: f32) -> u16 {
+  value.round().clamp(0.0, u16::MAX as f32) as u16
+}
+
+fn parse_linear(value: &str) -> Option<Linear> {
+  let mut input = ParserInput::new(value);
+  let mut parser = Parser::new(&mut input);
+  parser.parse_entirely(parse_term).ok()
+}
+
+/// A single number, percentage or `calc()` expression
+fn parse_term<'i>(input: &mut Parser<'i, '_>) -> Result<Linear, ParseError<'i, ()>> {
+  let location = input.current_source_location();
+  match input.next()?.clone() {
+    Token::Number { value, .. } => Ok(Linear {
+      percent: 0.0,
+      cells: value,
+    }),
+    Token::Percentage { unit_value, .. } => Ok(Linear {
+      percent: unit_value * 100.0,
+      cells: 0.0,
+    }),
+    Token::ParenthesisBlock => input.parse_nested_block(parse_sum),
+    Token::Function(name) if name.eq_ignore_ascii_case("calc") => {
+      input.parse_nested_block(parse_sum)
+    }
+    other => Err(location.new_unexpected_token_error(other)),
+  }
+}
+
+fn parse_sum<'i>(input: &mut Parser<'i, '_>) -> Result<Linear, ParseError<'i, ()>> {
+  let mut total = parse_product(input)?;
+  loop {
+    let state = input.state();
+    match input.next() {
+      Ok(Token::Delim('+')) => total = total.add(parse_product(input)?),
+      Ok(Token::Delim('-')) => total = total.add(parse_product(input)?.scale(-1.0)),
+      _ => {
+        input.reset(&state);
+        return Ok(total);
+      }
+    }
+  }
+}
+
+fn parse_product<'i>(input: &mut Parser<'i, '_>) -> Result<Linear, ParseError<'i, ()>> {
+  let mut product = parse_term(input)?;
+  loop {
+    let state = input.state();
+    let location = input.current_source_location();
+    match input.next() {
+      Ok(Token::Delim('*')) => {
+        let factor = parse_term(input)?;
+        // At least one side of a multiplication must be a plain number
+        product = if factor.is_scalar() {
+          product.scale(factor.cells)
+        } else if product.is_scalar() {
+          factor.scale(product.cells)
+        } else {
+          return Err(location.new_custom_error(()));
+        };
+      }
+      Ok(Token::Delim('/')) => {
+        let divisor = parse_term(input)?;
+        if !divisor.is_scalar() || divisor.cells == 0.0 {
+          return Err(location.new_custom_error(()));
+        }
+        product = product.scale(1.0 / divisor.cells);
+      }
+      _ => {
+        input.reset(&state);
+        return Ok(product);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn substitute(value: &str, properties: &[(&str, &str)]) -> Option<String> {
+    let map: HashMap<&str, &str> = properties.iter().copied().collect();
+    substitute_vars(value, &mut |name| map.get(name).map(|v| v.to_string()))
+  }
+
+  #[test]
+  fn test_substitute_vars() {
+    let props = [("--accent", "#007acc"), ("--gap", "2")];
+    assert_eq!(
+      substitute("var(--accent)", &props).as_deref(),
+      Some("#007acc")
+    );
+    assert_eq!(
+      substitute("calc(var(--gap) * 2)", &props).as_deref(),
+      Some("calc(2 * 2)")
+    );
+    assert_eq!(
+      substitute("var(--missing, red)", &props).as_deref(),
+      Some("red")
+    );
+    assert_eq!(
+      substitute("var(--missing, var(--gap))", &props).as_deref(),
+      Some("2")
+    );
+    assert_eq!(
+      substitute("var(--gap, var(--missing))", &props).as_deref(),
+      Some("2")
+    );
+    assert_eq!(substitute("var(--missing)", &props), None);
+  }
+
+  #[test]
+  fn test_resolve_custom_properties_with_inheritance_and_cycles() {
+    let mut computed = HashMap::from([("--base".to_string(), "1".to_string())]);
+    resolve_custom_properties(
+      &[
+        ("--a", "var(--base)"),
+        ("--b", "var(--a) var(--a)"),
+        ("--x", "var(--y)"),
+        ("--y", "var(--x)"),
+      ],
+      &mut computed,
+    );
+    let inherited = computed.clone();
+    assert_eq!(computed.get("--a").map(String::as_str), Some("1"));
+    assert_eq!(computed.get("--b").map(String::as_str), Some("1 1"));
+    assert!(!computed.contains_key("--x"));
+    assert!(!computed.contains_key("--y"));
+
+    let mut computed = inherited;
+    resolve_custom_properties(
+      &[
+        ("--a", "inherit"),
+        ("--base", "initial"),
+        ("--c", "var(--a)"),
+      ],
+      &mut computed,
+    );
+    assert_eq!(computed.get("--c").map(String::as_str), Some("1"));
+    assert!(!computed.contains_key("--base"));
+  }
+
+  #[test]
+  fn test_parse_size_with_calc() {
+    assert_eq!(parse_size("12"), Some(SizeValue::Pixels(12)));
+    assert_eq!(parse_size("50%"), Some(SizeValue::Percent(50.0)));
+    assert_eq!(
+      parse_size("calc(100% - 4)"),
+      Some(SizeValue::Calc {
+        percent: 100.0,
+        cells: -4.0
+      })
+    );
+    assert_eq!(parse_size("calc((10 + 2) / 4)"), Some(SizeValue::Pixels(3)));
+    assert_eq!(parse_size("calc(2 * 25%)"), Some(SizeValue::Percent(50.0)));
+    assert_eq!(parse_size("calc(10% * 10%)"), None);
+    assert_eq!(parse_size("calc(4 / 0)"), None);
+    assert_eq!(parse_size("calc(4 -)"), None);
+    assert_eq!(parse_size("fit-content"), Some(SizeValue::FitContent));
+  }
+
+  #[test]
+  fn test_parse_aspect_ratio() {
+    assert_eq!(parse_aspect_ratio("2"), Some(2.0));
+    assert_eq!(parse_aspect_ratio("16 / 4"), Some(4.0));
+    assert_eq!(parse_aspect_ratio("3/0"), None);
+    assert_eq!(parse_aspect_ratio("1 / 2 / 3"), None);
+  }
+
+  #[test]
+  fn test_parse_cells_rejects_percentages() {
+    assert_eq!(parse_cells("calc(3 * 2)"), Some(6));
+    assert_eq!(parse_cells("calc(1 - 5)"), Some(0));
+    assert_eq!(parse_cells("50%"), None);
+  }
+
+  #[test]
+  fn test_generated_text() {
+    let element = crate::components::Element::with_tag("li")
+      .attr("data-count", "3")
+      .build();
+    assert_eq!(generated_text("\"▶ \"", &element).as_deref(), Some("▶ "));
+    assert_eq!(
+      generated_text("\"(\" attr(data-count) \")\"", &element).as_deref(),
+      Some("(3)")
+    );
+    assert_eq!(
+      generated_text("attr(data-missing, \"-\")", &element).as_deref(),
+      Some("-")
+    );
+    assert_eq!(generated_text("none", &element), None);
+    assert_eq!(generated_text("counter(item)", &element), None);
+  }
+}
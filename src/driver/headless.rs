@@ -46,6 +46,9 @@ impl HeadlessDriver {
       supports_mouse: config.mouse,
       supports_colors: true,
       max_colors: 16_777_216, // Simulate full color support
+      // Only the config decides, so tests don't depend on the environment
+      synchronized_output: config.synchronized_output.unwrap_or(false),
+      hyperlinks: config.hyperlinks.unwrap_or(false),
     };
 
     Ok(Self {
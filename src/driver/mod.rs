@@ -19,6 +19,16 @@ pub mod unix;
 #[cfg(windows)]
 pub mod windows;
 
+/// Begin a synchronized update (DEC private mode 2026); the terminal holds
+/// the screen until the matching end sequence
+pub const BEGIN_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026h";
+
+/// End a synchronized update and present everything written since the begin
+pub const END_SYNCHRONIZED_UPDATE: &str = "\x1b[?2026l";
+
+/// Environment variable forcing synchronized output on (`1`) or off (`0`)
+pub const SYNC_OUTPUT_ENV: &str = "REACTIVE_TUI_SYNC_OUTPUT";
+
 /// Events that can be sent from drivers to the application
 #[derive(Debug, Clone)]
 pub enum DriverEvent {
@@ -51,6 +61,11 @@ pub struct DriverCapabilities {
   pub supports_colors: bool,
   /// Maximum number of colors supported
   pub max_colors: u32,
+  /// Does the terminal support synchronized output (DEC mode 2026)
+  pub synchronized_output: bool,
+  /// Does the terminal support OSC 8 hyperlinks; links are printed with
+  /// their URL in parentheses when it does not
+  pub hyperlinks: bool,
 }
 
 impl Default for DriverCapabilities {
@@ -63,6 +78,8 @@ impl Default for DriverCapabilities {
       supports_mouse: true,
       supports_colors: true,
       max_colors: 16_777_216, // 24-bit color
+      synchronized_output: false,
+      hyperlinks: false,
     }
   }
 }
@@ -151,6 +168,24 @@ pub trait Driver: Send + Sync {
     let data_str = String::from_utf8_lossy(data);
     self.write(&data_str)
   }
+
+  /// Write one complete frame and flush it. The frame is wrapped in a
+  /// synchronized update when the terminal supports it, so it is presented
+  /// at once instead of tearing mid-repaint.
+  fn write_frame(&mut self, frame: &[u8]) -> Result<()> {
+    if frame.is_empty() {
+      return Ok(());
+    }
+    let synchronized = self.capabilities().synchronized_output;
+    if synchronized {
+      self.write_bytes(BEGIN_SYNCHRONIZED_UPDATE.as_bytes())?;
+    }
+    self.write_bytes(frame)?;
+    if synchronized {
+      self.write_bytes(END_SYNCHRONIZED_UPDATE.as_bytes())?;
+    }
+    self.flush()
+  }
 }
 
 /// Driver manager that selects and manages the appropriate driver
@@ -305,6 +340,11 @@ pub struct DriverConfig {
   pub inline: bool,
   /// Custom title for the terminal
   pub title: Option<String>,
+  /// Force synchronized output on or off (None to query the terminal).
+  /// `REACTIVE_TUI_SYNC_OUTPUT` takes precedence when set.
+  pub synchronized_output: Option<bool>,
+  /// Force OSC 8 hyperlinks on or off (None to detect from the environment)
+  pub hyperlinks: Option<bool>,
 }
 
 impl Default for DriverConfig {
@@ -316,6 +356,8 @@ impl Default for DriverConfig {
       size: None,
       inline: false,
       title: None,
+      synchronized_output: None,
+      hyperlinks: None,
     }
   }
 }
@@ -378,6 +420,160 @@ pub mod utils {
       1
     }
   }
+
+  /// Synchronized output setting forced by `REACTIVE_TUI_SYNC_OUTPUT` or the
+  /// driver config, if any
+  pub fn synchronized_output_override(config: &DriverConfig) -> Option<bool> {
+    let from_env = std::env::var(SYNC_OUTPUT_ENV).ok().and_then(|value| {
+      match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "on" | "true" | "yes" => Some(true),
+        "0" | "off" | "false" | "no" => Some(false),
+        _ => None,
+      }
+    });
+    from_env.or(config.synchronized_output)
+  }
+
+  /// DECRQM query for mode 2026, followed by a primary device attributes
+  /// request that every terminal answers, so terminals that ignore DECRQM
+  /// still end the exchange
+  pub const SYNC_OUTPUT_QUERY: &str = "\x1b[?2026$p\x1b[c";
+
+  /// The terminal's side of the [`SYNC_OUTPUT_QUERY`] exchange, read from
+  /// stdin together with whatever was typed meanwhile. Replies are consumed
+  /// and everything else is kept as typed input. The exchange is over once
+  /// the device attributes reply arrives.
+  #[derive(Debug, Default)]
+  pub struct SyncOutputReply {
+    /// Bytes that may be the start of a reply still arriving
+    pending: Vec<u8>,
+    supported: bool,
+    finished: bool,
+    typed: Vec<u8>,
+  }
+
+  impl SyncOutputReply {
+    /// Take in bytes read from stdin
+    pub fn feed(&mut self, bytes: &[u8]) {
+      if self.finished {
+        self.typed.extend_from_slice(bytes);
+        return;
+      }
+      self.pending.extend_from_slice(bytes);
+      let mut index = 0;
+      while index < self.pending.len() && !self.finished {
+        let rest = &self.pending[index..];
+        if !rest.starts_with(b"\x1b[?") {
+          if rest.len() < 3 && b"\x1b[?".starts_with(rest) {
+            // Wait for the rest of what may be a reply
+            break;
+          }
+          self.typed.push(rest[0]);
+          index += 1;
+          continue;
+        }
+        let Some(end) = rest[3..].iter().position(|b| (0x40..=0x7e).contains(b)) else {
+          break;
+        };
+        let (body, final_byte) = (&rest[3..3 + end], rest[3 + end]);
+        match final_byte {
+          // DECRPM: CSI ? 2026 ; Ps $ y where Ps 1-3 means recognized and
+          // settable (4 is permanently reset, 0 not recognized)
+          b'y' => {
+            let params = body.strip_suffix(b"$").unwrap_or(body);
+            if let Ok(params) = std::str::from_utf8(params) {
+              if let Some(("2026", state)) = params.split_once(';') {
+                self.supported = matches!(state, "1" | "2" | "3");
+              }
+            }
+          }
+          // Device attributes, which every terminal answers last
+          b'c' => self.finished = true,
+          // Some other report; not input either
+          _ => {}
+        }
+        index += 3 + end + 1;
+      }
+      self.pending.drain(..index);
+      if self.finished {
+        self.typed.append(&mut self.pending);
+      }
+    }
+
+    /// Whether the device attributes reply that ends the exchange arrived
+    pub fn is_finished(&self) -> bool {
+      self.finished
+    }
+
+    /// Whether the terminal reported mode 2026 as supported; terminals that
+    /// ignore DECRQM only answer the device attributes request
+    pub fn supported(&self) -> bool {
+      self.finished && self.supported
+    }
+
+    /// Input typed during the exchange, with an unfinished reply given up
+    /// on counted as typed
+    pub fn into_typed(mut self) -> Vec<u8> {
+      self.typed.append(&mut self.pending);
+      self.typed
+    }
+  }
+
+  /// Key presses for input read before crossterm's event reader started.
+  /// Covers text, control keys and the usual cursor and editing key
+  /// sequences; other escape sequences are dropped.
+  pub fn decode_typed_input(bytes: &[u8]) -> Vec<KeyEvent> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys = Vec::new();
+    while let Some(c) = chars.next() {
+      let (code, modifiers) = match c {
+        '\x1b' => match chars.next_if(|&c| c == '[' || c == 'O') {
+          // CSI or SS3: parameters up to a final character
+          Some(_) => {
+            let mut params = String::new();
+            let mut final_char = None;
+            for c in chars.by_ref() {
+              if ('\x40'..='\x7e').contains(&c) {
+                final_char = Some(c);
+                break;
+              }
+              params.push(c);
+            }
+            let code = match (final_char, params.as_str()) {
+              (Some('A'), _) => KeyCode::Up,
+              (Some('B'), _) => KeyCode::Down,
+              (Some('C'), _) => KeyCode::Right,
+              (Some('D'), _) => KeyCode::Left,
+              (Some('H'), _) | (Some('~'), "1" | "7") => KeyCode::Home,
+              (Some('F'), _) | (Some('~'), "4" | "8") => KeyCode::End,
+              (Some('~'), "2") => KeyCode::Insert,
+              (Some('~'), "3") => KeyCode::Delete,
+              (Some('~'), "5") => KeyCode::PageUp,
+              (Some('~'), "6") => KeyCode::PageDown,
+              _ => continue,
+            };
+            (code, KeyModifiers::NONE)
+          }
+          None => match chars.next_if(|&c| c != '\x1b') {
+            Some(c) => (KeyCode::Char(c), KeyModifiers::ALT),
+            None => (KeyCode::Esc, KeyModifiers::NONE),
+          },
+        },
+        '\r' | '\n' => (KeyCode::Enter, KeyModifiers::NONE),
+        '\t' => (KeyCode::Tab, KeyModifiers::NONE),
+        '\x7f' | '\x08' => (KeyCode::Backspace, KeyModifiers::NONE),
+        '\x01'..='\x1a' => {
+          let letter = char::from(c as u8 - 1 + b'a');
+          (KeyCode::Char(letter), KeyModifiers::CONTROL)
+        }
+        c if c.is_control() => continue,
+        c => (KeyCode::Char(c), KeyModifiers::NONE),
+      };
+      keys.push(KeyEvent::new(code, modifiers));
+    }
+    keys
+  }
 }
 
 #[cfg(test)]
@@ -406,6 +602,80 @@ mod tests {
     assert!(caps.supports_mouse);
     assert!(caps.supports_colors);
     assert_eq!(caps.max_colors, 16_777_216);
+    assert!(!caps.synchronized_output);
+    assert!(!caps.hyperlinks);
+  }
+
+  fn sync_reply(chunks: &[&[u8]]) -> utils::SyncOutputReply {
+    let mut reply = utils::SyncOutputReply::default();
+    for chunk in chunks {
+      reply.feed(chunk);
+    }
+    reply
+  }
+
+  #[test]
+  fn test_sync_output_reply() {
+    assert!(!sync_reply(&[b""]).is_finished());
+    // The exchange only ends with the device attributes reply
+    let reply = sync_reply(&[b"\x1b[?2026;2$y"]);
+    assert!(!reply.is_finished() && !reply.supported());
+    let reply = sync_reply(&[b"\x1b[?2026;2", b"$y\x1b[?6", b"2;22c"]);
+    assert!(reply.is_finished() && reply.supported());
+    assert!(reply.into_typed().is_empty());
+    assert!(sync_reply(&[b"\x1b[?2026;1$y\x1b[?1c"]).supported());
+    assert!(!sync_reply(&[b"\x1b[?2026;0$y\x1b[?1c"]).supported());
+    assert!(!sync_reply(&[b"\x1b[?2026;4$y\x1b[?1c"]).supported());
+    // Terminals without DECRQM only answer the device attributes request
+    let reply = sync_reply(&[b"\x1b[?1;2c"]);
+    assert!(reply.is_finished() && !reply.supported());
+  }
+
+  #[test]
+  fn test_sync_output_reply_keeps_typed_input() {
+    let reply = sync_reply(&[b"ab\x1b", b"[A\x1b[?2026;2$y", b"c\x1b[?62c", b"d"]);
+    assert!(reply.supported());
+    assert_eq!(reply.into_typed(), b"ab\x1b[Acd");
+    // A reply given up on before it ends is not swallowed
+    assert_eq!(sync_reply(&[b"x\x1b"]).into_typed(), b"x\x1b");
+  }
+
+  #[test]
+  fn test_decode_typed_input() {
+    let typed = b"h\xc3\xa9\r\x03\x1b[A\x1b[5~\x1bx\x1b[99z\x1b";
+    let keys: Vec<_> = utils::decode_typed_input(typed)
+      .into_iter()
+      .map(|key| (key.code, key.modifiers))
+      .collect();
+    assert_eq!(
+      keys,
+      vec![
+        (KeyCode::Char('h'), KeyModifiers::NONE),
+        (KeyCode::Char('é'), KeyModifiers::NONE),
+        (KeyCode::Enter, KeyModifiers::NONE),
+        (KeyCode::Char('c'), KeyModifiers::CONTROL),
+        (KeyCode::Up, KeyModifiers::NONE),
+        (KeyCode::PageUp, KeyModifiers::NONE),
+        (KeyCode::Char('x'), KeyModifiers::ALT),
+        (KeyCode::Esc, KeyModifiers::NONE),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_write_frame_brackets_synchronized_updates() {
+    let config = DriverConfig {
+      synchronized_output: Some(true),
+      ..Default::default()
+    };
+    let mut driver = headless::HeadlessDriver::new(config).unwrap();
+    driver.write_frame(b"frame").unwrap();
+    assert_eq!(driver.get_output(), "\x1b[?2026hframe\x1b[?2026l");
+
+    let mut driver = headless::HeadlessDriver::new(DriverConfig::default()).unwrap();
+    driver.write_frame(b"frame").unwrap();
+    driver.write_frame(b"").unwrap();
+    assert_eq!(driver.get_output(), "frame");
   }
 
   #[test]
@@ -7,11 +7,13 @@
 //! - Async event processing
 //! - Suspend/resume support
 
+use super::utils::SyncOutputReply;
 use super::{Driver, DriverCapabilities, DriverConfig, DriverEvent};
 use crate::error::{Result, TuiError};
+use crate::rendering::hyperlink::global_hyperlink_support;
 use crossterm::{
   cursor,
-  event::{self, Event, KeyCode, KeyModifiers},
+  event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
   execute,
   style::Print,
   terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
@@ -28,6 +30,14 @@ use tokio::sync::mpsc;
 #[cfg(unix)]
 extern crate libc;
 
+/// How long setup waits for the terminal to answer the synchronized output
+/// query before drawing without it
+const SYNC_OUTPUT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How much longer the event loop keeps reading a slow answer before
+/// handing stdin to crossterm, so a late reply is not taken for input
+const LATE_REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Unix driver for Linux, macOS, and other POSIX systems
 pub struct UnixDriver {
   capabilities: DriverCapabilities,
@@ -57,6 +67,9 @@ pub struct UnixDriver {
   stdout: Arc<Mutex<io::Stdout>>,
   /// Internal write buffer for batching operations
   write_buffer: Vec<u8>,
+  /// The synchronized output query's exchange, until the event loop has
+  /// finished reading it and forwarded what was typed meanwhile
+  sync_reply: Option<SyncOutputReply>,
 }
 
 impl UnixDriver {
@@ -72,6 +85,11 @@ impl UnixDriver {
       supports_mouse: config.mouse,
       supports_colors: Self::detect_color_support(),
       max_colors: Self::detect_max_colors(),
+      // Queried once the terminal is in raw mode
+      synchronized_output: false,
+      hyperlinks: config
+        .hyperlinks
+        .unwrap_or_else(global_hyperlink_support::detect_from_env),
     };
 
     let driver = Self {
@@ -96,6 +114,7 @@ impl UnixDriver {
       resume_callback: None,
       stdout: Arc::new(Mutex::new(io::stdout())),
       write_buffer: Vec::with_capacity(4096),
+      sync_reply: None,
     };
     #[cfg(feature = "tracing")]
     tracing::trace!("UnixDriver::new - end");
@@ -302,6 +321,11 @@ impl UnixDriver {
     terminal::enable_raw_mode()?;
     self.cursor_visible = false;
 
+    // Raw mode is needed to read the reply without echo or line buffering
+    self.capabilities.synchronized_output =
+      super::utils::synchronized_output_override(&self.config)
+        .unwrap_or_else(|| self.query_synchronized_output());
+
     // Set up mouse capture if enabled
     if self.capabilities.supports_mouse {
       let mut stdout = self
@@ -322,6 +346,36 @@ impl UnixDriver {
     Ok(())
   }
 
+  /// Ask the terminal whether it supports synchronized output (DEC mode
+  /// 2026). Runs before the event loop starts so the reply is not read as
+  /// input; terminals that do not answer in time count as unsupported. The
+  /// exchange is kept for the event loop, which finishes reading a late
+  /// reply and forwards the keys typed meanwhile.
+  fn query_synchronized_output(&mut self) -> bool {
+    if !crossterm::tty::IsTty::is_tty(&io::stdin()) {
+      return false;
+    }
+    {
+      let Ok(mut stdout) = self.stdout.lock() else {
+        return false;
+      };
+      let query = super::utils::SYNC_OUTPUT_QUERY.as_bytes();
+      if stdout
+        .write_all(query)
+        .and_then(|_| stdout.flush())
+        .is_err()
+      {
+        return false;
+      }
+    }
+
+    let mut reply = SyncOutputReply::default();
+    read_reply(&mut reply, SYNC_OUTPUT_TIMEOUT);
+    let supported = reply.supported();
+    self.sync_reply = Some(reply);
+    supported
+  }
+
   /// Restore terminal to original state
   fn cleanup_terminal(&mut self) -> Result<()> {
     // Disable mouse capture
@@ -442,37 +496,53 @@ impl UnixDriver {
     Ok(())
   }
 
+  /// Send a key press, turning Ctrl+C into a quit event. Returns whether
+  /// the receiver is still listening.
+  fn send_key(event_sender: &mpsc::UnboundedSender<DriverEvent>, key_event: KeyEvent) -> bool {
+    // Handle special key combinations
+    if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+      match key_event.code {
+        KeyCode::Char('c') => {
+          // Ctrl+C - send quit event
+          return event_sender.send(DriverEvent::Quit).is_ok();
+        }
+        KeyCode::Char('z') => {
+          // Ctrl+Z - suspend (handled by system)
+          // We could send a custom event here if needed
+        }
+        _ => {}
+      }
+    }
+    event_sender.send(DriverEvent::Key(key_event)).is_ok()
+  }
+
   /// Process events in a background thread
   fn event_loop(
     event_sender: mpsc::UnboundedSender<DriverEvent>,
     stop_flag: Arc<AtomicBool>,
     supports_mouse: bool,
+    sync_reply: Option<SyncOutputReply>,
   ) {
+    if let Some(mut reply) = sync_reply {
+      // Finish reading a slow reply before crossterm reads stdin, then
+      // deliver what was typed during the query
+      if !reply.is_finished() {
+        read_reply(&mut reply, LATE_REPLY_TIMEOUT);
+      }
+      for key_event in super::utils::decode_typed_input(&reply.into_typed()) {
+        if !Self::send_key(&event_sender, key_event) {
+          return;
+        }
+      }
+    }
+
     while !stop_flag.load(Ordering::Relaxed) {
       // Check for events with a timeout
       match event::poll(Duration::from_millis(100)) {
         Ok(true) => {
           match event::read() {
             Ok(Event::Key(key_event)) => {
-              // Handle special key combinations
-              if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                match key_event.code {
-                  KeyCode::Char('c') => {
-                    // Ctrl+C - send quit event
-                    if event_sender.send(DriverEvent::Quit).is_err() {
-                      break;
-                    }
-                    continue;
-                  }
-                  KeyCode::Char('z') => {
-                    // Ctrl+Z - suspend (handled by system)
-                    // We could send a custom event here if needed
-                  }
-                  _ => {}
-                }
-              }
-
-              if event_sender.send(DriverEvent::Key(key_event)).is_err() {
+              if !Self::send_key(&event_sender, key_event) {
                 break;
               }
             }
@@ -656,11 +726,12 @@ impl Driver for UnixDriver {
     // Start event processing thread
     let stop_flag = self.stop_flag.clone();
     let supports_mouse = self.capabilities.supports_mouse;
+    let sync_reply = self.sync_reply.take();
 
     #[cfg(feature = "tracing")]
     tracing::debug!("UnixDriver event loop thread starting");
     let handle = thread::spawn(move || {
-      Self::event_loop(event_sender.clone(), stop_flag, supports_mouse);
+      Self::event_loop(event_sender.clone(), stop_flag, supports_mouse, sync_reply);
     });
 
     // Start signal handling thread now that we have a sender
@@ -827,6 +898,36 @@ impl Drop for UnixDriver {
   }
 }
 
+/// Read the terminal's side of the synchronized output query from stdin
+/// until the exchange is over, `timeout` passes or stdin fails
+fn read_reply(reply: &mut SyncOutputReply, timeout: Duration) {
+  use std::os::unix::io::AsRawFd;
+
+  let fd = io::stdin().as_raw_fd();
+  let deadline = std::time::Instant::now() + timeout;
+  let mut buf = [0u8; 64];
+  while !reply.is_finished() {
+    let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+    if remaining.is_zero() {
+      return;
+    }
+    let mut pollfd = libc::pollfd {
+      fd,
+      events: libc::POLLIN,
+      revents: 0,
+    };
+    let ready = unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis() as libc::c_int) };
+    if ready <= 0 {
+      return;
+    }
+    let read = unsafe { libc::read(fd, buf.as_mut_ptr().cast(), buf.len()) };
+    if read <= 0 {
+      return;
+    }
+    reply.feed(&buf[..read as usize]);
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -9,6 +9,7 @@
 
 use super::{Driver, DriverCapabilities, DriverConfig, DriverEvent};
 use crate::error::{Result, TuiError};
+use crate::rendering::hyperlink::global_hyperlink_support;
 use crossterm::{
   cursor,
   event::{self, Event, KeyCode, KeyModifiers},
@@ -68,6 +69,10 @@ impl WindowsDriver {
       supports_mouse: config.mouse,
       supports_colors: Self::detect_color_support(),
       max_colors: Self::detect_max_colors(),
+      synchronized_output: super::utils::synchronized_output_override(&config).unwrap_or(false),
+      hyperlinks: config
+        .hyperlinks
+        .unwrap_or_else(global_hyperlink_support::detect_from_env),
     };
 
     Ok(Self {
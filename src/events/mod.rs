@@ -401,6 +401,13 @@ impl EventHandler {
     Ok(())
   }
 
+  /// Mark hovered and pressed components in an element tree for `:hover` and `:active`
+  pub async fn apply_pointer_state_to_tree(&self, element: &mut crate::components::Element) {
+    if let Some(router) = &self.event_router {
+      router.apply_pointer_state_to_tree(element).await;
+    }
+  }
+
   /// Register a component event handler
   pub fn register_component_handler<F>(
     &mut self,
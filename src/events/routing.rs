@@ -101,6 +101,12 @@ impl EventRouter {
     Ok(())
   }
 
+  /// Mark hovered and pressed components in a freshly rendered element tree
+  pub async fn apply_pointer_state_to_tree(&self, root: &mut Element) {
+    let targeting = self.mouse_targeting.read().await;
+    targeting.apply_pointer_state_to_tree(root);
+  }
+
   /// Route a mouse event to the appropriate component - ACTUALLY WORKS
   pub async fn route_mouse_event(&self, mouse_event: MouseEvent) -> Result<()> {
     // REAL hit testing with actual bounds
@@ -127,6 +133,13 @@ impl EventRouter {
       hit_result
     };
 
+    // Track hover and pressed state for `:hover` and `:active` styling
+    self
+      .mouse_targeting
+      .write()
+      .await
+      .update_pointer_state(&mouse_event);
+
     // Convert mouse event to REAL message
     let message = self.mouse_event_to_message(mouse_event)?;
 
@@ -1,6 +1,10 @@
 //! WORKING Mouse event targeting and hit testing system
 
-use crate::{components::Element, layout::Layout};
+use crate::{
+  compat::{MouseEvent, MouseEventKind},
+  components::Element,
+  layout::{Layout, ScrollView},
+};
 use std::collections::HashMap;
 
 /// Rectangle bounds for hit testing - ACTUALLY WORKS
@@ -51,6 +55,10 @@ pub struct MouseTargeting {
   component_bounds: HashMap<String, ComponentTarget>,
   /// Z-index ordered components for efficient hit testing
   z_ordered_components: Vec<String>,
+  /// Component under the mouse pointer, for `:hover`
+  hovered_element: Option<String>,
+  /// Component a mouse button was pressed on and is still held, for `:active`
+  active_element: Option<String>,
 }
 
 impl MouseTargeting {
@@ -58,6 +66,8 @@ impl MouseTargeting {
     Self {
       component_bounds: HashMap::new(),
       z_ordered_components: Vec::new(),
+      hovered_element: None,
+      active_element: None,
     }
   }
 
@@ -101,13 +111,69 @@ impl MouseTargeting {
     None
   }
 
+  /// Track the hovered and pressed components from a mouse event.
+  /// Returns whether either of them changed.
+  pub fn update_pointer_state(&mut self, event: &MouseEvent) -> bool {
+    let target = self.hit_test(event.column, event.row);
+    let active = match event.kind {
+      MouseEventKind::Down(_) => target.clone(),
+      MouseEventKind::Up(_) => None,
+      _ => self.active_element.clone(),
+    };
+
+    let changed = self.hovered_element != target || self.active_element != active;
+    self.hovered_element = target;
+    self.active_element = active;
+    changed
+  }
+
+  pub fn hovered_element(&self) -> Option<&str> {
+    self.hovered_element.as_deref()
+  }
+
+  pub fn active_element(&self) -> Option<&str> {
+    self.active_element.as_deref()
+  }
+
+  /// Mark the hovered and pressed components in an element tree. As in CSS,
+  /// their ancestors are marked too. State already set on elements (e.g. by
+  /// widgets tracking hover themselves) is kept.
+  pub fn apply_pointer_state_to_tree(&self, root: &mut Element) {
+    Self::apply_pointer_state_recursive(
+      root,
+      self.hovered_element.as_deref(),
+      self.active_element.as_deref(),
+    );
+  }
+
+  fn apply_pointer_state_recursive(
+    element: &mut Element,
+    hovered: Option<&str>,
+    active: Option<&str>,
+  ) -> (bool, bool) {
+    let id = element.id.as_deref();
+    let mut contains_hovered = element.hovered || (id.is_some() && id == hovered);
+    let mut contains_active = element.active || (id.is_some() && id == active);
+
+    for child in &mut element.children {
+      let (child_hovered, child_active) =
+        Self::apply_pointer_state_recursive(child, hovered, active);
+      contains_hovered |= child_hovered;
+      contains_active |= child_active;
+    }
+
+    element.hovered = contains_hovered;
+    element.active = contains_active;
+    (contains_hovered, contains_active)
+  }
+
   /// Build component bounds from element tree and layout - ACTUALLY WORKS
   pub fn build_from_element_tree(&mut self, element: &Element, layout: &Layout) {
     self.component_bounds.clear();
     self.z_ordered_components.clear();
 
     // Actually traverse the layout tree with real bounds
-    self.collect_component_bounds_recursive(element, layout, 0);
+    self.collect_component_bounds_recursive(element, layout, 0, ScrollView::default());
 
     // Sort z-index ordering (highest first for proper hit testing)
     self.z_ordered_components.sort_by(|a, b| {
@@ -122,6 +188,7 @@ impl MouseTargeting {
     element: &Element,
     layout: &Layout,
     default_z_index: i32,
+    view: ScrollView,
   ) {
     // Process this element if it has an ID
     if let Some(element_id) = &element.id {
@@ -135,22 +202,29 @@ impl MouseTargeting {
         || element.tag == "button"
         || element.tag == "input";
 
-      // Use REAL layout bounds
-      let bounds = Bounds::from_layout_rect(&layout.rect);
-
+      // Use the part of the layout bounds that shows on screen, so content
+      // scrolled out of a scroll container cannot be hit
       // Only add if it has non-zero dimensions and is interactive
-      if bounds.width > 0 && bounds.height > 0 && is_interactive {
+      let visible = view.visible(layout.rect).filter(|_| is_interactive);
+      if let Some(visible) = visible {
+        let bounds = Bounds::from_layout_rect(&visible);
         self.update_component_bounds(element_id.clone(), bounds, z_index, true);
       }
     }
 
     // Recursively process children with their ACTUAL layouts
+    let inner = view.enter(layout);
     let child_count = std::cmp::min(element.children.len(), layout.children.len());
     for i in 0..child_count {
       if let (Some(child_element), Some(child_layout)) =
         (element.children.get(i), layout.children.get(i))
       {
-        self.collect_component_bounds_recursive(child_element, child_layout, default_z_index);
+        self.collect_component_bounds_recursive(
+          child_element,
+          child_layout,
+          default_z_index,
+          inner.place(child_layout),
+        );
       }
     }
   }
@@ -245,4 +319,37 @@ mod tests {
     // Should not hit non-interactive components
     assert_eq!(targeting.hit_test(15, 15), None);
   }
+
+  #[test]
+  fn test_pointer_state_marks_element_and_ancestors() {
+    use crate::compat::{KeyModifiers, MouseButton};
+
+    let mut targeting = MouseTargeting::new();
+    targeting.update_component_bounds("ok".to_string(), Bounds::new(0, 0, 10, 1), 0, true);
+
+    let event = |kind, column| MouseEvent {
+      kind,
+      column,
+      row: 0,
+      modifiers: KeyModifiers::NONE,
+    };
+
+    assert!(targeting.update_pointer_state(&event(MouseEventKind::Moved, 2)));
+    assert!(!targeting.update_pointer_state(&event(MouseEventKind::Moved, 3)));
+    assert!(targeting.update_pointer_state(&event(MouseEventKind::Down(MouseButton::Left), 3)));
+    assert_eq!(targeting.active_element(), Some("ok"));
+
+    let mut root = Element::with_tag("div")
+      .child(Element::with_tag("button").id("ok").build())
+      .child(Element::with_tag("button").id("cancel").build())
+      .build();
+    targeting.apply_pointer_state_to_tree(&mut root);
+    assert!(root.hovered && root.active);
+    assert!(root.children[0].hovered && root.children[0].active);
+    assert!(!root.children[1].hovered && !root.children[1].active);
+
+    assert!(targeting.update_pointer_state(&event(MouseEventKind::Up(MouseButton::Left), 20)));
+    assert_eq!(targeting.hovered_element(), None);
+    assert_eq!(targeting.active_element(), None);
+  }
 }
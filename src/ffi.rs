@@ -463,6 +463,39 @@ pub mod exports {
     }
   }
 
+  /// A stylesheet error or warning returned by `TuiUtils.lintCss`
+  #[napi(object)]
+  pub struct JsCssDiagnostic {
+    /// `error` or `warning`
+    pub severity: String,
+    /// Kind of problem, such as `unknown-property` or `invalid-value`
+    pub kind: String,
+    pub message: String,
+    /// File the diagnostic points into, when linting a file
+    pub file: Option<String>,
+    pub line: u32,
+    pub column: u32,
+    /// Suggested fix, such as ``did you mean `padding`?``
+    pub suggestion: Option<String>,
+  }
+
+  impl From<&crate::css::CssDiagnostic> for JsCssDiagnostic {
+    fn from(diagnostic: &crate::css::CssDiagnostic) -> Self {
+      Self {
+        severity: diagnostic.severity.as_str().to_string(),
+        kind: diagnostic.kind.as_str().to_string(),
+        message: diagnostic.message.clone(),
+        file: diagnostic
+          .file
+          .as_ref()
+          .map(|file| file.display().to_string()),
+        line: diagnostic.location.line,
+        column: diagnostic.location.column,
+        suggestion: diagnostic.suggestion.clone(),
+      }
+    }
+  }
+
   /// Utility functions for common TUI operations
   #[napi]
   pub struct TuiUtils;
@@ -495,33 +528,40 @@ pub mod exports {
       JsElement::new("input".to_string())
     }
 
-    /// Parse CSS and return validation errors
+    /// Parse CSS and return validation errors and warnings as
+    /// `line:column: severity: message` strings
     #[napi]
     pub fn validate_css(css: String) -> napi::Result<Vec<String>> {
       use crate::css::CssEngine;
 
-      let mut errors = Vec::new();
-
       // Check for empty CSS
       if css.trim().is_empty() {
-        errors.push("Empty CSS input".to_string());
-        return Ok(errors);
+        return Ok(vec!["Empty CSS input".to_string()]);
       }
 
-      // Use the CSS engine to validate CSS by attempting to parse it
-      let mut engine = CssEngine::new();
-      match engine.load_stylesheet(&css) {
-        Ok(_) => {
-          // CSS parsed successfully, no errors
-          // Could potentially return warnings about unsupported properties
-        }
-        Err(e) => {
-          // Return parsing errors
-          errors.push(format!("CSS parsing error: {e}"));
-        }
-      }
+      Ok(
+        CssEngine::validate_css(&css)
+          .iter()
+          .map(ToString::to_string)
+          .collect(),
+      )
+    }
+
+    /// Lint CSS and return structured diagnostics
+    #[napi]
+    pub fn lint_css(css: String) -> Vec<JsCssDiagnostic> {
+      crate::css::CssEngine::validate_css(&css)
+        .iter()
+        .map(JsCssDiagnostic::from)
+        .collect()
+    }
 
-      Ok(errors)
+    /// Lint a stylesheet file and the files it imports
+    #[napi]
+    pub fn lint_css_file(path: String) -> napi::Result<Vec<JsCssDiagnostic>> {
+      let diagnostics = crate::css::CssEngine::validate_css_file(&path)
+        .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+      Ok(diagnostics.iter().map(JsCssDiagnostic::from).collect())
     }
 
     /// Get terminal size
@@ -0,0 +1,454 @@
+//! # Hot Reload
+//!
+//! File watching for live stylesheet, theme and plugin configuration updates
+//! during development. Enabled with the `hot-reload` cargo feature.
+//!
+//! [`TuiApp`](crate::app::TuiApp) watches every stylesheet registered through
+//! the builder or [`TuiApp::add_stylesheet`](crate::app::TuiApp::add_stylesheet)
+//! automatically. Theme directories and plugin configuration files are opted in
+//! with `TuiApp::watch_theme_directory` and `TuiApp::watch_plugin_config`.
+//!
+//! Loading themes does not watch them: a directory of JSON themes only reloads
+//! once it is registered with `TuiApp::watch_theme_directory`, which reads each
+//! changed file with [`load_theme_from_file`] and passes the theme to the
+//! app's callback to apply.
+//!
+//! Changes are debounced and delivered to the app loop as a [`ReloadRequest`].
+//! Anything that fails to reload is drawn as an overlay on top of the UI until
+//! the file is fixed, so a typo never takes the application down.
+
+use crate::error::{Result, TuiError};
+use crate::plugin::PluginManager;
+use crate::themes::{load_theme_from_file, ColorTheme};
+use crossterm::{
+  cursor::MoveTo,
+  queue,
+  style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+  },
+};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use unicode_width::UnicodeWidthChar;
+
+/// Callback receiving a theme that was reloaded from disk
+pub type ThemeReloadCallback = Arc<dyn Fn(ColorTheme) + Send + Sync>;
+
+/// Something the [`HotReloader`] keeps an eye on
+#[derive(Clone)]
+pub enum WatchTarget {
+  /// A CSS file; any change reloads all stylesheets
+  Stylesheet(PathBuf),
+  /// A directory of JSON themes; changed files are re-parsed and passed on
+  ThemeDirectory {
+    dir: PathBuf,
+    on_reload: ThemeReloadCallback,
+  },
+  /// A plugin configuration file loaded with [`PluginManager::load_config`]
+  PluginConfig {
+    path: PathBuf,
+    manager: Arc<PluginManager>,
+  },
+}
+
+/// Everything that changed since the last request
+#[derive(Default)]
+pub struct ReloadRequest {
+  /// At least one stylesheet changed
+  pub stylesheets: bool,
+  /// Changed theme files with the callback of the directory they belong to
+  pub themes: Vec<(PathBuf, ThemeReloadCallback)>,
+  /// Changed plugin configuration files
+  pub plugin_configs: Vec<(PathBuf, Arc<PluginManager>)>,
+  /// Errors reported by the file watcher itself
+  pub watcher_errors: Vec<String>,
+}
+
+impl ReloadRequest {
+  pub fn is_empty(&self) -> bool {
+    !self.stylesheets
+      && self.themes.is_empty()
+      && self.plugin_configs.is_empty()
+      && self.watcher_errors.is_empty()
+  }
+
+  /// Reload changed themes and plugin configurations, recording the outcome
+  /// for each file in `errors`
+  pub fn reload_themes_and_plugins(&self, errors: &mut ReloadErrors) {
+    for (path, on_reload) in &self.themes {
+      match load_theme_from_file(path) {
+        Ok(theme) => {
+          on_reload(theme);
+          errors.set(path, Vec::new());
+        }
+        // A theme deleted from the directory is simply no longer offered
+        Err(_) if !path.exists() => errors.set(path, Vec::new()),
+        Err(error) => errors.set(path, vec![format!("{}: {error}", path.display())]),
+      }
+    }
+
+    for (path, manager) in &self.plugin_configs {
+      let result = manager.load_config(&path.to_string_lossy());
+      let failure = result.err().map(|e| format!("{}: {e}", path.display()));
+      errors.set(path, failure.into_iter().collect());
+    }
+  }
+}
+
+/// Watches files and turns filesystem events into [`ReloadRequest`]s
+pub struct HotReloader {
+  watcher: RecommendedWatcher,
+  events: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+  targets: Vec<WatchTarget>,
+  watched_dirs: HashSet<PathBuf>,
+  debounce: Duration,
+}
+
+impl HotReloader {
+  pub fn new() -> Result<Self> {
+    let (sender, events) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event| {
+      // The receiver is gone once the app shuts down
+      let _ = sender.send(event);
+    })
+    .map_err(|e| TuiError::component(format!("Failed to start file watcher: {e}")))?;
+
+    Ok(Self {
+      watcher,
+      events,
+      targets: Vec::new(),
+      watched_dirs: HashSet::new(),
+      debounce: Duration::from_millis(50),
+    })
+  }
+
+  /// Set how long to wait for related events (e.g. an editor's write then
+  /// rename) before reporting a change
+  pub fn set_debounce(&mut self, debounce: Duration) {
+    self.debounce = debounce;
+  }
+
+  /// Start watching `target`.
+  ///
+  /// Files are watched through their parent directory so editors that save by
+  /// replacing the file keep being tracked.
+  pub fn watch(&mut self, target: WatchTarget) -> Result<()> {
+    let target = match target {
+      WatchTarget::Stylesheet(path) => WatchTarget::Stylesheet(absolute_path(&path)?),
+      WatchTarget::ThemeDirectory { dir, on_reload } => WatchTarget::ThemeDirectory {
+        dir: absolute_path(&dir)?,
+        on_reload,
+      },
+      WatchTarget::PluginConfig { path, manager } => WatchTarget::PluginConfig {
+        path: absolute_path(&path)?,
+        manager,
+      },
+    };
+
+    let dir = match &target {
+      WatchTarget::Stylesheet(path) | WatchTarget::PluginConfig { path, .. } => {
+        path.parent().map(Path::to_path_buf).unwrap_or_default()
+      }
+      WatchTarget::ThemeDirectory { dir, .. } => dir.clone(),
+    };
+    // Stylesheets imported by several others are only watched once
+    if self.targets.iter().any(|watched| match (watched, &target) {
+      (WatchTarget::Stylesheet(a), WatchTarget::Stylesheet(b)) => a == b,
+      _ => false,
+    }) {
+      return Ok(());
+    }
+
+    if self.watched_dirs.insert(dir.clone()) {
+      if let Err(e) = self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        self.watched_dirs.remove(&dir);
+        return Err(TuiError::component(format!(
+          "Failed to watch {}: {e}",
+          dir.display()
+        )));
+      }
+    }
+
+    self.targets.push(target);
+    Ok(())
+  }
+
+  /// Wait for the next batch of relevant changes.
+  ///
+  /// Returns `None` once the watcher has shut down.
+  pub async fn next_request(&mut self) -> Option<ReloadRequest> {
+    loop {
+      let event = self.events.recv().await?;
+      let mut request = ReloadRequest::default();
+      self.classify(event, &mut request);
+
+      // Collect the rest of a burst of events into the same request
+      let deadline = tokio::time::Instant::now() + self.debounce;
+      while let Ok(Some(event)) = tokio::time::timeout_at(deadline, self.events.recv()).await {
+        self.classify(event, &mut request);
+      }
+
+      if !request.is_empty() {
+        return Some(request);
+      }
+    }
+  }
+
+  fn classify(&self, event: notify::Result<notify::Event>, request: &mut ReloadRequest) {
+    let event = match event {
+      Ok(event) => event,
+      Err(error) => {
+        request.watcher_errors.push(error.to_string());
+        return;
+      }
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+      return;
+    }
+
+    for path in &event.paths {
+      for target in &self.targets {
+        match target {
+          WatchTarget::Stylesheet(stylesheet) if stylesheet == path => {
+            request.stylesheets = true;
+          }
+          WatchTarget::ThemeDirectory { dir, on_reload }
+            if path.parent() == Some(dir.as_path())
+              && path.extension().is_some_and(|ext| ext == "json")
+              && !request.themes.iter().any(|(seen, _)| seen == path) =>
+          {
+            request.themes.push((path.clone(), on_reload.clone()));
+          }
+          WatchTarget::PluginConfig {
+            path: config,
+            manager,
+          } if config == path && !request.plugin_configs.iter().any(|(seen, _)| seen == path) => {
+            request.plugin_configs.push((path.clone(), manager.clone()));
+          }
+          _ => {}
+        }
+      }
+    }
+  }
+}
+
+/// Resolve `path` against the working directory, canonicalizing its parent so
+/// it compares equal to the paths in watcher events
+fn absolute_path(path: &Path) -> Result<PathBuf> {
+  let path = if path.is_absolute() {
+    path.to_path_buf()
+  } else {
+    std::env::current_dir()?.join(path)
+  };
+  if let Ok(canonical) = path.canonicalize() {
+    return Ok(canonical);
+  }
+  // The file itself may not exist yet
+  match (path.parent(), path.file_name()) {
+    (Some(parent), Some(name)) => Ok(
+      parent
+        .canonicalize()
+        .unwrap_or_else(|_| parent.to_path_buf())
+        .join(name),
+    ),
+    _ => Ok(path),
+  }
+}
+
+/// Reload error messages grouped by the file that caused them
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReloadErrors {
+  by_path: BTreeMap<PathBuf, Vec<String>>,
+}
+
+impl ReloadErrors {
+  /// Replace the messages recorded for `path`; an empty list clears them
+  pub fn set(&mut self, path: &Path, errors: Vec<String>) {
+    if errors.is_empty() {
+      self.by_path.remove(path);
+    } else {
+      self.by_path.insert(path.to_path_buf(), errors);
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.by_path.is_empty()
+  }
+
+  /// All messages, ordered by file
+  pub fn lines(&self) -> Vec<String> {
+    self.by_path.values().flatten().cloned().collect()
+  }
+}
+
+/// Parse every stylesheet and record its read and parse errors
+pub fn check_stylesheets(paths: &[PathBuf], errors: &mut ReloadErrors) {
+  for path in paths {
+    let found = match std::fs::read_to_string(path) {
+      Ok(css) => match crate::css::Stylesheet::from_string(&css) {
+        Ok(mut sheet) => {
+          sheet.resolve_imports(path.parent().unwrap_or(Path::new("")));
+          sheet
+            .errors
+            .iter()
+            .map(|error| format!("{}:{error}", path.display()))
+            .collect()
+        }
+        Err(e) => vec![format!("{}: {e}", path.display())],
+      },
+      Err(e) => vec![format!("{}: {e}", path.display())],
+    };
+    errors.set(path, found);
+  }
+}
+
+/// Draw `errors` in a banner along the bottom of a `width` x `height` screen
+pub fn error_overlay(errors: &ReloadErrors, width: u16, height: u16) -> Vec<u8> {
+  let mut out = Vec::new();
+  if errors.is_empty() || width == 0 || height == 0 {
+    return out;
+  }
+
+  let mut lines = vec![" Hot reload errors".to_string()];
+  lines.extend(errors.lines().into_iter().map(|line| format!(" {line}")));
+  let max_lines = usize::from(height / 2).max(1);
+  if lines.len() > max_lines {
+    let hidden = lines.len() - max_lines + 1;
+    lines.truncate(max_lines - 1);
+    lines.push(format!(" … and {hidden} more"));
+  }
+
+  let top = height - lines.len() as u16;
+  let _ = queue!(
+    out,
+    SetBackgroundColor(Color::DarkRed),
+    SetForegroundColor(Color::White)
+  );
+  for (row, line) in lines.iter().enumerate() {
+    let attribute = if row == 0 {
+      Attribute::Bold
+    } else {
+      Attribute::NormalIntensity
+    };
+    let _ = queue!(
+      out,
+      MoveTo(0, top + row as u16),
+      SetAttribute(attribute),
+      Print(fit_to_width(line, usize::from(width)))
+    );
+  }
+  let _ = queue!(out, SetAttribute(Attribute::Reset), ResetColor);
+  out
+}
+
+/// Truncate or pad `text` to exactly `width` columns
+fn fit_to_width(text: &str, width: usize) -> String {
+  let mut fitted = String::new();
+  let mut used = 0;
+  for c in text.chars() {
+    let w = c.width().unwrap_or(0);
+    if used + w > width {
+      break;
+    }
+    fitted.push(c);
+    used += w;
+  }
+  fitted.extend(std::iter::repeat_n(' ', width - used));
+  fitted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::Mutex;
+
+  #[test]
+  fn test_reload_errors_replace_per_file() {
+    let mut errors = ReloadErrors::default();
+    errors.set(Path::new("b.json"), vec!["b.json: invalid".to_string()]);
+    errors.set(Path::new("a.css"), vec!["a.css:1:2: bad".to_string()]);
+    assert_eq!(errors.lines(), vec!["a.css:1:2: bad", "b.json: invalid"]);
+
+    errors.set(Path::new("a.css"), Vec::new());
+    assert_eq!(errors.lines(), vec!["b.json: invalid"]);
+  }
+
+  #[test]
+  fn test_error_overlay_fits_screen() {
+    let mut errors = ReloadErrors::default();
+    errors.set(
+      Path::new("app.css"),
+      (0..10).map(|i| format!("app.css:{i}:1: error")).collect(),
+    );
+    let overlay = String::from_utf8(error_overlay(&errors, 20, 8)).unwrap();
+    assert!(overlay.contains("Hot reload errors"));
+    assert!(overlay.contains("app.css:1:1"));
+    assert!(overlay.contains("… and 8 more"));
+    assert!(!overlay.contains("app.css:2:1"));
+    assert!(error_overlay(&ReloadErrors::default(), 20, 8).is_empty());
+  }
+
+  #[test]
+  fn test_check_stylesheets_reports_locations() {
+    let path = std::env::temp_dir().join(format!("reactive-tui-check-{}.css", std::process::id()));
+    std::fs::write(&path, ".a { color: red }\n.b { color blue }").unwrap();
+    let mut errors = ReloadErrors::default();
+    check_stylesheets(std::slice::from_ref(&path), &mut errors);
+    let lines = errors.lines();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with(&format!("{}:2:", path.display())));
+
+    std::fs::write(&path, ".b { color: blue }").unwrap();
+    check_stylesheets(std::slice::from_ref(&path), &mut errors);
+    assert!(errors.is_empty());
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[tokio::test]
+  async fn test_watches_stylesheets_and_themes() {
+    let dir = std::env::temp_dir().join(format!("reactive-tui-hot-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let stylesheet = dir.join("app.css");
+    std::fs::write(&stylesheet, ".a { color: red }").unwrap();
+
+    let reloaded = Arc::new(Mutex::new(Vec::new()));
+    let seen = reloaded.clone();
+    let mut reloader = HotReloader::new().unwrap();
+    reloader
+      .watch(WatchTarget::Stylesheet(stylesheet.clone()))
+      .unwrap();
+    reloader
+      .watch(WatchTarget::ThemeDirectory {
+        dir: dir.clone(),
+        on_reload: Arc::new(move |theme| seen.lock().unwrap().push(theme.name)),
+      })
+      .unwrap();
+
+    std::fs::write(&stylesheet, ".a { color: blue }").unwrap();
+    let request = tokio::time::timeout(Duration::from_secs(5), reloader.next_request())
+      .await
+      .expect("no change reported")
+      .unwrap();
+    assert!(request.stylesheets);
+    assert!(request.themes.is_empty());
+
+    std::fs::write(dir.join("broken.json"), "{ not json").unwrap();
+    let request = tokio::time::timeout(Duration::from_secs(5), reloader.next_request())
+      .await
+      .expect("no change reported")
+      .unwrap();
+    assert!(!request.stylesheets);
+    assert_eq!(request.themes.len(), 1);
+
+    let mut errors = ReloadErrors::default();
+    request.reload_themes_and_plugins(&mut errors);
+    assert_eq!(errors.lines().len(), 1);
+    assert!(reloaded.lock().unwrap().is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
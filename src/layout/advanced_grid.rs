@@ -20,6 +20,12 @@
 //! - `col-end-{n}` - Ends at column n
 //! - `row-start-{n}` - Starts at row n
 //! - `row-end-{n}` - Ends at row n
+//! - `area-{name}` - Placed in the named area
+//!
+//! **Named Areas:**
+//! A container's `grid-template-areas` attribute (or CSS property) maps
+//! names to cells, such as `"header header" "sidebar main"`; items name
+//! their area with an `area-{name}` class or a `grid-area` attribute.
 //!
 //! **Responsive & Auto Classes:**
 //! - `grid-cols-auto` - Auto-sized columns
@@ -29,7 +35,11 @@
 use crate::{
   components::Element,
   error::Result,
-  layout::{ComputedStyles, DisplayType, Layout, LayoutRect},
+  layout::{
+    grid::{template, GridAreas},
+    grid_debug::GridWarning,
+    ComputedStyles, DisplayType, Layout, LayoutRect,
+  },
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -70,6 +80,8 @@ pub struct Grid {
   pub flow: GridFlow,
   /// Auto-placement algorithm
   pub auto_placement: AutoPlacement,
+  /// Area names of each row (grid-template-areas)
+  pub areas: Vec<Vec<String>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -149,6 +161,8 @@ pub struct AdvancedGridItem {
   pub row_start: Option<u8>,
   /// Row end position (row-end-{n})
   pub row_end: Option<u8>,
+  /// Named area (area-{name} or grid-area)
+  pub area: Option<String>,
 }
 
 impl Default for Grid {
@@ -159,6 +173,7 @@ impl Default for Grid {
       gap: GridGap { x: 0, y: 0 },
       flow: GridFlow::Row,
       auto_placement: AutoPlacement::Auto,
+      areas: Vec::new(),
     }
   }
 }
@@ -172,6 +187,7 @@ impl Default for AdvancedGridItem {
       column_end: None,
       row_start: None,
       row_end: None,
+      area: None,
     }
   }
 }
@@ -327,6 +343,13 @@ impl GridLayout {
       }
     }
 
+    if let Some(areas) = element
+      .get_attribute("grid-template-areas")
+      .and_then(|value| template::parse_areas(value))
+    {
+      config.areas = areas;
+    }
+
     config
   }
 
@@ -411,10 +434,18 @@ impl GridLayout {
         "row-end-7" => item.row_end = Some(7),
         "row-end-auto" => item.row_end = None,
 
-        _ => {}
+        class => {
+          if let Some(area) = class.strip_prefix("area-") {
+            item.area = Some(area.to_string());
+          }
+        }
       }
     }
 
+    if let Some(area) = element.get_attribute("grid-area") {
+      item.area = Some(area.clone());
+    }
+
     // Handle explicit positioning from start/end
     if let (Some(start), Some(end)) = (item.column_start, item.column_end) {
       item.column_span = (end - start).max(1);
@@ -437,6 +468,27 @@ impl GridLayout {
     }
 
     let grid_config = self.parse_grid_config(parent);
+    let items: Vec<_> = parent
+      .children
+      .iter()
+      .map(|child| self.parse_grid_item(child))
+      .collect();
+    self.compute_grid_layout(parent, &grid_config, &items, container_rect)
+  }
+
+  /// Lay out `parent`'s children with `config`, each placed as described by
+  /// the item at the same index of `items`. Unlike [`compute_layout`], the
+  /// configuration need not come from classes, so stylesheets can supply
+  /// named areas.
+  ///
+  /// [`compute_layout`]: Self::compute_layout
+  pub fn compute_grid_layout(
+    &self,
+    parent: &Element,
+    grid_config: &Grid,
+    items: &[AdvancedGridItem],
+    container_rect: LayoutRect,
+  ) -> Result<Layout> {
     let children = &parent.children;
 
     if children.is_empty() {
@@ -453,7 +505,7 @@ impl GridLayout {
     }
 
     // Build grid placement map
-    let placements = self.build_grid_placements(&grid_config, children, container_rect)?;
+    let placements = self.build_grid_placements(grid_config, children, items, container_rect)?;
 
     // Convert placements to child layouts
     let mut child_layouts = Vec::new();
@@ -492,8 +544,12 @@ impl GridLayout {
     &self,
     config: &Grid,
     children: &[Element],
+    items: &[AdvancedGridItem],
     container_rect: LayoutRect,
   ) -> Result<Vec<(Element, LayoutRect)>> {
+    // An invalid area map is ignored, as a browser drops the declaration
+    let areas = GridAreas::new(&config.areas).unwrap_or_default();
+
     // Determine grid dimensions
     let column_count = match &config.columns {
       GridColumns::Fixed(n) => *n as usize,
@@ -508,14 +564,18 @@ impl GridLayout {
       }
       GridColumns::Subgrid => 1,   // Simplified for now
       GridColumns::Custom(_) => 1, // Simplified for now
-    };
+    }
+    .max(areas.columns())
+    .max(1);
 
     let row_count = match &config.rows {
       GridRows::Fixed(n) => *n as usize,
       GridRows::Auto => children.len().div_ceil(column_count),
       GridRows::Subgrid => 1,   // Simplified for now
       GridRows::Custom(_) => 1, // Simplified for now
-    };
+    }
+    .max(areas.rows())
+    .max(1);
 
     // Calculate cell dimensions with improved precision
     let total_gap_x = config.gap.x as u16 * (column_count.saturating_sub(1)) as u16;
@@ -538,17 +598,25 @@ impl GridLayout {
     // Place items with explicit positions first
     let mut auto_items = Vec::new();
 
-    for (index, child) in children.iter().enumerate() {
-      let item_config = self.parse_grid_item(child);
-
-      if let (Some(col_start), Some(row_start)) = (item_config.column_start, item_config.row_start)
-      {
+    for (index, (child, item_config)) in children.iter().zip(items).enumerate() {
+      let area = item_config.area.as_deref().and_then(|name| areas.get(name));
+      let explicit = match (item_config.column_start, item_config.row_start) {
         // Explicit positioning (1-based to 0-based)
-        let col = (col_start - 1) as usize;
-        let row = (row_start - 1) as usize;
+        (Some(col_start), Some(row_start)) => Some((
+          (col_start as usize).saturating_sub(1).min(column_count - 1),
+          (row_start as usize).saturating_sub(1).min(row_count - 1),
+          item_config.column_span as usize,
+          item_config.row_span as usize,
+        )),
+        _ => None,
+      };
 
-        let span_cols = (item_config.column_span as usize).min(column_count - col);
-        let span_rows = (item_config.row_span as usize).min(row_count - row);
+      if let Some((col, row, span_cols, span_rows)) = area
+        .map(|cell| (cell.column, cell.row, cell.column_span, cell.row_span))
+        .or(explicit)
+      {
+        let span_cols = span_cols.min(column_count - col);
+        let span_rows = span_rows.min(row_count - row);
 
         // Mark cells as occupied
         for r in row..(row + span_rows) {
@@ -575,7 +643,7 @@ impl GridLayout {
           row_count,
         );
 
-        placements.push((child.clone(), rect));
+        placements.push((index, child.clone(), rect));
       } else {
         auto_items.push((index, child, item_config));
       }
@@ -627,7 +695,7 @@ impl GridLayout {
         row_count,
       );
 
-      placements.push((child.clone(), rect));
+      placements.push((index, child.clone(), rect));
 
       // Update current position based on flow
       match config.flow {
@@ -648,7 +716,29 @@ impl GridLayout {
       }
     }
 
-    Ok(placements)
+    // Back in tree order, so the layouts line up with the children
+    placements.sort_by_key(|(index, _, _)| *index);
+    Ok(
+      placements
+        .into_iter()
+        .map(|(_, child, rect)| (child, rect))
+        .collect(),
+    )
+  }
+
+  /// Problems with the named areas of `config` and the areas `items` ask
+  /// for. An invalid area map is ignored and items naming an unknown area
+  /// are placed automatically.
+  pub fn validate(&self, config: &Grid, items: &[AdvancedGridItem]) -> Vec<GridWarning> {
+    let areas = match GridAreas::new(&config.areas) {
+      Ok(areas) => areas,
+      Err(warning) => return vec![warning],
+    };
+    items
+      .iter()
+      .enumerate()
+      .filter_map(|(index, item)| areas.place(item.area.as_deref()?, index).err())
+      .collect()
   }
 
   /// Find the next available position in the grid with optimized search
@@ -891,4 +981,87 @@ mod tests {
     // Second child should be in the remaining column
     assert_eq!(result.children[1].rect.width, 30);
   }
+
+  #[test]
+  fn test_grid_with_named_areas() {
+    let layout = GridLayout::new();
+    let parent = Element::with_tag("div")
+      .class("grid")
+      .attr(
+        "grid-template-areas",
+        "\"header header\" \"sidebar main\" \"footer footer\"",
+      )
+      .child(Element::with_tag("item").class("area-main").build())
+      .child(
+        Element::with_tag("item")
+          .attr("grid-area", "header")
+          .build(),
+      )
+      .child(Element::with_tag("item").class("area-footer").build())
+      .child(Element::with_tag("item").class("area-sidebar").build())
+      .build();
+
+    let config = layout.parse_grid_config(&parent);
+    assert_eq!(config.areas.len(), 3);
+
+    let container = LayoutRect {
+      x: 0,
+      y: 0,
+      width: 80,
+      height: 30,
+    };
+    let result = layout.compute_layout(&parent, container).unwrap();
+    let rects: Vec<_> = result
+      .children
+      .iter()
+      .map(|child| {
+        (
+          child.rect.x,
+          child.rect.y,
+          child.rect.width,
+          child.rect.height,
+        )
+      })
+      .collect();
+    // Layouts stay in tree order
+    assert_eq!(
+      rects,
+      vec![
+        (40, 10, 40, 10),
+        (0, 0, 80, 10),
+        (0, 20, 80, 10),
+        (0, 10, 40, 10),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_validate_named_areas() {
+    let layout = GridLayout::new();
+    let mut config = Grid {
+      areas: template::parse_areas("\"a b\" \"b b\"").unwrap(),
+      ..Grid::default()
+    };
+    let warnings = layout.validate(&config, &[]);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+      warnings[0].warning_type,
+      crate::layout::grid_debug::GridWarningType::InvalidAreas
+    );
+
+    config.areas = template::parse_areas("\"a b\"").unwrap();
+    let items = [
+      AdvancedGridItem {
+        area: Some("b".to_string()),
+        ..Default::default()
+      },
+      AdvancedGridItem {
+        area: Some("c".to_string()),
+        ..Default::default()
+      },
+    ];
+    let warnings = layout.validate(&config, &items);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].item_index, Some(1));
+  }
 }
@@ -5,9 +5,17 @@
 //! - Widget spanning across multiple cells
 //! - Auto-sizing based on content
 //! - Grid gutters and spacing
+//! - Named areas from a `grid-template-areas` map
 //! - Responsive behavior with expand/shrink options
 
-use crate::{components::Element, error::Result, layout::LayoutRect};
+use crate::{
+  components::Element,
+  error::Result,
+  layout::{
+    grid_debug::{GridWarning, GridWarningType},
+    LayoutRect,
+  },
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -117,6 +125,9 @@ pub struct GridConfig {
   pub shrink: bool,
   /// Auto-detect minimum widths when shrinking
   pub auto_minimum: bool,
+  /// Area names of each row, as in `grid-template-areas`; children name
+  /// theirs with a `grid-area` attribute
+  pub areas: Vec<Vec<String>>,
 }
 
 impl Default for GridConfig {
@@ -133,6 +144,7 @@ impl Default for GridConfig {
       expand: false,
       shrink: false,
       auto_minimum: false,
+      areas: Vec::new(),
     }
   }
 }
@@ -193,6 +205,142 @@ pub struct GridTrack {
   pub size: u16,
 }
 
+/// Named areas of a grid, resolved from rows of cell names such as
+/// `"header header" "sidebar main"`. A `.` marks a cell no area covers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridAreas {
+  columns: usize,
+  rows: usize,
+  areas: HashMap<String, GridCell>,
+}
+
+impl GridAreas {
+  /// Resolve `rows` of cell names. Every row needs the same number of
+  /// cells and every name has to cover a rectangle.
+  pub fn new(rows: &[Vec<String>]) -> std::result::Result<Self, GridWarning> {
+    let columns = rows.first().map_or(0, Vec::len);
+    if let Some((index, row)) = rows
+      .iter()
+      .enumerate()
+      .find(|(_, row)| row.len() != columns || row.is_empty())
+    {
+      return Err(invalid_areas(
+        format!(
+          "Row {} of grid-template-areas has {} cells but the first row has {columns}.",
+          index + 1,
+          row.len()
+        ),
+        "Give every row the same number of cells, using `.` for empty ones.",
+      ));
+    }
+
+    let mut areas: HashMap<String, GridCell> = HashMap::new();
+    for (row, names) in rows.iter().enumerate() {
+      for (column, name) in names.iter().enumerate() {
+        if is_null_cell(name) {
+          continue;
+        }
+        areas
+          .entry(name.clone())
+          .and_modify(|cell| {
+            let right = (cell.column + cell.column_span).max(column + 1);
+            let bottom = (cell.row + cell.row_span).max(row + 1);
+            cell.column = cell.column.min(column);
+            cell.row = cell.row.min(row);
+            cell.column_span = right - cell.column;
+            cell.row_span = bottom - cell.row;
+          })
+          .or_insert_with(|| GridCell::new(column, row));
+      }
+    }
+
+    // An area is a rectangle when every cell inside its bounds carries its name
+    for (name, cell) in &areas {
+      let rectangular = cell
+        .occupied_coords()
+        .into_iter()
+        .all(|(column, row)| &rows[row][column] == name);
+      if !rectangular {
+        return Err(invalid_areas(
+          format!("Area `{name}` in grid-template-areas is not a rectangle."),
+          "Name the same cells in every row an area spans, or split it into two areas.",
+        ));
+      }
+    }
+
+    Ok(Self {
+      columns,
+      rows: rows.len(),
+      areas,
+    })
+  }
+
+  /// Parse a `grid-template-areas` value and resolve its areas
+  pub fn parse(value: &str) -> std::result::Result<Self, GridWarning> {
+    let rows = template::parse_areas(value).ok_or_else(|| {
+      invalid_areas(
+        format!("`{value}` is not a list of quoted rows."),
+        "Write each row as a quoted string, such as `\"header header\" \"sidebar main\"`.",
+      )
+    })?;
+    Self::new(&rows)
+  }
+
+  /// Number of columns the areas span
+  pub fn columns(&self) -> usize {
+    self.columns
+  }
+
+  /// Number of rows the areas span
+  pub fn rows(&self) -> usize {
+    self.rows
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.areas.is_empty()
+  }
+
+  /// Cells covered by the area called `name`
+  pub fn get(&self, name: &str) -> Option<GridCell> {
+    self.areas.get(name).copied()
+  }
+
+  /// Cells of the area the `index`th item asks for, or a warning when no
+  /// area has that name
+  pub fn place(&self, name: &str, index: usize) -> std::result::Result<GridCell, GridWarning> {
+    self.get(name).ok_or_else(|| {
+      let mut known: Vec<_> = self.areas.keys().map(String::as_str).collect();
+      known.sort_unstable();
+      GridWarning {
+        warning_type: GridWarningType::UnknownArea,
+        message: format!(
+          "Item {} is placed in area `{name}`, which grid-template-areas does not name.",
+          index + 1
+        ),
+        item_index: Some(index),
+        suggestion: Some(if known.is_empty() {
+          "Define the area in grid-template-areas on the grid container.".to_string()
+        } else {
+          format!("Known areas: {}.", known.join(", "))
+        }),
+      }
+    })
+  }
+}
+
+fn is_null_cell(name: &str) -> bool {
+  name.chars().all(|c| c == '.')
+}
+
+fn invalid_areas(message: String, suggestion: &str) -> GridWarning {
+  GridWarning {
+    warning_type: GridWarningType::InvalidAreas,
+    message,
+    item_index: None,
+    suggestion: Some(suggestion.to_string()),
+  }
+}
+
 /// Advanced grid layout engine
 #[derive(Debug)]
 pub struct GridLayout {
@@ -241,7 +389,8 @@ impl GridLayout {
     }
 
     // Build cell map - assign each widget to grid cells
-    let (cell_map, column_count, row_count) = self.build_cell_map(children)?;
+    let areas = GridAreas::new(&self.config.areas).unwrap_or_default();
+    let (cell_map, column_count, row_count) = self.build_cell_map(children, &areas)?;
 
     // Resolve column and row tracks
     let columns = self.resolve_tracks(
@@ -280,6 +429,7 @@ impl GridLayout {
   fn build_cell_map(
     &self,
     children: &[Element],
+    areas: &GridAreas,
   ) -> Result<(HashMap<String, GridCell>, usize, usize)> {
     let mut cell_map = HashMap::new();
     let mut occupied_coords: HashMap<(usize, usize), String> = HashMap::new();
@@ -287,11 +437,13 @@ impl GridLayout {
     let mut current_column = 0;
     let mut current_row = 0;
     let mut max_column = 0;
-    let mut max_row = 0;
+    let mut max_row = areas.rows().saturating_sub(1);
 
     // Determine grid size
     let column_count = self.config.column_count.unwrap_or_else(|| {
-      if let Some(min_width) = self.config.min_column_width {
+      if !areas.is_empty() {
+        areas.columns()
+      } else if let Some(min_width) = self.config.min_column_width {
         let available_width = self.terminal_width;
         let cols =
           (available_width + self.config.column_gap) / (min_width + self.config.column_gap);
@@ -301,6 +453,23 @@ impl GridLayout {
       }
     });
 
+    // Cells of named areas in use are taken before anything is placed
+    // automatically
+    for child in children {
+      if let Some(cell) = child
+        .get_attribute("grid-area")
+        .and_then(|name| areas.get(name))
+      {
+        let element_id = child
+          .id
+          .clone()
+          .unwrap_or_else(|| format!("element_{}", child.tag));
+        for coord in cell.occupied_coords() {
+          occupied_coords.insert(coord, element_id.clone());
+        }
+      }
+    }
+
     for child in children {
       let element_id = child
         .id
@@ -323,18 +492,28 @@ impl GridLayout {
         .and_then(|s| s.parse().ok());
       let explicit_row = child.get_attribute("grid-row").and_then(|s| s.parse().ok());
 
-      // Find next available position
-      let (column, row) = if let (Some(col), Some(row)) = (explicit_column, explicit_row) {
-        (col, row)
-      } else {
-        self.find_next_available_cell(
-          &occupied_coords,
-          current_column,
-          current_row,
-          column_span,
-          row_span,
-          column_count,
-        )
+      // Items in a named area take its cells; unknown names are placed
+      // automatically
+      let area = child
+        .get_attribute("grid-area")
+        .and_then(|name| areas.get(name));
+      let (column, row, column_span, row_span) = match area {
+        Some(cell) => (cell.column, cell.row, cell.column_span, cell.row_span),
+        None => {
+          let (column, row) = if let (Some(col), Some(row)) = (explicit_column, explicit_row) {
+            (col, row)
+          } else {
+            self.find_next_available_cell(
+              &occupied_coords,
+              current_column,
+              current_row,
+              column_span,
+              row_span,
+              column_count,
+            )
+          };
+          (column, row, column_span, row_span)
+        }
       };
 
       let cell = GridCell::with_span(column, row, column_span, row_span);
@@ -349,11 +528,14 @@ impl GridLayout {
       max_column = max_column.max(column + column_span - 1);
       max_row = max_row.max(row + row_span - 1);
 
-      // Update current position for next widget
-      current_column = column + column_span;
-      if current_column >= column_count {
-        current_column = 0;
-        current_row = row + 1;
+      // Update current position for next widget; the cursor only follows
+      // automatically placed ones
+      if area.is_none() {
+        current_column = column + column_span;
+        if current_column >= column_count {
+          current_column = 0;
+          current_row = row + 1;
+        }
       }
     }
 
@@ -573,6 +755,25 @@ impl GridLayout {
   pub fn config(&self) -> &GridConfig {
     &self.config
   }
+
+  /// Problems with the configured areas and the areas `parent`'s children
+  /// ask for. An invalid area map is ignored and items naming an unknown
+  /// area are placed automatically.
+  pub fn validate(&self, parent: &Element) -> Vec<GridWarning> {
+    let areas = match GridAreas::new(&self.config.areas) {
+      Ok(areas) => areas,
+      Err(warning) => return vec![warning],
+    };
+    parent
+      .children
+      .iter()
+      .enumerate()
+      .filter_map(|(index, child)| {
+        let name = child.get_attribute("grid-area")?;
+        areas.place(name, index).err()
+      })
+      .collect()
+  }
 }
 
 /// CSS-style grid template parsing utilities
@@ -595,6 +796,28 @@ pub mod template {
       .collect()
   }
 
+  /// Parse a `grid-template-areas` value such as
+  /// `"header header" "sidebar main"` into the cell names of each row.
+  /// Returns `None` unless the value is one or more quoted strings.
+  pub fn parse_areas(value: &str) -> Option<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut rest = value.trim();
+    while let Some(quote) = rest.chars().next() {
+      if quote != '"' && quote != '\'' {
+        return None;
+      }
+      let end = rest[1..].find(quote)? + 1;
+      rows.push(
+        rest[1..end]
+          .split_whitespace()
+          .map(str::to_string)
+          .collect(),
+      );
+      rest = rest[end + 1..].trim_start();
+    }
+    (!rows.is_empty()).then_some(rows)
+  }
+
   fn parse_repeat_template(template: &str) -> Vec<GridScalar> {
     // Extract content between repeat( and )
     let content = &template[7..template.len() - 1]; // Remove "repeat(" and ")"
@@ -623,6 +846,19 @@ pub mod template {
       assert!(matches!(template[2], GridScalar::Fr(2.0)));
     }
 
+    #[test]
+    fn test_parse_areas() {
+      assert_eq!(
+        parse_areas("\"header header\" 'sidebar  main'"),
+        Some(vec![
+          vec!["header".to_string(), "header".to_string()],
+          vec!["sidebar".to_string(), "main".to_string()],
+        ])
+      );
+      assert_eq!(parse_areas("header"), None);
+      assert_eq!(parse_areas("\"a b"), None);
+    }
+
     #[test]
     fn test_parse_repeat() {
       let template = parse_repeat_template("repeat(3, 1fr)");
@@ -688,4 +924,103 @@ mod tests {
     assert!(columns.contains(&0));
     assert!(columns.contains(&1));
   }
+
+  #[test]
+  fn test_named_areas() {
+    let areas = GridAreas::parse("\"header header\" \"sidebar main\" \". footer\"").unwrap();
+    assert_eq!((areas.columns(), areas.rows()), (2, 3));
+    assert_eq!(areas.get("header"), Some(GridCell::with_span(0, 0, 2, 1)));
+    assert_eq!(areas.get("footer"), Some(GridCell::new(1, 2)));
+    assert_eq!(areas.get("."), None);
+
+    let l_shape = GridAreas::parse("\"a a\" \"a b\"").unwrap_err();
+    assert_eq!(l_shape.warning_type, GridWarningType::InvalidAreas);
+    assert!(l_shape.message.contains("`a`"));
+    let ragged = GridAreas::parse("\"a a\" \"b\"").unwrap_err();
+    assert_eq!(ragged.warning_type, GridWarningType::InvalidAreas);
+
+    let unknown = areas.place("aside", 3).unwrap_err();
+    assert_eq!(unknown.warning_type, GridWarningType::UnknownArea);
+    assert_eq!(unknown.item_index, Some(3));
+  }
+
+  #[test]
+  fn test_grid_layout_places_items_in_named_areas() {
+    let config = GridConfig {
+      columns: vec![GridScalar::Cells(20), GridScalar::Fr(1.0)],
+      rows: vec![GridScalar::Cells(3), GridScalar::Fr(1.0)],
+      areas: template::parse_areas("\"header header\" \"sidebar main\"").unwrap(),
+      ..Default::default()
+    };
+    let grid = GridLayout::new(config);
+
+    // Tree order differs from the area map
+    let parent = Element::with_tag("div")
+      .child(
+        Element::with_tag("div")
+          .id("main")
+          .attr("grid-area", "main")
+          .build(),
+      )
+      .child(
+        Element::with_tag("div")
+          .id("header")
+          .attr("grid-area", "header")
+          .build(),
+      )
+      .child(
+        Element::with_tag("div")
+          .id("sidebar")
+          .attr("grid-area", "sidebar")
+          .build(),
+      )
+      .child(
+        Element::with_tag("div")
+          .id("stray")
+          .attr("grid-area", "aside")
+          .build(),
+      )
+      .build();
+    let container = LayoutRect {
+      x: 0,
+      y: 0,
+      width: 80,
+      height: 24,
+    };
+    let placements = grid.compute_layout(&parent, container).unwrap();
+    let rect = |id: &str| {
+      placements
+        .iter()
+        .find(|placement| placement.element_id == id)
+        .unwrap()
+        .rect
+    };
+
+    assert_eq!(
+      rect("header"),
+      LayoutRect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 3
+      }
+    );
+    assert_eq!(
+      (rect("sidebar").x, rect("sidebar").y, rect("sidebar").width),
+      (0, 3, 20)
+    );
+    assert_eq!(
+      (rect("main").x, rect("main").y, rect("main").width),
+      (20, 3, 60)
+    );
+    // An unknown area is reported and the item goes in the next free row
+    let stray = placements
+      .iter()
+      .find(|placement| placement.element_id == "stray")
+      .unwrap();
+    assert_eq!((stray.cell.column, stray.cell.row), (0, 2));
+    let warnings = grid.validate(&parent);
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].item_index, Some(3));
+  }
 }
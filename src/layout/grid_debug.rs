@@ -179,6 +179,11 @@ pub enum GridWarningType {
   ContentTruncation,
   /// Performance issues (too many items, complex layout)
   Performance,
+  /// A `grid-template-areas` map with ragged rows or an area that is not a
+  /// rectangle; the map is ignored
+  InvalidAreas,
+  /// An item placed in an area the map does not name
+  UnknownArea,
 }
 
 /// Performance metrics for grid layout computation
@@ -306,7 +311,15 @@ impl GridDebugger {
     // Analyze placement
     let items = self.analyze_item_placements(element, &layout);
     let stats = self.calculate_statistics(&grid_config, &items, container_rect);
-    let warnings = self.detect_warnings(&grid_config, &items, &stats);
+    let mut warnings = grid_layout.validate(
+      &grid_config,
+      &element
+        .children
+        .iter()
+        .map(|child| grid_layout.parse_grid_item(child))
+        .collect::<Vec<_>>(),
+    );
+    warnings.extend(self.detect_warnings(&grid_config, &items, &stats));
 
     let elapsed = start_time.elapsed();
     let performance = GridPerformanceMetrics {
@@ -470,6 +483,7 @@ impl GridDebugger {
           GridWarningType::ExcessiveGaps => "📏",
           GridWarningType::ContentTruncation => "✂️",
           GridWarningType::Performance => "⏱️",
+          GridWarningType::InvalidAreas | GridWarningType::UnknownArea => "🗺️",
         };
 
         let _ = writeln!(output, "├─ {} {}", icon, warning.message);
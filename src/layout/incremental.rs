@@ -0,0 +1,1514 @@
+//! Persistent taffy tree behind the component tree layout
+//!
+//! [`LayoutEngine`](super::LayoutEngine) keeps one taffy node per element
+//! between frames, keyed by the element's id or, without one, its tag and
+//! index under its parent. Each pass compares an element's layout style,
+//! content and children with what its node was last given and only updates
+//! the nodes that changed, which marks them and their ancestors dirty.
+//! Taffy answers every clean subtree from its cache, so typing into one
+//! input lays out that input and its ancestors rather than the whole screen.
+
+use super::scroll::{Container, ScrollPass, Scrolling};
+use super::{
+  advanced_grid, position, text, AlignContent, AlignItems, AlignSelf, ComputedStyles, DisplayType,
+  FlexDirection, FlexWrap, JustifyContent, Layout, LayoutRect, Overflow, PositionType, ScrollState,
+  ScrollView, ScrollbarWidth, SizeValue, Spacing,
+};
+use crate::components::Element;
+use crate::css::ComponentNode;
+use crate::error::{Result, TuiError};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use taffy::{AvailableSpace, NodeId, TaffyTree};
+
+/// How long the last layout pass took, in total and for each child of the
+/// root element
+#[derive(Debug, Clone, Default)]
+pub struct LayoutTimings {
+  pub total: Duration,
+  /// One entry per child of the root element, in document order
+  pub subtrees: Vec<SubtreeTiming>,
+  /// Nodes whose style, content or children changed since the pass before
+  pub dirty_nodes: usize,
+  /// Times a leaf was measured because taffy had no cached size for it
+  pub measurements: usize,
+}
+
+/// Time spent updating and measuring the nodes of one subtree
+#[derive(Debug, Clone)]
+pub struct SubtreeTiming {
+  /// Key of the subtree's root element, a path of `#id` or `tag[index]`
+  /// segments
+  pub key: String,
+  pub duration: Duration,
+}
+
+/// What a leaf's size is measured from
+#[derive(Debug, Clone, PartialEq)]
+enum Measure {
+  /// Element content, broken into lines the way the renderer draws it
+  Text {
+    content: String,
+    style: text::TextStyle,
+  },
+  /// A grid container, whose children advanced grid places after layout;
+  /// one row per child
+  Grid { rows: u16 },
+}
+
+/// Widths a box takes when it is sized by its content: as narrow as it gets
+/// without overflowing, and as wide as it is without wrapping
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ContentWidths {
+  min: f32,
+  max: f32,
+}
+
+/// Width properties of an element that are sized by its content
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ContentSizing {
+  width: SizeValue,
+  min_width: SizeValue,
+  max_width: SizeValue,
+}
+
+impl ContentSizing {
+  fn of(styles: &ComputedStyles) -> Option<Self> {
+    let sizing = Self {
+      width: styles.width,
+      min_width: styles.min_width,
+      max_width: styles.max_width,
+    };
+    (sizing.width.is_intrinsic()
+      || sizing.min_width.is_intrinsic()
+      || sizing.max_width.is_intrinsic())
+    .then_some(sizing)
+  }
+}
+
+/// A taffy style with no `calc()` lengths; taffy stores those as pointers
+/// to an expression, which makes its styles neither `Send` nor `Sync`
+#[derive(Debug, Clone, PartialEq)]
+struct NodeStyle(taffy::Style);
+
+// SAFETY: every length of the style is a plain tagged value, checked where
+// it is built, so nothing is shared behind a pointer
+unsafe impl Send for NodeStyle {}
+unsafe impl Sync for NodeStyle {}
+
+impl NodeStyle {
+  fn new(style: taffy::Style) -> Self {
+    debug_assert!(
+      !has_calc(&style),
+      "calc() length in a layout style: {style:?}"
+    );
+    Self(style)
+  }
+}
+
+/// The taffy tree, whose nodes only ever get a [`NodeStyle`]
+struct LayoutTree(TaffyTree<Measure>);
+
+// SAFETY: the tree only holds styles given to it as a `NodeStyle`, and
+// measure contexts, which are `Send` and `Sync`
+unsafe impl Send for LayoutTree {}
+unsafe impl Sync for LayoutTree {}
+
+impl std::ops::Deref for LayoutTree {
+  type Target = TaffyTree<Measure>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.0
+  }
+}
+
+impl LayoutTree {
+  fn new_leaf(
+    &mut self,
+    style: &NodeStyle,
+    measure: Option<Measure>,
+  ) -> taffy::TaffyResult<NodeId> {
+    match measure {
+      Some(measure) => self.0.new_leaf_with_context(style.0.clone(), measure),
+      None => self.0.new_leaf(style.0.clone()),
+    }
+  }
+
+  fn set_style(&mut self, id: NodeId, style: &NodeStyle) -> taffy::TaffyResult<()> {
+    self.0.set_style(id, style.0.clone())
+  }
+
+  fn set_node_context(&mut self, id: NodeId, measure: Option<Measure>) -> taffy::TaffyResult<()> {
+    self.0.set_node_context(id, measure)
+  }
+
+  fn set_children(&mut self, id: NodeId, children: &[NodeId]) -> taffy::TaffyResult<()> {
+    self.0.set_children(id, children)
+  }
+
+  fn remove(&mut self, id: NodeId) -> taffy::TaffyResult<NodeId> {
+    self.0.remove(id)
+  }
+
+  fn compute_layout_with_measure<F>(
+    &mut self,
+    root: NodeId,
+    available: taffy::Size<AvailableSpace>,
+    measure: F,
+  ) -> taffy::TaffyResult<()>
+  where
+    F: FnMut(
+      taffy::Size<Option<f32>>,
+      taffy::Size<AvailableSpace>,
+      NodeId,
+      Option<&mut Measure>,
+      &taffy::Style,
+    ) -> taffy::Size<f32>,
+  {
+    self.0.compute_layout_with_measure(root, available, measure)
+  }
+}
+
+/// What a taffy node was last given
+struct CachedNode {
+  id: NodeId,
+  style: NodeStyle,
+  measure: Option<Measure>,
+  children: Vec<NodeId>,
+  /// Pass that last reached the node; nodes left behind are removed
+  pass: u64,
+}
+
+pub(crate) struct IncrementalLayout {
+  taffy: LayoutTree,
+  nodes: HashMap<String, CachedNode>,
+  /// Index of the root child each node belongs to, for the timings
+  subtree_of: HashMap<NodeId, usize>,
+  /// Node of each element of the last pass, in document order
+  order: Vec<NodeId>,
+  /// Keys of the `overflow: auto` containers of the pass, with whether
+  /// each axis is `auto`
+  auto_scrollers: Vec<(String, (bool, bool))>,
+  /// Scrollbars given to `overflow: auto` containers, horizontal and
+  /// vertical, since their content overflowed
+  gutters: HashMap<NodeId, (bool, bool)>,
+  /// Keys of the elements of the pass sized by their content, in document
+  /// order, with the properties that are
+  content_sized: Vec<(String, ContentSizing)>,
+  /// Content widths last measured for the nodes sized by their content
+  content_widths: HashMap<NodeId, ContentWidths>,
+  scrolling: Scrolling,
+  viewport: (u16, u16),
+  pass: u64,
+  timings: LayoutTimings,
+}
+
+impl IncrementalLayout {
+  pub(crate) fn new() -> Self {
+    Self {
+      taffy: LayoutTree(TaffyTree::new()),
+      nodes: HashMap::new(),
+      subtree_of: HashMap::new(),
+      order: Vec::new(),
+      auto_scrollers: Vec::new(),
+      gutters: HashMap::new(),
+      content_sized: Vec::new(),
+      content_widths: HashMap::new(),
+      scrolling: Scrolling::default(),
+      viewport: (0, 0),
+      pass: 0,
+      timings: LayoutTimings::default(),
+    }
+  }
+
+  pub(crate) fn timings(&self) -> &LayoutTimings {
+    &self.timings
+  }
+
+  pub(crate) fn scrolling(&self) -> &Scrolling {
+    &self.scrolling
+  }
+
+  pub(crate) fn scrolling_mut(&mut self) -> &mut Scrolling {
+    &mut self.scrolling
+  }
+
+  /// Lay out `element` with its resolved styles in `node` to fill a
+  /// `width` by `height` viewport
+  pub(crate) fn compute(
+    &mut self,
+    element: &Element,
+    node: &ComponentNode,
+    width: u16,
+    height: u16,
+  ) -> Result<Layout> {
+    let started = Instant::now();
+    self.pass += 1;
+    self.viewport = (width, height);
+    self.subtree_of.clear();
+    self.order.clear();
+    self.auto_scrollers.clear();
+    self.content_sized.clear();
+    self.timings = LayoutTimings::default();
+
+    let root_key = match &element.id {
+      Some(id) => format!("#{id}"),
+      None => element.tag.clone(),
+    };
+    let root = self.sync(
+      root_key,
+      element,
+      node,
+      None,
+      &mut Vec::new(),
+      &mut Vec::new(),
+    )?;
+
+    // Nodes of elements that are gone
+    let pass = self.pass;
+    let taffy = &mut self.taffy;
+    let gutters = &mut self.gutters;
+    let content_widths = &mut self.content_widths;
+    self.nodes.retain(|_, cached| {
+      let live = cached.pass == pass;
+      if !live {
+        let _ = taffy.remove(cached.id);
+        gutters.remove(&cached.id);
+        content_widths.remove(&cached.id);
+      }
+      live
+    });
+
+    self.measure_content()?;
+    let viewport = taffy::Size {
+      width: AvailableSpace::Definite(f32::from(width)),
+      height: AvailableSpace::Definite(f32::from(height)),
+    };
+    self.run_taffy(root, viewport)?;
+    // Containers whose content no longer fits get their scrollbars, and
+    // those whose content now does lose them; that changes their
+    // scrollport, so once more for a scrollbar that needs the other
+    for _ in 0..2 {
+      if !self.fit_gutters()? {
+        break;
+      }
+      self.run_taffy(root, viewport)?;
+    }
+
+    // Fixed boxes and those without a positioned ancestor are placed in the root
+    let root_location = self.taffy.layout(root).map_err(layout_error)?.location;
+    let root_origin = (root_location.x, root_location.y);
+    let origins = Origins {
+      parent: (0.0, 0.0),
+      containing: root_origin,
+      root: root_origin,
+      view: ScrollView::default(),
+      container: None,
+    };
+    let mut scrolled = ScrollPass::default();
+    let mut layout = self.output(
+      element,
+      node,
+      &mut self.order.iter(),
+      origins,
+      &mut scrolled,
+    )?;
+    self.scrolling.finish(scrolled);
+    let viewport = LayoutRect {
+      x: 0,
+      y: 0,
+      width,
+      height,
+    };
+    position::apply_sticky(&mut layout, viewport);
+    self.timings.total = started.elapsed();
+    Ok(layout)
+  }
+
+  /// Lay out the tree from `root` in `available` space, measuring the
+  /// leaves taffy has no cached size for
+  fn run_taffy(&mut self, root: NodeId, available: taffy::Size<AvailableSpace>) -> Result<()> {
+    let subtree_of = &self.subtree_of;
+    let mut measure_times = vec![Duration::ZERO; self.timings.subtrees.len()];
+    let mut measurements = 0;
+    self
+      .taffy
+      .compute_layout_with_measure(root, available, |known, available, id, measure, _style| {
+        let Some(measure) = measure else {
+          return known.unwrap_or(taffy::Size::ZERO);
+        };
+        let measure_started = Instant::now();
+        let size = measure_leaf(measure, known, available);
+        measurements += 1;
+        if let Some(&subtree) = subtree_of.get(&id) {
+          measure_times[subtree] += measure_started.elapsed();
+        }
+        size
+      })
+      .map_err(layout_error)?;
+    for (timing, measured) in self.timings.subtrees.iter_mut().zip(measure_times) {
+      timing.duration += measured;
+    }
+    self.timings.measurements += measurements;
+    Ok(())
+  }
+
+  /// Measure the content widths of the boxes sized by their content whose
+  /// subtree changed, innermost first so that the boxes around them see
+  /// their final widths, and size the boxes by them
+  fn measure_content(&mut self) -> Result<()> {
+    let content_sized = std::mem::take(&mut self.content_sized);
+    for (key, sizing) in content_sized.iter().rev() {
+      let Some(id) = self.nodes.get(key).map(|cached| cached.id) else {
+        continue;
+      };
+      let clean = !self.taffy.dirty(id).map_err(layout_error)?;
+      if clean && self.content_widths.contains_key(&id) {
+        continue;
+      }
+
+      // Lay the box out on its own at either extreme, as wide as its
+      // content alone makes it
+      let mut style = self.nodes[key].style.0.clone();
+      style.size.width = taffy::Dimension::auto();
+      style.min_size.width = taffy::Dimension::auto();
+      style.max_size.width = taffy::Dimension::auto();
+      self
+        .taffy
+        .set_style(id, &NodeStyle::new(style))
+        .map_err(layout_error)?;
+      let mut extreme = |width: AvailableSpace| -> Result<f32> {
+        self.run_taffy(
+          id,
+          taffy::Size {
+            width,
+            height: AvailableSpace::MaxContent,
+          },
+        )?;
+        Ok(self.taffy.layout(id).map_err(layout_error)?.size.width)
+      };
+      let widths = ContentWidths {
+        min: extreme(AvailableSpace::MinContent)?,
+        max: extreme(AvailableSpace::MaxContent)?,
+      };
+      self.content_widths.insert(id, widths);
+
+      let cached = self.nodes.get_mut(key).expect("node of a synced element");
+      let mut style = cached.style.0.clone();
+      with_content_widths(&mut style, *sizing, Some(widths));
+      cached.style = NodeStyle::new(style);
+      self
+        .taffy
+        .set_style(id, &cached.style)
+        .map_err(layout_error)?;
+    }
+    self.content_sized = content_sized;
+    Ok(())
+  }
+
+  /// Give `overflow: auto` containers scrollbars along the axes their
+  /// content overflows and take them from the rest, returning whether any
+  /// changed
+  fn fit_gutters(&mut self) -> Result<bool> {
+    let mut changed = false;
+    for (key, auto) in &self.auto_scrollers {
+      let Some(cached) = self.nodes.get_mut(key) else {
+        continue;
+      };
+      let placed = self.taffy.layout(cached.id).map_err(layout_error)?;
+      let port = taffy::Size {
+        width: placed.size.width - placed.scrollbar_size.width,
+        height: placed.size.height - placed.scrollbar_size.height,
+      };
+      let gutter = (
+        auto.0 && placed.content_size.width > port.width,
+        auto.1 && placed.content_size.height > port.height,
+      );
+      let had = self.gutters.get(&cached.id).copied().unwrap_or_default();
+      if gutter == had {
+        continue;
+      }
+      self.gutters.insert(cached.id, gutter);
+      let mut style = cached.style.0.clone();
+      with_gutter(&mut style, *auto, gutter);
+      cached.style = NodeStyle::new(style);
+      self
+        .taffy
+        .set_style(cached.id, &cached.style)
+        .map_err(layout_error)?;
+      changed = true;
+    }
+    Ok(changed)
+  }
+
+  /// Bring the node for `element` and its descendants up to date. `subtree`
+  /// is the index of the root child the element belongs to, `None` for the
+  /// root itself.
+  ///
+  /// Taffy places absolutely positioned boxes in their parent node, so their
+  /// nodes are collected into `containing`, the children of the nearest
+  /// positioned ancestor, and fixed boxes into `fixed`, the root's children.
+  fn sync(
+    &mut self,
+    key: String,
+    element: &Element,
+    node: &ComponentNode,
+    subtree: Option<usize>,
+    containing: &mut Vec<NodeId>,
+    fixed: &mut Vec<NodeId>,
+  ) -> Result<NodeId> {
+    let styles = node.styles.to_layout_styles();
+    let mut style = taffy_style(&styles);
+    let positioned = subtree.is_none() || styles.position != PositionType::Static;
+    let auto = (
+      styles.overflow.x == Overflow::Auto,
+      styles.overflow.y == Overflow::Auto,
+    );
+    if auto.0 || auto.1 {
+      // Keep the scrollbars the content needed last time
+      let gutter = self
+        .nodes
+        .get(&key)
+        .and_then(|cached| self.gutters.get(&cached.id))
+        .copied()
+        .unwrap_or_default();
+      with_gutter(&mut style, auto, gutter);
+      self.auto_scrollers.push((key.clone(), auto));
+    }
+    if let Some(sizing) = ContentSizing::of(&styles) {
+      // Keep the widths the content had last time; they are measured again
+      // if anything in the box changed
+      let widths = self
+        .nodes
+        .get(&key)
+        .and_then(|cached| self.content_widths.get(&cached.id))
+        .copied();
+      with_content_widths(&mut style, sizing, widths);
+      self.content_sized.push((key.clone(), sizing));
+    }
+    let mut contained = Vec::new();
+    let slot = self.order.len();
+    self.order.push(NodeId::from(0u64));
+    if subtree.is_none() {
+      // The root fills the viewport
+      style.size = taffy::Size {
+        width: taffy::Dimension::length(f32::from(self.viewport.0)),
+        height: taffy::Dimension::length(f32::from(self.viewport.1)),
+      };
+    }
+
+    let mut measure = None;
+    let mut children = Vec::new();
+    match styles.display {
+      DisplayType::None => {}
+      DisplayType::Grid => {
+        measure = Some(Measure::Grid {
+          rows: element.children.len().clamp(1, usize::from(u16::MAX)) as u16,
+        });
+      }
+      _ => {
+        if let Some(content) = node.inline_content() {
+          let leaf = Measure::Text {
+            content,
+            style: styles.text.clone(),
+          };
+          if element.children.is_empty() {
+            measure = Some(leaf);
+          } else {
+            // Content ahead of child elements gets a box of its own
+            let text_style = NodeStyle::new(taffy::Style {
+              display: taffy::Display::Block,
+              ..Default::default()
+            });
+            children.push(self.update(
+              format!("{key}/::text"),
+              text_style,
+              Some(leaf),
+              Vec::new(),
+              subtree,
+            )?);
+          }
+        }
+
+        for (index, (child_element, child_node)) in
+          element.children.iter().zip(&node.children).enumerate()
+        {
+          let child_key = self.child_key(&key, child_element, index);
+          let child_started = Instant::now();
+          let containing: &mut Vec<NodeId> = if positioned {
+            &mut contained
+          } else {
+            &mut *containing
+          };
+          let child = self.sync(
+            child_key.clone(),
+            child_element,
+            child_node,
+            Some(subtree.unwrap_or(index)),
+            &mut *containing,
+            &mut *fixed,
+          )?;
+          if subtree.is_none() {
+            self.timings.subtrees.push(SubtreeTiming {
+              key: child_key,
+              duration: child_started.elapsed(),
+            });
+          }
+          match child_node.styles.position {
+            PositionType::Absolute => containing.push(child),
+            PositionType::Fixed => fixed.push(child),
+            _ => children.push(child),
+          }
+        }
+      }
+    }
+    children.append(&mut contained);
+    if subtree.is_none() {
+      children.append(fixed);
+    }
+
+    let id = self.update(key, NodeStyle::new(style), measure, children, subtree)?;
+    self.order[slot] = id;
+    Ok(id)
+  }
+
+  /// Key of the `index`th child of the element keyed `parent`
+  fn child_key(&self, parent: &str, element: &Element, index: usize) -> String {
+    if let Some(id) = &element.id {
+      let key = format!("{parent}/#{id}");
+      // A repeated id among siblings falls back to the position
+      if self
+        .nodes
+        .get(&key)
+        .is_none_or(|cached| cached.pass != self.pass)
+      {
+        return key;
+      }
+    }
+    format!("{parent}/{}[{index}]", element.tag)
+  }
+
+  /// Create the node for `key` or update the parts of it that changed
+  fn update(
+    &mut self,
+    key: String,
+    style: NodeStyle,
+    measure: Option<Measure>,
+    children: Vec<NodeId>,
+    subtree: Option<usize>,
+  ) -> Result<NodeId> {
+    let id = match self.nodes.get_mut(&key) {
+      Some(cached) => {
+        let mut dirty = false;
+        if cached.style != style {
+          self
+            .taffy
+            .set_style(cached.id, &style)
+            .map_err(layout_error)?;
+          cached.style = style;
+          dirty = true;
+        }
+        if cached.measure != measure {
+          self
+            .taffy
+            .set_node_context(cached.id, measure.clone())
+            .map_err(layout_error)?;
+          cached.measure = measure;
+          dirty = true;
+        }
+        if cached.children != children {
+          self
+            .taffy
+            .set_children(cached.id, &children)
+            .map_err(layout_error)?;
+          cached.children = children;
+          dirty = true;
+        }
+        cached.pass = self.pass;
+        self.timings.dirty_nodes += usize::from(dirty);
+        cached.id
+      }
+      None => {
+        let id = self
+          .taffy
+          .new_leaf(&style, measure.clone())
+          .map_err(layout_error)?;
+        self
+          .taffy
+          .set_children(id, &children)
+          .map_err(layout_error)?;
+        self.nodes.insert(
+          key,
+          CachedNode {
+            id,
+            style,
+            measure,
+            children,
+            pass: self.pass,
+          },
+        );
+        self.timings.dirty_nodes += 1;
+        id
+      }
+    };
+    if let Some(subtree) = subtree {
+      self.subtree_of.insert(id, subtree);
+    }
+    Ok(id)
+  }
+
+  /// Convert the laid out node for `element`, the next of `ids`, to a
+  /// [`Layout`] with absolute coordinates, given the positions of the nodes
+  /// it may be placed in. Scroll containers and elements with an id are
+  /// recorded in `scrolled`.
+  fn output<'a>(
+    &self,
+    element: &Element,
+    node: &ComponentNode,
+    ids: &mut impl Iterator<Item = &'a NodeId>,
+    origins: Origins,
+    scrolled: &mut ScrollPass,
+  ) -> Result<Layout> {
+    let Some(&id) = ids.next() else {
+      return Err(TuiError::layout(
+        "layout tree out of sync with the element tree",
+      ));
+    };
+    let styles = node.styles.to_layout_styles();
+    let placed = self.taffy.layout(id).map_err(layout_error)?;
+    let parent = match styles.position {
+      PositionType::Absolute => origins.containing,
+      PositionType::Fixed => origins.root,
+      _ => origins.parent,
+    };
+    let origin = (parent.0 + placed.location.x, parent.1 + placed.location.y);
+    let rect = LayoutRect {
+      x: cells(origin.0),
+      y: cells(origin.1),
+      width: cells(placed.size.width),
+      height: cells(placed.size.height),
+    };
+    let mut layout = Layout {
+      rect,
+      children: Vec::new(),
+      element_id: element.id.clone(),
+      tag: element.tag.clone(),
+      content: node.inline_content(),
+      styles,
+      focused: false,
+      focusable: false,
+    };
+
+    let (view, mut container) = match layout.styles.position {
+      PositionType::Fixed => (ScrollView::default(), None),
+      _ => (origins.view, origins.container),
+    };
+    if let Some(element_id) = &element.id {
+      scrolled.element(element_id.clone(), rect, container);
+    }
+    let overflow = layout.styles.overflow;
+    if overflow.scrolls() {
+      let (x, y) = self.scrolling.offset(id);
+      let mut state = ScrollState {
+        offset_x: 0,
+        offset_y: 0,
+        content_width: cells(placed.content_size.width),
+        content_height: cells(placed.content_size.height),
+        viewport_width: cells(placed.size.width - placed.scrollbar_size.width),
+        viewport_height: cells(placed.size.height - placed.scrollbar_size.height),
+      };
+      if overflow.x.scrolls() {
+        state.offset_x = x.min(state.max_offset_x());
+      }
+      if overflow.y.scrolls() {
+        state.offset_y = y.min(state.max_offset_y());
+      }
+      layout.styles.scroll_state = state;
+      let port = state.scrollport(rect);
+      container = Some(scrolled.container(Container {
+        node: id,
+        element_id: element.id.clone(),
+        port,
+        visible: view.visible(port),
+        state,
+        axes: (overflow.x.scrolls(), overflow.y.scrolls()),
+        parent: container,
+      }));
+    }
+    let child_origins = Origins {
+      parent: origin,
+      containing: match layout.styles.position {
+        PositionType::Static => origins.containing,
+        _ => origin,
+      },
+      root: origins.root,
+      view: view.enter(&layout),
+      container,
+    };
+
+    layout.children = match layout.styles.display {
+      DisplayType::None => Vec::new(),
+      DisplayType::Grid => grid_children(element, node, &layout.styles, rect)?,
+      _ => element
+        .children
+        .iter()
+        .zip(&node.children)
+        .map(|(child_element, child_node)| {
+          self.output(child_element, child_node, ids, child_origins, scrolled)
+        })
+        .collect::<Result<Vec<_>>>()?,
+    };
+    Ok(layout)
+  }
+}
+
+/// Lay out the children of a grid container, configured by its classes
+/// and by the named areas of its stylesheet
+fn grid_children(
+  element: &Element,
+  node: &ComponentNode,
+  styles: &ComputedStyles,
+  rect: LayoutRect,
+) -> Result<Vec<Layout>> {
+  let grid = advanced_grid::GridLayout::new();
+  let mut config = grid.parse_grid_config(element);
+  if !styles.grid_template_areas.is_empty() {
+    config.areas = styles.grid_template_areas.clone();
+  }
+  let items: Vec<_> = element
+    .children
+    .iter()
+    .zip(&node.children)
+    .map(|(child, child_node)| {
+      let mut item = grid.parse_grid_item(child);
+      if let Some(area) = &child_node.styles.grid_area {
+        item.area = Some(area.clone());
+      }
+      item
+    })
+    .collect();
+  Ok(
+    grid
+      .compute_grid_layout(element, &config, &items, rect)?
+      .children,
+  )
+}
+
+/// Absolute positions of the boxes a node may be placed in, and where
+/// its parent's content shows on screen
+#[derive(Debug, Clone, Copy)]
+struct Origins {
+  parent: (f32, f32),
+  /// The nearest positioned ancestor, for absolutely positioned boxes
+  containing: (f32, f32),
+  /// The root, for fixed boxes
+  root: (f32, f32),
+  view: ScrollView,
+  /// Index of the nearest scroll container in the pass
+  container: Option<usize>,
+}
+
+fn layout_error(error: taffy::TaffyError) -> TuiError {
+  TuiError::layout(format!("taffy: {error}"))
+}
+
+/// Round a taffy length to whole cells
+fn cells(value: f32) -> u16 {
+  value.round().clamp(0.0, f32::from(u16::MAX)) as u16
+}
+
+fn measure_leaf(
+  measure: &Measure,
+  known: taffy::Size<Option<f32>>,
+  available: taffy::Size<AvailableSpace>,
+) -> taffy::Size<f32> {
+  let available_width = match available.width {
+    AvailableSpace::Definite(width) => Some(width),
+    AvailableSpace::MinContent | AvailableSpace::MaxContent => None,
+  };
+  match measure {
+    Measure::Text { content, style } => {
+      // Text wraps to fit the space, down to its widest word
+      let width = known.width.unwrap_or_else(|| match available.width {
+        AvailableSpace::MinContent => f32::from(text::measure_min_content_width(content, style)),
+        AvailableSpace::MaxContent => f32::from(text::measure_text_width(content, style)),
+        AvailableSpace::Definite(available) => {
+          f32::from(text::measure_text_width(content, style)).min(available)
+        }
+      });
+      let height = known
+        .height
+        .unwrap_or_else(|| f32::from(text::measure_text_height(content, cells(width), style)));
+      taffy::Size { width, height }
+    }
+    Measure::Grid { rows } => taffy::Size {
+      width: known.width.or(available_width).unwrap_or(0.0),
+      height: known.height.unwrap_or(f32::from(*rows)),
+    },
+  }
+}
+
+fn taffy_style(styles: &ComputedStyles) -> taffy::Style {
+  taffy::Style {
+    display: match styles.display {
+      DisplayType::Flex => taffy::Display::Flex,
+      DisplayType::None => taffy::Display::None,
+      // Inline boxes stack like blocks, and grids are leaves sized here and
+      // filled by advanced grid
+      DisplayType::Block | DisplayType::Inline | DisplayType::Grid => taffy::Display::Block,
+    },
+    position: match styles.position {
+      PositionType::Absolute | PositionType::Fixed => taffy::Position::Absolute,
+      PositionType::Static | PositionType::Relative | PositionType::Sticky => {
+        taffy::Position::Relative
+      }
+    },
+    // Sticky offsets only apply once the box is scrolled, after layout
+    inset: match styles.position {
+      PositionType::Static | PositionType::Sticky => taffy::Rect {
+        left: taffy::LengthPercentageAuto::auto(),
+        right: taffy::LengthPercentageAuto::auto(),
+        top: taffy::LengthPercentageAuto::auto(),
+        bottom: taffy::LengthPercentageAuto::auto(),
+      },
+      _ => taffy::Rect {
+        left: inset(styles.left),
+        right: inset(styles.right),
+        top: inset(styles.top),
+        bottom: inset(styles.bottom),
+      },
+    },
+    // Scroll containers keep their size rather than grow with their
+    // content; `auto` axes only get a scrollbar once their content
+    // overflows, see `with_gutter`
+    overflow: taffy::Point {
+      x: overflow(styles.overflow.x),
+      y: overflow(styles.overflow.y),
+    },
+    scrollbar_width: match styles.scrollbar_width {
+      ScrollbarWidth::Auto | ScrollbarWidth::Thin => 1.0,
+      ScrollbarWidth::None => 0.0,
+    },
+    size: taffy::Size {
+      width: dimension(styles.width),
+      height: dimension(styles.height),
+    },
+    min_size: taffy::Size {
+      width: dimension(styles.min_width),
+      height: dimension(styles.min_height),
+    },
+    max_size: taffy::Size {
+      width: dimension(styles.max_width),
+      height: dimension(styles.max_height),
+    },
+    aspect_ratio: styles.aspect_ratio,
+    margin: spacing(styles.margin, taffy::LengthPercentageAuto::length),
+    padding: spacing(styles.padding, taffy::LengthPercentage::length),
+    flex_direction: match styles.flex_direction {
+      FlexDirection::Row => taffy::FlexDirection::Row,
+      FlexDirection::Column => taffy::FlexDirection::Column,
+      FlexDirection::RowReverse => taffy::FlexDirection::RowReverse,
+      FlexDirection::ColumnReverse => taffy::FlexDirection::ColumnReverse,
+    },
+    flex_wrap: match styles.flex_wrap {
+      FlexWrap::NoWrap => taffy::FlexWrap::NoWrap,
+      FlexWrap::Wrap => taffy::FlexWrap::Wrap,
+      FlexWrap::WrapReverse => taffy::FlexWrap::WrapReverse,
+    },
+    flex_grow: styles.flex_grow,
+    flex_shrink: styles.flex_shrink,
+    flex_basis: dimension(styles.flex_basis),
+    justify_content: Some(match styles.justify_content {
+      JustifyContent::FlexStart => taffy::JustifyContent::FlexStart,
+      JustifyContent::FlexEnd => taffy::JustifyContent::FlexEnd,
+      JustifyContent::Center => taffy::JustifyContent::Center,
+      JustifyContent::SpaceBetween => taffy::JustifyContent::SpaceBetween,
+      JustifyContent::SpaceAround => taffy::JustifyContent::SpaceAround,
+      JustifyContent::SpaceEvenly => taffy::JustifyContent::SpaceEvenly,
+    }),
+    align_items: Some(match styles.align_items {
+      AlignItems::FlexStart => taffy::AlignItems::FlexStart,
+      AlignItems::FlexEnd => taffy::AlignItems::FlexEnd,
+      AlignItems::Center => taffy::AlignItems::Center,
+      AlignItems::Stretch => taffy::AlignItems::Stretch,
+      AlignItems::Baseline => taffy::AlignItems::Baseline,
+    }),
+    align_self: match styles.align_self {
+      AlignSelf::Auto => None,
+      AlignSelf::FlexStart => Some(taffy::AlignSelf::FlexStart),
+      AlignSelf::FlexEnd => Some(taffy::AlignSelf::FlexEnd),
+      AlignSelf::Center => Some(taffy::AlignSelf::Center),
+      AlignSelf::Stretch => Some(taffy::AlignSelf::Stretch),
+      AlignSelf::Baseline => Some(taffy::AlignSelf::Baseline),
+    },
+    align_content: Some(match styles.align_content {
+      AlignContent::FlexStart => taffy::AlignContent::FlexStart,
+      AlignContent::FlexEnd => taffy::AlignContent::FlexEnd,
+      AlignContent::Center => taffy::AlignContent::Center,
+      AlignContent::SpaceBetween => taffy::AlignContent::SpaceBetween,
+      AlignContent::SpaceAround => taffy::AlignContent::SpaceAround,
+      AlignContent::SpaceEvenly => taffy::AlignContent::SpaceEvenly,
+      AlignContent::Stretch => taffy::AlignContent::Stretch,
+    }),
+    ..Default::default()
+  }
+}
+
+/// Whether any length of `style` is a `calc()` expression
+fn has_calc(style: &taffy::Style) -> bool {
+  let dimensions = [
+    style.size.width,
+    style.size.height,
+    style.min_size.width,
+    style.min_size.height,
+    style.max_size.width,
+    style.max_size.height,
+    style.flex_basis,
+  ]
+  .map(taffy::Dimension::into_raw);
+  let auto_lengths = [
+    style.inset.left,
+    style.inset.right,
+    style.inset.top,
+    style.inset.bottom,
+    style.margin.left,
+    style.margin.right,
+    style.margin.top,
+    style.margin.bottom,
+  ]
+  .map(taffy::LengthPercentageAuto::into_raw);
+  let lengths = [
+    style.padding.left,
+    style.padding.right,
+    style.padding.top,
+    style.padding.bottom,
+    style.border.left,
+    style.border.right,
+    style.border.top,
+    style.border.bottom,
+    style.gap.width,
+    style.gap.height,
+  ]
+  .map(taffy::LengthPercentage::into_raw);
+  dimensions
+    .into_iter()
+    .chain(auto_lengths)
+    .chain(lengths)
+    .any(taffy::CompactLength::is_calc)
+}
+
+fn overflow(overflow: Overflow) -> taffy::Overflow {
+  match overflow {
+    Overflow::Visible => taffy::Overflow::Visible,
+    Overflow::Clip => taffy::Overflow::Clip,
+    Overflow::Hidden | Overflow::Auto => taffy::Overflow::Hidden,
+    Overflow::Scroll => taffy::Overflow::Scroll,
+  }
+}
+
+/// Reserve scrollbars along the `auto` axes whose content overflows.
+/// Taffy takes a vertical scrollbar's cells from the width of a box that
+/// scrolls along `y`, and a horizontal one's from the height.
+fn with_gutter(style: &mut taffy::Style, auto: (bool, bool), gutter: (bool, bool)) {
+  let axis = |auto: bool, gutter: bool, current: taffy::Overflow| match (auto, gutter) {
+    (false, _) => current,
+    (true, true) => taffy::Overflow::Scroll,
+    (true, false) => taffy::Overflow::Hidden,
+  };
+  style.overflow = taffy::Point {
+    x: axis(auto.0, gutter.0, style.overflow.x),
+    y: axis(auto.1, gutter.1, style.overflow.y),
+  };
+}
+
+/// Size the widths `sizing` leaves to the content by the content's
+/// `widths`, or leave them to taffy while they are unknown. A `fit-content`
+/// width stretches like `auto` between the two content widths.
+fn with_content_widths(
+  style: &mut taffy::Style,
+  sizing: ContentSizing,
+  widths: Option<ContentWidths>,
+) {
+  let length = |value: SizeValue| match (value, widths) {
+    (SizeValue::MinContent, Some(widths)) => taffy::Dimension::length(widths.min),
+    (SizeValue::MaxContent, Some(widths)) => taffy::Dimension::length(widths.max),
+    _ => taffy::Dimension::auto(),
+  };
+  let fits = sizing.width == SizeValue::FitContent;
+  if sizing.width.is_intrinsic() && !fits {
+    style.size.width = length(sizing.width);
+  }
+  match sizing.min_width {
+    SizeValue::MaxContent => style.min_size.width = length(SizeValue::MaxContent),
+    value if value.is_intrinsic() => style.min_size.width = length(SizeValue::MinContent),
+    SizeValue::Auto if fits => style.min_size.width = length(SizeValue::MinContent),
+    _ => {}
+  }
+  match sizing.max_width {
+    SizeValue::MinContent => style.max_size.width = length(SizeValue::MinContent),
+    value if value.is_intrinsic() => style.max_size.width = length(SizeValue::MaxContent),
+    SizeValue::Auto if fits => style.max_size.width = length(SizeValue::MaxContent),
+    _ => {}
+  }
+}
+
+/// A size in cells or a percentage; `calc()` sums only keep whichever part
+/// is set, `fr` only means something to grid tracks, and sizes taken from
+/// the content are set once it is measured, see `with_content_widths`
+fn dimension(value: SizeValue) -> taffy::Dimension {
+  match value {
+    SizeValue::Pixels(cells) => taffy::Dimension::length(f32::from(cells)),
+    SizeValue::Percent(percent) => taffy::Dimension::percent(percent / 100.0),
+    SizeValue::Calc {
+      percent: 0.0,
+      cells,
+    } => taffy::Dimension::length(cells),
+    SizeValue::Calc {
+      percent,
+      cells: 0.0,
+    } => taffy::Dimension::percent(percent / 100.0),
+    SizeValue::Auto
+    | SizeValue::Fr(_)
+    | SizeValue::Calc { .. }
+    | SizeValue::MinContent
+    | SizeValue::MaxContent
+    | SizeValue::FitContent => taffy::Dimension::auto(),
+  }
+}
+
+fn inset(value: SizeValue) -> taffy::LengthPercentageAuto {
+  match value {
+    SizeValue::Pixels(cells) => taffy::LengthPercentageAuto::length(f32::from(cells)),
+    SizeValue::Percent(percent) => taffy::LengthPercentageAuto::percent(percent / 100.0),
+    SizeValue::Calc {
+      percent: 0.0,
+      cells,
+    } => taffy::LengthPercentageAuto::length(cells),
+    SizeValue::Calc {
+      percent,
+      cells: 0.0,
+    } => taffy::LengthPercentageAuto::percent(percent / 100.0),
+    SizeValue::Auto
+    | SizeValue::Fr(_)
+    | SizeValue::Calc { .. }
+    | SizeValue::MinContent
+    | SizeValue::MaxContent
+    | SizeValue::FitContent => taffy::LengthPercentageAuto::auto(),
+  }
+}
+
+fn spacing<T>(spacing: Spacing, length: fn(f32) -> T) -> taffy::Rect<T> {
+  taffy::Rect {
+    left: length(f32::from(spacing.left)),
+    right: length(f32::from(spacing.right)),
+    top: length(f32::from(spacing.top)),
+    bottom: length(f32::from(spacing.bottom)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::LayoutEngine;
+  use crate::components::Element;
+  use crate::css::CssEngine;
+
+  fn screen(edited: &str) -> Element {
+    let mut root = Element::with_tag("div");
+    for row in 0..50 {
+      let mut list = Element::with_tag("div").id(format!("row-{row}"));
+      for item in 0..100 {
+        list = list.child(
+          Element::with_tag("text")
+            .content(format!("item {row}.{item}"))
+            .build(),
+        );
+      }
+      root = root.child(list.build());
+    }
+    root = root.child(
+      Element::with_tag("input")
+        .id("query")
+        .content(edited)
+        .build(),
+    );
+    root.build()
+  }
+
+  #[test]
+  fn test_only_changed_nodes_are_laid_out_again() {
+    let css = CssEngine::new();
+    let mut engine = LayoutEngine::with_dimensions(80, 24);
+
+    let element = screen("he");
+    let first = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    assert_eq!(engine.last_timings().subtrees.len(), 51);
+    assert!(engine.last_timings().measurements >= 5_000);
+
+    // Nothing changed: every node is answered from the cache
+    let again = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    assert_eq!(engine.last_timings().dirty_nodes, 0);
+    assert_eq!(engine.last_timings().measurements, 0);
+    assert_eq!(again.children[50].rect, first.children[50].rect);
+
+    // Typing into the input only measures the input again
+    let element = screen("hello");
+    let typed = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    assert_eq!(engine.last_timings().dirty_nodes, 1);
+    assert!(engine.last_timings().measurements <= 2);
+    assert_eq!(typed.children[50].content.as_deref(), Some("hello"));
+    assert_eq!(typed.children[50].rect, first.children[50].rect);
+
+    // A taller terminal gives the rows the same width, so they keep their sizes
+    engine.update_dimensions(80, 40);
+    engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    assert_eq!(engine.last_timings().dirty_nodes, 1);
+    assert_eq!(engine.last_timings().measurements, 0);
+  }
+
+  #[test]
+  fn test_content_comes_before_children_and_flex_rows_share_a_line() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(".bar { display: flex; flex-direction: row }")
+      .unwrap();
+    let element = Element::with_tag("div")
+      .child(
+        Element::with_tag("div")
+          .content("Title")
+          .child(Element::with_tag("text").content("body").build())
+          .build(),
+      )
+      .child(
+        Element::with_tag("div")
+          .class("bar")
+          .child(Element::with_tag("text").content("left").build())
+          .child(Element::with_tag("text").content("right").build())
+          .build(),
+      )
+      .build();
+
+    let mut engine = LayoutEngine::with_dimensions(40, 10);
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+
+    let section = &layout.children[0];
+    assert_eq!(section.rect.height, 2);
+    assert_eq!(section.children.len(), 1);
+    assert_eq!(section.children[0].rect.y, 1);
+
+    let bar = &layout.children[1];
+    assert_eq!(bar.rect.y, 2);
+    let (left, right) = (bar.children[0].rect, bar.children[1].rect);
+    assert_eq!((left.x, left.y, left.width), (0, 2, 4));
+    assert_eq!((right.x, right.y), (4, 2));
+  }
+
+  #[test]
+  fn test_positioned_boxes_are_placed_by_their_offsets() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(
+        ".card { position: relative; left: 10; width: 20; height: 3 }
+         .badge { position: absolute; top: 0; right: 0; width: 3; height: 1 }
+         .corner { position: absolute; bottom: 1; left: 2; width: 4; height: 1 }
+         .status { position: fixed; bottom: 0; left: 0; width: 100%; height: 1 }
+         .nudged { position: relative; top: 1; left: -2; height: 1 }
+         .spacer { height: 20 }
+         .footer { position: sticky; bottom: 0; height: 1 }",
+      )
+      .unwrap();
+    let element = Element::with_tag("div")
+      .child(
+        Element::with_tag("div")
+          .class("card")
+          .child(Element::with_tag("text").content("Inbox").build())
+          .child(
+            Element::with_tag("text")
+              .class("badge")
+              .content("3")
+              .build(),
+          )
+          .child(
+            Element::with_tag("div")
+              .child(
+                Element::with_tag("text")
+                  .class("status")
+                  .content("ok")
+                  .build(),
+              )
+              .build(),
+          )
+          .build(),
+      )
+      .child(
+        Element::with_tag("text")
+          .class("corner")
+          .content("tip")
+          .build(),
+      )
+      .child(
+        Element::with_tag("text")
+          .class("nudged")
+          .content("x")
+          .build(),
+      )
+      .child(
+        Element::with_tag("div")
+          .child(Element::with_tag("text").content("top").build())
+          .child(Element::with_tag("div").class("spacer").build())
+          .child(
+            Element::with_tag("text")
+              .class("footer")
+              .content("end")
+              .build(),
+          )
+          .build(),
+      )
+      .build();
+
+    let mut engine = LayoutEngine::with_dimensions(40, 10);
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+
+    let card = &layout.children[0];
+    // Absolute boxes take no room in flow and sit in the nearest positioned ancestor
+    assert_eq!(card.children[1].rect, rect(27, 0, 3, 1));
+    assert_eq!(card.children[2].rect.y, 1);
+    // Without one they sit in the root
+    assert_eq!(layout.children[1].rect, rect(2, 8, 4, 1));
+    // Fixed boxes sit in the viewport wherever they are in the tree
+    assert_eq!(card.children[2].children[0].rect, rect(0, 9, 40, 1));
+    // Relative boxes keep their place in flow and are shifted from it
+    assert_eq!(layout.children[2].rect, rect(0, 4, 40, 1));
+    // The section runs past the viewport, so its sticky footer stays on the last row
+    let section = &layout.children[3];
+    assert_eq!(section.rect.y, 4);
+    assert_eq!(section.children[2].rect.y, 9);
+  }
+
+  #[test]
+  fn test_grid_items_fill_named_areas_from_the_stylesheet() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(
+        ".shell { display: grid; height: 9;
+                  grid-template-areas: \"header header\" \"side main\" \"footer footer\" }
+         .main { grid-area: main }
+         .header { grid-area: header }
+         .side { grid-area: side }
+         .footer { grid-area: footer }",
+      )
+      .unwrap();
+    let mut shell = Element::with_tag("div").class("shell");
+    for area in ["main", "header", "footer", "side"] {
+      shell = shell.child(Element::with_tag("div").class(area).build());
+    }
+    let element = Element::with_tag("div").child(shell.build()).build();
+
+    let mut engine = LayoutEngine::with_dimensions(40, 10);
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    let rects: Vec<_> = layout.children[0]
+      .children
+      .iter()
+      .map(|child| child.rect)
+      .collect();
+    assert_eq!(
+      rects,
+      vec![
+        rect(20, 3, 20, 3),
+        rect(0, 0, 40, 3),
+        rect(0, 6, 40, 3),
+        rect(0, 3, 20, 3),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_scroll_containers_keep_their_size_and_offset() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(
+        ".list { height: 4; overflow-y: auto }
+         .short { height: 3; overflow: auto }",
+      )
+      .unwrap();
+    let mut list = Element::with_tag("div").id("list").class("list");
+    for item in 0..10 {
+      list = list.child(
+        Element::with_tag("text")
+          .id(format!("item-{item}"))
+          .content(format!("item {item}"))
+          .build(),
+      );
+    }
+    let element = Element::with_tag("div")
+      .child(Element::with_tag("text").content("Title").build())
+      .child(list.build())
+      .child(
+        Element::with_tag("div")
+          .id("short")
+          .class("short")
+          .child(Element::with_tag("text").content("fits").build())
+          .build(),
+      )
+      .build();
+    let tree = css.create_component_tree(&element);
+    let mut engine = LayoutEngine::with_dimensions(20, 10);
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &tree)
+      .unwrap();
+
+    // The list keeps its height and gets a scrollbar column; the short
+    // container's content fits, so it gets none
+    let list = &layout.children[1];
+    assert_eq!(list.rect, rect(0, 1, 20, 4));
+    let state = list.styles.scroll_state;
+    assert_eq!(
+      (
+        state.content_height,
+        state.viewport_width,
+        state.viewport_height
+      ),
+      (10, 19, 4)
+    );
+    assert_eq!(list.children[9].rect, rect(0, 10, 19, 1));
+    let short = &layout.children[2];
+    assert_eq!(short.rect, rect(0, 5, 20, 3));
+    assert_eq!(short.styles.scroll_state.viewport_width, 20);
+
+    // Offsets are clamped to the content and kept by the next layout
+    assert!(engine.scroll_by("list", 0, 100));
+    assert!(!engine.scroll_by("list", 0, 1));
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &tree)
+      .unwrap();
+    assert_eq!(layout.children[1].styles.scroll_state.offset_y, 6);
+    assert_eq!(layout.children[1].children[0].rect, rect(0, 1, 19, 1));
+
+    assert!(engine.scroll_into_view("item-1"));
+    assert_eq!(engine.scroll_state("list").unwrap().offset_y, 1);
+    assert!(!engine.scroll_into_view("item-3"));
+    assert!(engine.scroll_page("item-3", 1));
+    assert_eq!(engine.scroll_state("list").unwrap().offset_y, 4);
+    // The wheel scrolls the container under the pointer
+    assert!(engine.scroll_at(3, 2, 0, -2));
+    assert_eq!(engine.scroll_state("list").unwrap().offset_y, 2);
+    assert!(!engine.scroll_at(3, 6, 0, 1));
+  }
+
+  fn rect(x: u16, y: u16, width: u16, height: u16) -> super::LayoutRect {
+    super::LayoutRect {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  #[test]
+  fn test_boxes_sized_by_their_content() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(
+        ".fit { width: fit-content }
+         .min { width: min-content }
+         .max { width: max-content }
+         .at-least { width: 3; min-width: max-content }
+         .square { width: 10; aspect-ratio: 2 }",
+      )
+      .unwrap();
+    let boxed = |class: &str, text: &str| {
+      Element::with_tag("div")
+        .class(class)
+        .child(Element::with_tag("text").content(text).build())
+        .build()
+    };
+    let screen = |label: &str| {
+      Element::with_tag("div")
+        .child(boxed("fit", label))
+        .child(boxed("fit", "alpha beta gamma delta epsilon"))
+        .child(boxed("min", "one two three"))
+        .child(boxed("max", "a label wider than the screen"))
+        .child(boxed("at-least", "hello"))
+        .child(Element::with_tag("div").class("square").build())
+        .build()
+    };
+
+    let mut engine = LayoutEngine::with_dimensions(20, 24);
+    let element = screen("OK");
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    let sizes: Vec<_> = layout
+      .children
+      .iter()
+      .map(|child| (child.rect.width, child.rect.height))
+      .collect();
+    // Fit-content shrinks to short text and wraps long text to the space,
+    // min-content wraps at every word, and max-content never wraps
+    assert_eq!(sizes, [(2, 1), (20, 2), (5, 3), (29, 1), (5, 1), (10, 5)]);
+
+    // A box is measured again when its content changes
+    let element = screen("Cancel");
+    let layout = engine
+      .compute_layout_with_component_tree(&element, &css.create_component_tree(&element))
+      .unwrap();
+    assert_eq!(layout.children[0].rect.width, 6);
+    assert_eq!(layout.children[1].rect.width, 20);
+  }
+
+  #[test]
+  fn test_styles_never_get_calc_lengths() {
+    use super::super::{ComputedStyles, PositionType, SizeValue};
+    use super::{has_calc, taffy_style};
+
+    let values = [
+      SizeValue::Auto,
+      SizeValue::Pixels(4),
+      SizeValue::Percent(50.0),
+      SizeValue::Fr(1.0),
+      crate::css::values::parse_size("calc(100% - 4)").unwrap(),
+      SizeValue::Calc {
+        percent: 0.0,
+        cells: 3.0,
+      },
+      SizeValue::Calc {
+        percent: 25.0,
+        cells: 0.0,
+      },
+      SizeValue::MinContent,
+      SizeValue::MaxContent,
+      SizeValue::FitContent,
+    ];
+    for value in values {
+      let styles = ComputedStyles {
+        position: PositionType::Absolute,
+        flex_basis: value,
+        top: value,
+        right: value,
+        bottom: value,
+        left: value,
+        width: value,
+        height: value,
+        min_width: value,
+        min_height: value,
+        max_width: value,
+        max_height: value,
+        ..ComputedStyles::default()
+      };
+      assert!(!has_calc(&taffy_style(&styles)), "{value:?}");
+    }
+  }
+}
@@ -3,6 +3,13 @@
 pub mod advanced_grid;
 pub mod grid;
 pub mod grid_debug;
+pub mod incremental;
+mod position;
+mod scroll;
+pub mod text;
+
+pub use incremental::{LayoutTimings, SubtreeTiming};
+pub use scroll::ScrollView;
 
 use crate::components::Element;
 use crate::error::{Result, TuiError};
@@ -52,6 +59,37 @@ impl Default for OverflowBehavior {
   }
 }
 
+impl Overflow {
+  /// Whether content overflowing along the axis can be scrolled to
+  pub fn scrolls(self) -> bool {
+    matches!(self, Overflow::Scroll | Overflow::Auto)
+  }
+}
+
+impl OverflowBehavior {
+  /// Whether descendants are clipped to the box; either axis clipping
+  /// clips both
+  pub fn clips(&self) -> bool {
+    self.x != Overflow::Visible || self.y != Overflow::Visible
+  }
+
+  /// Whether the box is a scroll container, scrolling along either axis
+  pub fn scrolls(&self) -> bool {
+    self.x.scrolls() || self.y.scrolls()
+  }
+}
+
+/// Room kept for the scrollbars of a scroll container; `thin` bars take
+/// a cell like `auto` ones but are drawn with lighter lines
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ScrollbarWidth {
+  #[default]
+  Auto,
+  Thin,
+  /// No scrollbars; the content still scrolls
+  None,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct ScrollState {
   pub offset_x: u16,
@@ -102,7 +140,10 @@ pub struct ComputedStyles {
   pub min_height: SizeValue,
   pub max_width: SizeValue,
   pub max_height: SizeValue,
+  /// Width over height, both in cells
+  pub aspect_ratio: Option<f32>,
   pub overflow: OverflowBehavior,
+  pub scrollbar_width: ScrollbarWidth,
   pub scroll_state: ScrollState,
   pub grid_template_columns: Vec<SizeValue>,
   pub grid_template_rows: Vec<SizeValue>,
@@ -114,6 +155,7 @@ pub struct ComputedStyles {
   pub grid_row_start: GridPosition,
   pub grid_row_end: GridPosition,
   pub grid_area: Option<String>,
+  pub text: text::TextStyle,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -275,6 +317,9 @@ pub enum WarningType {
   Overlap,
 }
 
+/// A positioned or z-indexed box and the descendants painted with it.
+/// Descendants that form stacking contexts of their own are taken out of
+/// `layout` into `children`, ordered by z-index and then document order.
 #[derive(Debug, Clone)]
 pub struct StackingContext {
   pub z_index: i32,
@@ -291,7 +336,12 @@ impl StackingContext {
     }
   }
 
-  /// Flatten the stacking context tree into a sorted list for rendering
+  /// Whether `layout` starts a stacking context of its own
+  pub fn forms_context(layout: &Layout) -> bool {
+    layout.styles.position != PositionType::Static || layout.styles.z_index != 0
+  }
+
+  /// Flatten the stacking context tree into a list in paint order
   pub fn flatten_for_rendering<'a>(&'a self) -> Vec<&'a Layout> {
     let mut result = Vec::new();
     self.collect_layouts(&mut result);
@@ -299,14 +349,14 @@ impl StackingContext {
   }
 
   fn collect_layouts<'a>(&'a self, result: &mut Vec<&'a Layout>) {
-    // Add this layout
+    // Negative z-indices paint below this box, the rest above it
+    let (below, above): (Vec<_>, Vec<_>) =
+      self.children.iter().partition(|child| child.z_index < 0);
+    for child in below {
+      child.collect_layouts(result);
+    }
     result.push(&self.layout);
-
-    // Sort children by z-index and collect their layouts
-    let mut sorted_children: Vec<_> = self.children.iter().collect();
-    sorted_children.sort_by_key(|child| child.z_index);
-
-    for child in sorted_children {
+    for child in above {
       child.collect_layouts(result);
     }
   }
@@ -324,9 +374,15 @@ pub enum DisplayType {
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum PositionType {
   Static,
+  /// Laid out in flow, then shifted by its offsets
   Relative,
+  /// Out of flow, placed by its offsets in the nearest positioned ancestor
   Absolute,
+  /// Out of flow, placed by its offsets in the viewport
   Fixed,
+  /// Laid out in flow, then kept within its offsets of the edges of the
+  /// nearest scroll container while its parent is in view
+  Sticky,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -384,7 +440,7 @@ pub enum AlignSelf {
   Baseline,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct Spacing {
   pub top: u16,
   pub right: u16,
@@ -398,6 +454,28 @@ pub enum SizeValue {
   Pixels(u16),
   Percent(f32),
   Fr(f32),
+  /// `calc()` result: a percentage of the available space plus a number of cells
+  Calc { percent: f32, cells: f32 },
+  /// As narrow as the content gets without overflowing, which for text is
+  /// its widest word
+  MinContent,
+  /// As wide as the content without wrapping any of it
+  MaxContent,
+  /// The available space, but no wider than `MaxContent` and no narrower
+  /// than `MinContent`
+  FitContent,
+}
+
+impl SizeValue {
+  /// Whether the size comes from the box's content
+  pub fn is_intrinsic(self) -> bool {
+    matches!(self, Self::MinContent | Self::MaxContent | Self::FitContent)
+  }
+
+  /// Resolve a percentage-plus-cells value against the available space
+  pub fn resolve_calc(percent: f32, cells: f32, available: u16) -> u16 {
+    ((available as f32) * (percent / 100.0) + cells).max(0.0) as u16
+  }
 }
 
 impl Default for ComputedStyles {
@@ -428,7 +506,9 @@ impl Default for ComputedStyles {
       min_height: SizeValue::Auto,
       max_width: SizeValue::Auto,
       max_height: SizeValue::Auto,
+      aspect_ratio: None,
       overflow: OverflowBehavior::default(),
+      scrollbar_width: ScrollbarWidth::Auto,
       scroll_state: ScrollState::default(),
       grid_template_columns: Vec::new(),
       grid_template_rows: Vec::new(),
@@ -440,6 +520,7 @@ impl Default for ComputedStyles {
       grid_row_start: GridPosition::default(),
       grid_row_end: GridPosition::default(),
       grid_area: None,
+      text: text::TextStyle::default(),
     }
   }
 }
@@ -476,6 +557,8 @@ impl Spacing {
 pub struct LayoutEngine {
   terminal_width: u16,
   terminal_height: u16,
+  /// Taffy nodes kept between component tree layouts
+  tree: incremental::IncrementalLayout,
 }
 
 impl LayoutEngine {
@@ -491,6 +574,7 @@ impl LayoutEngine {
     Self {
       terminal_width: width,
       terminal_height: height,
+      tree: incremental::IncrementalLayout::new(),
     }
   }
 
@@ -530,26 +614,65 @@ impl LayoutEngine {
     self.compute_layout_recursive(element, available_rect, &styles)
   }
 
-  /// Compute layout using component tree with per-element styles
+  /// Compute layout using component tree with per-element styles. Only the
+  /// elements whose layout styles, content or children changed since the
+  /// last call are laid out again.
   pub fn compute_layout_with_component_tree(
     &mut self,
     element: &Element,
     component_tree: &crate::css::ComponentTree,
   ) -> Result<Layout> {
-    let available_rect = LayoutRect {
-      x: 0,
-      y: 0,
-      width: self.terminal_width,
-      height: self.terminal_height,
-    };
-
-    self.compute_layout_with_component_tree_recursive(
+    self.tree.compute(
       element,
       component_tree.root(),
-      available_rect,
+      self.terminal_width,
+      self.terminal_height,
     )
   }
 
+  /// Timings of the last [`compute_layout_with_component_tree`] call
+  ///
+  /// [`compute_layout_with_component_tree`]: Self::compute_layout_with_component_tree
+  pub fn last_timings(&self) -> &LayoutTimings {
+    self.tree.timings()
+  }
+
+  /// Scroll state of the scroll container with the element id `id` as of
+  /// the last component tree layout, with any scrolling since
+  pub fn scroll_state(&self, id: &str) -> Option<ScrollState> {
+    self.tree.scrolling().state(id)
+  }
+
+  /// Scroll the scroll container `id` by `dx` columns and `dy` rows.
+  /// Returns whether it moved; the next layout shows it.
+  pub fn scroll_by(&mut self, id: &str, dx: i32, dy: i32) -> bool {
+    self.tree.scrolling_mut().scroll_by(id, dx, dy)
+  }
+
+  /// Scroll the scroll container `id` to an offset, as far as its content
+  /// allows
+  pub fn scroll_to(&mut self, id: &str, x: u16, y: u16) -> bool {
+    self.tree.scrolling_mut().scroll_to(id, x, y)
+  }
+
+  /// Scroll the innermost scroll container on screen at `column`, `row`
+  /// that can move by `dx`, `dy`, as the mouse wheel does
+  pub fn scroll_at(&mut self, column: u16, row: u16, dx: i32, dy: i32) -> bool {
+    self.tree.scrolling_mut().scroll_at(column, row, dx, dy)
+  }
+
+  /// Scroll the scroll container `id`, or the one the element `id` is in,
+  /// a number of pages down, or up when negative
+  pub fn scroll_page(&mut self, id: &str, pages: i32) -> bool {
+    self.tree.scrolling_mut().scroll_page(id, pages)
+  }
+
+  /// Scroll the scroll containers around the element `id` just enough to
+  /// show it
+  pub fn scroll_into_view(&mut self, id: &str) -> bool {
+    self.tree.scrolling_mut().scroll_into_view(id)
+  }
+
   /// Compute layout using pre-computed styles from CSS engine
   pub fn compute_layout_with_styles(
     &mut self,
@@ -615,27 +738,27 @@ impl LayoutEngine {
   /// Build stacking contexts for proper z-index rendering
   pub fn build_stacking_context(&self, layout: Layout) -> StackingContext {
     let mut context = StackingContext::new(layout);
+    self.extract_stacking_contexts(&mut context.layout, &mut context.children);
 
-    // Group children by z-index
-    let mut z_groups: std::collections::BTreeMap<i32, Vec<Layout>> = std::collections::BTreeMap::new();
+    // Stable, so equal z-indices keep document order
+    context.children.sort_by_key(|child| child.z_index);
 
-    for child in context.layout.children.drain(..) {
-      let z_index = child.styles.z_index;
-      z_groups.entry(z_index).or_insert_with(Vec::new).push(child);
-    }
+    context
+  }
 
-    // Create stacking contexts for each z-index group
-    for (_z_index, children) in z_groups {
-      for child in children {
-        let child_context = self.build_stacking_context(child);
-        context.children.push(child_context);
+  /// Move the descendants of `layout` that form stacking contexts into
+  /// `contexts`, leaving the rest to paint with it
+  fn extract_stacking_contexts(&self, layout: &mut Layout, contexts: &mut Vec<StackingContext>) {
+    let mut kept = Vec::with_capacity(layout.children.len());
+    for mut child in layout.children.drain(..) {
+      if StackingContext::forms_context(&child) {
+        contexts.push(self.build_stacking_context(child));
+      } else {
+        self.extract_stacking_contexts(&mut child, contexts);
+        kept.push(child);
       }
     }
-
-    // Sort children by z-index
-    context.children.sort_by_key(|child| child.z_index);
-
-    context
+    layout.children = kept;
   }
 
   fn compute_element_rect(
@@ -648,7 +771,10 @@ impl LayoutEngine {
       PositionType::Absolute => self.compute_absolute_rect(available_rect, styles),
       PositionType::Fixed => self.compute_fixed_rect(styles),
       PositionType::Relative => self.compute_relative_rect(available_rect, styles),
-      PositionType::Static => self.compute_static_rect(available_rect, styles),
+      // Nothing scrolls in this layout, so sticky boxes stay in flow
+      PositionType::Static | PositionType::Sticky => {
+        self.compute_static_rect(available_rect, styles)
+      }
     }
   }
 
@@ -853,9 +979,13 @@ impl LayoutEngine {
 
   fn resolve_size_value(&self, value: SizeValue, available: u16) -> u16 {
     match value {
-      SizeValue::Auto => available,
+      // Without a content measure, intrinsic sizes fill the space like `auto`
+      SizeValue::Auto | SizeValue::MinContent | SizeValue::MaxContent | SizeValue::FitContent => {
+        available
+      }
       SizeValue::Pixels(px) => px,
       SizeValue::Percent(pct) => ((available as f32) * (pct / 100.0)) as u16,
+      SizeValue::Calc { percent, cells } => SizeValue::resolve_calc(percent, cells, available),
       SizeValue::Fr(fraction) => {
         // Fr units take up a fraction of the remaining space
         // This is a simplified implementation - proper flex layout would
@@ -1423,12 +1553,17 @@ impl LayoutEngine {
           sizes.push(size);
           remaining = remaining.saturating_sub(size);
         }
+        SizeValue::Calc { percent, cells } => {
+          let size = SizeValue::resolve_calc(*percent, *cells, available_for_tracks);
+          sizes.push(size);
+          remaining = remaining.saturating_sub(size);
+        }
         SizeValue::Fr(fr) => {
           sizes.push(0); // Placeholder
           fr_tracks.push(i);
           total_fr += fr;
         }
-        SizeValue::Auto => {
+        SizeValue::Auto | SizeValue::MinContent | SizeValue::MaxContent | SizeValue::FitContent => {
           sizes.push(0); // Will be calculated later
         }
       }
@@ -1687,6 +1822,7 @@ impl LayoutEngine {
         "relative" => styles.position = PositionType::Relative,
         "absolute" => styles.position = PositionType::Absolute,
         "fixed" => styles.position = PositionType::Fixed,
+        "sticky" => styles.position = PositionType::Sticky,
 
         // Position values
         "top-0" => styles.top = SizeValue::Pixels(0),
@@ -2028,127 +2164,6 @@ impl LayoutEngine {
       rect1.y + rect1.height <= rect2.y ||
       rect2.y + rect2.height <= rect1.y)
   }
-
-  fn compute_layout_with_component_tree_recursive(
-    &self,
-    element: &Element,
-    component_node: &crate::css::ComponentNode,
-    container_rect: LayoutRect,
-  ) -> Result<Layout> {
-    let css_styles = &component_node.styles;
-    let layout_styles = css_styles.to_layout_styles();
-
-    // Compute this element's layout
-    let mut layout = Layout {
-      rect: container_rect,
-      children: Vec::new(),
-      element_id: element.id.clone(),
-      tag: element.tag.clone(),
-      content: element.content.clone(),
-      styles: layout_styles.clone(),
-      focused: false,
-      focusable: false,
-    };
-
-    // Compute children layouts using their component tree styles
-    match layout_styles.display {
-      DisplayType::Flex => {
-        layout.children = self.compute_flex_children_with_component_tree(
-          element,
-          component_node,
-          container_rect,
-        )?;
-      }
-      DisplayType::Block => {
-        layout.children = self.compute_block_children_with_component_tree(
-          element,
-          component_node,
-          container_rect,
-        )?;
-      }
-      DisplayType::Inline => {
-        layout.children = self.compute_inline_children_with_component_tree(
-          element,
-          component_node,
-          container_rect,
-        )?;
-      }
-      DisplayType::None => {
-        // Element is hidden, no children to compute
-      }
-      DisplayType::Grid => {
-        // Use advanced grid for component-tree-driven CSS
-        let advanced = crate::layout::advanced_grid::GridLayout::new();
-        // Reuse existing API to compute a grid layout for this element
-        let grid_result = advanced.compute_layout(element, container_rect)?;
-        layout.children = grid_result.children;
-      }
-    }
-
-    Ok(layout)
-  }
-
-  fn compute_block_children_with_component_tree(
-    &self,
-    element: &Element,
-    component_node: &crate::css::ComponentNode,
-    container_rect: LayoutRect,
-  ) -> Result<Vec<Layout>> {
-    let mut children = Vec::new();
-    let mut current_y = container_rect.y;
-
-    for (child_element, child_node) in element.children.iter().zip(component_node.children.iter()) {
-      let child_styles = &child_node.styles;
-
-      // Compute child height based on content or default to 1 line
-      let child_height = if child_element.content.is_some() {
-        1 // Text elements get 1 line height
-      } else {
-        // Container elements get height based on their children
-        let child_count = child_element.children.len() as u16;
-        if child_count > 0 {
-          child_count
-        } else {
-          1
-        }
-      };
-
-      let child_rect = LayoutRect {
-        x: container_rect.x,
-        y: current_y,
-        width: container_rect.width,
-        height: child_height,
-      };
-
-      let child_layout =
-        self.compute_layout_with_component_tree_recursive(child_element, child_node, child_rect)?;
-      current_y += child_layout.rect.height + child_styles.margin.top + child_styles.margin.bottom;
-
-      children.push(child_layout);
-    }
-
-    Ok(children)
-  }
-
-  fn compute_flex_children_with_component_tree(
-    &self,
-    element: &Element,
-    component_node: &crate::css::ComponentNode,
-    container_rect: LayoutRect,
-  ) -> Result<Vec<Layout>> {
-    // For now, treat flex as block layout
-    self.compute_block_children_with_component_tree(element, component_node, container_rect)
-  }
-
-  fn compute_inline_children_with_component_tree(
-    &self,
-    element: &Element,
-    component_node: &crate::css::ComponentNode,
-    container_rect: LayoutRect,
-  ) -> Result<Vec<Layout>> {
-    // For now, treat inline as block layout
-    self.compute_block_children_with_component_tree(element, component_node, container_rect)
-  }
 }
 
 impl Default for LayoutEngine {
@@ -0,0 +1,146 @@
+//! Sticky positioning
+//!
+//! Sticky boxes are laid out in flow like relative ones with no offset.
+//! This pass then moves each one, with its subtree, by as much as it takes
+//! to keep it `top`/`right`/`bottom`/`left` cells inside its scrollport,
+//! the nearest ancestor that clips its overflow or else the viewport,
+//! without leaving its parent's content box. Boxes in a scroll container
+//! are kept where they are laid out, so its scrollport is moved by its
+//! scroll offset instead.
+
+use super::{Layout, LayoutRect, PositionType, SizeValue};
+
+/// Move every sticky box under `layout` into place within `scrollport`
+pub(crate) fn apply_sticky(layout: &mut Layout, scrollport: LayoutRect) {
+  let overflow = layout.styles.overflow;
+  let port = if overflow.scrolls() {
+    let state = layout.styles.scroll_state;
+    let port = state.scrollport(layout.rect);
+    LayoutRect {
+      x: port.x.saturating_add(state.offset_x),
+      y: port.y.saturating_add(state.offset_y),
+      ..port
+    }
+  } else if overflow.clips() {
+    layout.rect
+  } else {
+    scrollport
+  };
+  let padding = layout.styles.padding;
+  let container =
+    Edges::of(layout.rect).inset(padding.top, padding.right, padding.bottom, padding.left);
+  for child in &mut layout.children {
+    if child.styles.position == PositionType::Sticky {
+      let (dx, dy) = sticky_shift(child, container, port);
+      if dx != 0 || dy != 0 {
+        translate(child, dx, dy);
+      }
+    }
+    apply_sticky(child, port);
+  }
+}
+
+/// Edges of a rect, signed so that shifted boxes can be compared
+#[derive(Debug, Clone, Copy)]
+struct Edges {
+  top: i32,
+  right: i32,
+  bottom: i32,
+  left: i32,
+}
+
+impl Edges {
+  fn of(rect: LayoutRect) -> Self {
+    Self {
+      top: i32::from(rect.y),
+      right: i32::from(rect.x) + i32::from(rect.width),
+      bottom: i32::from(rect.y) + i32::from(rect.height),
+      left: i32::from(rect.x),
+    }
+  }
+
+  fn inset(self, top: u16, right: u16, bottom: u16, left: u16) -> Self {
+    Self {
+      top: self.top + i32::from(top),
+      right: self.right - i32::from(right),
+      bottom: self.bottom - i32::from(bottom),
+      left: self.left + i32::from(left),
+    }
+  }
+}
+
+/// How far `sticky` has to move to honour its offsets within `port`
+/// while staying inside `container`
+fn sticky_shift(sticky: &Layout, container: Edges, port: LayoutRect) -> (i32, i32) {
+  let styles = &sticky.styles;
+  let bounds = Edges::of(sticky.rect);
+  let (rect, port) = (port, Edges::of(port));
+  let dy = shift(
+    bounds.top,
+    bounds.bottom,
+    container.top,
+    container.bottom,
+    offset(styles.top, rect.height).map(|top| port.top + top),
+    offset(styles.bottom, rect.height).map(|bottom| port.bottom - bottom),
+  );
+  let dx = shift(
+    bounds.left,
+    bounds.right,
+    container.left,
+    container.right,
+    offset(styles.left, rect.width).map(|left| port.left + left),
+    offset(styles.right, rect.width).map(|right| port.right - right),
+  );
+  (dx, dy)
+}
+
+/// Shift along one axis for a box spanning `start..end` in a container
+/// spanning `min..max`, given the lines it may not cross
+fn shift(
+  start: i32,
+  end: i32,
+  min: i32,
+  max: i32,
+  start_line: Option<i32>,
+  end_line: Option<i32>,
+) -> i32 {
+  if let Some(line) = start_line {
+    if start < line {
+      return (line - start).min(max - end).max(0);
+    }
+  }
+  if let Some(line) = end_line {
+    if end > line {
+      return -(end - line).min(start - min).max(0);
+    }
+  }
+  0
+}
+
+/// A sticky offset in cells, `None` for `auto`
+fn offset(value: SizeValue, available: u16) -> Option<i32> {
+  match value {
+    SizeValue::Pixels(cells) => Some(i32::from(cells)),
+    SizeValue::Percent(percent) => Some((f32::from(available) * percent / 100.0) as i32),
+    SizeValue::Calc { percent, cells } => {
+      Some((f32::from(available) * percent / 100.0 + cells) as i32)
+    }
+    SizeValue::Auto
+    | SizeValue::Fr(_)
+    | SizeValue::MinContent
+    | SizeValue::MaxContent
+    | SizeValue::FitContent => None,
+  }
+}
+
+/// Move `layout` and its descendants, except fixed ones, by `dx`, `dy`
+fn translate(layout: &mut Layout, dx: i32, dy: i32) {
+  let moved = |at: u16, by: i32| (i32::from(at) + by).clamp(0, i32::from(u16::MAX)) as u16;
+  layout.rect.x = moved(layout.rect.x, dx);
+  layout.rect.y = moved(layout.rect.y, dy);
+  for child in &mut layout.children {
+    if child.styles.position != PositionType::Fixed {
+      translate(child, dx, dy);
+    }
+  }
+}
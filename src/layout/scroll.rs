@@ -0,0 +1,328 @@
+//! Scroll containers
+//!
+//! An element with `overflow: auto` or `scroll` keeps the size it was given
+//! however much content it holds, and shows part of that content through
+//! its scrollport: its box less the cells its scrollbars take. Boxes stay
+//! where they were laid out. The container's [`ScrollState`] records how
+//! far its content is scrolled, and a [`ScrollView`] carries the offsets
+//! and clips of the containers above a box down the tree, so the renderer
+//! and hit testing know where the box shows on screen.
+//!
+//! Offsets are kept between layout passes for each taffy node and clamped
+//! to the content every pass.
+
+use super::{Layout, LayoutRect, PositionType, ScrollState};
+use std::collections::HashMap;
+use taffy::NodeId;
+
+/// Where the boxes below some point of the layout tree show on screen
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScrollView {
+  /// Columns the boxes are scrolled left by
+  pub dx: i32,
+  /// Rows the boxes are scrolled up by
+  pub dy: i32,
+  /// Screen area the boxes are visible in; `None` leaves them unclipped
+  pub clip: Option<LayoutRect>,
+}
+
+impl ScrollView {
+  /// The view of `layout`'s children: clipped to its scrollport, or to its
+  /// box when it only hides its overflow, and scrolled by its offset
+  pub fn enter(self, layout: &Layout) -> Self {
+    let overflow = layout.styles.overflow;
+    if !overflow.clips() {
+      return self;
+    }
+    let state = layout.styles.scroll_state;
+    let port = if overflow.scrolls() {
+      state.scrollport(layout.rect)
+    } else {
+      layout.rect
+    };
+    let nothing = LayoutRect {
+      x: 0,
+      y: 0,
+      width: 0,
+      height: 0,
+    };
+    Self {
+      dx: self.dx + i32::from(state.offset_x),
+      dy: self.dy + i32::from(state.offset_y),
+      clip: Some(self.visible(port).unwrap_or(nothing)),
+    }
+  }
+
+  /// The view of `child`, one of the boxes this is the view of. Fixed boxes
+  /// stay in place however their ancestors scroll.
+  pub fn place(self, child: &Layout) -> Self {
+    match child.styles.position {
+      PositionType::Fixed => Self::default(),
+      _ => self,
+    }
+  }
+
+  /// Screen position of the top left corner of `rect`, which may be above
+  /// or left of the screen
+  pub fn origin(self, rect: LayoutRect) -> (i32, i32) {
+    (i32::from(rect.x) - self.dx, i32::from(rect.y) - self.dy)
+  }
+
+  /// The part of `rect` that shows on screen
+  pub fn visible(self, rect: LayoutRect) -> Option<LayoutRect> {
+    let (x, y) = self.origin(rect);
+    let (mut left, mut top) = (x.max(0), y.max(0));
+    let mut right = x + i32::from(rect.width);
+    let mut bottom = y + i32::from(rect.height);
+    if let Some(clip) = self.clip {
+      left = left.max(i32::from(clip.x));
+      top = top.max(i32::from(clip.y));
+      right = right.min(i32::from(clip.x) + i32::from(clip.width));
+      bottom = bottom.min(i32::from(clip.y) + i32::from(clip.height));
+    }
+    let cells = |value: i32| value.clamp(0, i32::from(u16::MAX)) as u16;
+    (right > left && bottom > top).then(|| LayoutRect {
+      x: cells(left),
+      y: cells(top),
+      width: cells(right - left),
+      height: cells(bottom - top),
+    })
+  }
+}
+
+impl ScrollState {
+  /// Furthest the content can be scrolled left
+  pub fn max_offset_x(&self) -> u16 {
+    self.content_width.saturating_sub(self.viewport_width)
+  }
+
+  /// Furthest the content can be scrolled up
+  pub fn max_offset_y(&self) -> u16 {
+    self.content_height.saturating_sub(self.viewport_height)
+  }
+
+  /// The scrollport of a container laid out at `rect`, unscrolled
+  pub fn scrollport(&self, rect: LayoutRect) -> LayoutRect {
+    LayoutRect {
+      width: self.viewport_width,
+      height: self.viewport_height,
+      ..rect
+    }
+  }
+}
+
+/// A scroll container found by a layout pass
+#[derive(Debug, Clone)]
+pub(crate) struct Container {
+  pub(crate) node: NodeId,
+  pub(crate) element_id: Option<String>,
+  /// Scrollport in layout coordinates, unscrolled
+  pub(crate) port: LayoutRect,
+  /// Part of the scrollport that shows on screen
+  pub(crate) visible: Option<LayoutRect>,
+  pub(crate) state: ScrollState,
+  /// Whether it scrolls horizontally and vertically
+  pub(crate) axes: (bool, bool),
+  /// Index of the nearest scroll container it is in
+  pub(crate) parent: Option<usize>,
+}
+
+/// Layout rect of an element with an id and the scroll container it is in
+#[derive(Debug, Clone, Copy)]
+struct Target {
+  rect: LayoutRect,
+  container: Option<usize>,
+}
+
+/// Scroll containers, in tree order, and elements with an id found by one
+/// layout pass
+#[derive(Debug, Default)]
+pub(crate) struct ScrollPass {
+  containers: Vec<Container>,
+  targets: HashMap<String, Target>,
+}
+
+impl ScrollPass {
+  /// Record a scroll container, returning its index for the elements in it
+  pub(crate) fn container(&mut self, container: Container) -> usize {
+    self.containers.push(container);
+    self.containers.len() - 1
+  }
+
+  /// Record where the element `id` was laid out
+  pub(crate) fn element(&mut self, id: String, rect: LayoutRect, container: Option<usize>) {
+    self.targets.insert(id, Target { rect, container });
+  }
+}
+
+/// Scroll offsets kept between layout passes and what the last pass found
+#[derive(Debug, Default)]
+pub(crate) struct Scrolling {
+  offsets: HashMap<NodeId, (u16, u16)>,
+  last: ScrollPass,
+}
+
+impl Scrolling {
+  /// Offset the container laid out by `node` was last scrolled to
+  pub(crate) fn offset(&self, node: NodeId) -> (u16, u16) {
+    self.offsets.get(&node).copied().unwrap_or((0, 0))
+  }
+
+  /// Keep what a finished pass found, forgetting the offsets of containers
+  /// that are gone
+  pub(crate) fn finish(&mut self, pass: ScrollPass) {
+    self.offsets = pass
+      .containers
+      .iter()
+      .map(|container| {
+        let state = container.state;
+        (container.node, (state.offset_x, state.offset_y))
+      })
+      .collect();
+    self.last = pass;
+  }
+
+  /// Scroll state of the container with the element id `id`
+  pub(crate) fn state(&self, id: &str) -> Option<ScrollState> {
+    self.find(id).map(|index| self.last.containers[index].state)
+  }
+
+  /// Scroll the container `id` by `dx` columns and `dy` rows
+  pub(crate) fn scroll_by(&mut self, id: &str, dx: i32, dy: i32) -> bool {
+    self
+      .find(id)
+      .is_some_and(|index| self.scroll(index, dx, dy))
+  }
+
+  /// Scroll the container `id` to `x`, `y`
+  pub(crate) fn scroll_to(&mut self, id: &str, x: u16, y: u16) -> bool {
+    self
+      .find(id)
+      .is_some_and(|index| self.move_to(index, i32::from(x), i32::from(y)))
+  }
+
+  /// Scroll the innermost container showing at `column`, `row` that can
+  /// move by `dx`, `dy`, or else the ones around it
+  pub(crate) fn scroll_at(&mut self, column: u16, row: u16, dx: i32, dy: i32) -> bool {
+    let under: Vec<usize> = (0..self.last.containers.len())
+      .rev()
+      .filter(|&index| {
+        self.last.containers[index]
+          .visible
+          .is_some_and(|visible| contains(visible, column, row))
+      })
+      .collect();
+    under.into_iter().any(|index| self.scroll(index, dx, dy))
+  }
+
+  /// Scroll the container `id`, or the one it is in, by `pages` of its
+  /// scrollport's height, keeping a row of the last page in view
+  pub(crate) fn scroll_page(&mut self, id: &str, pages: i32) -> bool {
+    let index = self
+      .find(id)
+      .or_else(|| self.last.targets.get(id)?.container);
+    index.is_some_and(|index| {
+      let page = self.last.containers[index]
+        .state
+        .viewport_height
+        .saturating_sub(1)
+        .max(1);
+      self.scroll(index, 0, pages * i32::from(page))
+    })
+  }
+
+  /// Scroll every container around the element `id` as little as it takes
+  /// to bring the element into view
+  pub(crate) fn scroll_into_view(&mut self, id: &str) -> bool {
+    let Some(&Target {
+      mut rect,
+      mut container,
+    }) = self.last.targets.get(id)
+    else {
+      return false;
+    };
+    let mut moved = false;
+    while let Some(index) = container {
+      let Container { port, state, .. } = self.last.containers[index];
+      let x = reveal(rect.x, rect.width, port.x, port.width, state.offset_x);
+      let y = reveal(rect.y, rect.height, port.y, port.height, state.offset_y);
+      moved |= self.move_to(index, x, y);
+
+      // Where the element shows in the container, which is what has to be
+      // in view of the containers around it
+      let Container { parent, state, .. } = self.last.containers[index];
+      rect = LayoutRect {
+        x: rect.x.saturating_sub(state.offset_x).max(port.x),
+        y: rect.y.saturating_sub(state.offset_y).max(port.y),
+        width: rect.width.min(port.width),
+        height: rect.height.min(port.height),
+      };
+      container = parent;
+    }
+    moved
+  }
+
+  fn find(&self, id: &str) -> Option<usize> {
+    self
+      .last
+      .containers
+      .iter()
+      .position(|container| container.element_id.as_deref() == Some(id))
+  }
+
+  fn scroll(&mut self, index: usize, dx: i32, dy: i32) -> bool {
+    let state = self.last.containers[index].state;
+    self.move_to(
+      index,
+      i32::from(state.offset_x) + dx,
+      i32::from(state.offset_y) + dy,
+    )
+  }
+
+  /// Move container `index` to `x`, `y` as far as its content allows,
+  /// returning whether it moved
+  fn move_to(&mut self, index: usize, x: i32, y: i32) -> bool {
+    let container = &mut self.last.containers[index];
+    let state = &mut container.state;
+    let (scrolls_x, scrolls_y) = container.axes;
+    let clamp = |to: i32, scrolls: bool, max: u16| {
+      if scrolls {
+        to.clamp(0, i32::from(max)) as u16
+      } else {
+        0
+      }
+    };
+    let x = clamp(x, scrolls_x, state.max_offset_x());
+    let y = clamp(y, scrolls_y, state.max_offset_y());
+    if (x, y) == (state.offset_x, state.offset_y) {
+      return false;
+    }
+    state.offset_x = x;
+    state.offset_y = y;
+    self.offsets.insert(container.node, (x, y));
+    true
+  }
+}
+
+fn contains(rect: LayoutRect, column: u16, row: u16) -> bool {
+  column >= rect.x
+    && row >= rect.y
+    && u32::from(column) < u32::from(rect.x) + u32::from(rect.width)
+    && u32::from(row) < u32::from(rect.y) + u32::from(rect.height)
+}
+
+/// Offset along one axis that brings `start..start + length` into a port
+/// spanning `port_start..port_start + port_length` scrolled to `offset`,
+/// moving as little as possible and showing the start of what does not fit
+fn reveal(start: u16, length: u16, port_start: u16, port_length: u16, offset: u16) -> i32 {
+  let (start, end) = (i32::from(start), i32::from(start) + i32::from(length));
+  let (port_start, port_length) = (i32::from(port_start), i32::from(port_length));
+  let offset = i32::from(offset);
+  if start < port_start + offset || end - start > port_length {
+    start - port_start
+  } else if end > port_start + port_length + offset {
+    end - port_start - port_length
+  } else {
+    offset
+  }
+}
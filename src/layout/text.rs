@@ -0,0 +1,505 @@
+//! Line breaking and alignment for element text content
+//!
+//! The layout engine measures text with [`layout_text`] and the renderer draws
+//! the same lines, so wrapped paragraphs always fit the height layout reserved
+//! for them. Widths are display columns of grapheme clusters; ANSI escape
+//! sequences embedded in content take no space and are never split.
+//!
+//! Newlines in element content always start a new line, since terminal UIs
+//! put them there deliberately. `white-space` therefore only decides whether
+//! spaces collapse and whether long lines wrap.
+
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TextAlign {
+  #[default]
+  Left,
+  Center,
+  Right,
+  Justify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WhiteSpace {
+  /// Collapse spaces and wrap
+  #[default]
+  Normal,
+  /// Collapse spaces, never wrap
+  NoWrap,
+  /// Keep spaces, never wrap
+  Pre,
+  /// Keep spaces and wrap
+  PreWrap,
+  /// Collapse spaces and wrap; the same as `Normal` since newlines always break
+  PreLine,
+}
+
+impl WhiteSpace {
+  fn collapses_spaces(self) -> bool {
+    matches!(self, Self::Normal | Self::NoWrap | Self::PreLine)
+  }
+
+  fn wraps(self) -> bool {
+    matches!(self, Self::Normal | Self::PreWrap | Self::PreLine)
+  }
+}
+
+/// Whether a word too long for its line may be broken between graphemes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverflowWrap {
+  #[default]
+  Normal,
+  Anywhere,
+  BreakWord,
+}
+
+/// How a line that still does not fit is cut off
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TextOverflow {
+  #[default]
+  Clip,
+  /// Cut and end the line with `…`
+  Ellipsis,
+  /// Cut and end the line with a custom marker
+  String(String),
+}
+
+/// Text properties that control line breaking and alignment
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TextStyle {
+  pub white_space: WhiteSpace,
+  pub overflow_wrap: OverflowWrap,
+  pub text_overflow: TextOverflow,
+  pub text_align: TextAlign,
+}
+
+/// A line of laid out text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextLine {
+  /// Columns to skip before the text, from `text-align`
+  pub indent: u16,
+  pub text: String,
+}
+
+/// Break `text` into lines for a box `width` columns wide, then cut off and
+/// align each line according to `style`
+pub fn layout_text(text: &str, width: u16, style: &TextStyle) -> Vec<TextLine> {
+  let width = usize::from(width);
+  let mut lines = Vec::new();
+
+  for paragraph in text.split('\n') {
+    let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+    let broken = if style.white_space.wraps() && width > 0 {
+      wrap_paragraph(paragraph, width, style)
+    } else {
+      vec![join_words(paragraph, style.white_space)]
+    };
+
+    let last = broken.len() - 1;
+    for (index, line) in broken.into_iter().enumerate() {
+      let line = if display_width(&line) > width {
+        cut_off(&line, width, &style.text_overflow)
+      } else {
+        line
+      };
+      lines.push(align(line, width, style.text_align, index == last));
+    }
+  }
+
+  // Links broken across lines are closed and reopened on each of them
+  crate::rendering::hyperlink::balance_lines(lines.iter_mut().map(|line| &mut line.text));
+  lines
+}
+
+/// Number of lines `text` takes in a box `width` columns wide
+pub fn measure_text_height(text: &str, width: u16, style: &TextStyle) -> u16 {
+  layout_text(text, width, style)
+    .len()
+    .min(usize::from(u16::MAX)) as u16
+}
+
+/// Columns the widest line of `text` takes when nothing wraps
+pub fn measure_text_width(text: &str, style: &TextStyle) -> u16 {
+  let left = TextStyle {
+    text_align: TextAlign::Left,
+    ..style.clone()
+  };
+  layout_text(text, u16::MAX, &left)
+    .iter()
+    .map(|line| display_width(&line.text))
+    .max()
+    .unwrap_or(0)
+    .min(usize::from(u16::MAX)) as u16
+}
+
+/// Columns the widest piece of `text` that never wraps takes: a word, or a
+/// grapheme when `overflow-wrap: anywhere` may break words, or the widest
+/// line when the text does not wrap at all
+pub fn measure_min_content_width(text: &str, style: &TextStyle) -> u16 {
+  if !style.white_space.wraps() {
+    return measure_text_width(text, style);
+  }
+  let words = text
+    .split('\n')
+    .flat_map(|paragraph| words(paragraph, style.white_space))
+    .map(|(_, word)| word);
+  let widest = match style.overflow_wrap {
+    OverflowWrap::Anywhere => words
+      .flat_map(|word| word.graphemes(true))
+      .map(display_width)
+      .max(),
+    OverflowWrap::Normal | OverflowWrap::BreakWord => words.map(display_width).max(),
+  };
+  widest.unwrap_or(0).min(usize::from(u16::MAX)) as u16
+}
+
+/// Split a paragraph into `(whitespace, word)` pairs, collapsing whitespace
+/// runs to a single space unless `white_space` preserves them
+fn words(paragraph: &str, white_space: WhiteSpace) -> Vec<(&str, &str)> {
+  let mut words = Vec::new();
+  let mut rest = paragraph;
+  while !rest.is_empty() {
+    let word_start = rest
+      .find(|c: char| !c.is_whitespace())
+      .unwrap_or(rest.len());
+    let (space, tail) = rest.split_at(word_start);
+    let word_end = tail.find(char::is_whitespace).unwrap_or(tail.len());
+    let (word, tail) = tail.split_at(word_end);
+    let space = match white_space.collapses_spaces() {
+      // Leading and trailing whitespace disappears when collapsing
+      true if words.is_empty() || word.is_empty() => "",
+      true if !space.is_empty() => " ",
+      _ => space,
+    };
+    if !space.is_empty() || !word.is_empty() {
+      words.push((space, word));
+    }
+    rest = tail;
+  }
+  words
+}
+
+fn join_words(paragraph: &str, white_space: WhiteSpace) -> String {
+  words(paragraph, white_space)
+    .into_iter()
+    .flat_map(|(space, word)| [space, word])
+    .collect()
+}
+
+/// Greedily fill lines of `width` columns, breaking at whitespace
+fn wrap_paragraph(paragraph: &str, width: usize, style: &TextStyle) -> Vec<String> {
+  let may_break_words = style.overflow_wrap != OverflowWrap::Normal;
+  let mut lines = Vec::new();
+  let mut line = String::new();
+  let mut line_width = 0;
+
+  for (index, (space, word)) in words(paragraph, style.white_space).into_iter().enumerate() {
+    let space_width = display_width(space);
+    let word_width = display_width(word);
+
+    if line_width > 0 && line_width + space_width + word_width > width && !word.is_empty() {
+      // Whitespace at a soft break is dropped
+      lines.push(std::mem::take(&mut line));
+      line_width = 0;
+    } else if index == 0 || line_width > 0 || space.is_empty() {
+      line.push_str(space);
+      line_width += space_width;
+    }
+
+    if line_width + word_width > width && may_break_words {
+      // A word too long for a line of its own is split between graphemes
+      let mut rest = word;
+      while line_width + display_width(rest) > width {
+        let available = width.saturating_sub(line_width).max(1);
+        let mut head = truncate_to_display_width(rest, available);
+        if head.is_empty() {
+          head = rest.graphemes(true).next().unwrap_or(rest);
+        }
+        line.push_str(head);
+        lines.push(std::mem::take(&mut line));
+        line_width = 0;
+        rest = &rest[head.len()..];
+      }
+      line.push_str(rest);
+      line_width = display_width(rest);
+    } else {
+      line.push_str(word);
+      line_width += word_width;
+    }
+  }
+
+  lines.push(line);
+  lines
+}
+
+/// Shorten a line to `width` columns, ending it with the overflow marker
+fn cut_off(line: &str, width: usize, overflow: &TextOverflow) -> String {
+  let marker = match overflow {
+    TextOverflow::Clip => "",
+    TextOverflow::Ellipsis => "…",
+    TextOverflow::String(marker) => marker.as_str(),
+  };
+  let marker = truncate_to_display_width(marker, width);
+  let kept = truncate_to_display_width(line, width - display_width(marker));
+  format!("{}{marker}", kept.trim_end())
+}
+
+fn align(text: String, width: usize, text_align: TextAlign, last_line: bool) -> TextLine {
+  let free = width.saturating_sub(display_width(&text));
+  let indent = match text_align {
+    TextAlign::Left | TextAlign::Justify => 0,
+    TextAlign::Center => free / 2,
+    TextAlign::Right => free,
+  };
+  // The last line of a paragraph stays left-aligned when justifying
+  let text = if text_align == TextAlign::Justify && !last_line && free > 0 {
+    justify(&text, free)
+  } else {
+    text
+  };
+  TextLine {
+    indent: indent.min(usize::from(u16::MAX)) as u16,
+    text,
+  }
+}
+
+/// Widen the gaps between words by `free` columns in total
+fn justify(text: &str, free: usize) -> String {
+  let leading = text.len() - text.trim_start().len();
+  let words: Vec<&str> = text[leading..]
+    .split(' ')
+    .filter(|w| !w.is_empty())
+    .collect();
+  let gaps = words.len().saturating_sub(1);
+  if gaps == 0 {
+    return text.to_string();
+  }
+  // Count the existing spaces so preserved runs keep their extra width
+  let existing =
+    display_width(&text[leading..]) - words.iter().map(|w| display_width(w)).sum::<usize>();
+  let total = existing + free;
+  let mut out = String::from(&text[..leading]);
+  for (index, word) in words.iter().enumerate() {
+    if index > 0 {
+      // Earlier gaps take the remainder
+      let gap = total / gaps + usize::from(index <= total % gaps);
+      out.extend(std::iter::repeat_n(' ', gap));
+    }
+    out.push_str(word);
+  }
+  out
+}
+
+/// End of the ANSI escape sequence starting at byte `start`, if there is one
+pub(crate) fn ansi_token_end(s: &str, start: usize) -> Option<usize> {
+  let bytes = s.as_bytes();
+  if start >= bytes.len() || bytes[start] != 0x1b {
+    // ESC
+    return None;
+  }
+  let len = bytes.len();
+  if start + 1 >= len {
+    return Some(len);
+  }
+  match bytes[start + 1] {
+    b'[' => {
+      // CSI: ESC [ ... final byte 0x40..=0x7E
+      let mut j = start + 2;
+      while j < len {
+        let b = bytes[j];
+        if (0x40..=0x7e).contains(&b) {
+          return Some(j + 1);
+        }
+        j += 1;
+      }
+      Some(len)
+    }
+    b']' => {
+      // OSC: ESC ] ... BEL (0x07) or ST (ESC \)
+      let mut j = start + 2;
+      while j < len {
+        if bytes[j] == 0x07 {
+          return Some(j + 1);
+        }
+        if bytes[j] == 0x1b && j + 1 < len && bytes[j + 1] == b'\\' {
+          return Some(j + 2);
+        }
+        j += 1;
+      }
+      Some(len)
+    }
+    _ => {
+      // Fallback: skip ESC and next byte
+      Some((start + 2).min(len))
+    }
+  }
+}
+
+/// Display columns of `s`, skipping ANSI escape sequences
+pub fn display_width(s: &str) -> usize {
+  let mut i = 0usize;
+  let mut w = 0usize;
+  while i < s.len() {
+    if let Some(end) = ansi_token_end(s, i) {
+      i = end;
+      continue;
+    }
+    if let Some((_, g)) = s[i..].grapheme_indices(true).next() {
+      w += UnicodeWidthStr::width(g);
+      i += g.len();
+    } else {
+      break;
+    }
+  }
+  w
+}
+
+/// The longest prefix of `s` at most `max_cols` columns wide, without
+/// splitting grapheme clusters or ANSI escape sequences
+pub fn truncate_to_display_width(s: &str, max_cols: usize) -> &str {
+  if max_cols == 0 {
+    return "";
+  }
+  let mut i = 0usize;
+  let mut cols = 0usize;
+  let mut last_end = 0usize;
+  while i < s.len() {
+    if let Some(end) = ansi_token_end(s, i) {
+      // Include ANSI sequences, zero-width
+      i = end;
+      last_end = i;
+      continue;
+    }
+    if let Some((rel_idx, g)) = s[i..].grapheme_indices(true).next() {
+      let gi = i + rel_idx; // should be i
+      let gw = UnicodeWidthStr::width(g);
+      if cols + gw > max_cols {
+        break;
+      }
+      cols += gw;
+      i = gi + g.len();
+      last_end = i;
+    } else {
+      break;
+    }
+  }
+  &s[..last_end]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn lines(text: &str, width: u16, style: &TextStyle) -> Vec<String> {
+    layout_text(text, width, style)
+      .into_iter()
+      .map(|line| format!("{}{}", " ".repeat(line.indent as usize), line.text))
+      .collect()
+  }
+
+  fn style(white_space: WhiteSpace) -> TextStyle {
+    TextStyle {
+      white_space,
+      ..TextStyle::default()
+    }
+  }
+
+  #[test]
+  fn test_normal_collapses_and_wraps() {
+    let normal = style(WhiteSpace::Normal);
+    assert_eq!(
+      lines("  the quick   brown fox\njumps", 10, &normal),
+      vec!["the quick", "brown fox", "jumps"]
+    );
+    assert_eq!(measure_text_height("the quick brown fox", 10, &normal), 2);
+    // Words never split by default; the overlong line is clipped
+    assert_eq!(
+      lines("a extraordinary b", 6, &normal),
+      vec!["a", "extrao", "b"]
+    );
+  }
+
+  #[test]
+  fn test_nowrap_pre_and_pre_wrap() {
+    assert_eq!(lines("a   b c", 3, &style(WhiteSpace::NoWrap)), vec!["a b"]);
+    assert_eq!(lines("a   b", 10, &style(WhiteSpace::Pre)), vec!["a   b"]);
+    assert_eq!(
+      lines("  ab  cd  ef", 6, &style(WhiteSpace::PreWrap)),
+      vec!["  ab", "cd  ef"]
+    );
+  }
+
+  #[test]
+  fn test_overflow_wrap_breaks_long_words_by_grapheme() {
+    let anywhere = TextStyle {
+      overflow_wrap: OverflowWrap::Anywhere,
+      ..TextStyle::default()
+    };
+    assert_eq!(
+      lines("ab abcdefgh", 4, &anywhere),
+      vec!["ab", "abcd", "efgh"]
+    );
+    // Wide and combining characters are never split
+    assert_eq!(
+      lines("日本語テキスト", 5, &anywhere),
+      vec!["日本", "語テ", "キス", "ト"]
+    );
+    assert_eq!(
+      lines("e\u{301}e\u{301}e\u{301}", 2, &anywhere),
+      vec!["e\u{301}e\u{301}", "e\u{301}"]
+    );
+  }
+
+  #[test]
+  fn test_min_content_width_is_the_widest_unbreakable_piece() {
+    let normal = style(WhiteSpace::Normal);
+    assert_eq!(
+      measure_min_content_width("a wide\nextraordinary b", &normal),
+      13
+    );
+    assert_eq!(measure_min_content_width("日本 語", &normal), 4);
+    assert_eq!(
+      measure_min_content_width("a wide b", &style(WhiteSpace::NoWrap)),
+      8
+    );
+    let anywhere = TextStyle {
+      overflow_wrap: OverflowWrap::Anywhere,
+      ..TextStyle::default()
+    };
+    assert_eq!(measure_min_content_width("ab 日本", &anywhere), 2);
+  }
+
+  #[test]
+  fn test_text_overflow_markers() {
+    let ellipsis = TextStyle {
+      white_space: WhiteSpace::NoWrap,
+      text_overflow: TextOverflow::Ellipsis,
+      ..TextStyle::default()
+    };
+    assert_eq!(lines("Quarterly report", 10, &ellipsis), vec!["Quarterly…"]);
+    assert_eq!(lines("short", 10, &ellipsis), vec!["short"]);
+    let custom = TextStyle {
+      text_overflow: TextOverflow::String(" >>".to_string()),
+      ..ellipsis
+    };
+    assert_eq!(lines("Quarterly report", 10, &custom), vec!["Quarter >>"]);
+    assert_eq!(lines("日本語テキスト", 6, &ellipsis), vec!["日本…"]);
+  }
+
+  #[test]
+  fn test_alignment_and_justify() {
+    let aligned = |text_align| TextStyle {
+      text_align,
+      ..TextStyle::default()
+    };
+    assert_eq!(lines("abc", 7, &aligned(TextAlign::Center)), vec!["  abc"]);
+    assert_eq!(lines("abc", 7, &aligned(TextAlign::Right)), vec!["    abc"]);
+    assert_eq!(
+      lines("a bb c dd eee f", 10, &aligned(TextAlign::Justify)),
+      vec!["a  bb c dd", "eee f"]
+    );
+  }
+}
@@ -202,7 +202,9 @@
 //! ### Feature Flags
 //!
 //! - `default = ["css"]` - Basic CSS support (always enabled)
-//! - `hot-reload` - CSS hot reload during development
+//! - `hot-reload` - Live reload of stylesheets, theme JSON and plugin configs during development.
+//!   Stylesheets are watched automatically; theme directories must be registered with
+//!   `TuiApp::watch_theme_directory`
 //! - `ffi` - NAPI bindings for JavaScript integration
 //! - `typescript` - TypeScript type generation
 //!
@@ -222,6 +224,8 @@ pub mod display;
 pub mod driver;
 pub mod error;
 pub mod events;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
 pub mod integration;
 pub mod layout;
 pub mod performance;
@@ -233,7 +237,7 @@ pub mod screens;
 pub mod themes;
 pub mod widgets;
 
-// Removed hot_reload and testing modules - they had API compatibility issues
+// Removed testing module - it had API compatibility issues
 
 pub mod prelude {
   //! Common imports for TUI applications
@@ -273,7 +277,7 @@ pub mod prelude {
     RecoveryAttempt, RecoveryConfig, RecoveryManager, RecoveryManagerBuilder, RecoveryState,
     RecoveryStats, RecoveryStrategy,
   };
-  pub use crate::rendering::{RenderStyle, Renderer};
+  pub use crate::rendering::{render_to_ansi, render_to_plain, RenderStyle, Renderer};
   pub use crate::rendering::batch::{
     BatchConfig, BatchStats, RenderBatcher, RenderBatcherBuilder, RenderOperation,
     RenderPriority, RenderRequest,
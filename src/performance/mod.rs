@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -15,6 +15,7 @@ pub struct PerformanceMonitor {
   component_update_times: HashMap<String, VecDeque<Duration>>,
   css_processing_times: VecDeque<Duration>,
   layout_computation_times: VecDeque<Duration>,
+  layout_subtree_times: HashMap<String, VecDeque<Duration>>,
   start_time: Instant,
   last_report: Instant,
   report_interval: Duration,
@@ -50,6 +51,8 @@ pub struct PerformanceMetrics {
   pub component_update_frequency: HashMap<String, u64>,
   pub css_processing_avg: Duration,
   pub layout_computation_avg: Duration,
+  /// Average layout time of each child of the root element, by its layout key
+  pub layout_subtree_avg: HashMap<String, Duration>,
 
   // Overall health
   pub performance_score: f64, // 0.0-100.0, higher is better
@@ -156,6 +159,7 @@ impl Default for PerformanceMetrics {
       component_update_frequency: HashMap::new(),
       css_processing_avg: Duration::ZERO,
       layout_computation_avg: Duration::ZERO,
+      layout_subtree_avg: HashMap::new(),
       performance_score: 100.0,
       bottlenecks: Vec::new(),
       recommendations: Vec::new(),
@@ -177,6 +181,7 @@ impl PerformanceMonitor {
       component_update_times: HashMap::new(),
       css_processing_times: VecDeque::with_capacity(120),
       layout_computation_times: VecDeque::with_capacity(120),
+      layout_subtree_times: HashMap::new(),
       start_time: now,
       last_report: now,
       report_interval: Duration::from_secs(1),
@@ -370,6 +375,47 @@ impl PerformanceMonitor {
     }
   }
 
+  /// Record the timings of a layout pass, in total and for each subtree
+  /// under the root
+  pub fn record_layout_timings(&mut self, timings: &crate::layout::LayoutTimings) {
+    self.record_layout_computation(timings.total);
+
+    // Only subtrees still on screen are tracked
+    let live: HashSet<&str> = timings
+      .subtrees
+      .iter()
+      .map(|subtree| subtree.key.as_str())
+      .collect();
+    self
+      .layout_subtree_times
+      .retain(|key, _| live.contains(key.as_str()));
+    for subtree in &timings.subtrees {
+      let times = self
+        .layout_subtree_times
+        .entry(subtree.key.clone())
+        .or_insert_with(|| VecDeque::with_capacity(30));
+      if times.len() >= 30 {
+        times.pop_front();
+      }
+      times.push_back(subtree.duration);
+    }
+
+    let mut metrics = self
+      .metrics
+      .lock()
+      .expect("performance metrics lock poisoned");
+    metrics.layout_subtree_avg = self
+      .layout_subtree_times
+      .iter()
+      .map(|(key, times)| {
+        (
+          key.clone(),
+          times.iter().sum::<Duration>() / times.len() as u32,
+        )
+      })
+      .collect();
+  }
+
   /// Get current performance metrics
   pub fn get_metrics(&self) -> PerformanceMetrics {
     let mut metrics = self
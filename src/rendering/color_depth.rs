@@ -0,0 +1,386 @@
+//! Output-time color downsampling
+//!
+//! Widgets and themes emit truecolor freely. Before a frame reaches the
+//! terminal every color is mapped to what the terminal can show: the nearest
+//! entry of the 256 or 16 color palette in CIELAB space, a gray level, or no
+//! color at all. An ordered dither can be enabled so gradients keep their
+//! shape on small palettes. Mapped colors are cached.
+
+use super::target::CellGrid;
+use super::{RenderStyle, SerializableColor};
+use crate::themes::ColorSupport;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+type Lab = [f32; 3];
+
+/// 4x4 Bayer matrix used for ordered dithering
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Cache key for undithered lookups
+const NO_DITHER: u8 = u8::MAX;
+
+/// Gray levels available in grayscale mode, darkest first
+const GRAYS: [SerializableColor; 4] = [
+  SerializableColor::Black,
+  SerializableColor::DarkGrey,
+  SerializableColor::Grey,
+  SerializableColor::White,
+];
+
+/// Maps colors to the terminal's [`ColorSupport`] level
+#[derive(Debug, Clone)]
+pub struct ColorDownsampler {
+  support: ColorSupport,
+  dithering: bool,
+  cache: HashMap<(u8, u8, u8, u8), Option<SerializableColor>>,
+}
+
+impl Default for ColorDownsampler {
+  fn default() -> Self {
+    Self::new(ColorSupport::Truecolor)
+  }
+}
+
+impl ColorDownsampler {
+  pub fn new(support: ColorSupport) -> Self {
+    Self {
+      support,
+      dithering: false,
+      cache: HashMap::new(),
+    }
+  }
+
+  /// Dither truecolor values by cell position instead of picking the
+  /// nearest palette entry, which suits gradients
+  pub fn with_dithering(mut self, dithering: bool) -> Self {
+    self.set_dithering(dithering);
+    self
+  }
+
+  pub fn support(&self) -> ColorSupport {
+    self.support
+  }
+
+  pub fn set_support(&mut self, support: ColorSupport) {
+    if self.support != support {
+      self.support = support;
+      self.cache.clear();
+    }
+  }
+
+  pub fn dithering(&self) -> bool {
+    self.dithering
+  }
+
+  pub fn set_dithering(&mut self, dithering: bool) {
+    self.dithering = dithering;
+  }
+
+  /// Whether colors are written unchanged
+  pub fn is_passthrough(&self) -> bool {
+    self.support == ColorSupport::Truecolor
+  }
+
+  /// The color to write for `color` in the cell at `(x, y)`; `None` when
+  /// the terminal should use its default color
+  pub fn map_color(
+    &mut self,
+    color: &SerializableColor,
+    x: u16,
+    y: u16,
+  ) -> Option<SerializableColor> {
+    match (self.support, color) {
+      (ColorSupport::Truecolor, _) | (_, SerializableColor::Reset) => Some(color.clone()),
+      (ColorSupport::NoColor, _) => None,
+      (ColorSupport::Ansi256, SerializableColor::Rgb { r, g, b }) => {
+        self.lookup((*r, *g, *b), x, y)
+      }
+      (ColorSupport::Ansi256, _) => Some(color.clone()),
+      (ColorSupport::Ansi16, SerializableColor::Rgb { r, g, b }) => self.lookup((*r, *g, *b), x, y),
+      (ColorSupport::Ansi16, SerializableColor::AnsiValue(n)) if *n < 16 => {
+        Some(named_color(*n as usize))
+      }
+      (ColorSupport::Ansi16, SerializableColor::AnsiValue(_)) => {
+        self.lookup(color.to_rgb()?, u16::MAX, u16::MAX)
+      }
+      (ColorSupport::Ansi16, _) => Some(color.clone()),
+      (ColorSupport::Grayscale, SerializableColor::Rgb { .. }) => {
+        self.lookup(color.to_rgb()?, x, y)
+      }
+      (ColorSupport::Grayscale, _) => self.lookup(color.to_rgb()?, u16::MAX, u16::MAX),
+    }
+  }
+
+  /// `style` with both colors mapped for the cell at `(x, y)`
+  pub fn map_style(&mut self, style: &RenderStyle, x: u16, y: u16) -> RenderStyle {
+    if self.is_passthrough() {
+      return style.clone();
+    }
+    RenderStyle {
+      color: style
+        .color
+        .as_ref()
+        .and_then(|color| self.map_color(color, x, y)),
+      background: style
+        .background
+        .as_ref()
+        .and_then(|color| self.map_color(color, x, y)),
+      underline_color: style
+        .underline_color
+        .as_ref()
+        .and_then(|color| self.map_color(color, x, y)),
+      ..style.clone()
+    }
+  }
+
+  /// Map every cell of `grid` in place
+  pub fn map_grid(&mut self, grid: &mut CellGrid) {
+    if self.is_passthrough() {
+      return;
+    }
+    for y in 0..grid.height {
+      for x in 0..grid.width {
+        if let Some(cell) = grid.get_mut(x, y) {
+          let style = &cell.style;
+          if style.color.is_some() || style.background.is_some() || style.underline_color.is_some()
+          {
+            cell.style = self.map_style(&cell.style, x, y);
+          }
+        }
+      }
+    }
+  }
+
+  /// Nearest palette color for `rgb`, dithered by position unless `x` is
+  /// `u16::MAX`
+  fn lookup(&mut self, rgb: (u8, u8, u8), x: u16, y: u16) -> Option<SerializableColor> {
+    let threshold = if self.dithering && x != u16::MAX {
+      BAYER[(y % 4) as usize][(x % 4) as usize]
+    } else {
+      NO_DITHER
+    };
+    let key = (rgb.0, rgb.1, rgb.2, threshold);
+    if let Some(mapped) = self.cache.get(&key) {
+      return mapped.clone();
+    }
+
+    let rgb = if threshold == NO_DITHER {
+      rgb
+    } else {
+      // Shift the color by up to half a palette step either way
+      let offset = (threshold as f32 + 0.5) / 16.0 - 0.5;
+      let shift = |v: u8| (v as f32 + offset * self.palette_step()).clamp(0.0, 255.0) as u8;
+      (shift(rgb.0), shift(rgb.1), shift(rgb.2))
+    };
+    let mapped = self.nearest(rgb);
+    self.cache.insert(key, mapped.clone());
+    mapped
+  }
+
+  /// Typical distance between neighbouring palette entries on one channel
+  fn palette_step(&self) -> f32 {
+    match self.support {
+      ColorSupport::Ansi256 => 40.0,
+      ColorSupport::Ansi16 => 128.0,
+      ColorSupport::Grayscale => 85.0,
+      ColorSupport::Truecolor | ColorSupport::NoColor => 0.0,
+    }
+  }
+
+  fn nearest(&self, rgb: (u8, u8, u8)) -> Option<SerializableColor> {
+    let lab = to_lab(rgb);
+    match self.support {
+      ColorSupport::Truecolor => Some(SerializableColor::Rgb {
+        r: rgb.0,
+        g: rgb.1,
+        b: rgb.2,
+      }),
+      // Entries 0-15 vary between terminals, so only the cube and the
+      // gray ramp are candidates
+      ColorSupport::Ansi256 => {
+        let index = closest(&palette_256()[16..], lab) + 16;
+        Some(SerializableColor::AnsiValue(index as u8))
+      }
+      ColorSupport::Ansi16 => Some(named_color(closest(&palette_256()[..16], lab))),
+      ColorSupport::Grayscale => {
+        let grays: Vec<Lab> = GRAYS
+          .iter()
+          .filter_map(SerializableColor::to_rgb)
+          .map(to_lab)
+          .collect();
+        // Compare lightness only
+        let index = grays
+          .iter()
+          .enumerate()
+          .min_by(|(_, a), (_, b)| (a[0] - lab[0]).abs().total_cmp(&(b[0] - lab[0]).abs()))
+          .map_or(0, |(i, _)| i);
+        Some(GRAYS[index].clone())
+      }
+      ColorSupport::NoColor => None,
+    }
+  }
+}
+
+/// The xterm 256 color palette in CIELAB
+fn palette_256() -> &'static [Lab] {
+  static PALETTE: OnceLock<Vec<Lab>> = OnceLock::new();
+  PALETTE.get_or_init(|| {
+    (0..=255u8)
+      .filter_map(|n| SerializableColor::AnsiValue(n).to_rgb())
+      .map(to_lab)
+      .collect()
+  })
+}
+
+fn closest(palette: &[Lab], lab: Lab) -> usize {
+  let distance = |p: &Lab| (0..3).map(|i| (p[i] - lab[i]).powi(2)).sum::<f32>();
+  palette
+    .iter()
+    .enumerate()
+    .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+    .map_or(0, |(i, _)| i)
+}
+
+fn named_color(index: usize) -> SerializableColor {
+  match index {
+    0 => SerializableColor::Black,
+    1 => SerializableColor::DarkRed,
+    2 => SerializableColor::DarkGreen,
+    3 => SerializableColor::DarkYellow,
+    4 => SerializableColor::DarkBlue,
+    5 => SerializableColor::DarkMagenta,
+    6 => SerializableColor::DarkCyan,
+    7 => SerializableColor::Grey,
+    8 => SerializableColor::DarkGrey,
+    9 => SerializableColor::Red,
+    10 => SerializableColor::Green,
+    11 => SerializableColor::Yellow,
+    12 => SerializableColor::Blue,
+    13 => SerializableColor::Magenta,
+    14 => SerializableColor::Cyan,
+    _ => SerializableColor::White,
+  }
+}
+
+/// sRGB to CIELAB under the D65 white point
+fn to_lab((r, g, b): (u8, u8, u8)) -> Lab {
+  let linear = |v: u8| {
+    let v = v as f32 / 255.0;
+    if v <= 0.04045 {
+      v / 12.92
+    } else {
+      ((v + 0.055) / 1.055).powf(2.4)
+    }
+  };
+  let (r, g, b) = (linear(r), linear(g), linear(b));
+  let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+  let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+  let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+  let f = |t: f32| {
+    if t > 216.0 / 24389.0 {
+      t.cbrt()
+    } else {
+      (24389.0 / 27.0 * t + 16.0) / 116.0
+    }
+  };
+  let (fx, fy, fz) = (f(x), f(y), f(z));
+  [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rgb(r: u8, g: u8, b: u8) -> SerializableColor {
+    SerializableColor::Rgb { r, g, b }
+  }
+
+  #[test]
+  fn test_truecolor_passes_through() {
+    let mut downsampler = ColorDownsampler::new(ColorSupport::Truecolor);
+    assert_eq!(
+      downsampler.map_color(&rgb(1, 2, 3), 0, 0),
+      Some(rgb(1, 2, 3))
+    );
+  }
+
+  #[test]
+  fn test_ansi256_picks_nearest_cube_or_gray() {
+    let mut downsampler = ColorDownsampler::new(ColorSupport::Ansi256);
+    assert_eq!(
+      downsampler.map_color(&rgb(255, 0, 0), 0, 0),
+      Some(SerializableColor::AnsiValue(196))
+    );
+    // Near-grays land on the gray ramp rather than a tinted cube entry
+    assert_eq!(
+      downsampler.map_color(&rgb(120, 121, 119), 0, 0),
+      Some(SerializableColor::AnsiValue(243))
+    );
+    // Indexed colors are already representable
+    assert_eq!(
+      downsampler.map_color(&SerializableColor::AnsiValue(42), 0, 0),
+      Some(SerializableColor::AnsiValue(42))
+    );
+  }
+
+  #[test]
+  fn test_ansi16_uses_named_colors() {
+    let mut downsampler = ColorDownsampler::new(ColorSupport::Ansi16);
+    assert_eq!(
+      downsampler.map_color(&rgb(10, 10, 220), 0, 0),
+      Some(SerializableColor::DarkBlue)
+    );
+    assert_eq!(
+      downsampler.map_color(&SerializableColor::AnsiValue(196), 0, 0),
+      Some(SerializableColor::Red)
+    );
+    assert_eq!(
+      downsampler.map_color(&SerializableColor::Cyan, 0, 0),
+      Some(SerializableColor::Cyan)
+    );
+  }
+
+  #[test]
+  fn test_grayscale_and_no_color() {
+    let mut downsampler = ColorDownsampler::new(ColorSupport::Grayscale);
+    assert_eq!(
+      downsampler.map_color(&rgb(250, 250, 250), 0, 0),
+      Some(SerializableColor::White)
+    );
+    assert_eq!(
+      downsampler.map_color(&SerializableColor::AnsiValue(17), 0, 0),
+      Some(SerializableColor::Black)
+    );
+
+    let mut downsampler = ColorDownsampler::new(ColorSupport::NoColor);
+    let style = RenderStyle {
+      color: Some(rgb(255, 0, 0)),
+      background: Some(SerializableColor::Blue),
+      bold: true,
+      ..RenderStyle::default()
+    };
+    let mapped = downsampler.map_style(&style, 0, 0);
+    assert_eq!(mapped.color, None);
+    assert_eq!(mapped.background, None);
+    assert!(mapped.bold);
+  }
+
+  #[test]
+  fn test_ordered_dither_mixes_neighbouring_entries() {
+    // Halfway between two gray levels of the 16 color palette
+    let mid = rgb(178, 178, 178);
+    let mut plain = ColorDownsampler::new(ColorSupport::Ansi16);
+    let mut dithered = ColorDownsampler::new(ColorSupport::Ansi16).with_dithering(true);
+
+    let plain_colors: Vec<_> = (0..4).map(|x| plain.map_color(&mid, x, 0)).collect();
+    assert!(plain_colors.windows(2).all(|w| w[0] == w[1]));
+
+    let dithered_colors: Vec<_> = (0..4)
+      .flat_map(|y| (0..4).map(move |x| (x, y)))
+      .map(|(x, y)| dithered.map_color(&mid, x, y))
+      .collect();
+    assert!(dithered_colors.contains(&Some(SerializableColor::Grey)));
+    assert!(dithered_colors.contains(&Some(SerializableColor::DarkGrey)));
+  }
+}
@@ -0,0 +1,320 @@
+//! Cell-level compositing of stacked layers
+//!
+//! Overlays such as modals, toasts and drawers are drawn into their own
+//! [`Layer`] and blended over the layers below instead of overwriting them.
+//! Blank cells without a background are transparent, and a layer with an
+//! `opacity` below 1.0 mixes its colors with what is underneath, so a
+//! backdrop can dim the app behind a dialog while keeping it readable.
+
+use super::intersect_rect;
+use crate::layout::LayoutRect;
+use crate::rendering::target::{Cell, CellGrid};
+use crate::rendering::SerializableColor;
+
+type Rgb = (u8, u8, u8);
+
+/// A screen-sized cell layer drawn over the layers below it
+#[derive(Debug, Clone)]
+pub struct Layer {
+  /// Stacking order; layers with equal z-index are drawn in the order added
+  pub z_index: i32,
+  /// Screen area the layer draws into; cells outside it are clipped
+  pub bounds: LayoutRect,
+  /// 1.0 covers what is below, 0.0 leaves it untouched
+  pub opacity: f32,
+  /// The layer's cells in screen coordinates
+  pub grid: CellGrid,
+}
+
+impl Layer {
+  /// A transparent layer covering `bounds` on a `width` x `height` screen
+  pub fn new(width: u16, height: u16, bounds: LayoutRect) -> Self {
+    Self {
+      z_index: 0,
+      bounds,
+      opacity: 1.0,
+      grid: CellGrid::new(width, height),
+    }
+  }
+
+  /// A layer holding terminal output, such as a widget's rendered frame
+  pub fn from_ansi(width: u16, height: u16, bounds: LayoutRect, bytes: &[u8]) -> Self {
+    let mut layer = Self::new(width, height, bounds);
+    layer.grid.rasterize(bytes);
+    layer
+  }
+
+  pub fn with_z_index(mut self, z_index: i32) -> Self {
+    self.z_index = z_index;
+    self
+  }
+
+  pub fn with_opacity(mut self, opacity: f32) -> Self {
+    self.opacity = opacity.clamp(0.0, 1.0);
+    self
+  }
+}
+
+/// Blends layers into the grid the terminal should show
+#[derive(Debug, Clone)]
+pub struct Compositor {
+  width: u16,
+  height: u16,
+  layers: Vec<Layer>,
+  /// Colors assumed for cells using the terminal's default colors when
+  /// they are blended
+  default_foreground: Rgb,
+  default_background: Rgb,
+}
+
+impl Compositor {
+  pub fn new(width: u16, height: u16) -> Self {
+    Self {
+      width,
+      height,
+      layers: Vec::new(),
+      default_foreground: (229, 229, 229),
+      default_background: (0, 0, 0),
+    }
+  }
+
+  /// Set the RGB values blending uses for the terminal's default colors
+  pub fn with_default_colors(mut self, foreground: Rgb, background: Rgb) -> Self {
+    self.default_foreground = foreground;
+    self.default_background = background;
+    self
+  }
+
+  pub fn add_layer(&mut self, layer: Layer) {
+    self.layers.push(layer);
+  }
+
+  pub fn layers(&self) -> &[Layer] {
+    &self.layers
+  }
+
+  /// Blend all layers from the lowest z-index up
+  pub fn compose(&self) -> CellGrid {
+    let mut out = CellGrid::new(self.width, self.height);
+    let mut order: Vec<&Layer> = self.layers.iter().collect();
+    order.sort_by_key(|layer| layer.z_index);
+    for layer in order {
+      self.blend(&mut out, layer);
+      out.adopt_links(&layer.grid);
+    }
+    out
+  }
+
+  fn blend(&self, out: &mut CellGrid, layer: &Layer) {
+    let opacity = layer.opacity.clamp(0.0, 1.0);
+    let screen = LayoutRect {
+      x: 0,
+      y: 0,
+      width: out.width.min(layer.grid.width),
+      height: out.height.min(layer.grid.height),
+    };
+    let Some(clip) = intersect_rect(&layer.bounds, &screen) else {
+      return;
+    };
+    if opacity == 0.0 {
+      return;
+    }
+    let x_end = clip.x + clip.width;
+
+    for y in clip.y..clip.y + clip.height {
+      let mut x = clip.x;
+      while x < x_end {
+        let Some(cell) = layer.grid.get(x, y) else {
+          break;
+        };
+        // A wide character is drawn whole or not at all
+        let wide = layer.grid.get(x + 1, y).is_some_and(Cell::is_continuation);
+        let (top, width) = if cell.is_continuation() || (wide && x + 1 >= x_end) {
+          (Cell::new(" ", cell.style.clone()), 1)
+        } else {
+          (cell.clone(), if wide { 2 } else { 1 })
+        };
+        let cell_x = x;
+        x += width;
+
+        // Nothing painted here
+        if top.is_erased() && top.style.background.is_none() {
+          continue;
+        }
+
+        if opacity < 1.0 && top.is_erased() {
+          // A translucent blank tints what is below and keeps its text
+          let tint = self.rgb(top.style.background.as_ref(), self.default_background);
+          self.tint(out, cell_x, y, tint, opacity);
+          continue;
+        }
+
+        let below_bg = out
+          .get(cell_x, y)
+          .and_then(|cell| cell.style.background.clone());
+        let mut style = top.style.clone();
+        if opacity < 1.0 {
+          let backdrop = self.rgb(below_bg.as_ref(), self.default_background);
+          style.background = match &top.style.background {
+            Some(bg) => Some(mix(
+              self.rgb(Some(bg), self.default_background),
+              backdrop,
+              opacity,
+            )),
+            None => below_bg,
+          };
+          if top.symbol != " " {
+            let fg = self.rgb(top.style.color.as_ref(), self.default_foreground);
+            style.color = Some(mix(fg, backdrop, opacity));
+          }
+        } else if style.background.is_none() {
+          style.background = below_bg;
+        }
+
+        out.set(cell_x, y, Cell::new(&top.symbol, style.clone()));
+        if width == 2 {
+          out.set(cell_x + 1, y, Cell::new("", style));
+        }
+      }
+    }
+  }
+
+  /// Mix `tint` into the colors of the cell at `(x, y)`
+  fn tint(&self, out: &mut CellGrid, x: u16, y: u16, tint: Rgb, opacity: f32) {
+    let (default_fg, default_bg) = (self.default_foreground, self.default_background);
+    let Some(cell) = out.get_mut(x, y) else {
+      return;
+    };
+    let bg = self.rgb(cell.style.background.as_ref(), default_bg);
+    cell.style.background = Some(mix(tint, bg, opacity));
+    // Blanks carry no foreground
+    if cell.symbol != " " {
+      let fg = self.rgb(cell.style.color.as_ref(), default_fg);
+      cell.style.color = Some(mix(tint, fg, opacity));
+    }
+  }
+
+  fn rgb(&self, color: Option<&SerializableColor>, default: Rgb) -> Rgb {
+    color.and_then(SerializableColor::to_rgb).unwrap_or(default)
+  }
+}
+
+/// `top` drawn at `opacity` over `below`
+fn mix(top: Rgb, below: Rgb, opacity: f32) -> SerializableColor {
+  let channel = |t: u8, b: u8| (t as f32 * opacity + b as f32 * (1.0 - opacity)).round() as u8;
+  SerializableColor::Rgb {
+    r: channel(top.0, below.0),
+    g: channel(top.1, below.1),
+    b: channel(top.2, below.2),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rendering::RenderStyle;
+
+  fn rect(x: u16, y: u16, width: u16, height: u16) -> LayoutRect {
+    LayoutRect {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  fn base(text: &str) -> Layer {
+    let mut layer = Layer::new(10, 2, rect(0, 0, 10, 2));
+    let style = RenderStyle {
+      color: Some(SerializableColor::White),
+      background: Some(SerializableColor::Blue),
+      ..RenderStyle::default()
+    };
+    layer.grid.fill(rect(0, 0, 10, 2), &style);
+    layer.grid.put_str(0, 0, text, &style);
+    layer
+  }
+
+  #[test]
+  fn test_transparent_cells_pass_through() {
+    let mut overlay = Layer::new(10, 2, rect(0, 0, 10, 2)).with_z_index(1);
+    overlay.grid.put_str(2, 0, "XY", &RenderStyle::default());
+
+    let mut compositor = Compositor::new(10, 2);
+    compositor.add_layer(overlay);
+    compositor.add_layer(base("abcdef"));
+    let grid = compositor.compose();
+
+    assert_eq!(grid.row_text(0), "abXYef");
+    // The overlay text keeps the background below it
+    assert_eq!(
+      grid.get(2, 0).unwrap().style.background,
+      Some(SerializableColor::Blue)
+    );
+  }
+
+  #[test]
+  fn test_layers_are_clipped_to_bounds() {
+    let mut overlay = Layer::new(10, 2, rect(1, 0, 2, 1)).with_z_index(1);
+    overlay.grid.put_str(0, 0, "WXYZ", &RenderStyle::default());
+    overlay.grid.put_str(0, 1, "WXYZ", &RenderStyle::default());
+
+    let mut compositor = Compositor::new(10, 2);
+    compositor.add_layer(base("abcdef"));
+    compositor.add_layer(overlay);
+    let grid = compositor.compose();
+
+    assert_eq!(grid.row_text(0), "aXYdef");
+    assert_eq!(grid.row_text(1), "");
+  }
+
+  #[test]
+  fn test_translucent_backdrop_dims_content_below() {
+    let mut backdrop = Layer::new(10, 2, rect(0, 0, 10, 2))
+      .with_z_index(1)
+      .with_opacity(0.5);
+    let black = RenderStyle {
+      background: Some(SerializableColor::Black),
+      ..RenderStyle::default()
+    };
+    backdrop.grid.fill(rect(0, 0, 10, 2), &black);
+
+    let mut compositor = Compositor::new(10, 2);
+    compositor.add_layer(base("text"));
+    compositor.add_layer(backdrop);
+    let grid = compositor.compose();
+
+    let cell = grid.get(0, 0).unwrap();
+    assert_eq!(cell.symbol, "t");
+    assert_eq!(
+      cell.style.color,
+      Some(SerializableColor::Rgb {
+        r: 128,
+        g: 128,
+        b: 128
+      })
+    );
+    assert_eq!(
+      cell.style.background,
+      Some(SerializableColor::Rgb {
+        r: 46,
+        g: 46,
+        b: 128
+      })
+    );
+  }
+
+  #[test]
+  fn test_wide_characters_are_clipped_whole() {
+    let mut overlay = Layer::new(10, 2, rect(0, 0, 3, 1)).with_z_index(1);
+    overlay.grid.put_str(0, 0, "a中文", &RenderStyle::default());
+
+    let mut compositor = Compositor::new(10, 2);
+    compositor.add_layer(overlay);
+    let grid = compositor.compose();
+
+    assert_eq!(grid.row_text(0), "a中");
+    assert!(grid.get(2, 0).unwrap().is_continuation());
+    assert_eq!(grid.get(3, 0), Some(&Cell::blank()));
+  }
+}
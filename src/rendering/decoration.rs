@@ -0,0 +1,154 @@
+//! Terminal support for text decorations
+//!
+//! Styled underlines (`CSI 4:3 m`) and underline colors (`CSI 58 m`) use
+//! colon subparameters that older terminals misread as separate attributes,
+//! so they are only written where the terminal is known to handle them.
+//! Elsewhere a curly underline becomes a plain one and unsupported lines
+//! are dropped.
+
+use super::{RenderStyle, UnderlineStyle};
+
+/// Which decorations the terminal draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecorationSupport {
+  /// Double, curly, dotted and dashed underlines (SGR 4:x)
+  pub styled_underlines: bool,
+  /// Underline colors (SGR 58)
+  pub underline_color: bool,
+  /// Overline (SGR 53)
+  pub overline: bool,
+  /// Strikethrough (SGR 9)
+  pub strikethrough: bool,
+}
+
+impl Default for DecorationSupport {
+  fn default() -> Self {
+    Self::all()
+  }
+}
+
+impl DecorationSupport {
+  /// Every decoration is written as is
+  pub fn all() -> Self {
+    Self {
+      styled_underlines: true,
+      underline_color: true,
+      overline: true,
+      strikethrough: true,
+    }
+  }
+
+  /// Only plain underlines, as on the Linux console
+  pub fn basic() -> Self {
+    Self {
+      styled_underlines: false,
+      underline_color: false,
+      overline: false,
+      strikethrough: false,
+    }
+  }
+
+  /// Detect support from `TERM`, `TERM_PROGRAM` and `VTE_VERSION`
+  pub fn detect() -> Self {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    let vte_version = std::env::var("VTE_VERSION")
+      .ok()
+      .and_then(|version| version.parse().ok());
+    let kitty = std::env::var_os("KITTY_WINDOW_ID").is_some();
+    Self::for_terminal(&term, &term_program, vte_version, kitty)
+  }
+
+  /// Support for the terminal described by the given environment values
+  pub fn for_terminal(
+    term: &str,
+    term_program: &str,
+    vte_version: Option<u32>,
+    kitty: bool,
+  ) -> Self {
+    if term.is_empty() || term == "dumb" || term == "linux" || term.starts_with("vt") {
+      return Self::basic();
+    }
+
+    const EXTENDED_TERMS: &[&str] = &[
+      "kitty",
+      "wezterm",
+      "foot",
+      "alacritty",
+      "ghostty",
+      "contour",
+    ];
+    const EXTENDED_PROGRAMS: &[&str] = &["WezTerm", "iTerm.app", "vscode", "ghostty"];
+    let extended = kitty
+      || EXTENDED_TERMS.iter().any(|name| term.contains(name))
+      || EXTENDED_PROGRAMS.contains(&term_program)
+      // VTE 0.52 added styled and colored underlines
+      || vte_version.is_some_and(|version| version >= 5200);
+
+    Self {
+      styled_underlines: extended,
+      underline_color: extended,
+      overline: extended,
+      strikethrough: true,
+    }
+  }
+
+  /// Whether styles are written unchanged
+  pub fn is_complete(&self) -> bool {
+    *self == Self::all()
+  }
+
+  /// Replace the decorations in `style` the terminal cannot draw
+  pub fn degrade(&self, style: &mut RenderStyle) {
+    if !self.styled_underlines {
+      style.underline_style = UnderlineStyle::Single;
+    }
+    if !self.underline_color {
+      style.underline_color = None;
+    }
+    if !self.overline {
+      style.overline = false;
+    }
+    if !self.strikethrough {
+      style.strikethrough = false;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rendering::SerializableColor;
+
+  #[test]
+  fn test_detection_by_terminal() {
+    let console = DecorationSupport::for_terminal("linux", "", None, false);
+    assert_eq!(console, DecorationSupport::basic());
+
+    let kitty = DecorationSupport::for_terminal("xterm-kitty", "", None, false);
+    assert!(kitty.styled_underlines && kitty.underline_color);
+
+    let gnome = DecorationSupport::for_terminal("xterm-256color", "", Some(7600), false);
+    assert!(gnome.styled_underlines);
+
+    let xterm = DecorationSupport::for_terminal("xterm-256color", "", None, false);
+    assert!(!xterm.styled_underlines && xterm.strikethrough);
+  }
+
+  #[test]
+  fn test_degrade_keeps_a_plain_underline() {
+    let mut style = RenderStyle {
+      underline: true,
+      underline_style: UnderlineStyle::Curly,
+      underline_color: Some(SerializableColor::Red),
+      overline: true,
+      strikethrough: true,
+      ..RenderStyle::default()
+    };
+    DecorationSupport::basic().degrade(&mut style);
+    assert!(style.underline);
+    assert_eq!(style.underline_style, UnderlineStyle::Single);
+    assert_eq!(style.underline_color, None);
+    assert!(!style.overline && !style.strikethrough);
+  }
+}
@@ -1,204 +1,486 @@
+//! Cell-level diff rendering
+//!
+//! Each frame is rasterized into a back [`CellGrid`] and compared with the
+//! front grid holding what the terminal currently shows. Only changed cells
+//! are written, using the shortest cursor moves available and REP, ECH and
+//! EL for runs of repeated or blank cells.
+
 use crate::error::Result;
 use crate::layout::Layout;
-use crate::rendering::{display_width, Renderer};
+use crate::rendering::target::{Cell, CellGrid};
+use crate::rendering::{hyperlink, FrameBuffer, Hyperlink, RenderStyle, Renderer};
+use std::time::Instant;
+
+#[cfg(not(target_family = "wasm"))]
+use crossterm::{
+  cursor::{Hide, Show},
+  terminal::{Clear, ClearType},
+};
+
+#[cfg(target_family = "wasm")]
+use crate::compat::{Clear, ClearType, Hide, Show};
+
+/// Control sequences the diff encoder may use besides cursor moves and SGR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffEncoding {
+  /// Repeat the previous character with REP (`CSI n b`)
+  pub repeat: bool,
+  /// Blank runs of cells with ECH (`CSI n X`) and row ends with EL (`CSI K`)
+  pub erase: bool,
+}
+
+impl Default for DiffEncoding {
+  fn default() -> Self {
+    Self {
+      repeat: true,
+      erase: true,
+    }
+  }
+}
 
+/// Writes the changes between two grids as terminal output
+struct DiffEncoder<'a> {
+  out: &'a mut Vec<u8>,
+  encoding: DiffEncoding,
+  /// Where the terminal cursor is; `None` when unknown, such as after
+  /// writing the last column
+  cursor: Option<(u16, u16)>,
+  style: RenderStyle,
+}
+
+impl DiffEncoder<'_> {
+  fn csi(&mut self, count: u16, final_byte: char) {
+    if count == 1 {
+      self
+        .out
+        .extend_from_slice(format!("\u{1b}[{final_byte}").as_bytes());
+    } else {
+      self
+        .out
+        .extend_from_slice(format!("\u{1b}[{count}{final_byte}").as_bytes());
+    }
+  }
+
+  fn move_to(&mut self, x: u16, y: u16) {
+    match self.cursor {
+      Some(cursor) if cursor == (x, y) => {}
+      Some((cx, cy)) if cy == y && cx < x => self.csi(x - cx, 'C'),
+      _ => self
+        .out
+        .extend_from_slice(format!("\u{1b}[{};{}H", y + 1, x + 1).as_bytes()),
+    }
+    self.cursor = Some((x, y));
+  }
+
+  fn set_style(&mut self, style: &RenderStyle) {
+    if self.style != *style {
+      let mut sgr = String::new();
+      FrameBuffer::ansi_sgr_for_style_diff(&self.style, style, &mut sgr);
+      Hyperlink::push_change(
+        self.style.hyperlink.as_ref(),
+        style.hyperlink.as_ref(),
+        &mut sgr,
+      );
+      self.out.extend_from_slice(sgr.as_bytes());
+      self.style = style.clone();
+    }
+  }
+
+  /// Set the pen so erasing produces `cell`
+  fn set_erase_style(&mut self, cell: &Cell) {
+    let style = RenderStyle {
+      background: cell.style.background.clone(),
+      ..self.style.clone()
+    };
+    self.set_style(&style);
+  }
 
+  fn advance(&mut self, x: u16, y: u16, width: u16, row_width: u16) {
+    let next = x + width;
+    self.cursor = (next < row_width).then_some((next, y));
+  }
 
-fn build_rows_from_grid(grid: &crate::rendering::target::CellGrid) -> Vec<Vec<u8>> {
-  let mut rows: Vec<Vec<u8>> = Vec::with_capacity(grid.rows.len());
-  for (y, segments) in grid.rows.iter().enumerate() {
-    if segments.is_empty() {
-      rows.push(Vec::new());
-      continue;
+  fn encode_row(&mut self, front: &[Cell], back: &[Cell], y: u16) {
+    let width = back.len() as u16;
+    let mut changed: Vec<bool> = front.iter().zip(back).map(|(a, b)| a != b).collect();
+    // A wide character is always written whole
+    for x in 0..back.len() {
+      if changed[x] && back[x].is_continuation() && x > 0 {
+        changed[x - 1] = true;
+      }
+    }
+    for x in (0..back.len()).rev() {
+      if changed[x] && x + 1 < back.len() && back[x + 1].is_continuation() {
+        changed[x + 1] = true;
+      }
     }
-    let mut segs = segments.clone();
-    segs.sort_by_key(|c| c.x);
-
-    // Coalesce adjacent segments with identical styles and contiguous columns
-    let mut merged: Vec<(u16, crate::rendering::RenderStyle, String)> = Vec::new();
-    for cell in segs {
-      if let Some((ref mut last_x, ref mut last_style, ref mut last_text)) = merged.last_mut() {
-        let last_w = display_width(last_text) as u16;
-        let expected_next_x = *last_x + last_w;
-        if *last_style == cell.style && cell.x == expected_next_x {
-          last_text.push_str(&cell.ch);
+    let Some(last_changed) = changed.iter().rposition(|&c| c) else {
+      return;
+    };
+
+    // Blank tail of the row that a single EL can produce
+    let tail_start = if self.encoding.erase {
+      let style = &back[back.len() - 1];
+      let blank_from = back
+        .iter()
+        .rposition(|cell| !cell.is_erased() || cell.style != style.style)
+        .map_or(0, |x| x + 1);
+      (style.is_erased() && blank_from <= last_changed && width as usize - blank_from > 3)
+        .then_some(blank_from as u16)
+    } else {
+      None
+    };
+    let end = tail_start.map_or(last_changed + 1, usize::from);
+
+    let mut x = 0;
+    while x < end {
+      if !changed[x] {
+        x += 1;
+        continue;
+      }
+      let cell = &back[x];
+      if cell.is_continuation() {
+        x += 1;
+        continue;
+      }
+
+      // Reprint a short unchanged gap instead of moving over it
+      if let Some((cx, cy)) = self.cursor {
+        let gap = x.saturating_sub(cx as usize);
+        if cy == y && gap > 0 && gap <= 3 && cx as usize <= x {
+          let reprint = &back[cx as usize..x];
+          if reprint
+            .iter()
+            .all(|c| c.style == self.style && c.symbol.len() == 1)
+          {
+            for c in reprint {
+              self.out.extend_from_slice(c.symbol.as_bytes());
+            }
+            self.cursor = Some((x as u16, y));
+          }
+        }
+      }
+      self.move_to(x as u16, y);
+
+      let run = back[x..end]
+        .iter()
+        .take_while(|other| *other == cell)
+        .count();
+      if self.encoding.erase && cell.is_erased() && run > 1 {
+        let ech = format!("\u{1b}[{run}X").len();
+        // ECH leaves the cursor in place, so anything after needs a move
+        let more = changed[x + run..end].iter().any(|&c| c);
+        let cost = ech
+          + if more {
+            format!("\u{1b}[{run}C").len()
+          } else {
+            0
+          };
+        if cost < run {
+          self.set_erase_style(cell);
+          self.csi(run as u16, 'X');
+          x += run;
           continue;
         }
       }
-      merged.push((cell.x, cell.style.clone(), cell.ch.clone()));
-    }
 
-    // Build minimal ANSI line: MoveTo + style changes only when needed
-    let mut line = String::new();
-    let mut cur_style = crate::rendering::RenderStyle::default();
-    for (x, style, text) in merged {
-      line.push_str(&format!("\u{1b}[{};{}H", y + 1, (x as usize) + 1));
-      if style != cur_style {
-        crate::rendering::FrameBuffer::ansi_sgr_for_style_diff(&cur_style, &style, &mut line);
-        cur_style = style;
+      self.set_style(&cell.style);
+      self.out.extend_from_slice(cell.symbol.as_bytes());
+      let cell_width = if back.get(x + 1).is_some_and(Cell::is_continuation) {
+        2
+      } else {
+        1
+      };
+      if self.encoding.repeat && cell_width == 1 && run > 1 && cell.symbol.is_ascii() {
+        let repeats = run - 1;
+        let rep = format!("\u{1b}[{repeats}b").len();
+        if rep < repeats * cell.symbol.len() {
+          self.csi(repeats as u16, 'b');
+          self.advance(x as u16, y, run as u16, width);
+          x += run;
+          continue;
+        }
       }
-      line.push_str(&text);
+      self.advance(x as u16, y, cell_width, width);
+      x += cell_width as usize;
     }
 
-    rows.push(line.into_bytes());
+    if let Some(tail) = tail_start {
+      self.move_to(tail, y);
+      self.set_erase_style(&back[tail as usize]);
+      self.out.extend_from_slice(b"\x1b[K");
+    }
   }
-  rows
+}
+
+/// Append to `out` the output that turns the terminal showing `front` into
+/// `back`. Both grids must have the same size; the pen is assumed to be in
+/// the default style, with no hyperlink open, and is left in it.
+pub(crate) fn encode_diff(
+  front: &CellGrid,
+  back: &CellGrid,
+  encoding: DiffEncoding,
+  out: &mut Vec<u8>,
+) {
+  let mut encoder = DiffEncoder {
+    out,
+    encoding,
+    cursor: None,
+    style: RenderStyle::default(),
+  };
+  for y in 0..back.height {
+    encoder.encode_row(front.row(y), back.row(y), y);
+  }
+  if encoder.style.hyperlink.take().is_some() {
+    encoder.out.extend_from_slice(hyperlink::CLOSE.as_bytes());
+  }
+  if encoder.style != RenderStyle::default() {
+    encoder.out.extend_from_slice(b"\x1b[0m");
+  }
+}
+
+/// A rendered frame handed to the diff encoder
+enum DiffFrame {
+  /// Terminal output, rasterized into the back buffer
+  Ansi(Vec<u8>),
+  /// Cells composed from layers, diffed as they are
+  Cells(CellGrid),
 }
 
 impl Renderer {
+  /// Render a frame as the changes from the previously rendered frame.
+  ///
+  /// The first frame, the first after a resize or [`Renderer::invalidate`],
+  /// and every frame while diff mode is disabled repaint the whole screen.
   pub async fn render_diff(&mut self, layout: &Layout) -> Result<Vec<u8>> {
-    // Force a full repaint on the first diff frame or interval
-    let need_full_repaint = self
-      .last_diff_rows
-      .as_ref()
-      .map(|rows| rows.is_empty())
-      .unwrap_or(true)
-      || self
-        .diff_full_repaint_interval
-        .map(|n| self.diff_frames_since_full >= n)
-        .unwrap_or(false);
-
-    if need_full_repaint {
-      let bytes = self.render(layout).await?;
-      let rows = self.bytes_to_rows(&bytes);
-      self.last_diff_rows = Some(rows);
-      // If we have a raster grid from a previous offscreen render, prefer diffing on rows directly
-      // Note: Stage 3 partial – future step will populate this in render_offscreen via GridTarget
-
-      self.diff_frames_since_full = 0;
-      return Ok(bytes);
-    }
+    let frame_start = Instant::now();
+    self.frame_buffer.clear();
+    self.render_layout_to_buffer(layout, None)?;
+    let render_time = frame_start.elapsed();
+    let frame = self.frame_buffer.take_bytes();
+    self.finish_diff_frame(DiffFrame::Ansi(frame), frame_start, render_time)
+  }
 
-    // Render offscreen to get current frame without Clear/Hide/Show
-    let current_bytes = if self.diff_mode_enabled {
-      self.render_offscreen(layout).await?
-    } else {
-      self.render(layout).await?
+  /// Render a frame with CSS component tree styles as the changes from the
+  /// previously rendered frame; see [`Renderer::render_diff`]
+  pub async fn render_diff_with_component_tree(
+    &mut self,
+    layout: &Layout,
+    component_tree: &crate::css::ComponentTree,
+  ) -> Result<Vec<u8>> {
+    let frame_start = Instant::now();
+    self.frame_buffer.clear();
+    // Composed layers are diffed as they are, without a trip through ANSI
+    let frame = match self.compose_layers(layout, component_tree.root())? {
+      Some(grid) => DiffFrame::Cells(grid),
+      None => DiffFrame::Ansi(self.frame_buffer.take_bytes()),
     };
-    let emit_rows = self.bytes_to_rows(&current_bytes);
+    let render_time = frame_start.elapsed();
+    self.finish_diff_frame(frame, frame_start, render_time)
+  }
 
-    // Prefer building rows from raster grid for diff comparison when diff mode is enabled,
-    // while using emit_rows as the authoritative bytes to send (preserves styles)
-    let current_rows_for_diff = if self.diff_mode_enabled {
-      if let Some(grid) = &self.grid_for_diff {
-        build_rows_from_grid(grid)
-      } else {
-        emit_rows.clone()
+  /// The cells the terminal shows after the last diff frame
+  pub fn front_buffer(&self) -> Option<&CellGrid> {
+    self.front_buffer.as_ref()
+  }
+
+  /// Forget what the terminal shows, so the next diff frame repaints
+  /// everything. Call after writing to the terminal outside the renderer.
+  pub fn invalidate(&mut self) {
+    self.front_buffer = None;
+  }
+
+  /// Rasterize `frame` into the back buffer, or take its composed cells as
+  /// the back buffer, and encode it against the front buffer, then swap the
+  /// two
+  fn finish_diff_frame(
+    &mut self,
+    frame: DiffFrame,
+    frame_start: Instant,
+    render_time: std::time::Duration,
+  ) -> Result<Vec<u8>> {
+    let (width, height) = (self.width, self.height);
+    let (back, frame) = match frame {
+      DiffFrame::Ansi(frame) => {
+        let mut back = self
+          .back_buffer
+          .take()
+          .filter(|grid| grid.width == width && grid.height == height)
+          .unwrap_or_else(|| CellGrid::new(width, height));
+        back.clear();
+        if let Some(front) = &self.front_buffer {
+          back.share_links(front);
+        }
+        back.rasterize(&frame);
+        // Widgets may have written truecolor and decorations directly into
+        // the frame
+        self.frame_buffer.map_grid(&mut back);
+        (back, Some(frame))
       }
-    } else {
-      emit_rows.clone()
+      DiffFrame::Cells(grid) => (grid, None),
     };
 
-    // Diff with previous
+    let front = self
+      .front_buffer
+      .take()
+      .filter(|grid| grid.width == width && grid.height == height);
+    let interval_reached = self
+      .diff_full_repaint_interval
+      .is_some_and(|n| self.diff_frames_since_full >= n);
+
     let mut output = Vec::new();
-    let prev_rows = self.last_diff_rows.as_ref().unwrap();
-    let max_rows = prev_rows.len().max(current_rows_for_diff.len());
-
-    for row in 0..max_rows {
-      let prev = prev_rows.get(row).map(|v| v.as_slice()).unwrap_or(&[]);
-      let curr = current_rows_for_diff
-        .get(row)
-        .map(|v| v.as_slice())
-        .unwrap_or(&[]);
-      if prev != curr {
-        // Choose emission mode
-        if self.diff_minimal_ansi_enabled {
-          // Emit coalesced minimal ANSI from grid rows (curr)
-          output.extend_from_slice(curr);
-          // Clear to end of line to avoid stale right-edge characters
-          output.extend_from_slice("\u{1b}[K".as_bytes());
-          // Reset style to prevent bleed
-          output.extend_from_slice("\u{1b}[0m".as_bytes());
-        } else {
-          // Emit authoritative row bytes from emit_rows (not the grid), so styles are preserved
-          output.extend_from_slice(format!("\u{1b}[{};{}H", row + 1, 1).as_bytes());
-          let authoritative = emit_rows.get(row).map(|v| v.as_slice()).unwrap_or(&[]);
-          output.extend_from_slice(authoritative);
-
-          // If the new row is shorter (by display width) than the previous, pad with spaces
-          let prev_s = String::from_utf8_lossy(prev);
-          let curr_s = String::from_utf8_lossy(authoritative);
-          let term_w = self.width as usize;
-          let prev_w = display_width(&prev_s).min(term_w);
-          let curr_w = display_width(&curr_s).min(term_w);
-          if curr_w < prev_w {
-            let pad = " ".repeat(prev_w - curr_w);
-            output.extend_from_slice(pad.as_bytes());
+    match front {
+      Some(front) if self.diff_mode_enabled && !interval_reached => {
+        encode_diff(&front, &back, self.diff_encoding, &mut output);
+        self.back_buffer = Some(front);
+        self.diff_frames_since_full = self.diff_frames_since_full.saturating_add(1);
+      }
+      previous => {
+        self.frame_buffer.queue(Clear(ClearType::All))?;
+        self.frame_buffer.queue(Hide)?;
+        output = self.frame_buffer.take_bytes();
+        match frame {
+          Some(frame) if self.frame_buffer.writes_styles_unchanged() => {
+            output.extend_from_slice(&frame);
           }
-
-          // ANSI reset at end of row to prevent style bleed
-          output.extend_from_slice("\u{1b}[0m".as_bytes());
+          _ => encode_diff(
+            &CellGrid::new(width, height),
+            &back,
+            self.diff_encoding,
+            &mut output,
+          ),
         }
+        self.frame_buffer.queue(Show)?;
+        output.extend(self.frame_buffer.take_bytes());
+        self.back_buffer = previous;
+        self.diff_frames_since_full = 0;
       }
     }
+    self.front_buffer = Some(back);
 
-    // Update baseline and frame counter
-    self.last_diff_rows = Some(current_rows_for_diff);
-    self.diff_frames_since_full = self.diff_frames_since_full.saturating_add(1);
+    let total_frame_time = frame_start.elapsed();
+    let target_duration = self
+      .get_target_frame_duration()
+      .unwrap_or(std::time::Duration::from_millis(16));
+    self.record_frame_performance(
+      total_frame_time,
+      render_time,
+      total_frame_time > target_duration,
+    );
 
     Ok(output)
   }
+}
 
-  /// Convert a full-frame ANSI byte buffer into per-line rows by tracking cursor moves (CUP).
-  fn bytes_to_rows(&self, bytes: &[u8]) -> Vec<Vec<u8>> {
-    let mut rows: Vec<Vec<u8>> = Vec::new();
-    let mut i = 0;
-    let len = bytes.len();
-    let mut current_row: usize = 0;
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rendering::SerializableColor;
+  use std::sync::Arc;
 
-    let ensure_row = |rows: &mut Vec<Vec<u8>>, idx: usize| {
-      if rows.len() <= idx {
-        rows.resize_with(idx + 1, Vec::new);
-      }
+  fn grid(rows: &[&str]) -> CellGrid {
+    let mut grid = CellGrid::new(20, rows.len() as u16);
+    for (y, row) in rows.iter().enumerate() {
+      grid.put_str(0, y as u16, row, &RenderStyle::default());
+    }
+    grid
+  }
+
+  fn diff(front: &CellGrid, back: &CellGrid) -> String {
+    let mut out = Vec::new();
+    encode_diff(front, back, DiffEncoding::default(), &mut out);
+    // Applying the diff to the front grid must reproduce the back grid
+    let mut applied = front.clone();
+    applied.rasterize(&out);
+    assert_eq!(
+      &applied,
+      back,
+      "diff output: {:?}",
+      String::from_utf8_lossy(&out)
+    );
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn test_unchanged_grid_emits_nothing() {
+    assert_eq!(diff(&grid(&["hello"]), &grid(&["hello"])), "");
+  }
+
+  #[test]
+  fn test_only_changed_cells_are_written() {
+    assert_eq!(
+      diff(&grid(&["name: ab"]), &grid(&["name: abc"])),
+      "\u{1b}[1;9Hc"
+    );
+    // A short unchanged gap is reprinted rather than skipped over
+    assert_eq!(
+      diff(&grid(&["abcdef"]), &grid(&["xbcyef"])),
+      "\u{1b}[1;1Hxbcy"
+    );
+    assert_eq!(
+      diff(&grid(&["a", "b", "c"]), &grid(&["a", "B", "c"])),
+      "\u{1b}[2;1HB"
+    );
+  }
+
+  #[test]
+  fn test_repeat_and_erase_sequences() {
+    assert_eq!(
+      diff(&grid(&[""]), &grid(&["----------"])),
+      "\u{1b}[1;1H-\u{1b}[9b"
+    );
+    assert_eq!(
+      diff(&grid(&["ABCDEFGHIJ"]), &grid(&["ABC"])),
+      "\u{1b}[1;4H\u{1b}[K"
+    );
+    let before = grid(&["x..............x"]);
+    let after = grid(&["x              x"]);
+    assert_eq!(diff(&before, &after), "\u{1b}[1;2H\u{1b}[14X");
+  }
+
+  #[test]
+  fn test_erase_uses_background_color() {
+    let blue = RenderStyle {
+      background: Some(SerializableColor::Blue),
+      ..RenderStyle::default()
     };
+    let mut back = grid(&["text"]);
+    back.fill(
+      crate::layout::LayoutRect {
+        x: 0,
+        y: 0,
+        width: 20,
+        height: 1,
+      },
+      &blue,
+    );
+    assert_eq!(
+      diff(&grid(&["text"]), &back),
+      "\u{1b}[1;1H\u{1b}[104m\u{1b}[K\u{1b}[0m"
+    );
+  }
 
-    while i < len {
-      if bytes[i] == 0x1B && i + 1 < len && bytes[i + 1] == b'[' {
-        // Parse CSI sequence: ESC [ params letter
-        let start = i;
-        i += 2; // skip ESC[
-        let mut params: Vec<usize> = Vec::new();
-        let mut num: usize = 0;
-
-        let mut has_num = false;
-        let mut term: u8 = 0;
-        while i < len {
-          let b = bytes[i];
-          if (b as char).is_ascii_digit() {
-            num = num.saturating_mul(10).saturating_add((b - b'0') as usize);
-            has_num = true;
-          } else if b == b';' {
-            if has_num {
-              params.push(num);
-              num = 0;
-              has_num = false;
-            }
-          } else if (b as char).is_ascii_alphabetic() {
-            if has_num {
-              params.push(num);
-            }
-            term = b;
-            i += 1; // consume terminator
-            break;
-          }
-          i += 1;
-        }
-        if term == b'H' || term == b'f' {
-          // CUP: row;col (1-based)
-          let row1 = params.first().cloned().unwrap_or(1);
-          current_row = row1.saturating_sub(1);
-          ensure_row(&mut rows, current_row);
-        } else {
-          // Keep other CSI sequences in content of current row
-          ensure_row(&mut rows, current_row);
-          rows[current_row].extend_from_slice(&bytes[start..i]);
-        }
-      } else {
-        ensure_row(&mut rows, current_row);
-        rows[current_row].push(bytes[i]);
-        i += 1;
-      }
-    }
-    rows
+  #[test]
+  fn test_wide_characters_are_written_whole() {
+    assert_eq!(diff(&grid(&["a中b"]), &grid(&["a文b"])), "\u{1b}[1;2H文");
+    assert_eq!(diff(&grid(&["a中b"]), &grid(&["axyb"])), "\u{1b}[1;2Hxy");
+    assert_eq!(diff(&grid(&["abcd"]), &grid(&["a中d"])), "\u{1b}[1;2H中");
+  }
+
+  #[test]
+  fn test_partial_rewrite_reopens_hyperlink() {
+    let link = Hyperlink::new("https://example.com");
+    let linked = RenderStyle {
+      hyperlink: Some(Arc::new(link.clone())),
+      ..RenderStyle::default()
+    };
+    let mut front = grid(&[""]);
+    front.put_str(0, 0, "docs", &linked);
+    let mut back = grid(&[""]);
+    back.put_str(0, 0, "dogs", &linked);
+    assert_eq!(
+      diff(&front, &back),
+      format!("\u{1b}[1;3H{}g{}", link.open_sequence(), hyperlink::CLOSE)
+    );
   }
 }
@@ -0,0 +1,271 @@
+//! OSC 8 hyperlinks
+//!
+//! Link text is wrapped in `ESC ] 8 ; id=… ; url ST` … `ESC ] 8 ; ; ST`.
+//! The rasterizer records the open link on every cell it draws, like any
+//! other pen attribute, so the diff renderer can reopen a link when it
+//! rewrites part of one. The `id` keeps the pieces one link for terminals
+//! that highlight links on hover. Where the terminal does not support
+//! hyperlinks, the URL is printed after the text in parentheses instead.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// Sequence that ends the open hyperlink
+pub const CLOSE: &str = "\x1b]8;;\x1b\\";
+
+/// A hyperlink target, attached to the cells of its text
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Hyperlink {
+  /// Groups separately written runs into one link
+  pub id: String,
+  pub url: String,
+}
+
+impl Hyperlink {
+  /// A link to `url` with an id derived from it
+  pub fn new(url: impl Into<String>) -> Self {
+    let url = url.into();
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    Self {
+      id: format!("{:x}", hasher.finish()),
+      url,
+    }
+  }
+
+  pub fn with_id(id: impl Into<String>, url: impl Into<String>) -> Self {
+    Self {
+      id: id.into(),
+      url: url.into(),
+    }
+  }
+
+  /// Sequence that starts this link
+  pub fn open_sequence(&self) -> String {
+    if self.id.is_empty() {
+      format!("\x1b]8;;{}\x1b\\", self.url)
+    } else {
+      format!("\x1b]8;id={};{}\x1b\\", self.id, self.url)
+    }
+  }
+
+  /// Interpret the body of an OSC string (between `ESC ]` and the
+  /// terminator). Returns `None` for anything but OSC 8, and `Some(None)`
+  /// when it closes the open link.
+  pub(crate) fn parse_osc(body: &str) -> Option<Option<Self>> {
+    let rest = body.strip_prefix("8;")?;
+    let (params, url) = rest.split_once(';')?;
+    if url.is_empty() {
+      return Some(None);
+    }
+    let id = params
+      .split(':')
+      .find_map(|param| param.strip_prefix("id="))
+      .unwrap_or_default();
+    Some(Some(Self::with_id(id, url)))
+  }
+
+  /// Emit the sequences that change the open link from `prev` to `next`
+  pub(crate) fn push_change(prev: Option<&Arc<Self>>, next: Option<&Arc<Self>>, out: &mut String) {
+    if prev == next {
+      return;
+    }
+    match next {
+      Some(link) => out.push_str(&link.open_sequence()),
+      None => out.push_str(CLOSE),
+    }
+  }
+}
+
+/// Whether the terminal described by the given environment values supports
+/// OSC 8 hyperlinks
+pub fn supports_hyperlinks(
+  term: &str,
+  term_program: &str,
+  vte_version: Option<u32>,
+  windows_terminal: bool,
+) -> bool {
+  if term == "dumb" || term == "linux" || term.starts_with("vt") {
+    return false;
+  }
+  const TERMS: &[&str] = &[
+    "kitty",
+    "wezterm",
+    "foot",
+    "alacritty",
+    "ghostty",
+    "contour",
+  ];
+  const PROGRAMS: &[&str] = &["WezTerm", "iTerm.app", "vscode", "ghostty", "Hyper"];
+  windows_terminal
+    || TERMS.iter().any(|name| term.contains(name))
+    || PROGRAMS.contains(&term_program)
+    // VTE 0.50 added OSC 8
+    || vte_version.is_some_and(|version| version >= 5000)
+}
+
+/// Global hyperlink support, set by the app from the driver's capabilities
+pub mod global_hyperlink_support {
+  use std::sync::atomic::{AtomicI8, Ordering};
+
+  /// Global override (highest priority)
+  static HYPERLINKS_GLOBAL: AtomicI8 = AtomicI8::new(-1);
+
+  /// Cached environment detection
+  static HYPERLINKS_CACHED: AtomicI8 = AtomicI8::new(-1);
+
+  /// Whether links are written as OSC 8 hyperlinks
+  pub fn detect() -> bool {
+    let override_value = HYPERLINKS_GLOBAL.load(Ordering::Relaxed);
+    if override_value != -1 {
+      return override_value == 1;
+    }
+
+    let cached_value = HYPERLINKS_CACHED.load(Ordering::Relaxed);
+    if cached_value != -1 {
+      return cached_value == 1;
+    }
+
+    let detected = detect_from_env();
+    HYPERLINKS_CACHED.store(detected as i8, Ordering::Relaxed);
+    detected
+  }
+
+  /// Detect support from `TERM`, `TERM_PROGRAM`, `VTE_VERSION` and
+  /// `WT_SESSION`
+  pub fn detect_from_env() -> bool {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    let vte_version = std::env::var("VTE_VERSION")
+      .ok()
+      .and_then(|version| version.parse().ok());
+    let windows_terminal = std::env::var_os("WT_SESSION").is_some();
+    super::supports_hyperlinks(&term, &term_program, vte_version, windows_terminal)
+  }
+
+  /// Set global hyperlink support override
+  pub fn set_override(enabled: bool) {
+    HYPERLINKS_GLOBAL.store(enabled as i8, Ordering::Relaxed);
+  }
+
+  /// Clear global hyperlink support override
+  pub fn clear_override() {
+    HYPERLINKS_GLOBAL.store(-1, Ordering::Relaxed);
+  }
+
+  /// Clear cached detection result (force re-detection)
+  pub fn clear_cache() {
+    HYPERLINKS_CACHED.store(-1, Ordering::Relaxed);
+  }
+}
+
+/// `text` linking to `url`, as a hyperlink where the terminal supports them
+/// and followed by the URL in parentheses elsewhere
+pub fn hyperlink(text: &str, url: &str) -> String {
+  format_hyperlink(text, url, global_hyperlink_support::detect())
+}
+
+/// `text` linking to `url`, as an OSC 8 hyperlink when `supported`
+pub fn format_hyperlink(text: &str, url: &str, supported: bool) -> String {
+  if supported {
+    format!("{}{text}{CLOSE}", Hyperlink::new(url).open_sequence())
+  } else if text.is_empty() || text == url {
+    url.to_string()
+  } else {
+    format!("{text} ({url})")
+  }
+}
+
+/// The OSC 8 sequence left open at the end of `s`, starting from `open`
+fn open_after(s: &str, mut open: Option<String>) -> Option<String> {
+  let mut rest = s;
+  while let Some(start) = rest.find("\x1b]8;") {
+    let body = &rest[start + 2..];
+    let (end, terminator) = match (body.find('\x07'), body.find("\x1b\\")) {
+      (Some(bel), Some(st)) if bel < st => (bel, 1),
+      (_, Some(st)) => (st, 2),
+      (Some(bel), None) => (bel, 1),
+      (None, None) => break,
+    };
+    open = match Hyperlink::parse_osc(&body[..end]) {
+      Some(Some(_)) => Some(rest[start..start + 2 + end + terminator].to_string()),
+      _ => None,
+    };
+    rest = &body[end + terminator..];
+  }
+  open
+}
+
+/// Make each line close the links it leaves open and reopen the ones
+/// carried over from the line before, so lines can be drawn apart
+pub(crate) fn balance_lines<'a>(lines: impl IntoIterator<Item = &'a mut String>) {
+  let mut open: Option<String> = None;
+  for line in lines {
+    let carried = open.take();
+    open = open_after(line, carried.clone());
+    if !line.contains("\x1b]8;") && carried.is_none() {
+      continue;
+    }
+    if let Some(sequence) = carried {
+      line.insert_str(0, &sequence);
+    }
+    if open.is_some() {
+      line.push_str(CLOSE);
+    }
+  }
+}
+
+/// `slice` of `text`, which starts at byte `start`, with the link open at
+/// that point reopened and any link it leaves open closed
+pub(crate) fn balance_slice(text: &str, start: usize, slice: &str) -> Option<String> {
+  if !text.contains("\x1b]8;") {
+    return None;
+  }
+  let carried = open_after(&text[..start], None);
+  let open = open_after(slice, carried.clone());
+  let mut out = carried.unwrap_or_default();
+  out.push_str(slice);
+  if open.is_some() {
+    out.push_str(CLOSE);
+  }
+  Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_format_and_parse() {
+    let linked = format_hyperlink("docs", "https://example.com", true);
+    let link = Hyperlink::new("https://example.com");
+    assert_eq!(linked, format!("{}docs{CLOSE}", link.open_sequence()));
+    let body = &link.open_sequence()[2..link.open_sequence().len() - 2];
+    assert_eq!(Hyperlink::parse_osc(body), Some(Some(link)));
+    assert_eq!(Hyperlink::parse_osc("8;;"), Some(None));
+    assert_eq!(Hyperlink::parse_osc("0;title"), None);
+
+    assert_eq!(
+      format_hyperlink("docs", "https://example.com", false),
+      "docs (https://example.com)"
+    );
+    assert!(supports_hyperlinks("xterm-kitty", "", None, false));
+    assert!(!supports_hyperlinks("xterm-256color", "", None, false));
+  }
+
+  #[test]
+  fn test_balance_lines_carries_links_across_breaks() {
+    let open = Hyperlink::new("https://example.com").open_sequence();
+    let mut lines = vec![
+      format!("see {open}the"),
+      format!("docs{CLOSE} now"),
+      "plain".to_string(),
+    ];
+    balance_lines(&mut lines);
+    assert_eq!(lines[0], format!("see {open}the{CLOSE}"));
+    assert_eq!(lines[1], format!("{open}docs{CLOSE} now"));
+    assert_eq!(lines[2], "plain");
+  }
+}
@@ -1,7 +1,19 @@
 //! Advanced terminal rendering system with CSS support and double buffering
 pub mod batch;
+mod color_depth;
+pub mod compositor;
+mod decoration;
 mod diff;
+pub mod hyperlink;
+mod oneshot;
 mod target;
+pub use color_depth::ColorDownsampler;
+pub use compositor::{Compositor, Layer};
+pub use decoration::DecorationSupport;
+pub use diff::DiffEncoding;
+pub use hyperlink::Hyperlink;
+pub use oneshot::{render_to_ansi, render_to_grid, render_to_plain};
+pub use target::{Cell, CellGrid};
 
 use target::RenderTarget;
 
@@ -23,10 +35,80 @@ fn intersect_rect(a: &LayoutRect, b: &LayoutRect) -> Option<LayoutRect> {
   }
 }
 
+type LayerRoot<'a> = (&'a Layout, &'a crate::css::ComponentNode, ScrollView);
+
+// The nearest elements below `layout`, seen through `view`, that are
+// composited as their own layer, in tree order
+fn collect_layer_roots<'a>(
+  layout: &'a Layout,
+  node: &'a crate::css::ComponentNode,
+  view: ScrollView,
+  roots: &mut Vec<LayerRoot<'a>>,
+) {
+  let inner = view.enter(layout);
+  for (child_layout, child_node) in layout.children.iter().zip(node.children.iter()) {
+    let child_view = inner.place(child_layout);
+    if child_node.styles.creates_layer() {
+      roots.push((child_layout, child_node, child_view));
+    } else {
+      collect_layer_roots(child_layout, child_node, child_view, roots);
+    }
+  }
+}
+
+// Layers in paint order, by stacking context: the layers nested in `layer`
+// with a negative z-index paint before it and the rest after it, each
+// group by z-index and then tree order. `None` stands for the root's own
+// content.
+fn stack_layers<'a>(
+  layer: Option<LayerRoot<'a>>,
+  layout: &'a Layout,
+  node: &'a crate::css::ComponentNode,
+  view: ScrollView,
+  order: &mut Vec<Option<LayerRoot<'a>>>,
+) {
+  let mut nested = Vec::new();
+  collect_layer_roots(layout, node, view, &mut nested);
+  nested.sort_by_key(|(_, nested_node, _)| nested_node.styles.z_index);
+  let (below, above): (Vec<_>, Vec<_>) = nested
+    .into_iter()
+    .partition(|(_, nested_node, _)| nested_node.styles.z_index < 0);
+  for root in below {
+    stack_layers(Some(root), root.0, root.1, root.2, order);
+  }
+  order.push(layer);
+  for root in above {
+    stack_layers(Some(root), root.0, root.1, root.2, order);
+  }
+}
+
+// Start and length of a scrollbar's thumb in a track `track` cells long,
+// for content `content` cells long scrolled by `offset`
+fn scrollbar_thumb(track: u16, content: u16, offset: u16) -> (u16, u16) {
+  if content <= track || track == 0 {
+    return (0, track);
+  }
+  let (track, content, offset) = (u32::from(track), u32::from(content), u32::from(offset));
+  let length = (track * track / content).clamp(1, track);
+  let travel = content - track;
+  let start = ((track - length) * offset.min(travel) + travel / 2) / travel;
+  (start as u16, length as u16)
+}
+
+// The part of a rect starting `columns` cells to the right
+fn shift_right(rect: LayoutRect, columns: u16) -> LayoutRect {
+  LayoutRect {
+    x: rect.x.saturating_add(columns),
+    width: rect.width.saturating_sub(columns),
+    ..rect
+  }
+}
+
 use crate::display::AdaptiveFpsManager;
 use crate::error::{Result, TuiError};
 use crate::layout::Layout;
 use crate::layout::LayoutRect;
+use crate::layout::ScrollView;
 
 pub mod borders;
 pub use borders::{BorderPosition, BorderSet, BorderStyle};
@@ -51,102 +133,10 @@ use crate::compat::{
 pub type CrosstermColor = crate::compat::Color;
 use std::collections::HashMap;
 use std::io::Write;
+use std::sync::Arc;
 use std::time::Instant;
 
-use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
-
-fn ansi_token_end(s: &str, start: usize) -> Option<usize> {
-  let bytes = s.as_bytes();
-  if start >= bytes.len() || bytes[start] != 0x1b {
-    // ESC
-    return None;
-  }
-  let len = bytes.len();
-  if start + 1 >= len {
-    return Some(len);
-  }
-  match bytes[start + 1] {
-    b'[' => {
-      // CSI: ESC [ ... final byte 0x40..=0x7E
-      let mut j = start + 2;
-      while j < len {
-        let b = bytes[j];
-        if (0x40..=0x7e).contains(&b) {
-          return Some(j + 1);
-        }
-        j += 1;
-      }
-      Some(len)
-    }
-    b']' => {
-      // OSC: ESC ] ... BEL (0x07) or ST (ESC \)
-      let mut j = start + 2;
-      while j < len {
-        if bytes[j] == 0x07 {
-          return Some(j + 1);
-        }
-        if bytes[j] == 0x1b && j + 1 < len && bytes[j + 1] == b'\\' {
-          return Some(j + 2);
-        }
-        j += 1;
-      }
-      Some(len)
-    }
-    _ => {
-      // Fallback: skip ESC and next byte
-      Some((start + 2).min(len))
-    }
-  }
-}
-
-fn display_width(s: &str) -> usize {
-  let mut i = 0usize;
-  let mut w = 0usize;
-  while i < s.len() {
-    if let Some(end) = ansi_token_end(s, i) {
-      i = end;
-      continue;
-    }
-    if let Some((_, g)) = s[i..].grapheme_indices(true).next() {
-      w += UnicodeWidthStr::width(g);
-      i += g.len();
-    } else {
-      break;
-    }
-  }
-  w
-}
-
-fn truncate_to_display_width(s: &str, max_cols: usize) -> &str {
-  if max_cols == 0 {
-    return "";
-  }
-  let mut i = 0usize;
-  let mut cols = 0usize;
-  let mut last_end = 0usize;
-  while i < s.len() {
-    if let Some(end) = ansi_token_end(s, i) {
-      // Include ANSI sequences, zero-width
-      i = end;
-      last_end = i;
-      continue;
-    }
-    if let Some((rel_idx, g)) = s[i..].grapheme_indices(true).next() {
-      let gi = i + rel_idx; // should be i
-      let gw = UnicodeWidthStr::width(g);
-      if cols + gw > max_cols {
-        break;
-      }
-      cols += gw;
-      i = gi + g.len();
-      last_end = i;
-    } else {
-      break;
-    }
-  }
-  &s[..last_end]
-}
+use crate::layout::text::{display_width, layout_text, truncate_to_display_width};
 
 /// Panel rendering configuration
 #[derive(Debug, Clone)]
@@ -235,6 +225,96 @@ impl From<SerializableColor> for CrosstermColor {
   }
 }
 
+impl SerializableColor {
+  /// Index of the color in the 256 color palette; `None` for RGB colors
+  /// and the terminal's default color
+  pub fn palette_index(&self) -> Option<u8> {
+    Some(match self {
+      SerializableColor::Black => 0,
+      SerializableColor::DarkRed => 1,
+      SerializableColor::DarkGreen => 2,
+      SerializableColor::DarkYellow => 3,
+      SerializableColor::DarkBlue => 4,
+      SerializableColor::DarkMagenta => 5,
+      SerializableColor::DarkCyan => 6,
+      SerializableColor::Grey => 7,
+      SerializableColor::DarkGrey => 8,
+      SerializableColor::Red => 9,
+      SerializableColor::Green => 10,
+      SerializableColor::Yellow => 11,
+      SerializableColor::Blue => 12,
+      SerializableColor::Magenta => 13,
+      SerializableColor::Cyan => 14,
+      SerializableColor::White => 15,
+      SerializableColor::AnsiValue(n) => *n,
+      SerializableColor::Rgb { .. } | SerializableColor::Reset => return None,
+    })
+  }
+
+  /// The color as RGB, using the xterm palette for named and indexed
+  /// colors; `None` for the terminal's default color
+  pub fn to_rgb(&self) -> Option<(u8, u8, u8)> {
+    const NAMED: [(u8, u8, u8); 16] = [
+      (0, 0, 0),
+      (205, 0, 0),
+      (0, 205, 0),
+      (205, 205, 0),
+      (0, 0, 238),
+      (205, 0, 205),
+      (0, 205, 205),
+      (229, 229, 229),
+      (127, 127, 127),
+      (255, 0, 0),
+      (0, 255, 0),
+      (255, 255, 0),
+      (92, 92, 255),
+      (255, 0, 255),
+      (0, 255, 255),
+      (255, 255, 255),
+    ];
+    let index = match self {
+      SerializableColor::Rgb { r, g, b } => return Some((*r, *g, *b)),
+      color => color.palette_index()?,
+    };
+    Some(match index {
+      0..=15 => NAMED[index as usize],
+      16..=231 => {
+        let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+        let i = index - 16;
+        (level(i / 36), level(i / 6 % 6), level(i % 6))
+      }
+      _ => {
+        let gray = 8 + (index - 232) * 10;
+        (gray, gray, gray)
+      }
+    })
+  }
+}
+
+/// Shape of the underline drawn when [`RenderStyle::underline`] is set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum UnderlineStyle {
+  #[default]
+  Single,
+  Double,
+  Curly,
+  Dotted,
+  Dashed,
+}
+
+impl UnderlineStyle {
+  /// The `CSI 4 : n m` subparameter selecting this style
+  pub fn sgr_subparameter(self) -> u16 {
+    match self {
+      UnderlineStyle::Single => 1,
+      UnderlineStyle::Double => 2,
+      UnderlineStyle::Curly => 3,
+      UnderlineStyle::Dotted => 4,
+      UnderlineStyle::Dashed => 5,
+    }
+  }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct RenderStyle {
   pub color: Option<SerializableColor>,
@@ -242,6 +322,22 @@ pub struct RenderStyle {
   pub bold: bool,
   pub italic: bool,
   pub underline: bool,
+  #[serde(default)]
+  pub underline_style: UnderlineStyle,
+  /// Underline color; `None` draws it in the text color
+  #[serde(default)]
+  pub underline_color: Option<SerializableColor>,
+  #[serde(default)]
+  pub strikethrough: bool,
+  #[serde(default)]
+  pub overline: bool,
+  #[serde(default)]
+  pub blink: bool,
+  #[serde(default)]
+  pub dim: bool,
+  /// OSC 8 link the text belongs to
+  #[serde(default)]
+  pub hyperlink: Option<Arc<Hyperlink>>,
 }
 
 /// Frame buffer for double-buffered rendering to eliminate flickering
@@ -254,6 +350,10 @@ pub struct FrameBuffer {
   cursor_y: u16,
   /// Current style state for optimization
   current_style: RenderStyle,
+  /// Maps colors to what the terminal can display
+  color_downsampler: ColorDownsampler,
+  /// Decorations the terminal can draw
+  decoration_support: DecorationSupport,
 }
 
 impl Default for FrameBuffer {
@@ -269,7 +369,59 @@ impl FrameBuffer {
       cursor_x: 0,
       cursor_y: 0,
       current_style: RenderStyle::default(),
+      color_downsampler: ColorDownsampler::default(),
+      decoration_support: DecorationSupport::default(),
+    }
+  }
+
+  /// Colors are mapped to this support level as they are queued
+  pub fn set_color_support(&mut self, support: crate::themes::ColorSupport) {
+    self.color_downsampler.set_support(support);
+  }
+
+  /// Dither truecolor values when mapping them to a smaller palette
+  pub fn set_color_dithering(&mut self, dithering: bool) {
+    self.color_downsampler.set_dithering(dithering);
+  }
+
+  pub fn color_downsampler(&self) -> &ColorDownsampler {
+    &self.color_downsampler
+  }
+
+  /// Decorations the terminal cannot draw are replaced as they are queued
+  pub fn set_decoration_support(&mut self, support: DecorationSupport) {
+    self.decoration_support = support;
+  }
+
+  pub fn decoration_support(&self) -> DecorationSupport {
+    self.decoration_support
+  }
+
+  /// `style` as written to the terminal in the cell at `(x, y)`
+  fn output_style(&mut self, style: &RenderStyle, x: u16, y: u16) -> RenderStyle {
+    let mut style = self.color_downsampler.map_style(style, x, y);
+    self.decoration_support.degrade(&mut style);
+    style
+  }
+
+  /// Map every cell of a rasterized frame to what the terminal can show
+  fn map_grid(&mut self, grid: &mut CellGrid) {
+    self.color_downsampler.map_grid(grid);
+    if self.decoration_support.is_complete() {
+      return;
     }
+    for y in 0..grid.height {
+      for x in 0..grid.width {
+        if let Some(cell) = grid.get_mut(x, y) {
+          self.decoration_support.degrade(&mut cell.style);
+        }
+      }
+    }
+  }
+
+  /// Whether queued styles reach the terminal unchanged
+  fn writes_styles_unchanged(&self) -> bool {
+    self.color_downsampler.is_passthrough() && self.decoration_support.is_complete()
   }
 
   /// Clear the frame buffer
@@ -303,6 +455,8 @@ impl FrameBuffer {
 
   /// Apply style changes only if they differ from current state
   pub fn apply_style(&mut self, style: &RenderStyle) -> Result<()> {
+    let style = &self.output_style(style, self.cursor_x, self.cursor_y);
+
     // Only apply color if different
     if style.color != self.current_style.color {
       if let Some(color) = &style.color {
@@ -321,13 +475,17 @@ impl FrameBuffer {
       self.queue(SetAttribute(Attribute::Bold))?;
     }
 
+    if style.dim != self.current_style.dim && style.dim {
+      self.queue(SetAttribute(Attribute::Dim))?;
+    }
+
     if style.italic != self.current_style.italic && style.italic {
       self.queue(SetAttribute(Attribute::Italic))?;
     }
 
-    if style.underline != self.current_style.underline && style.underline {
-      self.queue(SetAttribute(Attribute::Underlined))?;
-    }
+    let mut decorations = String::new();
+    Self::push_decoration_sgr(&self.current_style, style, &mut decorations);
+    self.buffer.extend_from_slice(decorations.as_bytes());
 
     self.current_style = style.clone();
     Ok(())
@@ -350,16 +508,43 @@ impl FrameBuffer {
     Ok(())
   }
 
-  /// Flush buffer to a TUI driver for optimal performance
+  /// Flush buffer to a TUI driver as one frame, synchronized when the
+  /// terminal supports it
   pub fn flush_to_driver(&mut self, driver: &mut dyn crate::driver::Driver) -> Result<()> {
     if !self.buffer.is_empty() {
-      driver.write_bytes(&self.buffer)?;
-      driver.flush()?;
+      driver.write_frame(&self.buffer)?;
       self.clear();
     }
     Ok(())
   }
 
+  /// Queue every cell of `grid` as a full frame. The encoder leaves the
+  /// cursor after the last cell it wrote and the default style, so the
+  /// cursor is sent home to keep tracking it.
+  fn queue_grid(&mut self, grid: &CellGrid, encoding: DiffEncoding) -> Result<()> {
+    diff::encode_diff(
+      &CellGrid::new(grid.width, grid.height),
+      grid,
+      encoding,
+      &mut self.buffer,
+    );
+    self.current_style = RenderStyle::default();
+    self.queue(MoveTo(0, 0))?;
+    self.cursor_x = 0;
+    self.cursor_y = 0;
+    Ok(())
+  }
+
+  /// Remove the bytes queued after `start`, resetting the tracked cursor and
+  /// style so the removed part can be rasterized on its own
+  fn split_off(&mut self, start: usize) -> Vec<u8> {
+    let bytes = self.buffer.split_off(start);
+    self.cursor_x = 0;
+    self.cursor_y = 0;
+    self.current_style = RenderStyle::default();
+    bytes
+  }
+
   /// Take the current buffered frame bytes and reset internal state
   pub fn take_bytes(&mut self) -> Vec<u8> {
     if self.buffer.is_empty() {
@@ -393,13 +578,55 @@ impl FrameBuffer {
         Some(c) => { Self::push_bg_sgr(c, out); }
       }
     }
-    // Attributes
-    if next.bold && !prev.bold { out.push_str("\u{1b}[1m"); }
-    if !next.bold && prev.bold { out.push_str("\u{1b}[22m"); }
+    // Attributes; SGR 22 turns off both bold and dim
+    let intensity_off = (prev.bold && !next.bold) || (prev.dim && !next.dim);
+    if intensity_off { out.push_str("\u{1b}[22m"); }
+    if next.bold && (!prev.bold || intensity_off) { out.push_str("\u{1b}[1m"); }
+    if next.dim && (!prev.dim || intensity_off) { out.push_str("\u{1b}[2m"); }
     if next.italic && !prev.italic { out.push_str("\u{1b}[3m"); }
     if !next.italic && prev.italic { out.push_str("\u{1b}[23m"); }
-    if next.underline && !prev.underline { out.push_str("\u{1b}[4m"); }
-    if !next.underline && prev.underline { out.push_str("\u{1b}[24m"); }
+    Self::push_decoration_sgr(prev, next, out);
+  }
+
+  /// SGR for changes to underline, strikethrough, overline and blink
+  fn push_decoration_sgr(prev: &RenderStyle, next: &RenderStyle, out: &mut String) {
+    let underline_changed = next.underline != prev.underline
+      || (next.underline && next.underline_style != prev.underline_style);
+    if underline_changed {
+      match (next.underline, next.underline_style) {
+        (false, _) => out.push_str("\u{1b}[24m"),
+        (true, UnderlineStyle::Single) => out.push_str("\u{1b}[4m"),
+        (true, style) => out.push_str(&format!("\u{1b}[4:{}m", style.sgr_subparameter())),
+      }
+    }
+    if next.underline_color != prev.underline_color {
+      match &next.underline_color {
+        None | Some(SerializableColor::Reset) => out.push_str("\u{1b}[59m"),
+        Some(SerializableColor::Rgb { r, g, b }) => {
+          out.push_str(&format!("\u{1b}[58:2::{r}:{g}:{b}m"))
+        }
+        Some(color) => {
+          if let Some(index) = color.palette_index() {
+            out.push_str(&format!("\u{1b}[58:5:{index}m"));
+          }
+        }
+      }
+    }
+    let toggles = [
+      (prev.blink, next.blink, "\u{1b}[5m", "\u{1b}[25m"),
+      (
+        prev.strikethrough,
+        next.strikethrough,
+        "\u{1b}[9m",
+        "\u{1b}[29m",
+      ),
+      (prev.overline, next.overline, "\u{1b}[53m", "\u{1b}[55m"),
+    ];
+    for (was, is, on, off) in toggles {
+      if was != is {
+        out.push_str(if is { on } else { off });
+      }
+    }
   }
 
   fn push_fg_sgr(c: &SerializableColor, out: &mut String) {
@@ -466,40 +693,44 @@ pub struct Renderer {
   frame_buffer: FrameBuffer,
   /// Optional adaptive FPS manager for intelligent refresh rate management
   fps_manager: Option<AdaptiveFpsManager>,
-  /// Previous frame rows for line-diff rendering (bytes per row)
-  last_diff_rows: Option<Vec<Vec<u8>>>,
+  /// Cells the terminal shows after the last diff frame
+  front_buffer: Option<CellGrid>,
+  /// Grid the next diff frame is rasterized into, kept to reuse its allocation
+  back_buffer: Option<CellGrid>,
   /// Optional interval to force a full repaint during diff mode (defensive reset)
   diff_full_repaint_interval: Option<usize>,
   /// Counter since last full repaint when diff mode is enabled
   diff_frames_since_full: usize,
-  /// Render target selection
+  /// Whether render_diff emits changed cells only
   diff_mode_enabled: bool,
-  /// When enabled, emit minimal ANSI per changed row using grid coalescing; otherwise emit authoritative bytes
-  diff_minimal_ansi_enabled: bool,
-
-  /// In-memory grid for diff mode rasterization
-  grid_for_diff: Option<target::CellGrid>,
+  /// Control sequences the diff encoder may use
+  diff_encoding: DiffEncoding,
 }
 
 impl Renderer {
   pub fn new() -> Result<Self> {
     let (width, height) = crate::compat::terminal::size()
       .map_err(|e| TuiError::render(format!("Failed to get terminal size: {e}")))?;
+    Ok(Self::with_dimensions(width, height))
+  }
 
-    Ok(Self {
+  /// Create a renderer for a screen of the given size, without querying the
+  /// terminal
+  pub fn with_dimensions(width: u16, height: u16) -> Self {
+    Self {
       width,
       height,
       style_cache: HashMap::new(),
       border_set: BorderSet::new(),
       frame_buffer: FrameBuffer::new(),
       fps_manager: None,
-      last_diff_rows: None,
-      diff_full_repaint_interval: Some(300), // default: repaint every 300 diff frames (~5s @60fps)
+      front_buffer: None,
+      back_buffer: None,
+      diff_full_repaint_interval: None,
       diff_frames_since_full: 0,
       diff_mode_enabled: true,
-      diff_minimal_ansi_enabled: false,
-      grid_for_diff: None,
-    })
+      diff_encoding: DiffEncoding::default(),
+    }
   }
 
   /// Create renderer with adaptive FPS management
@@ -534,12 +765,9 @@ impl Renderer {
       fps_manager.record_frame_performance(frame_time, render_time, dropped);
     }
   }
-  /// Enable line-diff rendering. Subsequent frames will use minimal updates without full Clear.
+  /// Enable diff rendering. Frames after the next full repaint only update changed cells.
   pub fn enable_diff_mode(&mut self) {
     self.diff_mode_enabled = true;
-    if self.last_diff_rows.is_none() {
-      self.last_diff_rows = Some(Vec::new());
-    }
   }
 
   /// Configure the interval for forcing a full repaint during diff mode.
@@ -554,26 +782,63 @@ impl Renderer {
     self.width = width;
     self.height = height;
     // Reset diff state to ensure next render is a full repaint
-    self.last_diff_rows = None;
+    self.invalidate();
+    self.back_buffer = None;
     self.diff_frames_since_full = 0;
   }
 
-  /// Disable line-diff rendering and clear diff state.
+  /// Disable diff rendering; every frame repaints the whole screen.
   pub fn disable_diff_mode(&mut self) {
     self.diff_mode_enabled = false;
-    self.last_diff_rows = None;
+    self.invalidate();
+  }
+
+  /// Choose which control sequences diff frames may use. Turn off `repeat`
+  /// for terminals without REP support, such as the Linux console.
+  pub fn set_diff_encoding(&mut self, encoding: DiffEncoding) {
+    self.diff_encoding = encoding;
   }
 
-  /// Enable coalesced minimal ANSI emission during diff mode
-  pub fn enable_diff_minimal_ansi(&mut self) {
-    self.diff_minimal_ansi_enabled = true;
+  /// Map every color written to the terminal to `support`, such as
+  /// [`ColorSupport::Ansi16`](crate::themes::ColorSupport::Ansi16) on the
+  /// Linux console. Defaults to truecolor, which writes colors unchanged.
+  pub fn set_color_support(&mut self, support: crate::themes::ColorSupport) {
+    if self.frame_buffer.color_downsampler.support() != support {
+      self.frame_buffer.set_color_support(support);
+      self.invalidate();
+    }
   }
 
-  /// Disable coalesced minimal ANSI emission during diff mode
-  pub fn disable_diff_minimal_ansi(&mut self) {
-    self.diff_minimal_ansi_enabled = false;
+  /// Use an ordered dither instead of the nearest palette color, which keeps
+  /// gradients smooth on 256 and 16 color terminals
+  pub fn set_color_dithering(&mut self, dithering: bool) {
+    if self.frame_buffer.color_downsampler.dithering() != dithering {
+      self.frame_buffer.set_color_dithering(dithering);
+      self.invalidate();
+    }
   }
 
+  pub fn color_support(&self) -> crate::themes::ColorSupport {
+    self.frame_buffer.color_downsampler.support()
+  }
+
+  /// Replace decorations the terminal cannot draw, such as curly underlines
+  /// on the Linux console. Defaults to writing every decoration.
+  pub fn set_decoration_support(&mut self, support: DecorationSupport) {
+    if self.frame_buffer.decoration_support != support {
+      self.frame_buffer.set_decoration_support(support);
+      self.invalidate();
+    }
+  }
+
+  /// Diff frames are always minimal; kept for compatibility.
+  #[deprecated(note = "diff frames always emit changed cells only")]
+  pub fn enable_diff_minimal_ansi(&mut self) {}
+
+  /// Diff frames are always minimal; kept for compatibility.
+  #[deprecated(note = "diff frames always emit changed cells only")]
+  pub fn disable_diff_minimal_ansi(&mut self) {}
+
   /// Get current target FPS
   pub fn get_target_fps(&self) -> Option<u32> {
     self.fps_manager.as_ref().map(|fps| fps.get_target_fps())
@@ -636,7 +901,7 @@ impl Renderer {
 
     // Render the layout tree recursively into buffer with component tree styles
     let render_start = Instant::now();
-    self.render_layout_with_component_tree(layout, component_tree.root())?;
+    self.render_composited(layout, component_tree.root())?;
     let render_time = render_start.elapsed();
 
     // Queue cursor show
@@ -664,12 +929,8 @@ impl Renderer {
     // Reset frame buffer but do not emit Clear/Hide/Show
     self.frame_buffer.clear();
 
-    // Render layout into buffer; if diff mode, also prepare a grid raster for emission
+    // Render layout into buffer
     let render_start = Instant::now();
-    if self.diff_mode_enabled {
-      // Initialize grid sized to current terminal
-      self.grid_for_diff = Some(target::CellGrid::new(self.width, self.height));
-    }
     self.render_layout_to_buffer(layout, None)?;
     let render_time = render_start.elapsed();
 
@@ -759,11 +1020,12 @@ impl Renderer {
 
     // Render element content (respect clip)
     if let Some(content) = &layout.content {
-      let lines: Vec<&str> = content.lines().collect();
+      let lines = layout_text(content, layout.rect.width, &layout.styles.text);
       for (i, line) in lines.iter().enumerate() {
         let y_pos = layout.rect.y + (i as u16);
         if y_pos < self.height {
-          self.print_clipped_line(&layout.rect, i as u16, line, content_clip)?;
+          let rect = shift_right(layout.rect, line.indent);
+          self.print_clipped_line(&rect, i as u16, &line.text, content_clip)?;
         }
       }
     }
@@ -817,12 +1079,13 @@ impl Renderer {
 
     // Render element content with styles (no overflow in css::ComputedStyles currently)
     if let Some(content) = &layout.content {
-      let lines: Vec<&str> = content.lines().collect();
+      let lines = layout_text(content, layout.rect.width, &css_styles.text_style());
       for (i, line) in lines.iter().enumerate() {
         let y_pos = layout.rect.y + (i as u16);
         if y_pos < self.height {
           self.frame_buffer.apply_style(&render_style)?;
-          self.print_clipped_line(&layout.rect, i as u16, line, None)?;
+          let rect = shift_right(layout.rect, line.indent);
+          self.print_clipped_line(&rect, i as u16, &line.text, None)?;
         }
       }
     }
@@ -841,10 +1104,97 @@ impl Renderer {
     Ok(())
   }
 
+  /// Render `layout` with its component styles; see
+  /// [`Renderer::compose_layers`]. A composed frame is queued cell by cell.
+  fn render_composited(
+    &mut self,
+    layout: &Layout,
+    component_node: &crate::css::ComponentNode,
+  ) -> Result<()> {
+    match self.compose_layers(layout, component_node)? {
+      Some(grid) => self.frame_buffer.queue_grid(&grid, self.diff_encoding),
+      None => Ok(()),
+    }
+  }
+
+  /// Render `layout` with its component styles. Positioned elements and
+  /// those with a `z-index` or an `opacity` below 1.0 are drawn into their
+  /// own layer grid and blended over the rest by a [`Compositor`], in
+  /// stacking context order, into the returned grid. Without any such
+  /// element the layout is queued to the frame buffer as is and `None`
+  /// returned.
+  fn compose_layers(
+    &mut self,
+    layout: &Layout,
+    component_node: &crate::css::ComponentNode,
+  ) -> Result<Option<CellGrid>> {
+    let mut layers = Vec::new();
+    let view = ScrollView::default();
+    stack_layers(None, layout, component_node, view, &mut layers);
+    if layers.len() == 1 {
+      self.render_layout_with_component_tree(layout, component_node, view)?;
+      return Ok(None);
+    }
+
+    let (width, height) = (self.width, self.height);
+    let screen = LayoutRect {
+      x: 0,
+      y: 0,
+      width,
+      height,
+    };
+    let start = self.frame_buffer.buffer.len();
+    let mut compositor = Compositor::new(width, height);
+    // Layers are added in paint order, which their z-index then keeps
+    for (z_index, layer) in (0..).zip(layers) {
+      let layer = match layer {
+        Some((layer_layout, layer_node, layer_view)) => {
+          self.render_layout_with_component_tree(layer_layout, layer_node, layer_view)?;
+          let bytes = self.frame_buffer.split_off(start);
+          let bounds = layer_view.visible(layer_layout.rect).unwrap_or(LayoutRect {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+          });
+          self
+            .layer_from_ansi(bounds, &bytes)
+            .with_opacity(layer_node.styles.opacity)
+        }
+        None => {
+          self.render_layout_with_component_tree(layout, component_node, view)?;
+          let base = self.frame_buffer.split_off(start);
+          self.layer_from_ansi(screen, &base)
+        }
+      };
+      compositor.add_layer(layer.with_z_index(z_index));
+    }
+
+    let mut grid = compositor.compose();
+    self.frame_buffer.map_grid(&mut grid);
+    Ok(Some(grid))
+  }
+
+  /// A screen-sized layer of the cells `bytes` draw within `bounds`. Its
+  /// links are the ones the terminal shows where equal, so the diff
+  /// compares them by pointer.
+  fn layer_from_ansi(&self, bounds: LayoutRect, bytes: &[u8]) -> Layer {
+    let mut layer = Layer::new(self.width, self.height, bounds);
+    if let Some(front) = &self.front_buffer {
+      layer.grid.share_links(front);
+    }
+    layer.grid.rasterize(bytes);
+    layer
+  }
+
+  /// Render `layout` where `view` shows it. The content of a scroll
+  /// container is drawn scrolled and clipped to its scrollport, and its
+  /// scrollbars over everything else in it.
   fn render_layout_with_component_tree(
     &mut self,
     layout: &Layout,
     component_node: &crate::css::ComponentNode,
+    view: ScrollView,
   ) -> Result<()> {
     // Convert CSS styles to render style and apply
     let render_style = component_node.styles.to_render_style();
@@ -852,29 +1202,89 @@ impl Renderer {
 
     // Render background if specified (before content)
     if let Some(bg_color) = component_node.styles.background_color {
-      self.render_background_at(
-        layout.rect.x,
-        layout.rect.y,
-        layout.rect.width,
-        layout.rect.height,
-        bg_color,
-      )?;
+      if let Some(area) = view.visible(layout.rect) {
+        self.render_background_at(area.x, area.y, area.width, area.height, bg_color)?;
+      }
     }
 
-    // Render element content
+    // `<a href>` content is written as a hyperlink where the terminal
+    // supports them
+    let href = component_node
+      .element
+      .attributes
+      .get("href")
+      .filter(|_| component_node.element.tag == "a")
+      .filter(|_| hyperlink::global_hyperlink_support::detect());
+
+    // Render element content; text generated by `::before`/`::after` was laid
+    // out as part of it and is drawn with its own styles. A scroll
+    // container's own text scrolls with the rest of its content.
+    let inner = view.enter(layout);
     if let Some(content) = &layout.content {
-      let lines: Vec<&str> = content.lines().collect();
-      for (line_idx, line) in lines.iter().enumerate() {
-        let y_pos = layout.rect.y + line_idx as u16;
-        if y_pos < layout.rect.y + layout.rect.height {
-          self.print_clipped_line(&layout.rect, line_idx as u16, line, None)?;
+      let (text_view, text_rect) = if layout.styles.overflow.scrolls() {
+        let state = layout.styles.scroll_state;
+        let rect = LayoutRect {
+          width: state.viewport_width,
+          height: state.content_height.max(layout.rect.height),
+          ..layout.rect
+        };
+        (inner, rect)
+      } else {
+        (view, layout.rect)
+      };
+      let lines = layout_text(content, text_rect.width, &component_node.styles.text_style());
+      let last = lines.len().saturating_sub(1);
+      for (line_idx, text_line) in lines.iter().enumerate() {
+        if line_idx >= usize::from(text_rect.height) {
+          continue;
+        }
+
+        let mut line = text_line.text.as_str();
+        let mut column = text_line.indent;
+        let before = component_node
+          .before
+          .as_ref()
+          .filter(|_| line_idx == 0)
+          .and_then(|before| Some((before, line.strip_prefix(before.text.as_str())?)));
+        if let Some((before, rest)) = before {
+          self.print_generated(text_rect, text_view, line_idx, column, before, &render_style)?;
+          column = column.saturating_add(display_width(&before.text) as u16);
+          line = rest;
+        }
+        let after = component_node
+          .after
+          .as_ref()
+          .filter(|_| line_idx == last)
+          .and_then(|after| Some((after, line.strip_suffix(after.text.as_str())?)));
+        if let Some((_, rest)) = after {
+          line = rest;
+        }
+
+        self.switch_style(&render_style)?;
+        match href {
+          Some(url) if !line.contains("\x1b]8;") => {
+            let linked = hyperlink::format_hyperlink(line, url, true);
+            self.print_line_in_view(text_view, text_rect, column, line_idx, &linked)?;
+          }
+          _ => self.print_line_in_view(text_view, text_rect, column, line_idx, line)?,
+        }
+        if let Some((after, _)) = after {
+          column = column.saturating_add(display_width(line) as u16);
+          self.print_generated(text_rect, text_view, line_idx, column, after, &render_style)?;
         }
       }
     }
 
-    // Render children recursively with their own styles
+    // Render children recursively with their own styles; children that
+    // create a layer are drawn by render_composited
     for (child_layout, child_node) in layout.children.iter().zip(component_node.children.iter()) {
-      self.render_layout_with_component_tree(child_layout, child_node)?;
+      if !child_node.styles.creates_layer() {
+        self.render_layout_with_component_tree(child_layout, child_node, inner.place(child_layout))?;
+      }
+    }
+
+    if layout.styles.overflow.scrolls() {
+      self.render_scrollbars(layout, &component_node.styles, view)?;
     }
 
     // Reset styles only if we actually changed something from default
@@ -886,6 +1296,122 @@ impl Renderer {
     Ok(())
   }
 
+  /// Draw the scrollbars of a scroll container in the cells its layout
+  /// left for them, right of and below its scrollport. `auto` scrollbars
+  /// are only drawn while the content overflows.
+  fn render_scrollbars(
+    &mut self,
+    layout: &Layout,
+    styles: &crate::css::ComputedStyles,
+    view: ScrollView,
+  ) -> Result<()> {
+    use crate::layout::{Overflow, ScrollbarWidth};
+
+    let state = layout.styles.scroll_state;
+    let rect = layout.rect;
+    let thin = styles.scrollbar_width == ScrollbarWidth::Thin;
+    let (thumb_style, track_style) = match styles.scrollbar_color {
+      Some(colors) => (
+        RenderStyle {
+          color: Some(colors.thumb.into()),
+          ..RenderStyle::default()
+        },
+        RenderStyle {
+          color: Some(colors.track.into()),
+          ..RenderStyle::default()
+        },
+      ),
+      None => (
+        RenderStyle {
+          color: styles.color.map(Into::into),
+          ..RenderStyle::default()
+        },
+        RenderStyle {
+          color: styles.color.map(Into::into),
+          dim: true,
+          ..RenderStyle::default()
+        },
+      ),
+    };
+
+    // Vertical bar in the column right of the scrollport
+    let overflows = state.content_height > state.viewport_height;
+    if state.viewport_width < rect.width
+      && (overflows || layout.styles.overflow.y == Overflow::Scroll)
+    {
+      let (thumb, track) = if thin { ("┃", "│") } else { ("█", "░") };
+      let (start, length) =
+        scrollbar_thumb(state.viewport_height, state.content_height, state.offset_y);
+      let bar = LayoutRect {
+        x: rect.x + state.viewport_width,
+        y: rect.y,
+        width: 1,
+        height: state.viewport_height,
+      };
+      for row in 0..bar.height {
+        if overflows && row >= start && row < start + length {
+          self.switch_style(&thumb_style)?;
+          self.print_line_in_view(view, bar, 0, usize::from(row), thumb)?;
+        } else {
+          self.switch_style(&track_style)?;
+          self.print_line_in_view(view, bar, 0, usize::from(row), track)?;
+        }
+      }
+    }
+
+    // Horizontal bar in the row below it
+    let overflows = state.content_width > state.viewport_width;
+    if state.viewport_height < rect.height
+      && (overflows || layout.styles.overflow.x == Overflow::Scroll)
+    {
+      let (thumb, track) = if thin { ("━", "─") } else { ("█", "░") };
+      let (start, length) =
+        scrollbar_thumb(state.viewport_width, state.content_width, state.offset_x);
+      let bar = LayoutRect {
+        x: rect.x,
+        y: rect.y + state.viewport_height,
+        width: state.viewport_width,
+        height: 1,
+      };
+      self.switch_style(&track_style)?;
+      self.print_line_in_view(view, bar, 0, 0, &track.repeat(usize::from(bar.width)))?;
+      if overflows {
+        self.switch_style(&thumb_style)?;
+        self.print_line_in_view(view, bar, start, 0, &thumb.repeat(usize::from(length)))?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Draw `::before`/`::after` text at `column` of a content line, over the
+  /// element's background unless the pseudo-element sets its own
+  fn print_generated(
+    &mut self,
+    rect: LayoutRect,
+    view: ScrollView,
+    line_index: usize,
+    column: u16,
+    generated: &crate::css::GeneratedContent,
+    element_style: &RenderStyle,
+  ) -> Result<()> {
+    let mut style = generated.styles.to_render_style();
+    if style.background.is_none() {
+      style.background = element_style.background.clone();
+    }
+    self.switch_style(&style)?;
+    self.print_line_in_view(view, rect, column, line_index, &generated.text)
+  }
+
+  /// Switch to `style`, resetting first since `apply_style` only adds attributes
+  fn switch_style(&mut self, style: &RenderStyle) -> Result<()> {
+    if self.frame_buffer.current_style != *style {
+      self.frame_buffer.queue(SetAttribute(Attribute::Reset))?;
+      self.frame_buffer.current_style = RenderStyle::default();
+      self.frame_buffer.apply_style(style)?;
+    }
+    Ok(())
+  }
+
   fn get_element_style(&self, layout: &Layout) -> Option<RenderStyle> {
     // Check cache first
     if let Some(cached) = self.style_cache.get(&layout.tag) {
@@ -947,8 +1473,7 @@ impl Renderer {
   // Note: apply_style is now handled by FrameBuffer::apply_style for optimization
 
   pub async fn resize(&mut self, width: u16, height: u16) -> Result<()> {
-    self.width = width;
-    self.height = height;
+    self.on_resize(width, height);
     // Clear frame buffer on resize to ensure clean state
     self.frame_buffer.clear();
     Ok(())
@@ -984,21 +1509,10 @@ impl Renderer {
     if max_rows == 0 {
       return Ok(());
     }
-    // Build a render target
     let style = RenderStyle { background: Some(color.into()), ..RenderStyle::default() };
-    if self.diff_mode_enabled {
-      if self.grid_for_diff.is_none() { self.grid_for_diff = Some(target::CellGrid::new(self.width, self.height)); }
-      if let Some(grid) = &mut self.grid_for_diff {
-        let mut target = target::MultiTarget::new(&mut self.frame_buffer, grid);
-        target.apply_style(&style)?;
-        target.fill_background_rect(LayoutRect { x, y, width, height: max_rows }, ' ')?;
-      }
-    } else {
-      // Non-diff path: just use frame buffer via AnsiTarget for consistency
-      let mut ansi = target::AnsiTarget::new(&mut self.frame_buffer);
-      ansi.apply_style(&style)?;
-      ansi.fill_background_rect(LayoutRect { x, y, width, height: max_rows }, ' ')?;
-    }
+    let mut ansi = target::AnsiTarget::new(&mut self.frame_buffer);
+    ansi.apply_style(&style)?;
+    ansi.fill_background_rect(LayoutRect { x, y, width, height: max_rows }, ' ')?;
     Ok(())
   }
 
@@ -1017,47 +1531,23 @@ impl Renderer {
 
     // Build a render target
     let style = RenderStyle { color: Some(color.into()), ..RenderStyle::default() };
-    if self.diff_mode_enabled {
-      if self.grid_for_diff.is_none() { self.grid_for_diff = Some(target::CellGrid::new(self.width, self.height)); }
-      if let Some(grid) = &mut self.grid_for_diff {
-        let mut target = target::MultiTarget::new(&mut self.frame_buffer, grid);
-        target.apply_style(&style)?;
-        // Top border
-        target.move_to(x, y)?;
-        target.print("┌")?;
-        for _ in 1..width - 1 { target.print("─")?; }
-        target.print("┐")?;
-        // Sides
-        for row in 1..height - 1 {
-          target.move_to(x, y + row)?; target.print("│")?;
-          target.move_to(x + width - 1, y + row)?; target.print("│")?;
-        }
-        // Bottom
-        target.move_to(x, y + height - 1)?;
-        target.print("└")?;
-        for _ in 1..width - 1 { target.print("─")?; }
-        target.print("┘")?;
-      }
-    } else {
-      // Non-diff path: draw directly via AnsiTarget for consistency
-      let mut ansi = target::AnsiTarget::new(&mut self.frame_buffer);
-      ansi.apply_style(&style)?;
-      // Top
-      ansi.move_to(x, y)?;
-      ansi.print("┌")?;
-      for _ in 1..width - 1 { ansi.print("─")?; }
-      ansi.print("┐")?;
-      // Sides
-      for row in 1..height - 1 {
-        ansi.move_to(x, y + row)?; ansi.print("│")?;
-        ansi.move_to(x + width - 1, y + row)?; ansi.print("│")?;
-      }
-      // Bottom
-      ansi.move_to(x, y + height - 1)?;
-      ansi.print("└")?;
-      for _ in 1..width - 1 { ansi.print("─")?; }
-      ansi.print("┘")?;
+    let mut ansi = target::AnsiTarget::new(&mut self.frame_buffer);
+    ansi.apply_style(&style)?;
+    // Top
+    ansi.move_to(x, y)?;
+    ansi.print("┌")?;
+    for _ in 1..width - 1 { ansi.print("─")?; }
+    ansi.print("┐")?;
+    // Sides
+    for row in 1..height - 1 {
+      ansi.move_to(x, y + row)?; ansi.print("│")?;
+      ansi.move_to(x + width - 1, y + row)?; ansi.print("│")?;
     }
+    // Bottom
+    ansi.move_to(x, y + height - 1)?;
+    ansi.print("└")?;
+    for _ in 1..width - 1 { ansi.print("─")?; }
+    ansi.print("┘")?;
 
     Ok(())
   }
@@ -1171,12 +1661,12 @@ impl Default for Renderer {
       border_set: BorderSet::new(),
       frame_buffer: FrameBuffer::new(),
       fps_manager: None,
-      last_diff_rows: None,
-      diff_full_repaint_interval: Some(300),
+      front_buffer: None,
+      back_buffer: None,
+      diff_full_repaint_interval: None,
       diff_frames_since_full: 0,
       diff_mode_enabled: true,
-      diff_minimal_ansi_enabled: false,
-      grid_for_diff: None,
+      diff_encoding: DiffEncoding::default(),
     })
   }
 }
@@ -1248,26 +1738,55 @@ impl Renderer {
     // Compute visible slice by display columns (ANSI/grapheme-aware)
     let left_cols = start_x.saturating_sub(rect.x) as usize;
     let visible_cols = end_x.saturating_sub(start_x) as usize;
+    self.print_columns(text, left_cols, visible_cols, start_x, y)
+  }
+
+  /// Print line `line_index` of the text of a box laid out at `rect`,
+  /// starting `column` cells in, where `view` shows the box
+  fn print_line_in_view(
+    &mut self,
+    view: ScrollView,
+    rect: LayoutRect,
+    column: u16,
+    line_index: usize,
+    text: &str,
+  ) -> Result<()> {
+    let Some(clip) = view.visible(rect) else {
+      return Ok(());
+    };
+    let (x, y) = view.origin(rect);
+    let (x, y) = (x + i32::from(column), y + line_index as i32);
+    if y < i32::from(clip.y) || y >= i32::from(clip.y) + i32::from(clip.height) {
+      return Ok(());
+    }
+    let start_x = x.max(i32::from(clip.x));
+    let end_x = i32::from(clip.x) + i32::from(clip.width);
+    if end_x <= start_x {
+      return Ok(());
+    }
+    let left_cols = (start_x - x) as usize;
+    let visible_cols = (end_x - start_x) as usize;
+    self.print_columns(text, left_cols, visible_cols, start_x as u16, y as u16)
+  }
 
-    let (visible, _s, _e) =
+  /// Print the `visible_cols` display columns of `text` after its first
+  /// `left_cols` at `x`, `y`
+  fn print_columns(
+    &mut self,
+    text: &str,
+    left_cols: usize,
+    visible_cols: usize,
+    x: u16,
+    y: u16,
+  ) -> Result<()> {
+    let (visible, start, _e) =
       crate::widgets::input_unicode::visible_slice_by_width(text, left_cols, visible_cols);
 
-    // Use RenderTarget: MultiTarget in diff mode (FrameBuffer + Grid), AnsiTarget otherwise
-    if self.diff_mode_enabled {
-      if self.grid_for_diff.is_none() {
-        self.grid_for_diff = Some(target::CellGrid::new(self.width, self.height));
-      }
-      if let Some(grid) = &mut self.grid_for_diff {
-        // Snapshot style before mutable borrow
-        let cur_style = self.frame_buffer.current_style.clone();
-        let mut multi = target::MultiTarget::new(&mut self.frame_buffer, grid);
-        multi.apply_style(&cur_style)?;
-        multi.move_to(start_x, y)?;
-        multi.print(visible)?;
-      }
-    } else {
-      self.frame_buffer.move_to(start_x, y)?;
-      self.frame_buffer.print(visible)?;
+    self.frame_buffer.move_to(x, y)?;
+    // Clipping must not cut a hyperlink's opening or closing sequence
+    match hyperlink::balance_slice(text, start, visible) {
+      Some(balanced) => self.frame_buffer.print(&balanced)?,
+      None => self.frame_buffer.print(visible)?,
     }
     Ok(())
   }
@@ -0,0 +1,222 @@
+//! One-shot rendering of an element tree to a string
+//!
+//! Runs the same CSS, layout and rendering pipeline as a [`TuiApp`] frame,
+//! but without a driver, alternate screen or event loop, so command line
+//! tools can print styled output and exit. The output is as tall as its
+//! content.
+//!
+//! [`TuiApp`]: crate::app::TuiApp
+
+use super::target::{Cell, CellGrid};
+use super::{FrameBuffer, Hyperlink, RenderStyle, Renderer};
+use crate::components::Element;
+use crate::css::{ComponentTree, CssEngine};
+use crate::error::Result;
+use crate::layout::{text, Layout, LayoutEngine};
+
+/// Height the tree is laid out in before its content height is known
+const MEASURE_HEIGHT: u16 = 4096;
+
+/// Render `element` styled by `css` at `width` columns as text with ANSI
+/// escape sequences, one line per row without a trailing newline
+pub fn render_to_ansi(element: &Element, css: &CssEngine, width: u16) -> Result<String> {
+  let grid = render_to_grid(element, css, width)?;
+  Ok(
+    (0..grid.height)
+      .map(|y| row_ansi(grid.row(y)))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  )
+}
+
+/// Render `element` styled by `css` at `width` columns as plain text,
+/// without escape sequences or trailing blanks
+pub fn render_to_plain(element: &Element, css: &CssEngine, width: u16) -> Result<String> {
+  let grid = render_to_grid(element, css, width)?;
+  Ok(
+    (0..grid.height)
+      .map(|y| grid.row_text(y))
+      .collect::<Vec<_>>()
+      .join("\n"),
+  )
+}
+
+/// Render `element` into a grid `width` columns wide and as tall as its
+/// content
+pub fn render_to_grid(element: &Element, css: &CssEngine, width: u16) -> Result<CellGrid> {
+  let tree = css.create_component_tree(element);
+  let mut layout_engine = LayoutEngine::with_dimensions(width, MEASURE_HEIGHT);
+  let measured = layout_engine.compute_layout_with_component_tree(element, &tree)?;
+  let height = content_height(&measured, &tree).clamp(1, MEASURE_HEIGHT);
+
+  layout_engine.update_dimensions(width, height);
+  let layout = layout_engine.compute_layout_with_component_tree(element, &tree)?;
+
+  let mut renderer = Renderer::with_dimensions(width, height);
+  renderer.frame_buffer.clear();
+  renderer.render_composited(&layout, tree.root())?;
+  let frame = renderer.frame_buffer.take_bytes();
+
+  let mut grid = CellGrid::new(width, height);
+  grid.rasterize(&frame);
+  renderer.frame_buffer.map_grid(&mut grid);
+  Ok(grid)
+}
+
+/// Rows down to the bottom of the lowest laid out box or line of text
+fn content_height(layout: &Layout, tree: &ComponentTree) -> u16 {
+  let root_text = tree.root().inline_content().map_or(0, |content| {
+    text::measure_text_height(
+      &content,
+      layout.rect.width,
+      &tree.root().styles.text_style(),
+    )
+  });
+  let bottom = layout.children.iter().map(lowest_bottom).max().unwrap_or(0);
+  bottom.saturating_sub(layout.rect.y).max(root_text)
+}
+
+/// Content clipped by an element that hides or scrolls its overflow does
+/// not make the output taller
+fn lowest_bottom(layout: &Layout) -> u16 {
+  let bottom = layout.rect.y.saturating_add(layout.rect.height);
+  if layout.styles.overflow.clips() {
+    return bottom;
+  }
+  layout
+    .children
+    .iter()
+    .map(lowest_bottom)
+    .fold(bottom, u16::max)
+}
+
+/// A row of cells as text and SGR sequences, without the blank default
+/// cells at its end
+fn row_ansi(row: &[Cell]) -> String {
+  let blank = Cell::blank();
+  let end = row
+    .iter()
+    .rposition(|cell| *cell != blank)
+    .map_or(0, |x| x + 1);
+
+  let mut out = String::new();
+  let mut style = RenderStyle::default();
+  for cell in &row[..end] {
+    if cell.style != style {
+      FrameBuffer::ansi_sgr_for_style_diff(&style, &cell.style, &mut out);
+      Hyperlink::push_change(
+        style.hyperlink.as_ref(),
+        cell.style.hyperlink.as_ref(),
+        &mut out,
+      );
+      style = cell.style.clone();
+    }
+    out.push_str(&cell.symbol);
+  }
+  if style.hyperlink.take().is_some() {
+    out.push_str(super::hyperlink::CLOSE);
+  }
+  if style != RenderStyle::default() {
+    out.push_str("\x1b[0m");
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_output_is_as_tall_as_its_content() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(".title { color: red; font-weight: bold }")
+      .unwrap();
+    let element = Element::with_tag("div")
+      .child(
+        Element::with_tag("text")
+          .class("title")
+          .content("Report")
+          .build(),
+      )
+      .child(
+        Element::with_tag("text")
+          .content("one two three four")
+          .build(),
+      )
+      .build();
+
+    let plain = render_to_plain(&element, &css, 10).unwrap();
+    assert_eq!(plain, "Report\none two\nthree four");
+
+    let ansi = render_to_ansi(&element, &css, 10).unwrap();
+    assert_eq!(ansi, "\x1b[91m\x1b[1mReport\x1b[0m\none two\nthree four");
+  }
+
+  #[test]
+  fn test_positioned_elements_paint_by_stacking_order() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(
+        ".badge { position: absolute; top: 0; left: 0 }
+         .front { position: absolute; top: 1; left: 0; z-index: 2 }
+         .back { position: absolute; top: 1; left: 0; z-index: 1 }",
+      )
+      .unwrap();
+    let element = Element::with_tag("div")
+      .child(
+        Element::with_tag("text")
+          .class("badge")
+          .content("XY")
+          .build(),
+      )
+      .child(Element::with_tag("text").content("abcdef").build())
+      .child(
+        Element::with_tag("text")
+          .class("front")
+          .content("AA")
+          .build(),
+      )
+      .child(
+        Element::with_tag("text")
+          .class("back")
+          .content("BBBB")
+          .build(),
+      )
+      .build();
+
+    // The badge covers the text after it, and the higher z-index wins
+    // whatever the tree order
+    let plain = render_to_plain(&element, &css, 10).unwrap();
+    assert_eq!(plain, "XYcdef\nAABB");
+  }
+
+  #[test]
+  fn test_scroll_containers_clip_their_content_and_draw_a_scrollbar() {
+    let mut css = CssEngine::new();
+    css
+      .load_stylesheet(
+        ".list { height: 3; overflow-y: auto; scrollbar-width: thin }
+         .auto { height: 1; overflow: auto }",
+      )
+      .unwrap();
+    let mut list = Element::with_tag("div").class("list");
+    for line in ["a", "b", "c", "d", "e", "f"] {
+      list = list.child(Element::with_tag("text").content(line).build());
+    }
+    let element = Element::with_tag("div")
+      .child(list.build())
+      .child(
+        Element::with_tag("div")
+          .class("auto")
+          .child(Element::with_tag("text").content("fits").build())
+          .build(),
+      )
+      .build();
+
+    // Only the scrollport's rows show, next to a bar with its thumb at the
+    // top; content that fits gets no bar
+    let plain = render_to_plain(&element, &css, 6).unwrap();
+    assert_eq!(plain, "a    ┃\nb    │\nc    │\nfits");
+  }
+}
@@ -1,8 +1,11 @@
 #![allow(dead_code)]
 
 use crate::error::Result;
+use crate::layout::text::display_width;
 use crate::layout::LayoutRect;
-use crate::rendering::{FrameBuffer, RenderStyle};
+use crate::rendering::{FrameBuffer, Hyperlink, RenderStyle, SerializableColor, UnderlineStyle};
+use std::sync::Arc;
+use unicode_segmentation::UnicodeSegmentation;
 
 pub trait RenderTarget {
   fn apply_style(&mut self, style: &RenderStyle) -> Result<()>;
@@ -43,40 +46,508 @@ impl<'a> RenderTarget for AnsiTarget<'a> {
   }
 }
 
+/// One terminal cell
 #[derive(Clone, Debug, PartialEq)]
 pub struct Cell {
-  pub x: u16,
-  pub ch: String, // preserve grapheme/ANSI sequences
+  /// Grapheme shown in the cell; empty for the cell covered by the right
+  /// half of a wide character
+  pub symbol: String,
   pub style: RenderStyle,
 }
 
+impl Cell {
+  /// A space with the terminal's default colors
+  pub fn blank() -> Self {
+    Self::new(" ", RenderStyle::default())
+  }
+
+  /// A cell showing `symbol`. Spaces only keep the attributes that are
+  /// visible on them, so blanks compare equal however they were drawn.
+  pub fn new(symbol: &str, style: RenderStyle) -> Self {
+    let style = if symbol == " " {
+      RenderStyle {
+        background: style.background,
+        underline: style.underline,
+        underline_style: style.underline_style,
+        underline_color: style.underline_color,
+        strikethrough: style.strikethrough,
+        overline: style.overline,
+        hyperlink: style.hyperlink,
+        ..RenderStyle::default()
+      }
+    } else {
+      style
+    };
+    Self {
+      symbol: symbol.to_string(),
+      style,
+    }
+  }
+
+  /// Whether the cell is the right half of a wide character
+  pub fn is_continuation(&self) -> bool {
+    self.symbol.is_empty()
+  }
+
+  /// Whether erasing (ECH, EL) with the cell's background as the current
+  /// color produces this cell
+  pub fn is_erased(&self) -> bool {
+    self.symbol == " "
+      && !self.style.underline
+      && !self.style.strikethrough
+      && !self.style.overline
+      && self.style.hyperlink.is_none()
+  }
+}
+
+/// A screen-sized raster of cells, as the terminal would show it
 #[derive(Clone, Debug)]
 pub struct CellGrid {
   pub width: u16,
   pub height: u16,
-  pub rows: Vec<Vec<Cell>>, // rows of cells with style
+  cells: Vec<Cell>,
+  /// Links rasterized since the last clear; cells of equal links share
+  /// one, so comparing them is a pointer check
+  links: Vec<Arc<Hyperlink>>,
+  /// Another grid's links, reused when an equal one is rasterized
+  shared_links: Vec<Arc<Hyperlink>>,
+}
+
+impl PartialEq for CellGrid {
+  fn eq(&self, other: &Self) -> bool {
+    self.width == other.width && self.height == other.height && self.cells == other.cells
+  }
 }
 
 impl CellGrid {
   pub fn new(width: u16, height: u16) -> Self {
-    let mut rows = Vec::with_capacity(height as usize);
-    for _ in 0..height {
-      rows.push(Vec::new());
-    }
     Self {
       width,
       height,
-      rows,
+      cells: vec![Cell::blank(); width as usize * height as usize],
+      links: Vec::new(),
+      shared_links: Vec::new(),
     }
   }
 
+  /// Reset every cell to a blank
   pub fn clear(&mut self) {
-    for row in &mut self.rows {
-      row.clear();
+    self.cells.fill(Cell::blank());
+    self.links.clear();
+    self.shared_links.clear();
+  }
+
+  /// Reuse `other`'s links for equal ones rasterized into this grid until
+  /// the next clear, so cells of the two grids compare by pointer
+  pub fn share_links(&mut self, other: &CellGrid) {
+    self.shared_links.clone_from(&other.links);
+  }
+
+  /// Keep `other`'s links as this grid's own, for a grid made from its
+  /// cells rather than rasterized
+  pub(crate) fn adopt_links(&mut self, other: &CellGrid) {
+    for link in &other.links {
+      if !self.links.iter().any(|own| Arc::ptr_eq(own, link)) {
+        self.links.push(link.clone());
+      }
+    }
+  }
+
+  /// The grid's shared copy of `link`
+  fn intern_link(&mut self, link: Hyperlink) -> Arc<Hyperlink> {
+    if let Some(own) = self.links.iter().find(|own| ***own == link) {
+      return own.clone();
+    }
+    let shared = match self.shared_links.iter().find(|shared| ***shared == link) {
+      Some(shared) => shared.clone(),
+      None => Arc::new(link),
+    };
+    self.links.push(shared.clone());
+    shared
+  }
+
+  pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+    if x < self.width && y < self.height {
+      self.cells.get(self.index(x, y))
+    } else {
+      None
+    }
+  }
+
+  pub(crate) fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+    if x < self.width && y < self.height {
+      let index = self.index(x, y);
+      self.cells.get_mut(index)
+    } else {
+      None
+    }
+  }
+
+  /// The cells of row `y`
+  pub fn row(&self, y: u16) -> &[Cell] {
+    let start = y as usize * self.width as usize;
+    &self.cells[start..start + self.width as usize]
+  }
+
+  /// The text of row `y` with trailing blanks removed
+  pub fn row_text(&self, y: u16) -> String {
+    let text: String = self
+      .row(y)
+      .iter()
+      .map(|cell| cell.symbol.as_str())
+      .collect();
+    text.trim_end().to_string()
+  }
+
+  fn index(&self, x: u16, y: u16) -> usize {
+    y as usize * self.width as usize + x as usize
+  }
+
+  /// Store `cell` at `(x, y)`, blanking the other half of any wide
+  /// character it overwrites
+  pub(crate) fn set(&mut self, x: u16, y: u16, cell: Cell) {
+    if x >= self.width || y >= self.height {
+      return;
+    }
+    let index = self.index(x, y);
+    if self.cells[index].is_continuation() && x > 0 {
+      let lead = &mut self.cells[index - 1];
+      *lead = Cell::new(" ", lead.style.clone());
+    }
+    if x + 1 < self.width && self.cells[index + 1].is_continuation() {
+      let continuation = &mut self.cells[index + 1];
+      *continuation = Cell::new(" ", continuation.style.clone());
+    }
+    self.cells[index] = cell;
+  }
+
+  /// Draw `text` starting at `(x, y)`, clipped to the row; returns the
+  /// column after the last grapheme drawn
+  pub fn put_str(&mut self, x: u16, y: u16, text: &str, style: &RenderStyle) -> u16 {
+    let mut x = x;
+    for grapheme in text.graphemes(true) {
+      let width = display_width(grapheme) as u16;
+      if width == 0 {
+        continue;
+      }
+      if x.saturating_add(width) > self.width {
+        // A wide character that does not fit leaves its first column blank
+        if x < self.width {
+          self.set(x, y, Cell::new(" ", style.clone()));
+        }
+        return self.width;
+      }
+      self.set(x, y, Cell::new(grapheme, style.clone()));
+      for offset in 1..width {
+        self.set(x + offset, y, Cell::new("", style.clone()));
+      }
+      x += width;
+    }
+    x
+  }
+
+  /// Erase `count` cells from `(x, y)` the way ECH and EL do, leaving
+  /// blanks in the current background color
+  pub fn erase(&mut self, x: u16, y: u16, count: u16, style: &RenderStyle) {
+    let blank = RenderStyle {
+      background: style.background.clone(),
+      ..RenderStyle::default()
+    };
+    let end = x.saturating_add(count).min(self.width);
+    for column in x..end {
+      self.set(column, y, Cell::new(" ", blank.clone()));
+    }
+  }
+
+  /// Fill `rect` with spaces in `style`
+  pub fn fill(&mut self, rect: LayoutRect, style: &RenderStyle) {
+    let y_end = rect.y.saturating_add(rect.height).min(self.height);
+    let x_end = rect.x.saturating_add(rect.width).min(self.width);
+    for y in rect.y..y_end {
+      for x in rect.x..x_end {
+        self.set(x, y, Cell::new(" ", style.clone()));
+      }
+    }
+  }
+
+  /// Apply a stream of terminal output to the grid: text, cursor moves
+  /// (CUP, CUF), SGR, erases (ED, EL, ECH) and REP. Other control
+  /// sequences are skipped.
+  pub fn rasterize(&mut self, bytes: &[u8]) {
+    let mut state = RasterState::default();
+    let mut i = 0;
+    while i < bytes.len() {
+      match bytes[i] {
+        0x1b => i = self.escape(bytes, i, &mut state),
+        b'\r' => {
+          state.x = 0;
+          i += 1;
+        }
+        b'\n' => {
+          state.y = state.y.saturating_add(1);
+          i += 1;
+        }
+        byte if byte < 0x20 => i += 1,
+        _ => {
+          let end = bytes[i..]
+            .iter()
+            .position(|&byte| byte < 0x20)
+            .map_or(bytes.len(), |offset| i + offset);
+          let text = String::from_utf8_lossy(&bytes[i..end]);
+          if let Some(last) = text.graphemes(true).next_back() {
+            state.last = Some(last.to_string());
+          }
+          state.x = self.put_str(state.x, state.y, &text, &state.style);
+          i = end;
+        }
+      }
+    }
+  }
+
+  /// Interpret the escape sequence starting at `start`; returns the index
+  /// after it
+  fn escape(&mut self, bytes: &[u8], start: usize, state: &mut RasterState) -> usize {
+    match bytes.get(start + 1) {
+      Some(b'[') => {}
+      // OSC strings end with BEL or ST; only hyperlinks change the pen
+      Some(b']') => {
+        let mut i = start + 2;
+        let (end, next) = loop {
+          match bytes.get(i) {
+            None => break (i, i),
+            Some(0x07) => break (i, i + 1),
+            Some(0x1b) if bytes.get(i + 1) == Some(&b'\\') => break (i, i + 2),
+            Some(_) => i += 1,
+          }
+        };
+        if let Some(link) = Hyperlink::parse_osc(&String::from_utf8_lossy(&bytes[start + 2..end])) {
+          state.style.hyperlink = link.map(|link| self.intern_link(link));
+        }
+        return next;
+      }
+      Some(_) => return start + 2,
+      None => return start + 1,
+    }
+
+    let mut i = start + 2;
+    let private = bytes
+      .get(i)
+      .is_some_and(|&byte| matches!(byte, b'?' | b'>' | b'<' | b'='));
+    // Parameters separated by `;`, each with its `:` subparameters
+    let mut groups: Vec<Vec<u16>> = Vec::new();
+    let mut group: Vec<u16> = Vec::new();
+    let mut current: Option<u16> = None;
+    let mut final_byte = 0;
+    while i < bytes.len() {
+      let byte = bytes[i];
+      i += 1;
+      match byte {
+        b'0'..=b'9' => {
+          current = Some(
+            current
+              .unwrap_or(0)
+              .saturating_mul(10)
+              .saturating_add((byte - b'0') as u16),
+          )
+        }
+        b':' => group.push(current.take().unwrap_or(0)),
+        b';' => {
+          group.push(current.take().unwrap_or(0));
+          groups.push(std::mem::take(&mut group));
+        }
+        0x40..=0x7e => {
+          final_byte = byte;
+          break;
+        }
+        _ => {}
+      }
+    }
+    if current.is_some() || !group.is_empty() {
+      group.push(current.unwrap_or(0));
+      groups.push(group);
+    }
+    if private {
+      return i;
+    }
+    let params: Vec<u16> = groups.iter().map(|group| group[0]).collect();
+
+    let first = |default: u16| {
+      params
+        .first()
+        .copied()
+        .filter(|&n| n > 0)
+        .unwrap_or(default)
+    };
+    match final_byte {
+      b'H' | b'f' => {
+        state.y = first(1) - 1;
+        state.x = params.get(1).copied().filter(|&n| n > 0).unwrap_or(1) - 1;
+      }
+      b'C' => {
+        state.x = state
+          .x
+          .saturating_add(first(1))
+          .min(self.width.saturating_sub(1))
+      }
+      b'D' => state.x = state.x.saturating_sub(first(1)),
+      b'A' => state.y = state.y.saturating_sub(first(1)),
+      b'B' => state.y = state.y.saturating_add(first(1)),
+      b'G' => state.x = first(1) - 1,
+      b'm' => apply_sgr(&mut state.style, &groups),
+      b'J' if params.first() == Some(&2) => {
+        for y in 0..self.height {
+          self.erase(0, y, self.width, &state.style);
+        }
+      }
+      b'K' => match params.first().copied().unwrap_or(0) {
+        0 => self.erase(state.x, state.y, self.width, &state.style),
+        1 => self.erase(0, state.y, state.x.saturating_add(1), &state.style),
+        _ => self.erase(0, state.y, self.width, &state.style),
+      },
+      b'X' => self.erase(state.x, state.y, first(1), &state.style),
+      b'b' => {
+        if let Some(last) = state.last.clone() {
+          let repeated = last.repeat(first(1) as usize);
+          state.x = self.put_str(state.x, state.y, &repeated, &state.style);
+        }
+      }
+      _ => {}
+    }
+    i
+  }
+}
+
+/// Cursor, pen and last printed grapheme while rasterizing output
+#[derive(Default)]
+struct RasterState {
+  x: u16,
+  y: u16,
+  style: RenderStyle,
+  last: Option<String>,
+}
+
+/// Update `style` with the SGR parameters `groups`, each holding a
+/// parameter followed by its `:` subparameters. A reset leaves the open
+/// hyperlink, which only OSC 8 ends.
+fn apply_sgr(style: &mut RenderStyle, groups: &[Vec<u16>]) {
+  let reset = |style: &mut RenderStyle| {
+    *style = RenderStyle {
+      hyperlink: style.hyperlink.take(),
+      ..RenderStyle::default()
+    }
+  };
+  if groups.is_empty() {
+    reset(style);
+    return;
+  }
+  let mut i = 0;
+  while i < groups.len() {
+    let group = &groups[i];
+    match group[0] {
+      0 => reset(style),
+      1 => style.bold = true,
+      2 => style.dim = true,
+      3 => style.italic = true,
+      4 => {
+        style.underline = group.get(1) != Some(&0);
+        style.underline_style = match group.get(1) {
+          Some(2) => UnderlineStyle::Double,
+          Some(3) => UnderlineStyle::Curly,
+          Some(4) => UnderlineStyle::Dotted,
+          Some(5) => UnderlineStyle::Dashed,
+          _ => UnderlineStyle::Single,
+        };
+      }
+      5 | 6 => style.blink = true,
+      9 => style.strikethrough = true,
+      21 => {
+        style.underline = true;
+        style.underline_style = UnderlineStyle::Double;
+      }
+      22 => {
+        style.bold = false;
+        style.dim = false;
+      }
+      23 => style.italic = false,
+      24 => style.underline = false,
+      25 => style.blink = false,
+      29 => style.strikethrough = false,
+      53 => style.overline = true,
+      55 => style.overline = false,
+      code @ 30..=37 => style.color = Some(indexed_color((code - 30) as u8)),
+      code @ 90..=97 => style.color = Some(indexed_color((code - 90 + 8) as u8)),
+      code @ 40..=47 => style.background = Some(indexed_color((code - 40) as u8)),
+      code @ 100..=107 => style.background = Some(indexed_color((code - 100 + 8) as u8)),
+      39 => style.color = None,
+      49 => style.background = None,
+      59 => style.underline_color = None,
+      code @ (38 | 48 | 58) => {
+        // `38:2::r:g:b` keeps the color in subparameters, `38;2;r;g;b` in
+        // the parameters that follow
+        let color = if group.len() > 1 {
+          extended_color(&group[1..]).0
+        } else {
+          let values: Vec<u16> = groups[i + 1..].iter().take(4).map(|g| g[0]).collect();
+          let (color, consumed) = extended_color(&values);
+          i += consumed;
+          color
+        };
+        match code {
+          38 => style.color = color,
+          48 => style.background = color,
+          _ => style.underline_color = color,
+        }
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+}
+
+/// The color selected by the values after 38, 48 or 58 and how many values
+/// it used
+fn extended_color(values: &[u16]) -> (Option<SerializableColor>, usize) {
+  let value = |index: usize| values.get(index).copied().unwrap_or(0);
+  match values.first() {
+    Some(5) => (Some(indexed_color(value(1).min(255) as u8)), 2),
+    Some(2) => {
+      // Subparameters may carry a color space id before the channels
+      let offset = if values.len() >= 5 { 2 } else { 1 };
+      let color = SerializableColor::Rgb {
+        r: value(offset) as u8,
+        g: value(offset + 1) as u8,
+        b: value(offset + 2) as u8,
+      };
+      (Some(color), 4)
     }
+    _ => (None, 0),
   }
 }
 
+/// The color for palette index `index`; the first 16 are the named colors
+fn indexed_color(index: u8) -> SerializableColor {
+  match index {
+    0 => SerializableColor::Black,
+    1 => SerializableColor::DarkRed,
+    2 => SerializableColor::DarkGreen,
+    3 => SerializableColor::DarkYellow,
+    4 => SerializableColor::DarkBlue,
+    5 => SerializableColor::DarkMagenta,
+    6 => SerializableColor::DarkCyan,
+    7 => SerializableColor::Grey,
+    8 => SerializableColor::DarkGrey,
+    9 => SerializableColor::Red,
+    10 => SerializableColor::Green,
+    11 => SerializableColor::Yellow,
+    12 => SerializableColor::Blue,
+    13 => SerializableColor::Magenta,
+    14 => SerializableColor::Cyan,
+    15 => SerializableColor::White,
+    n => SerializableColor::AnsiValue(n),
+  }
+}
+
+/// Draws straight into a [`CellGrid`]
 pub struct GridTarget<'a> {
   pub grid: &'a mut CellGrid,
   current_style: RenderStyle,
@@ -106,71 +577,149 @@ impl<'a> RenderTarget for GridTarget<'a> {
     Ok(())
   }
   fn print(&mut self, text: &str) -> Result<()> {
-    // Append as a single segment to the addressed row
-    let y = self.cur_y as usize;
-    if self.grid.rows.len() <= y {
-      self.grid.rows.resize_with(y + 1, Vec::new);
-    }
-    self.grid.rows[y].push(Cell {
-      x: self.cur_x,
-      ch: text.to_string(),
-      style: self.current_style.clone(),
-    });
+    self.cur_x = self
+      .grid
+      .put_str(self.cur_x, self.cur_y, text, &self.current_style);
     Ok(())
   }
-  fn fill_background_rect(&mut self, _rect: LayoutRect, _ch: char) -> Result<()> {
+  fn fill_background_rect(&mut self, rect: LayoutRect, ch: char) -> Result<()> {
+    let row = ch.to_string().repeat(rect.width as usize);
+    for y in rect.y..rect.y.saturating_add(rect.height) {
+      self.grid.put_str(rect.x, y, &row, &self.current_style);
+    }
     Ok(())
   }
 }
 
-impl<'a> GridTarget<'a> {
-  pub fn fill_background_rect_rows(&mut self, rect: LayoutRect, ch: char) {
-    let y_end = rect.y.saturating_add(rect.height);
-    let row_str = ch.to_string().repeat(rect.width as usize);
-    for y in rect.y..y_end {
-      let row_idx = y as usize;
-      if self.grid.rows.len() <= row_idx {
-        self.grid.rows.resize_with(row_idx + 1, Vec::new);
-      }
-      self.grid.rows[row_idx].push(Cell {
-        x: rect.x,
-        ch: row_str.clone(),
-        style: self.current_style.clone(),
-      });
-    }
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_rasterize_frame_buffer_output() {
+    let mut fb = FrameBuffer::new();
+    let style = RenderStyle {
+      color: Some(SerializableColor::Red),
+      bold: true,
+      ..RenderStyle::default()
+    };
+    fb.move_to(2, 1).unwrap();
+    fb.apply_style(&style).unwrap();
+    fb.print("hi").unwrap();
+
+    let mut grid = CellGrid::new(10, 3);
+    grid.rasterize(&fb.take_bytes());
+    assert_eq!(grid.row_text(1), "  hi");
+    assert_eq!(grid.get(2, 1).unwrap().style, style);
+    assert_eq!(grid.get(4, 1), Some(&Cell::blank()));
   }
-}
 
-pub struct MultiTarget<'a> {
-  pub ansi: AnsiTarget<'a>,
-  pub grid: GridTarget<'a>,
-}
+  #[test]
+  fn test_wide_characters_use_continuation_cells() {
+    let mut grid = CellGrid::new(6, 1);
+    assert_eq!(grid.put_str(0, 0, "中文", &RenderStyle::default()), 4);
+    assert!(grid.get(1, 0).unwrap().is_continuation());
+    // Overwriting half of a wide character blanks the other half
+    grid.put_str(1, 0, "x", &RenderStyle::default());
+    assert_eq!(grid.row_text(0), " x文");
+  }
 
-impl<'a> MultiTarget<'a> {
-  pub fn new(fb: &'a mut FrameBuffer, grid: &'a mut CellGrid) -> Self {
-    Self {
-      ansi: AnsiTarget::new(fb),
-      grid: GridTarget::new(grid),
-    }
+  #[test]
+  fn test_rasterize_repeat_and_erase() {
+    let mut grid = CellGrid::new(10, 1);
+    grid.rasterize(b"abcdefghij\x1b[1;3H\x1b[3X\x1b[1;8H\x1b[K\x1b[1;1H-\x1b[1b");
+    assert_eq!(grid.row_text(0), "--   fg");
   }
-}
 
-impl<'a> RenderTarget for MultiTarget<'a> {
-  fn apply_style(&mut self, style: &RenderStyle) -> Result<()> {
-    self.ansi.apply_style(style)?;
-    self.grid.apply_style(style)
+  #[test]
+  fn test_decorations_round_trip_through_sgr() {
+    let style = RenderStyle {
+      underline: true,
+      underline_style: UnderlineStyle::Curly,
+      underline_color: Some(SerializableColor::Rgb { r: 255, g: 0, b: 0 }),
+      strikethrough: true,
+      overline: true,
+      dim: true,
+      ..RenderStyle::default()
+    };
+    let mut fb = FrameBuffer::new();
+    fb.apply_style(&style).unwrap();
+    fb.print("err").unwrap();
+
+    let mut grid = CellGrid::new(10, 1);
+    grid.rasterize(&fb.take_bytes());
+    assert_eq!(grid.get(0, 0).unwrap().style, style);
+
+    // Subparameters belong to their parameter: `4:3` is not italic, and the
+    // semicolon form of 58 consumes its color values
+    grid.rasterize(b"\x1b[1;1H\x1b[0;4:3;58;5;196mx");
+    let cell = grid.get(0, 0).unwrap();
+    assert!(cell.style.underline && !cell.style.italic);
+    assert_eq!(
+      cell.style.underline_color,
+      Some(SerializableColor::AnsiValue(196))
+    );
+    assert!(!cell.style.bold);
   }
-  fn move_to(&mut self, x: u16, y: u16) -> Result<()> {
-    self.ansi.move_to(x, y)?;
-    self.grid.move_to(x, y)
+
+  #[test]
+  fn test_queued_grid_leaves_the_cursor_where_it_is_tracked() {
+    let mut cells = CellGrid::new(6, 2);
+    cells.put_str(2, 1, "abcd", &RenderStyle::default());
+    let mut fb = FrameBuffer::new();
+    fb.queue_grid(&cells, crate::rendering::DiffEncoding::default())
+      .unwrap();
+    fb.move_to(3, 1).unwrap();
+    fb.print("x").unwrap();
+    fb.move_to(0, 0).unwrap();
+    fb.print("y").unwrap();
+
+    let mut grid = CellGrid::new(6, 2);
+    grid.rasterize(&fb.take_bytes());
+    assert_eq!(grid.row_text(0), "y");
+    assert_eq!(grid.row_text(1), "  axcd");
   }
-  fn print(&mut self, text: &str) -> Result<()> {
-    self.ansi.print(text)?;
-    self.grid.print(text)
+
+  #[test]
+  fn test_hyperlinks_mark_cells_and_survive_resets() {
+    let link = Hyperlink::new("https://example.com");
+    let frame = format!(
+      "{}a\x1b[0mb c{}d",
+      link.open_sequence(),
+      crate::rendering::hyperlink::CLOSE
+    );
+    let mut grid = CellGrid::new(5, 1);
+    grid.rasterize(frame.as_bytes());
+    for x in 0..4 {
+      assert_eq!(
+        grid.get(x, 0).unwrap().style.hyperlink.as_deref(),
+        Some(&link)
+      );
+    }
+    assert!(!grid.get(2, 0).unwrap().is_erased());
+    assert_eq!(grid.get(4, 0).unwrap().style.hyperlink, None);
   }
-  fn fill_background_rect(&mut self, rect: LayoutRect, ch: char) -> Result<()> {
-    self.ansi.fill_background_rect(rect, ch)?;
-    self.grid.fill_background_rect_rows(rect, ch);
-    Ok(())
+
+  #[test]
+  fn test_equal_links_share_one_allocation() {
+    let frame = format!(
+      "{}ab{}",
+      Hyperlink::new("https://example.com").open_sequence(),
+      crate::rendering::hyperlink::CLOSE
+    );
+    let mut front = CellGrid::new(3, 1);
+    front.rasterize(frame.as_bytes());
+    let mut back = CellGrid::new(3, 1);
+    back.share_links(&front);
+    back.rasterize(frame.as_bytes());
+    let link = |grid: &CellGrid, x| grid.get(x, 0).unwrap().style.hyperlink.clone().unwrap();
+    assert!(Arc::ptr_eq(&link(&front, 0), &link(&front, 1)));
+    assert!(Arc::ptr_eq(&link(&front, 0), &link(&back, 0)));
+
+    // Links no longer shown are dropped on clear
+    let shared = link(&back, 0);
+    front.clear();
+    back.clear();
+    assert_eq!(Arc::strong_count(&shared), 1);
   }
 }
@@ -865,6 +865,8 @@ impl Accordion {
       attributes: std::collections::HashMap::new(),
       focusable: false,
       focused: false,
+      hovered: false,
+      active: false,
       disabled: false,
       tab_index: None,
       key_bindings: Vec::new(),
@@ -1146,6 +1146,8 @@ impl Animation {
       attributes: HashMap::new(),
       focusable: false,
       focused: false,
+      hovered: false,
+      active: false,
       disabled: false,
       tab_index: None,
       key_bindings: Vec::new(),
@@ -852,6 +852,8 @@ impl Autocomplete {
       attributes: std::collections::HashMap::new(),
       focusable: !self.is_disabled(),
       focused: self.is_focused(),
+      hovered: false,
+      active: false,
       disabled: self.is_disabled(),
       tab_index: Some(0),
       key_bindings: Vec::new(),
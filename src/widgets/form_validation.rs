@@ -1181,6 +1181,8 @@ impl FormValidator {
       attributes: std::collections::HashMap::new(),
       focusable: !self.is_disabled(),
       focused: false,
+      hovered: false,
+      active: false,
       disabled: self.is_disabled(),
       tab_index: Some(0),
       key_bindings: Vec::new(),
@@ -59,7 +59,9 @@
 
 use crate::components::Element;
 use crate::error::{Result, TuiError};
+use crate::layout::text::{display_width, truncate_to_display_width};
 use crate::layout::LayoutRect;
+use crate::rendering::hyperlink::{format_hyperlink, global_hyperlink_support};
 use crate::themes::ColorTheme;
 use crate::widgets::ResponsiveWidget;
 
@@ -236,6 +238,34 @@ impl LinkWidget {
       || (cfg!(windows) && self.config.url.len() > 2 && self.config.url.chars().nth(1) == Some(':'))
   }
 
+  /// URI a terminal opens when the link text is clicked; links that run a
+  /// command have none
+  pub fn hyperlink_uri(&self) -> Option<String> {
+    let url = &self.config.url;
+    if url.is_empty() || self.config.disabled || self.config.target == LinkTarget::Command {
+      return None;
+    }
+    if self.is_email() && !url.starts_with("mailto:") {
+      return Some(format!("mailto:{url}"));
+    }
+    if self.is_file_path() && !url.starts_with("file://") {
+      let path = std::path::absolute(url).ok()?;
+      return Some(format!("file://{}", path.display()));
+    }
+    Some(url.clone())
+  }
+
+  /// Display text, followed by the URL where the terminal cannot show it
+  /// as a hyperlink
+  fn get_label(&self) -> String {
+    let text = self.get_display_text();
+    if self.hyperlink_uri().is_some() && !global_hyperlink_support::detect() {
+      format_hyperlink(&text, &self.config.url, false)
+    } else {
+      text
+    }
+  }
+
   /// Validate the URL format
   pub fn validate_url(&self) -> Result<()> {
     if self.config.url.is_empty() {
@@ -458,7 +488,7 @@ impl ResponsiveWidget for LinkWidget {
       .classes(self.get_css_classes())
       .attr("href", &self.config.url)
       .attr("target", format!("{:?}", self.config.target).to_lowercase())
-      .content(self.get_display_text())
+      .content(self.get_label())
       .focusable(!self.config.disabled);
 
     if let Some(alt_text) = &self.config.alt_text {
@@ -482,15 +512,28 @@ impl ResponsiveWidget for LinkWidget {
   }
 
   fn render_with_layout(&self, layout: &LayoutRect, _theme: Option<&ColorTheme>) -> String {
-    let text = self.get_display_text();
+    let mut text = self.get_label();
     let available_width = layout.width as usize;
 
+    // Truncate before adding escape sequences so none are cut
+    if display_width(&text) > available_width {
+      text = format!(
+        "{}…",
+        truncate_to_display_width(&text, available_width.saturating_sub(1))
+      );
+    }
+    if let Some(uri) = self.hyperlink_uri() {
+      if global_hyperlink_support::detect() {
+        text = format_hyperlink(&text, &uri, true);
+      }
+    }
+
     // Apply decoration based on link decoration setting
     let decorated_text = match self.config.decoration {
       LinkDecoration::None => text,
       LinkDecoration::Underline => format!("\x1b[4m{text}\x1b[24m"),
-      LinkDecoration::Dotted => format!("\x1b[4:3m{text}\x1b[24m"),
-      LinkDecoration::Dashed => format!("\x1b[4:2m{text}\x1b[24m"),
+      LinkDecoration::Dotted => format!("\x1b[4:4m{text}\x1b[24m"),
+      LinkDecoration::Dashed => format!("\x1b[4:5m{text}\x1b[24m"),
       LinkDecoration::Double => format!("\x1b[4:2m{text}\x1b[24m"),
       LinkDecoration::Overline => format!("\x1b[53m{text}\x1b[55m"),
       LinkDecoration::StrikeThrough => format!("\x1b[9m{text}\x1b[29m"),
@@ -506,12 +549,7 @@ impl ResponsiveWidget for LinkWidget {
       LinkState::Disabled => format!("\x1b[2;37m{decorated_text}\x1b[22;39m"), // Dim gray
     };
 
-    // Truncate if needed
-    if styled_text.len() > available_width {
-      format!("{}…", &styled_text[..available_width.saturating_sub(1)])
-    } else {
-      styled_text
-    }
+    styled_text
   }
 
   fn min_size(&self) -> (u16, u16) {
@@ -768,6 +806,32 @@ mod tests {
     assert!(file_link.is_file_path());
   }
 
+  #[test]
+  fn test_hyperlink_uri() {
+    let link = |url: &str, target: LinkTarget| {
+      LinkWidget::new(
+        "link",
+        LinkConfig {
+          url: url.to_string(),
+          target,
+          ..LinkConfig::default()
+        },
+      )
+      .hyperlink_uri()
+    };
+    assert_eq!(
+      link("https://example.com", LinkTarget::Browser).as_deref(),
+      Some("https://example.com")
+    );
+    assert_eq!(
+      link("user@example.com", LinkTarget::System).as_deref(),
+      Some("mailto:user@example.com")
+    );
+    assert!(link("/etc/hosts", LinkTarget::System)
+      .is_some_and(|uri| uri.starts_with("file:///") && uri.ends_with("hosts")));
+    assert_eq!(link("cargo build", LinkTarget::Command), None);
+  }
+
   #[test]
   fn test_url_validation() {
     let valid_web = LinkWidget::new(
@@ -13,6 +13,7 @@
 
 use crate::{
   layout::LayoutRect,
+  rendering::{Layer, RenderStyle, SerializableColor},
   themes::{color_to_ansi, ColorDefinition, ColorTheme, UtilityProcessor},
   widgets::factory::WidgetConfig,
 };
@@ -184,6 +185,25 @@ pub struct ModalBuilder {
   modal: Modal,
 }
 
+impl ModalBackdrop {
+  /// A layer over `screen` that blends the backdrop color into everything
+  /// below it at the backdrop's opacity, dimming rather than hiding the app
+  pub fn layer(&self, screen: &LayoutRect) -> Layer {
+    let color = self.color.unwrap_or(ColorDefinition { r: 0, g: 0, b: 0 });
+    let mut layer = Layer::new(screen.width, screen.height, *screen).with_opacity(self.opacity);
+    let style = RenderStyle {
+      background: Some(SerializableColor::Rgb {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+      }),
+      ..RenderStyle::default()
+    };
+    layer.grid.fill(*screen, &style);
+    layer
+  }
+}
+
 impl Default for ModalBackdrop {
   fn default() -> Self {
     Self {
@@ -276,6 +296,9 @@ impl ModalButton {
 }
 
 impl Modal {
+  /// Z-index of the backdrop layer returned by [`Modal::layers`]
+  pub const LAYER_Z_INDEX: i32 = 1000;
+
   /// Create a new modal
   pub fn new(id: impl Into<String>) -> Self {
     Self {
@@ -532,6 +555,41 @@ impl Modal {
     output
   }
 
+  /// The open modal as compositor layers: the backdrop at
+  /// [`Modal::LAYER_Z_INDEX`] and the dialog above it
+  pub fn layers(&self, screen: &LayoutRect, theme: Option<&ColorTheme>) -> Vec<Layer> {
+    if !self.is_open {
+      return Vec::new();
+    }
+
+    let mut layers = Vec::new();
+    if self.backdrop.visible {
+      layers.push(
+        self
+          .backdrop
+          .layer(screen)
+          .with_z_index(Self::LAYER_Z_INDEX),
+      );
+    }
+
+    let modal_layout = self.calculate_layout(screen);
+    let mut dialog = String::new();
+    let content = self.render_modal_content(&modal_layout, theme);
+    for (row, line) in content.lines().enumerate() {
+      let _ = write!(
+        dialog,
+        "\x1b[{};{}H{line}",
+        modal_layout.y as usize + row + 1,
+        modal_layout.x + 1
+      );
+    }
+    layers.push(
+      Layer::from_ansi(screen.width, screen.height, modal_layout, dialog.as_bytes())
+        .with_z_index(Self::LAYER_Z_INDEX + 1),
+    );
+    layers
+  }
+
   /// Render backdrop
   fn render_backdrop(&self, screen: &LayoutRect, _theme: Option<&ColorTheme>) -> String {
     let mut output = String::new();
@@ -1192,6 +1250,43 @@ mod tests {
     assert!(!modal.closeable);
   }
 
+  #[test]
+  fn test_backdrop_layer_dims_the_screen() {
+    use crate::rendering::Compositor;
+
+    let screen = LayoutRect {
+      x: 0,
+      y: 0,
+      width: 80,
+      height: 24,
+    };
+    let mut modal = Modal::builder("dim").content("Saved").build();
+    modal.open();
+
+    let mut base = Layer::new(80, 24, screen);
+    base.grid.put_str(0, 0, "app", &RenderStyle::default());
+    let mut compositor = Compositor::new(80, 24);
+    compositor.add_layer(base);
+    for layer in modal.layers(&screen, None) {
+      compositor.add_layer(layer);
+    }
+    let grid = compositor.compose();
+
+    // The app stays visible behind the backdrop, dimmed toward black
+    let cell = grid.get(0, 0).unwrap();
+    assert_eq!(cell.symbol, "a");
+    assert_eq!(
+      cell.style.color,
+      Some(SerializableColor::Rgb {
+        r: 115,
+        g: 115,
+        b: 115
+      })
+    );
+    let dialog = modal.calculate_layout(&screen);
+    assert_eq!(grid.get(dialog.x, dialog.y).unwrap().symbol, "╭");
+  }
+
   #[test]
   fn test_modal_open_close() {
     let mut modal = Modal::new("test-modal");
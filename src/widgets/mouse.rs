@@ -751,7 +751,14 @@ impl ResponsiveWidget for MouseWidget {
         .attr("data-relative-y", position.relative_y.to_string());
     }
 
-    builder.build()
+    // Expose interaction state to `:hover` and `:active` selectors
+    let mut element = builder.build();
+    element.set_hovered(self.is_hovered);
+    element.set_active(matches!(
+      self.state,
+      MouseInteractionState::Pressed(_) | MouseInteractionState::Dragging(_)
+    ));
+    element
   }
 
   fn render_with_layout(&self, layout: &LayoutRect, _theme: Option<&ColorTheme>) -> String {
@@ -32,6 +32,7 @@ use crate::{
   components::element::Element,
   layout::LayoutRect,
   reactive::Reactive,
+  rendering::hyperlink::{balance_lines, format_hyperlink, global_hyperlink_support},
   themes::{ColorDefinition, ColorTheme},
 };
 use serde::{Deserialize, Serialize};
@@ -660,6 +661,11 @@ impl RichText {
       }
     }
 
+    // Links [text](url)
+    if self.config.hyperlinks_enabled {
+      result = Self::format_links(&result, global_hyperlink_support::detect());
+    }
+
     // Italic *text*
     while let Some(start) = result.find('*') {
       if let Some(end) = result[start + 1..].find('*') {
@@ -678,6 +684,35 @@ impl RichText {
     result
   }
 
+  /// Replace `[text](url)` with the text as a hyperlink, or followed by the
+  /// URL where the terminal cannot show hyperlinks
+  fn format_links(text: &str, supported: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+      let after = &rest[start + 1..];
+      let link = after.find("](").and_then(|mid| {
+        let url_len = after[mid + 2..].find(')')?;
+        let url = &after[mid + 2..mid + 2 + url_len];
+        Some((&after[..mid], url, mid + 2 + url_len + 1))
+      });
+      match link {
+        // `![alt](src)` is an image, not a link
+        Some((label, url, len)) if !url.is_empty() && !rest[..start].ends_with('!') => {
+          out.push_str(&rest[..start]);
+          out.push_str(&format_hyperlink(label, url, supported));
+          rest = &after[len..];
+        }
+        _ => {
+          out.push_str(&rest[..=start]);
+          rest = after;
+        }
+      }
+    }
+    out.push_str(rest);
+    out
+  }
+
   /// Render content to lines
   pub fn render_content(&mut self) {
     self.rendered_lines.clear();
@@ -826,7 +861,17 @@ impl RichText {
         }
         let mut acc = String::new();
         let mut acc_w = 0usize;
-        for g in word.graphemes(true) {
+        let mut i = 0;
+        while i < word.len() {
+          // Escape sequences are kept whole
+          if let Some(end) = ansi_token_end(word, i) {
+            acc.push_str(&word[i..end]);
+            i = end;
+            continue;
+          }
+          let Some(g) = word[i..].graphemes(true).next() else {
+            break;
+          };
           let gw = UnicodeWidthStr::width(g);
           if acc_w + gw > width {
             lines.push(std::mem::take(&mut acc));
@@ -834,6 +879,7 @@ impl RichText {
           }
           acc.push_str(g);
           acc_w += gw;
+          i += g.len();
         }
         if !acc.is_empty() {
           lines.push(acc);
@@ -862,6 +908,8 @@ impl RichText {
       lines.push(String::new());
     }
 
+    // A link broken across lines is reopened on each of them
+    balance_lines(&mut lines);
     lines
   }
 
@@ -1102,6 +1150,8 @@ impl RichText {
       attributes: std::collections::HashMap::new(),
       focusable: !self.is_disabled(),
       focused: self.is_focused(),
+      hovered: false,
+      active: false,
       disabled: self.is_disabled(),
       tab_index: Some(0),
       key_bindings: Vec::new(),
@@ -741,6 +741,8 @@ impl ScrollableList {
       attributes: HashMap::new(),
       focusable: true,
       focused: self.state.get().is_focused,
+      hovered: false,
+      active: false,
       disabled: false,
       tab_index: Some(0),
       key_bindings: Vec::new(),
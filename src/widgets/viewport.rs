@@ -932,6 +932,8 @@ impl Viewport {
       attributes: std::collections::HashMap::new(),
       focusable: !self.is_disabled(),
       focused: self.is_focused(),
+      hovered: false,
+      active: false,
       disabled: self.is_disabled(),
       tab_index: Some(0),
       key_bindings: Vec::new(),
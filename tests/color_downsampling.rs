@@ -0,0 +1,56 @@
+use reactive_tui::layout::{ComputedStyles, Layout, LayoutRect};
+use reactive_tui::rendering::Renderer;
+use reactive_tui::themes::ColorSupport;
+
+fn layout_with_text(text: &str) -> Layout {
+  Layout {
+    rect: LayoutRect {
+      x: 0,
+      y: 0,
+      width: 20,
+      height: 1,
+    },
+    children: vec![],
+    element_id: Some("root".to_string()),
+    tag: "div".to_string(),
+    content: Some(text.to_string()),
+    styles: ComputedStyles::default(),
+    focused: false,
+    focusable: false,
+  }
+}
+
+#[tokio::test]
+async fn truecolor_output_is_mapped_to_the_16_color_palette() {
+  let mut renderer = Renderer::new().expect("renderer");
+  renderer.set_color_support(ColorSupport::Ansi16);
+
+  let first = renderer
+    .render_diff(&layout_with_text("\u{1b}[38;2;255;0;0mError"))
+    .await
+    .expect("render");
+  let first = String::from_utf8(first).unwrap();
+  assert!(first.contains("\u{1b}[91mError"), "{first:?}");
+  assert!(!first.contains("38;2"), "{first:?}");
+
+  // A shade mapping to the same palette entry changes nothing on screen
+  let second = renderer
+    .render_diff(&layout_with_text("\u{1b}[38;2;250;10;10mError"))
+    .await
+    .expect("render");
+  assert!(second.is_empty(), "{:?}", String::from_utf8_lossy(&second));
+}
+
+#[tokio::test]
+async fn no_color_terminals_get_plain_text() {
+  let mut renderer = Renderer::new().expect("renderer");
+  renderer.set_color_support(ColorSupport::NoColor);
+
+  let frame = renderer
+    .render_diff(&layout_with_text("\u{1b}[1;48;5;21mTitle"))
+    .await
+    .expect("render");
+  let frame = String::from_utf8(frame).unwrap();
+  assert!(frame.contains("\u{1b}[1mTitle"), "{frame:?}");
+  assert!(!frame.contains("48;5"), "{frame:?}");
+}
@@ -0,0 +1,176 @@
+use reactive_tui::css::CssEngine;
+use reactive_tui::prelude::*;
+use reactive_tui::rendering::{CellGrid, SerializableColor};
+
+#[test]
+fn translucent_elements_blend_over_their_parent() {
+  let mut css = CssEngine::new();
+  css
+    .load_stylesheet(
+      ".app { background-color: blue }\n\
+       .veil { opacity: 0.5; background: black }\n\
+       .ghost { background: transparent; z-index: 1 }",
+    )
+    .unwrap();
+  let element = Element::with_tag("div")
+    .class("app")
+    .child(
+      Element::with_tag("text")
+        .class("veil")
+        .content("hi")
+        .build(),
+    )
+    .child(
+      Element::with_tag("text")
+        .class("ghost")
+        .content("yo")
+        .build(),
+    )
+    .build();
+
+  let tree = css.create_component_tree(&element);
+  let mut layout_engine = LayoutEngine::with_dimensions(40, 5);
+  let layout = layout_engine
+    .compute_layout_with_component_tree(&element, &tree)
+    .unwrap();
+
+  let mut renderer = Renderer::new().expect("renderer");
+  let rt = tokio::runtime::Runtime::new().unwrap();
+  let bytes = rt
+    .block_on(async { renderer.render_with_component_tree(&layout, &tree).await })
+    .expect("render");
+  let (width, height) = renderer.get_dimensions();
+  let mut grid = CellGrid::new(width, height);
+  grid.rasterize(&bytes);
+
+  // The veil's black background is mixed half and half with the app's blue
+  let veil = &layout.children[0].rect;
+  let cell = grid.get(veil.x, veil.y).unwrap();
+  assert_eq!(cell.symbol, "h");
+  assert_eq!(
+    cell.style.background,
+    Some(SerializableColor::Rgb {
+      r: 46,
+      g: 46,
+      b: 128
+    })
+  );
+
+  // A transparent layer shows the app's background through its text
+  let ghost = &layout.children[1].rect;
+  let cell = grid.get(ghost.x, ghost.y).unwrap();
+  assert_eq!(cell.symbol, "y");
+  assert_eq!(cell.style.background, Some(SerializableColor::Blue));
+}
+
+#[test]
+fn diff_frames_include_layers() {
+  let mut css = CssEngine::new();
+  css.load_stylesheet(".badge { z-index: 2 }").unwrap();
+  let element = Element::with_tag("div")
+    .child(
+      Element::with_tag("text")
+        .class("badge")
+        .content("new")
+        .build(),
+    )
+    .build();
+
+  let tree = css.create_component_tree(&element);
+  let mut layout_engine = LayoutEngine::with_dimensions(40, 5);
+  let layout = layout_engine
+    .compute_layout_with_component_tree(&element, &tree)
+    .unwrap();
+
+  let mut renderer = Renderer::new().expect("renderer");
+  let rt = tokio::runtime::Runtime::new().unwrap();
+  rt.block_on(async {
+    renderer
+      .render_diff_with_component_tree(&layout, &tree)
+      .await
+  })
+  .expect("render");
+
+  let badge = &layout.children[0].rect;
+  let grid = renderer.front_buffer().expect("front buffer");
+  assert_eq!(grid.get(badge.x, badge.y).unwrap().symbol, "n");
+
+  // The composed cells are diffed as they are, so an unchanged frame
+  // writes nothing
+  let unchanged = rt
+    .block_on(async {
+      renderer
+        .render_diff_with_component_tree(&layout, &tree)
+        .await
+    })
+    .expect("render");
+  assert!(unchanged.is_empty());
+}
+
+#[test]
+fn stacking_contexts_keep_their_descendants_and_order_by_z_index() {
+  let mut css = CssEngine::new();
+  css
+    .load_stylesheet(
+      ".menu { position: relative; z-index: 2 }\n\
+       .tip { position: absolute; z-index: -1 }\n\
+       .toast { position: fixed; z-index: 1 }",
+    )
+    .unwrap();
+  let element = Element::with_tag("div")
+    .child(
+      Element::with_tag("div")
+        .id("menu")
+        .class("menu")
+        .child(Element::with_tag("text").id("item").content("open").build())
+        .child(
+          Element::with_tag("text")
+            .id("tip")
+            .class("tip")
+            .content("?")
+            .build(),
+        )
+        .build(),
+    )
+    .child(
+      Element::with_tag("text")
+        .id("toast")
+        .class("toast")
+        .content("saved")
+        .build(),
+    )
+    .child(Element::with_tag("text").id("body").content("text").build())
+    .build();
+
+  let tree = css.create_component_tree(&element);
+  let mut layout_engine = LayoutEngine::with_dimensions(40, 5);
+  let layout = layout_engine
+    .compute_layout_with_component_tree(&element, &tree)
+    .unwrap();
+  let context = layout_engine.build_stacking_context(layout);
+
+  // The root keeps its in-flow body; the menu keeps its item, and its tip
+  // paints inside the menu's context, just below it
+  let painted: Vec<_> = context
+    .flatten_for_rendering()
+    .into_iter()
+    .map(|layout| {
+      let kept: Vec<_> = layout
+        .children
+        .iter()
+        .filter_map(|child| child.element_id.clone())
+        .collect();
+      (layout.element_id.clone(), kept)
+    })
+    .collect();
+  let id = |name: &str| Some(name.to_string());
+  assert_eq!(
+    painted,
+    vec![
+      (None, vec!["body".to_string()]),
+      (id("toast"), vec![]),
+      (id("tip"), vec![]),
+      (id("menu"), vec!["item".to_string()]),
+    ]
+  );
+}
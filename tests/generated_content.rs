@@ -0,0 +1,46 @@
+use reactive_tui::css::CssEngine;
+use reactive_tui::prelude::*;
+
+#[test]
+fn before_and_after_render_inline_with_their_own_styles() {
+  let mut css = CssEngine::new();
+  css
+    .load_stylesheet(
+      ".item::before { content: \"> \"; color: green }\n\
+       .item::after { content: \" [\" attr(data-count) \"]\" }",
+    )
+    .unwrap();
+  let element = Element::with_tag("div")
+    .child(
+      Element::with_tag("text")
+        .class("item")
+        .attr("data-count", "3")
+        .content("Inbox")
+        .build(),
+    )
+    .build();
+
+  let tree = css.create_component_tree(&element);
+  let mut layout_engine = LayoutEngine::with_dimensions(40, 5);
+  let layout = layout_engine
+    .compute_layout_with_component_tree(&element, &tree)
+    .unwrap();
+  assert_eq!(layout.children[0].content.as_deref(), Some("> Inbox [3]"));
+
+  let mut renderer = Renderer::new().expect("renderer");
+  let rt = tokio::runtime::Runtime::new().unwrap();
+  let bytes = rt
+    .block_on(async { renderer.render_with_component_tree(&layout, &tree).await })
+    .expect("render");
+  let s = String::from_utf8_lossy(&bytes);
+
+  // The marker is green, then the style resets for the element's own text
+  let marker = s.find("> ").expect("marker");
+  let green = s[..marker]
+    .rfind("\x1b[38;5;10m")
+    .expect("green before marker");
+  let reset = s[marker..].find("\x1b[0m").expect("reset after marker") + marker;
+  let text = s.find("Inbox").expect("content");
+  assert!(green < marker && marker < reset && reset < text);
+  assert!(s[text..].contains(" [3]"));
+}
@@ -20,7 +20,6 @@ fn layout_with(text: &str, _fg: Option<()>, _bg: Option<()>) -> Layout {
 async fn diff_minimal_reduces_cursor_moves_and_toggles_for_attribute_change() {
   let mut renderer = Renderer::new().expect("renderer");
   // Default now has diff_mode_enabled = true
-  renderer.enable_diff_minimal_ansi();
 
   let mut driver = HeadlessDriver::new(DriverConfig::default()).expect("headless");
   driver.start_application_mode().expect("start");
@@ -41,8 +40,6 @@ async fn diff_minimal_reduces_cursor_moves_and_toggles_for_attribute_change() {
 #[tokio::test]
 async fn diff_minimal_color_change_emits_color_toggle_only() {
   let mut renderer = Renderer::new().expect("renderer");
-  renderer.enable_diff_minimal_ansi();
-
   // Baseline: code tag has magenta fg and dark grey bg (per RenderStyle defaults)
   let mut base = layout_with("Color", None, None);
   base.tag = "code".into();
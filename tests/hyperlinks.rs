@@ -0,0 +1,64 @@
+use reactive_tui::layout::{ComputedStyles, Layout, LayoutRect};
+use reactive_tui::rendering::hyperlink::{format_hyperlink, CLOSE};
+use reactive_tui::rendering::{Hyperlink, Renderer};
+
+const URL: &str = "https://example.com/docs";
+
+fn layout_with_text(text: &str, width: u16) -> Layout {
+  Layout {
+    rect: LayoutRect {
+      x: 0,
+      y: 0,
+      width,
+      height: 1,
+    },
+    children: vec![],
+    element_id: Some("root".to_string()),
+    tag: "div".to_string(),
+    content: Some(text.to_string()),
+    styles: ComputedStyles::default(),
+    focused: false,
+    focusable: false,
+  }
+}
+
+#[tokio::test]
+async fn changed_link_text_is_rewritten_inside_the_link() {
+  let mut renderer = Renderer::new().expect("renderer");
+  let open = Hyperlink::new(URL).open_sequence();
+
+  let first = renderer
+    .render_diff(&layout_with_text(
+      &format!("see {}", format_hyperlink("docs", URL, true)),
+      20,
+    ))
+    .await
+    .expect("render");
+  let first = String::from_utf8(first).unwrap();
+  assert!(first.contains(&format!("{open}docs{CLOSE}")), "{first:?}");
+
+  let second = renderer
+    .render_diff(&layout_with_text(
+      &format!("see {}", format_hyperlink("dogs", URL, true)),
+      20,
+    ))
+    .await
+    .expect("render");
+  let second = String::from_utf8(second).unwrap();
+  assert!(second.contains(&format!("{open}g{CLOSE}")), "{second:?}");
+}
+
+#[tokio::test]
+async fn clipped_links_are_closed() {
+  let mut renderer = Renderer::new().expect("renderer");
+  let frame = renderer
+    .render_diff(&layout_with_text(
+      &format_hyperlink("documentation", URL, true),
+      4,
+    ))
+    .await
+    .expect("render");
+  let frame = String::from_utf8(frame).unwrap();
+  let open = frame.find("\u{1b}]8;id=").expect("link opened");
+  assert!(frame[open..].contains(CLOSE), "{frame:?}");
+}
@@ -0,0 +1,71 @@
+use reactive_tui::css::CssEngine;
+use reactive_tui::prelude::*;
+use reactive_tui::rendering::CellGrid;
+
+fn list() -> Element {
+  let mut list = Element::with_tag("div").id("log").class("log");
+  for (index, line) in ["one", "two", "three", "four", "five", "six"]
+    .iter()
+    .enumerate()
+  {
+    list = list.child(
+      Element::with_tag("text")
+        .id(format!("line-{index}"))
+        .content(*line)
+        .build(),
+    );
+  }
+  Element::with_tag("div").child(list.build()).build()
+}
+
+async fn render(
+  layout_engine: &mut LayoutEngine,
+  css: &CssEngine,
+  element: &Element,
+) -> Vec<String> {
+  let tree = css.create_component_tree(element);
+  let layout = layout_engine
+    .compute_layout_with_component_tree(element, &tree)
+    .unwrap();
+
+  let mut renderer = Renderer::with_dimensions(12, 5);
+  let bytes = renderer
+    .render_with_component_tree(&layout, &tree)
+    .await
+    .expect("render");
+  let mut grid = CellGrid::new(12, 5);
+  grid.rasterize(&bytes);
+  (0..3).map(|y| grid.row_text(y)).collect()
+}
+
+#[tokio::test]
+async fn scrolled_containers_show_their_content_from_the_offset() {
+  let mut css = CssEngine::new();
+  css
+    .load_stylesheet(".log { height: 3; width: 8; overflow-y: scroll }")
+    .unwrap();
+  let element = list();
+  let mut layout_engine = LayoutEngine::with_dimensions(12, 5);
+
+  assert_eq!(
+    render(&mut layout_engine, &css, &element).await,
+    ["one    █", "two    ░", "three  ░"]
+  );
+
+  // The offset is kept through the next layout and moves the thumb
+  assert!(layout_engine.scroll_by("log", 0, 2));
+  assert_eq!(
+    render(&mut layout_engine, &css, &element).await,
+    ["three  ░", "four   █", "five   ░"]
+  );
+
+  // Revealing the last line scrolls only as far as it takes
+  assert!(layout_engine.scroll_into_view("line-5"));
+  let state = layout_engine.scroll_state("log").unwrap();
+  assert_eq!((state.offset_y, state.max_offset_y()), (3, 3));
+  assert_eq!(
+    render(&mut layout_engine, &css, &element).await,
+    ["four   ░", "five   ░", "six    █"]
+  );
+  assert!(!layout_engine.scroll_page("log", 1));
+}
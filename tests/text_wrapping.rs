@@ -0,0 +1,50 @@
+use reactive_tui::css::CssEngine;
+use reactive_tui::prelude::*;
+
+#[test]
+fn wrapped_text_is_measured_and_rendered_with_the_same_lines() {
+  let mut css = CssEngine::new();
+  css
+    .load_stylesheet(
+      ".body { text-align: right }\n\
+       .title { white-space: nowrap; text-overflow: ellipsis }",
+    )
+    .unwrap();
+  let element = Element::with_tag("div")
+    .child(
+      Element::with_tag("text")
+        .class("body")
+        .content("terminal layouts wrap")
+        .build(),
+    )
+    .child(
+      Element::with_tag("text")
+        .class("title")
+        .content("A heading that is too long")
+        .build(),
+    )
+    .build();
+
+  let tree = css.create_component_tree(&element);
+  let mut layout_engine = LayoutEngine::with_dimensions(10, 6);
+  let layout = layout_engine
+    .compute_layout_with_component_tree(&element, &tree)
+    .unwrap();
+  // "terminal", "layouts", "wrap" take three rows; the title stays on one
+  assert_eq!(layout.children[0].rect.height, 3);
+  assert_eq!(layout.children[1].rect.y, layout.children[0].rect.y + 3);
+  assert_eq!(layout.children[1].rect.height, 1);
+
+  let mut renderer = Renderer::new().expect("renderer");
+  let rt = tokio::runtime::Runtime::new().unwrap();
+  let bytes = rt
+    .block_on(async { renderer.render_with_component_tree(&layout, &tree).await })
+    .expect("render");
+  let s = String::from_utf8_lossy(&bytes);
+
+  // Right-aligned rows start at the column their width leaves free
+  assert!(s.contains("\x1b[2;4Hlayouts"));
+  assert!(s.contains("\x1b[3;7Hwrap"));
+  assert!(s.contains("A heading…"));
+  assert!(!s.contains("too long"));
+}